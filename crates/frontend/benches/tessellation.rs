@@ -0,0 +1,28 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use frontend::tessellation::{GridTessellation, TessellationOptions};
+use polymap::PolyMap;
+
+const MAP_SEED: u64 = 1;
+const POISSON_RADIUS: f64 = 8.0;
+// The same sizes as the world benchmarks
+const SIZES: [(&str, usize, usize); 3] = [
+    ("small", 400, 225),
+    ("medium", 800, 450),
+    ("large", 1600, 900),
+];
+
+fn tessellation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tessellation");
+    group.sample_size(10);
+    let options = TessellationOptions::default();
+    for (name, width, height) in SIZES {
+        let poly = PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| GridTessellation::new(&poly, &options))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tessellation);
+criterion_main!(benches);
@@ -0,0 +1,129 @@
+use macroquad::prelude as mq;
+use polymap::{CellId, PolyMap};
+use world::relief::Landform;
+use world::LandBiome;
+
+// How the categorical views tell their classes apart. The safe palette only uses the colors
+// of Okabe and Ito, which stay distinct for every kind of color blindness, and draws a pattern
+// over most classes, so that they also read in grayscale prints.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    Standard,
+    ColorBlindSafe,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Pattern {
+    Plain,
+    Dots,
+    Hatch,
+    BackHatch,
+    CrossHatch,
+    Dashes,
+}
+
+const BLACK: mq::Color = mq::Color::new(0.0, 0.0, 0.0, 1.0);
+const ORANGE: mq::Color = mq::Color::new(0.90, 0.62, 0.0, 1.0);
+const SKY_BLUE: mq::Color = mq::Color::new(0.34, 0.71, 0.91, 1.0);
+const BLUISH_GREEN: mq::Color = mq::Color::new(0.0, 0.62, 0.45, 1.0);
+const YELLOW: mq::Color = mq::Color::new(0.94, 0.89, 0.26, 1.0);
+const BLUE: mq::Color = mq::Color::new(0.0, 0.45, 0.70, 1.0);
+const VERMILLION: mq::Color = mq::Color::new(0.84, 0.37, 0.0, 1.0);
+const REDDISH_PURPLE: mq::Color = mq::Color::new(0.80, 0.47, 0.65, 1.0);
+const GRAY: mq::Color = mq::Color::new(0.6, 0.6, 0.6, 1.0);
+
+// Distance between the marks of the patterns, in map units. The marks sit on a lattice over
+// the whole map, so that the pattern runs on across cells of the same class.
+const SPACING: f64 = 5.0;
+
+// By the names of World::cover
+pub fn cover_style(cover: &str) -> (mq::Color, Pattern) {
+    match cover {
+        "Open ocean" => (BLUE, Pattern::Plain),
+        "Abyss" => (BLUE, Pattern::CrossHatch),
+        "Continental shelf" => (SKY_BLUE, Pattern::Plain),
+        "Polar sea" => (SKY_BLUE, Pattern::CrossHatch),
+        "Reef" => (SKY_BLUE, Pattern::Dots),
+        "Kelp forest" => (BLUISH_GREEN, Pattern::Dashes),
+        "Lake" => (SKY_BLUE, Pattern::Dashes),
+        "Deciduous forest" => (BLUISH_GREEN, Pattern::Plain),
+        "Boreal forest" => (BLUISH_GREEN, Pattern::Hatch),
+        "Rock" => (GRAY, Pattern::BackHatch),
+        "Sand" => (ORANGE, Pattern::Dots),
+        "Grassland" => (YELLOW, Pattern::Plain),
+        _ => (REDDISH_PURPLE, Pattern::CrossHatch),
+    }
+}
+
+pub fn land_biome_style(biome: LandBiome) -> (mq::Color, Pattern) {
+    match biome {
+        LandBiome::None => (BLUE, Pattern::Plain),
+        LandBiome::Ice => (SKY_BLUE, Pattern::Plain),
+        LandBiome::Tundra => (GRAY, Pattern::Plain),
+        LandBiome::Taiga => (BLUISH_GREEN, Pattern::Hatch),
+        LandBiome::ColdDesert => (GRAY, Pattern::Dots),
+        LandBiome::Steppe => (YELLOW, Pattern::Dashes),
+        LandBiome::TemperateForest => (BLUISH_GREEN, Pattern::Plain),
+        LandBiome::TemperateRainforest => (BLUISH_GREEN, Pattern::CrossHatch),
+        LandBiome::Desert => (ORANGE, Pattern::Dots),
+        LandBiome::Savanna => (ORANGE, Pattern::Dashes),
+        LandBiome::SeasonalForest => (VERMILLION, Pattern::Hatch),
+        LandBiome::TropicalRainforest => (VERMILLION, Pattern::CrossHatch),
+    }
+}
+
+pub fn landform_style(landform: Landform) -> (mq::Color, Pattern) {
+    match landform {
+        Landform::Flat => (YELLOW, Pattern::Plain),
+        Landform::Slope => (GRAY, Pattern::Hatch),
+        Landform::Ridge => (VERMILLION, Pattern::CrossHatch),
+        Landform::Valley => (BLUE, Pattern::Dots),
+    }
+}
+
+pub fn sea_style() -> (mq::Color, Pattern) {
+    (BLACK, Pattern::Plain)
+}
+
+// Draws the pattern of each cell over it, in black or white, whichever stands out more
+// against the color of the cell. The map is drawn with y upwards.
+pub fn draw_patterns(poly: &PolyMap, cells: &[(CellId, mq::Color, Pattern)]) {
+    let half = (SPACING * 0.35) as f32;
+    let height = poly.height() as f64;
+    for &(id, color, pattern) in cells {
+        if pattern == Pattern::Plain {
+            continue;
+        }
+        let luminance = 0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b;
+        let ink = if luminance > 0.45 { mq::Color::new(0.0, 0.0, 0.0, 0.7) } else { mq::Color::new(1.0, 1.0, 1.0, 0.7) };
+
+        let cell = poly.cell(id);
+        let points = cell.polygon().exterior().points_iter().map(|p| (p.x(), p.y()));
+        let (min_x, max_x, min_y, max_y) = points.fold((f64::MAX, f64::MIN, f64::MAX, f64::MIN), |(x0, x1, y0, y1), (x, y)| {
+            (x0.min(x), x1.max(x), y0.min(y), y1.max(y))
+        });
+        let mut lattice_y = (min_y / SPACING).floor() * SPACING;
+        while lattice_y <= max_y {
+            let mut lattice_x = (min_x / SPACING).floor() * SPACING;
+            while lattice_x <= max_x {
+                if cell.contains(lattice_x, lattice_y) {
+                    let (x, y) = (lattice_x as f32, (height - lattice_y) as f32);
+                    match pattern {
+                        Pattern::Plain => {}
+                        Pattern::Dots => mq::draw_circle(x, y, 0.8, ink),
+                        Pattern::Hatch => mq::draw_line(x - half, y - half, x + half, y + half, 0.7, ink),
+                        Pattern::BackHatch => mq::draw_line(x - half, y + half, x + half, y - half, 0.7, ink),
+                        Pattern::CrossHatch => {
+                            mq::draw_line(x - half, y - half, x + half, y + half, 0.7, ink);
+                            mq::draw_line(x - half, y + half, x + half, y - half, 0.7, ink);
+                        }
+                        Pattern::Dashes => mq::draw_line(x - half, y, x + half, y, 0.7, ink),
+                    }
+                }
+                lattice_x += SPACING;
+            }
+            lattice_y += SPACING;
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::camera::CameraView;
+use crate::painter::ViewMode;
+
+pub const SLOTS: usize = 9;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub seed: u64,
+    // By name, so that adding view modes does not break saved bookmarks
+    pub view: String,
+    // Where the camera was, missing from the bookmarks saved before it was kept
+    #[serde(default)]
+    pub camera: Option<CameraView>,
+}
+
+impl Bookmark {
+    pub fn new(seed: u64, view: ViewMode, camera: CameraView) -> Self {
+        Self { seed, view: view.name().to_owned(), camera: Some(camera) }
+    }
+
+    pub fn view_mode(&self) -> Option<ViewMode> {
+        ViewMode::iter().find(|mode| mode.name() == self.view)
+    }
+}
+
+// Numbered slots of saved worlds and views, kept in a JSON file between runs
+pub struct Bookmarks {
+    path: String,
+    slots: Vec<Option<Bookmark>>,
+}
+
+impl Bookmarks {
+    // A missing file gives empty slots
+    pub fn load(path: &str) -> Self {
+        let mut slots: Vec<Option<Bookmark>> = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+                eprintln!("Failed to parse {}: {}", path, err);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        slots.resize(SLOTS, None);
+        Self { path: path.to_owned(), slots }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Bookmark> {
+        self.slots.get(slot).and_then(|bookmark| bookmark.as_ref())
+    }
+
+    // Saves the file right away, so that bookmarks survive a crash
+    pub fn set(&mut self, slot: usize, bookmark: Bookmark) {
+        if slot >= SLOTS {
+            return;
+        }
+        self.slots[slot] = Some(bookmark);
+        let saved = serde_json::to_string_pretty(&self.slots)
+            .map_err(|err| err.to_string())
+            .and_then(|text| std::fs::write(&self.path, text).map_err(|err| err.to_string()));
+        if let Err(err) = saved {
+            eprintln!("Failed to save {}: {}", self.path, err);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use macroquad::prelude as mq;
+use polymap::PolyMap;
+use serde_derive::{Deserialize, Serialize};
+
+// How much each notch of the mouse wheel zooms in or out
+const ZOOM_STEP: f32 = 1.25;
+// How many times closer than the whole map the camera goes at most
+const MAX_ZOOM: f32 = 32.0;
+
+// Which part of the map the window shows. At zoom 1 the whole map is stretched over the window;
+// closer, the window shows a part of it, starting at the offset. Map points are in map units
+// with y downwards, like the cell centers, and screen points in pixels from the top left.
+pub(crate) struct MapCamera {
+    width: f32,
+    height: f32,
+    zoom: f32,
+    // The map point at the top left corner of the window
+    offset: mq::Vec2,
+    // Where the pointer was, while the map is dragged
+    drag: Option<mq::Vec2>,
+}
+
+// Where the camera looks, for sessions and bookmarks: the map point at the middle of the
+// window, and how close it is
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CameraView {
+    pub center: (f32, f32),
+    pub zoom: f32,
+}
+
+impl MapCamera {
+    pub fn new(poly: &PolyMap) -> Self {
+        Self { width: poly.width() as f32, height: poly.height() as f32, zoom: 1.0, offset: mq::Vec2::ZERO, drag: None }
+    }
+
+    // Map units per screen pixel, across and down
+    pub fn scale(&self) -> (f32, f32) {
+        (self.width / mq::screen_width() / self.zoom, self.height / mq::screen_height() / self.zoom)
+    }
+
+    pub fn to_map(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+        let (scale_x, scale_y) = self.scale();
+        (self.offset.x + screen_x * scale_x, self.offset.y + screen_y * scale_y)
+    }
+
+    pub fn to_screen(&self, map_x: f32, map_y: f32) -> (f32, f32) {
+        let (scale_x, scale_y) = self.scale();
+        ((map_x - self.offset.x) / scale_x, (map_y - self.offset.y) / scale_y)
+    }
+
+    // The part of the map in the window, which the map texture is cut to
+    pub fn visible(&self) -> mq::Rect {
+        mq::Rect::new(self.offset.x, self.offset.y, self.width / self.zoom, self.height / self.zoom)
+    }
+
+    // Zooms by the factor, keeping the map point under the screen point where it is
+    pub fn zoom_at(&mut self, screen_x: f32, screen_y: f32, factor: f32) {
+        let (map_x, map_y) = self.to_map(screen_x, screen_y);
+        self.zoom = (self.zoom * factor).clamp(1.0, MAX_ZOOM);
+        let (scale_x, scale_y) = self.scale();
+        self.offset = mq::Vec2::new(map_x - screen_x * scale_x, map_y - screen_y * scale_y);
+        self.clamp();
+    }
+
+    pub fn view(&self) -> CameraView {
+        let visible = self.visible();
+        CameraView { center: (visible.x + visible.w / 2.0, visible.y + visible.h / 2.0), zoom: self.zoom }
+    }
+
+    // Looks where the view did, as far as the map allows
+    pub fn set_view(&mut self, view: CameraView) {
+        self.zoom = view.zoom.clamp(1.0, MAX_ZOOM);
+        self.drag = None;
+        let visible = self.visible();
+        self.offset = mq::Vec2::new(view.center.0 - visible.w / 2.0, view.center.1 - visible.h / 2.0);
+        self.clamp();
+    }
+
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.offset = mq::Vec2::ZERO;
+        self.drag = None;
+    }
+
+    // The mouse wheel zooms at the pointer, and dragging with the right or middle button pans.
+    // Returns whether the scale changed, as the labels depend on it.
+    pub fn handle_input(&mut self) -> bool {
+        let (screen_x, screen_y) = mq::mouse_position();
+        let pointer = mq::Vec2::new(screen_x, screen_y);
+        let dragging = mq::is_mouse_button_down(mq::MouseButton::Right) || mq::is_mouse_button_down(mq::MouseButton::Middle);
+        match (dragging, self.drag) {
+            (true, Some(last)) => {
+                let (scale_x, scale_y) = self.scale();
+                let moved = pointer - last;
+                self.offset -= mq::Vec2::new(moved.x * scale_x, moved.y * scale_y);
+                self.clamp();
+                self.drag = Some(pointer);
+            }
+            (true, None) => self.drag = Some(pointer),
+            (false, _) => self.drag = None,
+        }
+
+        let (_, wheel) = mq::mouse_wheel();
+        if wheel == 0.0 {
+            return false;
+        }
+        let zoom = self.zoom;
+        self.zoom_at(screen_x, screen_y, ZOOM_STEP.powf(wheel.signum()));
+        self.zoom != zoom
+    }
+
+    // Keeps the window over the map
+    fn clamp(&mut self) {
+        let visible = self.visible();
+        self.offset.x = self.offset.x.clamp(0.0, self.width - visible.w);
+        self.offset.y = self.offset.y.clamp(0.0, self.height - visible.h);
+    }
+}
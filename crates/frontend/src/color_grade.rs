@@ -0,0 +1,97 @@
+use serde_derive::Deserialize;
+use world::World;
+
+// Land colder than this, in °C, counts as polar, warmer than the tropical threshold as tropical,
+// roughly the mean temperatures bounding the Köppen polar and tropical groups
+const POLAR_TEMPERATURE: f64 = -5.0;
+const TROPICAL_TEMPERATURE: f64 = 18.0;
+
+// The climate most of the land of a world has
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Climate {
+    Polar,
+    Arid,
+    Temperate,
+    Tropical,
+}
+
+impl Climate {
+    pub const ALL: [Climate; 4] = [Climate::Polar, Climate::Arid, Climate::Temperate, Climate::Tropical];
+
+    // A world without land is temperate
+    pub fn of_world(world: &World) -> Self {
+        let mut counts = [0; 4];
+        for (id, _) in world.poly().cells().filter(|&(id, _)| world.heightmap()[id] >= world.sea_level()) {
+            let temperature = world.celsius()[id];
+            let cover = world.cover(id);
+            let climate = if temperature < POLAR_TEMPERATURE {
+                Climate::Polar
+            } else if cover == "Sand" {
+                Climate::Arid
+            } else if temperature > TROPICAL_TEMPERATURE && cover.ends_with("forest") {
+                Climate::Tropical
+            } else {
+                Climate::Temperate
+            };
+            counts[climate as usize] += 1;
+        }
+        // Ties go to temperate
+        Climate::ALL.into_iter()
+            .max_by_key(|&climate| (counts[climate as usize], climate == Climate::Temperate))
+            .unwrap_or(Climate::Temperate)
+    }
+}
+
+// A tint multiplied into the colours of the map, and how saturated they are. Both at 1 leave
+// the colours as they are.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub struct ColorGrade {
+    pub tint: [f32; 3],
+    pub saturation: f32,
+}
+
+impl ColorGrade {
+    pub const NEUTRAL: ColorGrade = ColorGrade { tint: [1.0, 1.0, 1.0], saturation: 1.0 };
+
+    // Part of the way from neutral to this grade
+    pub fn scaled(&self, strength: f32) -> Self {
+        let lerp = |value: f32| 1.0 + (value - 1.0) * strength;
+        Self { tint: self.tint.map(lerp), saturation: lerp(self.saturation) }
+    }
+}
+
+// The grade of each climate: a cool blue for polar worlds, warm and saturated deserts, lush
+// tropics. Strength 0 turns the grading off.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct GradingPalette {
+    pub strength: f32,
+    pub polar: ColorGrade,
+    pub arid: ColorGrade,
+    pub temperate: ColorGrade,
+    pub tropical: ColorGrade,
+}
+
+impl Default for GradingPalette {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            polar: ColorGrade { tint: [0.88, 0.95, 1.1], saturation: 0.8 },
+            arid: ColorGrade { tint: [1.1, 1.0, 0.85], saturation: 1.25 },
+            temperate: ColorGrade::NEUTRAL,
+            tropical: ColorGrade { tint: [0.95, 1.05, 0.95], saturation: 1.15 },
+        }
+    }
+}
+
+impl GradingPalette {
+    pub fn grade(&self, climate: Climate) -> ColorGrade {
+        let grade = match climate {
+            Climate::Polar => self.polar,
+            Climate::Arid => self.arid,
+            Climate::Temperate => self.temperate,
+            Climate::Tropical => self.tropical,
+        };
+        grade.scaled(self.strength.clamp(0.0, 1.0))
+    }
+}
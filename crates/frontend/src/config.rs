@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+use world::climatology::WindModel;
+use world::hypsometry::AutoLevels;
+use world::measure::{RainfallScale, TemperatureScale};
+use world::quality::{Quality, QualitySettings};
+use world::relax::{RelaxScope, Relaxation};
+use world::season::Orbit;
+use world::terrace::Terracing;
+use world::terrain_noise::{NoiseKind, NoiseLayer};
+
+use crate::accessibility::Palette;
+use crate::color_grade::GradingPalette;
+use crate::effects::Effects;
+use crate::lod::LodRules;
+use crate::tessellation::TessellationOptions;
+
+#[derive(Default, Deserialize)]
+pub struct Config {
+    // Hash of the config file contents, to tell worlds generated with different configs apart
+    #[serde(skip)]
+    pub hash: u32,
+    #[serde(default)]
+    pub world: WorldConfig,
+    #[serde(default)]
+    pub heightmap: HeightmapConfig,
+    #[serde(default)]
+    pub hydrology: HydrologyConfig,
+    #[serde(default)]
+    pub climate: ClimateConfig,
+    #[serde(default)]
+    pub mask: MaskConfig,
+    #[serde(default)]
+    pub stamps: StampsConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub rendering: RenderingConfig,
+    #[serde(default)]
+    pub grading: GradingPalette,
+    #[serde(default)]
+    pub lod: LodRules,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct WorldConfig {
+    pub symmetry: Option<String>,
+    pub map_seed: Option<u64>,
+    pub ocean_fraction: Option<f64>,
+    pub hypsometry: Option<Vec<f64>>,
+    pub auto_levels: Option<AutoLevelsConfig>,
+    pub classification_smoothing: usize,
+    pub quality: Option<String>,
+    pub stage_budget_ms: Option<u64>,
+    pub bake_detail: Option<f64>,
+    pub sea_level: Option<f64>,
+    pub coast_band: Option<usize>,
+    pub seams: Option<Vec<String>>,
+}
+
+impl WorldConfig {
+    pub fn quality(&self) -> QualitySettings {
+        let quality = self.quality.as_deref()
+            .map(|name| name.parse().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                Quality::Normal
+            }))
+            .unwrap_or_default();
+        QualitySettings { quality, stage_budget: self.stage_budget_ms.map(Duration::from_millis) }
+    }
+}
+
+// Fractions of the map area, missing ones are the defaults
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct AutoLevelsConfig {
+    pub water: Option<f64>,
+    pub highland: Option<f64>,
+    pub peak: Option<f64>,
+}
+
+impl AutoLevelsConfig {
+    pub fn levels(&self) -> Result<AutoLevels, String> {
+        let default = AutoLevels::default();
+        AutoLevels::new(
+            self.water.unwrap_or(default.water),
+            self.highland.unwrap_or(default.highland),
+            self.peak.unwrap_or(default.peak),
+        )
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct HydrologyConfig {
+    pub river_cutoff: Option<f64>,
+}
+
+// Missing values are those of the default scale
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct ClimateConfig {
+    pub equator_temperature: Option<f64>,
+    pub pole_temperature: Option<f64>,
+    pub lapse_rate: Option<f64>,
+    pub peak_elevation: Option<f64>,
+    pub rainfall_scale: Option<f64>,
+    pub wind: Option<String>,
+    pub wind_direction: Option<f64>,
+    pub axial_tilt: Option<f64>,
+    pub latitudes: Option<[f64; 2]>,
+}
+
+impl ClimateConfig {
+    pub fn temperature_scale(&self) -> TemperatureScale {
+        let default = TemperatureScale::default();
+        TemperatureScale {
+            equator: self.equator_temperature.unwrap_or(default.equator),
+            poles: self.pole_temperature.unwrap_or(default.poles),
+            lapse_rate: self.lapse_rate.unwrap_or(default.lapse_rate),
+            peak_elevation: self.peak_elevation.unwrap_or(default.peak_elevation),
+        }
+    }
+
+    pub fn wind_model(&self) -> WindModel {
+        self.wind.as_deref()
+            .map(|name| name.parse().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                WindModel::Clouds
+            }))
+            .unwrap_or_default()
+    }
+
+    pub fn rainfall_scale(&self) -> RainfallScale {
+        RainfallScale { mm_per_unit: self.rainfall_scale.unwrap_or(RainfallScale::default().mm_per_unit) }
+    }
+
+    pub fn orbit(&self) -> Orbit {
+        let default = Orbit::default();
+        let [north, south] = self.latitudes.unwrap_or([default.north, default.south]);
+        Orbit { axial_tilt: self.axial_tilt.unwrap_or(default.axial_tilt), north, south }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HeightmapConfig {
+    pub relax_iterations: usize,
+    pub relax_strength: f64,
+    pub relax_only: Option<String>,
+    pub terrace_steps: usize,
+    pub terrace_smoothness: f64,
+    pub terrace_only: Option<String>,
+    pub low_noise: NoiseConfig,
+    pub high_noise: NoiseConfig,
+}
+
+// Missing values are those of the default layer
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct NoiseConfig {
+    pub noise: Option<String>,
+    pub frequency: Option<f64>,
+    pub intensity: Option<f64>,
+}
+
+impl NoiseConfig {
+    pub fn layer(&self, default: NoiseLayer) -> NoiseLayer {
+        let kind = self.noise.as_deref()
+            .map(|name| name.parse().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                NoiseKind::Perlin
+            }))
+            .unwrap_or(default.kind);
+        NoiseLayer {
+            kind,
+            frequency: self.frequency.unwrap_or(default.frequency),
+            intensity: self.intensity.unwrap_or(default.intensity),
+        }
+    }
+}
+
+impl Default for HeightmapConfig {
+    fn default() -> Self {
+        let relaxation = Relaxation::default();
+        let terracing = Terracing::default();
+        Self {
+            relax_iterations: relaxation.iterations,
+            relax_strength: relaxation.strength,
+            relax_only: None,
+            terrace_steps: terracing.steps,
+            terrace_smoothness: terracing.smoothness,
+            terrace_only: None,
+            low_noise: NoiseConfig::default(),
+            high_noise: NoiseConfig::default(),
+        }
+    }
+}
+
+impl HeightmapConfig {
+    pub fn relaxation(&self) -> Relaxation {
+        let scope = self.relax_only.as_deref()
+            .map(|name| name.parse().unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                RelaxScope::All
+            }))
+            .unwrap_or_default();
+        Relaxation {
+            iterations: self.relax_iterations,
+            strength: self.relax_strength,
+            scope,
+        }
+    }
+
+    pub fn terracing(&self) -> Terracing {
+        let region = self.terrace_only.as_deref().and_then(|name| {
+            name.parse().map_err(|err| eprintln!("{}", err)).ok()
+        });
+        Terracing {
+            steps: self.terrace_steps,
+            smoothness: self.terrace_smoothness,
+            region,
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct MaskConfig {
+    pub path: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct StampsConfig {
+    pub path: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub frames: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RenderingConfig {
+    pub fill_tolerance: f32,
+    pub stroke_tolerance: f32,
+    pub cache: Option<String>,
+    pub water_shimmer: bool,
+    pub cloud_shadows: bool,
+    pub fog: bool,
+    pub color_grade: bool,
+    pub glyph_shadows: bool,
+    pub color_blind_safe: bool,
+    pub textures: bool,
+}
+
+impl Default for RenderingConfig {
+    fn default() -> Self {
+        let options = TessellationOptions::default();
+        Self {
+            fill_tolerance: options.fill_tolerance,
+            stroke_tolerance: options.stroke_tolerance,
+            cache: None,
+            water_shimmer: false,
+            cloud_shadows: false,
+            fog: false,
+            color_grade: false,
+            glyph_shadows: false,
+            color_blind_safe: false,
+            textures: false,
+        }
+    }
+}
+
+impl RenderingConfig {
+    pub fn effects(&self) -> Effects {
+        Effects {
+            water_shimmer: self.water_shimmer,
+            cloud_shadows: self.cloud_shadows,
+            fog: self.fog,
+            color_grade: self.color_grade,
+            glyph_shadows: self.glyph_shadows,
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        if self.color_blind_safe { Palette::ColorBlindSafe } else { Palette::Standard }
+    }
+
+    pub fn tessellation(&self) -> TessellationOptions {
+        TessellationOptions {
+            fill_tolerance: self.fill_tolerance,
+            stroke_tolerance: self.stroke_tolerance,
+        }
+    }
+}
+
+impl Config {
+    // A missing config file falls back to the defaults, and so does a malformed or invalid one,
+    // after its problems are reported
+    pub fn load(path: &str) -> Self {
+        if !std::path::Path::new(path).exists() {
+            return Self::default();
+        }
+        Self::from_toml_path(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            Self::default()
+        })
+    }
+
+    pub fn from_toml_path(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+        text.parse().map_err(|err| format!("Failed to load {}: {}", path, err))
+    }
+
+    // Every problem of the values, one per line
+    fn validate(&self) -> Result<(), String> {
+        let mut problems = vec![];
+        let world = &self.world;
+        between(&mut problems, "world.ocean_fraction", world.ocean_fraction, 0.0, 1.0);
+        between(&mut problems, "world.sea_level", world.sea_level, 0.0, 1.0);
+        at_least(&mut problems, "world.bake_detail", world.bake_detail, 0.0);
+        for (idx, &band) in world.hypsometry.iter().flatten().enumerate() {
+            between(&mut problems, &format!("world.hypsometry[{}]", idx), Some(band), 0.0, 1.0);
+        }
+        if let Some(auto) = world.auto_levels.as_ref() {
+            if let Err(err) = auto.levels() {
+                problems.push(format!("world.auto_levels: {}", err));
+            }
+        }
+
+        let heightmap = &self.heightmap;
+        between(&mut problems, "heightmap.relax_strength", Some(heightmap.relax_strength), 0.0, 1.0);
+        between(&mut problems, "heightmap.terrace_smoothness", Some(heightmap.terrace_smoothness), 0.0, 1.0);
+        for (key, noise) in [("heightmap.low_noise", &heightmap.low_noise), ("heightmap.high_noise", &heightmap.high_noise)] {
+            positive(&mut problems, &format!("{}.frequency", key), noise.frequency);
+            at_least(&mut problems, &format!("{}.intensity", key), noise.intensity, 0.0);
+        }
+
+        at_least(&mut problems, "hydrology.river_cutoff", self.hydrology.river_cutoff, 0.0);
+
+        let climate = &self.climate;
+        let scale = climate.temperature_scale();
+        if scale.poles >= scale.equator {
+            problems.push(format!(
+                "climate.pole_temperature must be below climate.equator_temperature, not {} against {}",
+                scale.poles, scale.equator,
+            ));
+        }
+        at_least(&mut problems, "climate.lapse_rate", climate.lapse_rate, 0.0);
+        positive(&mut problems, "climate.peak_elevation", climate.peak_elevation);
+        at_least(&mut problems, "climate.rainfall_scale", climate.rainfall_scale, 0.0);
+        between(&mut problems, "climate.axial_tilt", climate.axial_tilt, 0.0, 90.0);
+        for (idx, &latitude) in climate.latitudes.iter().flatten().enumerate() {
+            between(&mut problems, &format!("climate.latitudes[{}]", idx), Some(latitude), -90.0, 90.0);
+        }
+
+        positive(&mut problems, "rendering.fill_tolerance", Some(self.rendering.fill_tolerance as f64));
+        positive(&mut problems, "rendering.stroke_tolerance", Some(self.rendering.stroke_tolerance as f64));
+
+        let lod = &self.lod;
+        let rules = [
+            ("archipelago", lod.archipelago), ("region", lod.region), ("island", lod.island),
+            ("city", lod.city), ("town", lod.town), ("village", lod.village),
+            ("volcano", lod.volcano), ("poi", lod.poi), ("pass", lod.pass),
+        ];
+        for (name, rule) in rules {
+            if rule.min_scale > rule.max_scale {
+                problems.push(format!("lod.{}.min_scale must not be above max_scale, not {} against {}", name, rule.min_scale, rule.max_scale));
+            }
+            at_least(&mut problems, &format!("lod.{}.label_size", name), Some(rule.label_size as f64), 0.0);
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems.join("\n")) }
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = String;
+
+    // Missing sections and values are the defaults
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut config: Self = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.validate()?;
+        config.hash = crate::share::stable_hash(text.as_bytes());
+        Ok(config)
+    }
+}
+
+fn between(problems: &mut Vec<String>, key: &str, value: Option<f64>, min: f64, max: f64) {
+    if let Some(value) = value.filter(|value| !(min..=max).contains(value)) {
+        problems.push(format!("{} must be between {} and {}, not {}", key, min, max, value));
+    }
+}
+
+fn at_least(problems: &mut Vec<String>, key: &str, value: Option<f64>, min: f64) {
+    if let Some(value) = value.filter(|value| !(min..).contains(value)) {
+        problems.push(format!("{} must be at least {}, not {}", key, min, value));
+    }
+}
+
+fn positive(problems: &mut Vec<String>, key: &str, value: Option<f64>) {
+    if let Some(value) = value.filter(|&value| value <= 0.0 || value.is_nan()) {
+        problems.push(format!("{} must be positive, not {}", key, value));
+    }
+}
@@ -0,0 +1,198 @@
+use macroquad::prelude as mq;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::color_grade::ColorGrade;
+
+// Purely visual effects, applied when the map is drawn on screen. The map texture itself is
+// left alone, so they never end up in exported images.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Effects {
+    pub water_shimmer: bool,
+    // Darkens the land and sea under heavy rain clouds
+    pub cloud_shadows: bool,
+    // A translucent white over the cells covered in fog or low clouds
+    pub fog: bool,
+    // Tints the whole map after the climate of the world
+    pub color_grade: bool,
+    // Casts a shadow to the southeast of the glyphs of the mountains, drawn with the glyphs
+    // rather than in the shader pass
+    pub glyph_shadows: bool,
+}
+
+impl Effects {
+    // Whether the shader pass has anything to do
+    pub fn any(&self) -> bool {
+        self.water_shimmer || self.cloud_shadows || self.fog || self.color_grade
+    }
+}
+
+// A preview of the map under the sun, or at night under the lights of the settlements
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lighting {
+    pub enabled: bool,
+    // Clockwise from the north, in degrees
+    pub azimuth: f32,
+    // Above the horizon, in degrees
+    pub elevation: f32,
+    pub night: bool,
+}
+
+impl Default for Lighting {
+    // The usual light of hillshaded maps, from the northwest
+    fn default() -> Self {
+        Self { enabled: false, azimuth: 315.0, elevation: 45.0, night: false }
+    }
+}
+
+impl Lighting {
+    // Towards the sun, as east, north and up
+    fn sun_direction(&self) -> [f32; 3] {
+        let azimuth = self.azimuth.to_radians();
+        let elevation = self.elevation.clamp(0.0, 90.0).to_radians();
+        [azimuth.sin() * elevation.cos(), azimuth.cos() * elevation.cos(), elevation.sin()]
+    }
+}
+
+// A shader pass drawing the map texture with the effects on. What each pixel is made of
+// comes from a second texture of the size of the map: sea in red, cloud cover in green and
+// fog in blue. The lighting takes a third one, with the surface normals in red and green and
+// the lights in blue.
+pub(crate) struct EffectsPass {
+    material: mq::Material,
+    mask: mq::RenderTarget,
+    relief: mq::RenderTarget,
+}
+
+impl EffectsPass {
+    pub fn new(width: u32, height: u32) -> Self {
+        let params = mq::MaterialParams {
+            uniforms: vec![
+                ("Time".to_owned(), mq::UniformType::Float1),
+                ("Shimmer".to_owned(), mq::UniformType::Float1),
+                ("CloudShadows".to_owned(), mq::UniformType::Float1),
+                ("Fog".to_owned(), mq::UniformType::Float1),
+                ("Lighting".to_owned(), mq::UniformType::Float1),
+                ("SunDirection".to_owned(), mq::UniformType::Float3),
+                ("Night".to_owned(), mq::UniformType::Float1),
+                ("GradeTint".to_owned(), mq::UniformType::Float3),
+                ("GradeSaturation".to_owned(), mq::UniformType::Float1),
+            ],
+            textures: vec!["Mask".to_owned(), "Relief".to_owned()],
+            ..Default::default()
+        };
+        let material = mq::load_material(VERTEX_SHADER, FRAGMENT_SHADER, params)
+            .expect("the effects shader does not compile");
+        Self {
+            material,
+            mask: mq::render_target(width, height),
+            relief: mq::render_target(width, height),
+        }
+    }
+
+    // Where the cell mask is to be drawn
+    pub fn mask(&self) -> mq::RenderTarget {
+        self.mask
+    }
+
+    // Where the normals and lights are to be drawn
+    pub fn relief(&self) -> mq::RenderTarget {
+        self.relief
+    }
+
+    pub fn draw(&self, texture: mq::Texture2D, effects: &Effects, lighting: &Lighting, grade: &ColorGrade, params: mq::DrawTextureParams) {
+        let flag = |enabled: bool| if enabled { 1.0f32 } else { 0.0 };
+        self.material.set_uniform("Time", mq::get_time() as f32);
+        self.material.set_uniform("Shimmer", flag(effects.water_shimmer));
+        self.material.set_uniform("CloudShadows", flag(effects.cloud_shadows));
+        self.material.set_uniform("Fog", flag(effects.fog));
+        self.material.set_uniform("Lighting", flag(lighting.enabled));
+        self.material.set_uniform("SunDirection", lighting.sun_direction());
+        self.material.set_uniform("Night", flag(lighting.night));
+        let grade = if effects.color_grade { *grade } else { ColorGrade::NEUTRAL };
+        self.material.set_uniform("GradeTint", grade.tint);
+        self.material.set_uniform("GradeSaturation", grade.saturation);
+        self.material.set_texture("Mask", self.mask.texture);
+        self.material.set_texture("Relief", self.relief.texture);
+
+        mq::gl_use_material(self.material);
+        mq::draw_texture_ex(texture, 0.0, 0.0, mq::WHITE, params);
+        mq::gl_use_default_material();
+    }
+}
+
+const VERTEX_SHADER: &str = "#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}
+";
+
+const FRAGMENT_SHADER: &str = "#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D Mask;
+uniform sampler2D Relief;
+uniform float Time;
+uniform float Shimmer;
+uniform float CloudShadows;
+uniform float Fog;
+uniform float Lighting;
+uniform vec3 SunDirection;
+uniform float Night;
+uniform vec3 GradeTint;
+uniform float GradeSaturation;
+
+void main() {
+    vec3 base = texture2D(Texture, uv).rgb * color.rgb;
+    vec4 mask = texture2D(Mask, uv);
+
+    // Two slow waves crossing each other, only over the sea
+    float wave = sin(uv.x * 300.0 + Time * 1.5) * sin(uv.y * 200.0 - Time * 1.1);
+    base += Shimmer * mask.r * 0.06 * wave * vec3(0.8, 0.9, 1.0);
+
+    // Up to a fifth darker under the heaviest clouds
+    base *= 1.0 - CloudShadows * 0.2 * mask.g;
+
+    // Thick fog hides two thirds of what is under it
+    base = mix(base, vec3(0.92, 0.93, 0.95), Fog * 0.66 * mask.b);
+
+    if (Lighting > 0.5) {
+        vec4 relief = texture2D(Relief, uv);
+        vec2 slope = relief.rg * 2.0 - 1.0;
+        vec3 normal = vec3(slope, sqrt(max(1.0 - dot(slope, slope), 0.0)));
+        float diffuse = max(dot(normal, SunDirection), 0.0);
+        // Redder as the sun gets lower
+        vec3 sun = mix(vec3(1.0, 0.7, 0.45), vec3(1.0), SunDirection.z);
+        vec3 day = base * (0.35 + 0.65 * diffuse) * sun;
+        // The sea catches the light of a low sun
+        day += mask.r * 0.3 * (1.0 - SunDirection.z) * (1.0 - SunDirection.z) * sun;
+
+        vec3 night = base * vec3(0.1, 0.12, 0.22) + relief.b * vec3(1.0, 0.75, 0.35);
+        base = mix(day, night, Night);
+    }
+
+    // The grade goes last, over the lit map
+    base *= GradeTint;
+    float luma = dot(base, vec3(0.299, 0.587, 0.114));
+    base = clamp(mix(vec3(luma), base, GradeSaturation), 0.0, 1.0);
+
+    gl_FragColor = vec4(base, 1.0);
+}
+";
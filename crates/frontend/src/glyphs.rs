@@ -0,0 +1,216 @@
+use std::fmt::Write;
+
+use macroquad::prelude as mq;
+use world::features::{Feature, FeatureKind};
+use world::tags::TagValue;
+use world::{gazetteer, TerrainCategory, World};
+
+use crate::camera::MapCamera;
+use crate::labels::{self, Label};
+use crate::lod::{LodClass, LodRules};
+
+// Size of a glyph of the middle tier, in screen pixels
+const GLYPH_SIZE: f32 = 12.0;
+// How far the shadows of the glyphs fall, in screen pixels to the right and down, and how dark
+const SHADOW_OFFSET: f32 = 3.0;
+const SHADOW: mq::Color = mq::Color::new(0.0, 0.0, 0.0, 0.35);
+// Settlements this high are hill towns
+const HIGHLAND: f64 = 0.7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GlyphShape {
+    Town,
+    Port,
+    HillTown,
+    Pass,
+    Volcano,
+    Poi,
+}
+
+// A part of a glyph: a convex polygon, in a square from -1 to 1 with y upwards, and its fill
+type Part = (&'static [(f32, f32)], [u8; 3]);
+
+const WALL: [u8; 3] = [235, 225, 200];
+const ROOF: [u8; 3] = [170, 50, 40];
+const STONE: [u8; 3] = [130, 120, 110];
+const WATER: [u8; 3] = [40, 90, 170];
+const LAVA: [u8; 3] = [230, 80, 20];
+const MARKER: [u8; 3] = [240, 200, 40];
+
+impl GlyphShape {
+    // The glyphs which stand on the mountains, and cast shadows over them
+    fn is_mountain(&self) -> bool {
+        matches!(self, GlyphShape::HillTown | GlyphShape::Pass | GlyphShape::Volcano)
+    }
+
+    fn parts(&self) -> &'static [Part] {
+        match self {
+            GlyphShape::Town => &[
+                (&[(-0.7, -0.8), (0.7, -0.8), (0.7, 0.2), (-0.7, 0.2)], WALL),
+                (&[(-0.9, 0.2), (0.9, 0.2), (0.0, 0.9)], ROOF),
+            ],
+            GlyphShape::Port => &[
+                (&[(-1.0, -0.9), (1.0, -0.9), (1.0, -0.5), (-1.0, -0.5)], WATER),
+                (&[(-0.6, -0.5), (0.6, -0.5), (0.6, 0.3), (-0.6, 0.3)], WALL),
+                (&[(-0.8, 0.3), (0.8, 0.3), (0.0, 0.9)], ROOF),
+            ],
+            GlyphShape::HillTown => &[
+                (&[(-1.0, -0.9), (1.0, -0.9), (0.0, 0.3)], STONE),
+                (&[(-0.35, 0.1), (0.35, 0.1), (0.35, 0.6), (-0.35, 0.6)], WALL),
+                (&[(-0.5, 0.6), (0.5, 0.6), (0.0, 1.0)], ROOF),
+            ],
+            GlyphShape::Pass => &[
+                (&[(-1.0, -0.8), (-0.2, -0.8), (-0.2, 0.8), (-1.0, 0.2)], STONE),
+                (&[(0.2, -0.8), (1.0, -0.8), (1.0, 0.2), (0.2, 0.8)], STONE),
+            ],
+            GlyphShape::Volcano => &[
+                (&[(-1.0, -0.8), (1.0, -0.8), (0.35, 0.5), (-0.35, 0.5)], STONE),
+                (&[(-0.35, 0.5), (0.35, 0.5), (0.0, 1.0)], LAVA),
+            ],
+            GlyphShape::Poi => &[
+                (&[(0.0, -0.9), (0.9, 0.0), (0.0, 0.9), (-0.9, 0.0)], MARKER),
+            ],
+        }
+    }
+}
+
+// A feature drawn as a glyph. Positions are in map units, with y downwards like the cell
+// centers, and the size is the half width in screen pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct Glyph {
+    pub shape: GlyphShape,
+    pub x: f64,
+    pub y: f64,
+    pub size: f32,
+    // Higher priorities are placed first
+    pub priority: u8,
+    pub class: LodClass,
+}
+
+impl Glyph {
+    // Settlements are drawn after where they stand, by the sea or high up, and sized by their
+    // "tier" tag: 1 for villages, 2 for towns and 3 for cities. Points of interest tagged
+    // "icon" = "volcano" are drawn as volcanoes.
+    fn of_feature(world: &World, feature: &Feature) -> Option<Self> {
+        let cell = gazetteer::anchor(world.poly(), feature)?;
+        let class = LodClass::of_feature(feature)?;
+        let (shape, tier) = match feature.kind {
+            FeatureKind::Settlement => {
+                let tier = match feature.tags.get("tier") {
+                    Some(TagValue::Int(tier)) => (*tier).clamp(1, 3) as u8,
+                    _ => 2,
+                };
+                let shape = if world.terrain_category()[cell] == TerrainCategory::Coast {
+                    GlyphShape::Port
+                } else if world.heightmap()[cell] >= HIGHLAND {
+                    GlyphShape::HillTown
+                } else {
+                    GlyphShape::Town
+                };
+                (shape, tier)
+            }
+            FeatureKind::Pass => (GlyphShape::Pass, 1),
+            FeatureKind::Poi => match feature.tags.get("icon") {
+                Some(TagValue::Text(icon)) if icon == "volcano" => (GlyphShape::Volcano, 2),
+                _ => (GlyphShape::Poi, 1),
+            },
+            FeatureKind::River | FeatureKind::Lake | FeatureKind::Range | FeatureKind::Region
+                | FeatureKind::Island | FeatureKind::Archipelago | FeatureKind::Habitat
+                | FeatureKind::Migration => return None,
+        };
+        let (x, y) = world.poly()[cell].center();
+        let priority = match shape {
+            GlyphShape::Town | GlyphShape::Port | GlyphShape::HillTown => 2 + tier,
+            GlyphShape::Volcano => 3,
+            GlyphShape::Poi => 2,
+            GlyphShape::Pass => 1,
+        };
+        Some(Self { shape, x, y, size: GLYPH_SIZE * (0.5 + 0.25 * tier as f32), priority, class })
+    }
+
+    // The corners of the polygons of the glyph, in map units with y downwards, and their fills
+    fn polygons(&self, map_per_pixel: f32) -> impl Iterator<Item = (Vec<(f32, f32)>, [u8; 3])> + '_ {
+        let scale = self.size * map_per_pixel;
+        self.shape.parts().iter().map(move |(points, fill)| {
+            let points = points.iter().map(|&(px, py)| (self.x as f32 + px * scale, self.y as f32 - py * scale)).collect();
+            (points, *fill)
+        })
+    }
+}
+
+// The glyphs of the features of the world, most important first
+pub fn glyphs(world: &World) -> Vec<Glyph> {
+    let mut glyphs: Vec<_> = world.features().iter().filter_map(|feature| Glyph::of_feature(world, feature)).collect();
+    glyphs.sort_by_key(|glyph| std::cmp::Reverse(glyph.priority));
+    glyphs
+}
+
+// The glyphs which fit on the map at the given scale. The rules leave classes out as the map
+// shrinks on screen, and of glyphs which would overlap only the first is kept.
+pub fn place(glyphs: &[Glyph], rules: &LodRules, map_per_pixel: f32) -> Vec<Glyph> {
+    let mut placed: Vec<Glyph> = vec![];
+    for &glyph in glyphs.iter().filter(|glyph| rules.shows(glyph.class, map_per_pixel)) {
+        let overlaps = placed.iter().any(|other| {
+            let distance = ((glyph.x - other.x).powi(2) + (glyph.y - other.y).powi(2)).sqrt();
+            distance < ((glyph.size + other.size) * map_per_pixel) as f64
+        });
+        if !overlaps {
+            placed.push(glyph);
+        }
+    }
+    placed
+}
+
+// On the screen, over the part of the map the camera shows, like the labels: placed for the
+// scale of the camera, the glyphs keep their size in pixels at any zoom. The shadows of the
+// mountains all go first, so that none falls over another glyph.
+pub fn draw(glyphs: &[Glyph], camera: &MapCamera, shadows: bool) {
+    let (map_per_pixel, _) = camera.scale();
+    let on_screen = |points: Vec<(f32, f32)>, offset: f32| -> Vec<mq::Vec2> {
+        points.into_iter()
+            .map(|(x, y)| {
+                let (x, y) = camera.to_screen(x, y);
+                mq::Vec2::new(x + offset, y + offset)
+            })
+            .collect()
+    };
+    if shadows {
+        for glyph in glyphs.iter().filter(|glyph| glyph.shape.is_mountain()) {
+            for (points, _) in glyph.polygons(map_per_pixel) {
+                fill_polygon(&on_screen(points, SHADOW_OFFSET), SHADOW);
+            }
+        }
+    }
+    for glyph in glyphs {
+        for (points, [r, g, b]) in glyph.polygons(map_per_pixel) {
+            let points = on_screen(points, 0.0);
+            fill_polygon(&points, mq::Color::from_rgba(r, g, b, 255));
+            for idx in 0..points.len() {
+                let (a, b) = (points[idx], points[(idx + 1) % points.len()]);
+                mq::draw_line(a.x, a.y, b.x, b.y, 1.0, mq::BLACK);
+            }
+        }
+    }
+}
+
+// The parts of the glyphs are convex, so a fan of triangles covers them
+fn fill_polygon(points: &[mq::Vec2], color: mq::Color) {
+    for idx in 1..points.len() - 1 {
+        mq::draw_triangle(points[0], points[idx], points[idx + 1], color);
+    }
+}
+
+// An SVG of the size of the map, to lay over its image, with the labels over the glyphs
+pub fn to_svg(glyphs: &[Glyph], labels: &[Label], width: usize, height: usize, map_per_pixel: f32) -> String {
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n", width, height, width, height);
+    for glyph in glyphs {
+        for (points, [r, g, b]) in glyph.polygons(map_per_pixel) {
+            let points: Vec<_> = points.iter().map(|(x, y)| format!("{:.2},{:.2}", x, y)).collect();
+            let _ = writeln!(svg, "  <polygon points=\"{}\" fill=\"#{:02x}{:02x}{:02x}\" stroke=\"black\" stroke-width=\"{}\"/>",
+                points.join(" "), r, g, b, map_per_pixel);
+        }
+    }
+    labels::write_svg(labels, &mut svg, map_per_pixel);
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -1,30 +1,195 @@
 use macroquad::prelude as mq;
+use rand::Rng;
 use strum::IntoEnumIterator;
 
+use world::constraint::{Stroke, StrokeKind};
+use world::features::FeatureId;
+use world::hypsometry::TerrainLevels;
+use world::params::Param;
+use world::quality::Quality;
+use world::report::GenerationReport;
+use world::season::Season;
+
+use crate::accessibility::Palette;
+use crate::effects::{Effects, Lighting};
+use crate::histogram::{self, Histograms};
+use crate::inspector::Inspector;
+use crate::map_size::MapSize;
+use crate::session::Session;
 use crate::painter::ViewMode;
+use crate::stamp_tool::StampTool;
+use crate::vector_field::VectorFieldStyle;
+use crate::view_settings::{AlphaCurve, ViewTuning};
 
 pub enum GuiEvent {
     Close,
+    LoadShareString(String),
+    SetSeedText(String),
+    // Blends the world of the seed with the world of the seed from these words
+    BlendSeedText(String),
+    SetBlendAmount(f64),
+    // None goes back to the world of the seed
+    SetVariant(Option<u64>),
+    RebuildMap(MapSize),
+    // A new map like RebuildMap, with the current world moved onto it instead of generated
+    BakeMap(MapSize),
+    RestoreSession,
+    DiscardSession,
     SetViewMode(ViewMode),
+    SetDebugViews(bool),
+    SetVectorStyle(VectorFieldStyle),
+    SetViewTuning(ViewMode, ViewTuning),
+    SetEffects(Effects),
+    SetColorBlindSafe(bool),
+    SetTextures(bool),
+    SetLighting(Lighting),
+    SetSketchMode(Option<StrokeKind>),
+    SetSeason(Option<Season>),
+    // Replaces the strokes of the sketch. Only replays send it, with the strokes recorded before
+    // each apply-sketch, as they are drawn with the mouse and not recorded themselves.
+    SetSketch(Vec<Stroke>),
+    ApplySketch,
+    ClearSketch,
+    SelectStamp(Option<usize>),
+    // The next click copies a region into the stamps
+    StartCopy,
+    SetRiverCutoff(f64),
+    SetTerrainLevels(TerrainLevels),
+    // Regenerates from the stage the parameter changes
+    SetParam(Param, f64),
+    SetStampRadius(f32),
+    SetStampRotation(f32),
+    Export,
+    ExportCivilization,
+    ImportCivilization,
+    SaveWorld,
+    LoadWorld,
+    // Not from the toolbox, but recorded along with its events
+    PlaceStamp(f64, f64),
+    CopyRegion(f64, f64),
+    Regenerate(u64),
+    Inspect(f64, f64),
+    CloseInspector,
+    SetCellNote(String),
+    SetFeatureNote(FeatureId, String),
 }
 
+impl GuiEvent {
+    // Whether the event reads or edits the generated world, so that a generation under way has
+    // to finish first. Those starting a generation of their own supersede the one under way
+    // instead, and those of the views do not care.
+    pub fn acts_on_world(&self) -> bool {
+        !matches!(self,
+            GuiEvent::Close | GuiEvent::LoadShareString(_) | GuiEvent::SetSeedText(_) | GuiEvent::SetVariant(_)
+            | GuiEvent::Regenerate(_) | GuiEvent::SetParam(..) | GuiEvent::SetViewMode(_) | GuiEvent::SetDebugViews(_)
+            | GuiEvent::SetVectorStyle(_) | GuiEvent::SetViewTuning(..) | GuiEvent::SetEffects(_)
+            | GuiEvent::SetColorBlindSafe(_) | GuiEvent::SetTextures(_) | GuiEvent::SetLighting(_)
+            | GuiEvent::SetSketchMode(_) | GuiEvent::SetSeason(_) | GuiEvent::SetSketch(_) | GuiEvent::ClearSketch
+            | GuiEvent::SelectStamp(_) | GuiEvent::StartCopy
+            | GuiEvent::SetStampRadius(_) | GuiEvent::SetStampRotation(_) | GuiEvent::CloseInspector)
+    }
+}
+
+
+// What the toolbox shows of the application state
+pub(crate) struct GuiState<'a> {
+    pub seed: u64,
+    // The words the seed was hashed from, if any
+    pub seed_text: Option<&'a str>,
+    pub share: &'a str,
+    pub view_mode: ViewMode,
+    pub debug_views: bool,
+    pub view_tuning: ViewTuning,
+    pub vector_style: VectorFieldStyle,
+    pub effects: Effects,
+    pub palette: Palette,
+    pub textures: bool,
+    pub lighting: Lighting,
+    pub sketch_mode: Option<StrokeKind>,
+    // Of the temperature view, or the whole year
+    pub season: Option<Season>,
+    pub stamp_tool: &'a StampTool,
+    pub report: &'a GenerationReport,
+    pub quality: Quality,
+    pub river_cutoff: f64,
+    pub sea_level: f64,
+    pub levels: TerrainLevels,
+    pub params: &'a [(Param, f64)],
+    pub histograms: &'a Histograms,
+    pub variant: Option<u64>,
+    // How far towards the other seed, and whether there is one
+    pub blend_amount: f64,
+    pub blending: bool,
+    pub map_size: MapSize,
+    pub building_map: bool,
+    // Left from the last run, until it is restored or discarded
+    pub previous_session: Option<&'a Session>,
+}
 
-pub(crate) fn gui(seed:u64, view_mode: ViewMode) -> (bool, Vec<GuiEvent>) {
+// Returns whether the toolbox takes the pointer and the keyboard, and its events
+pub(crate) fn gui(state: &GuiState, seed_input: &mut String, share_input: &mut String, map_draft: &mut MapSize,
+                  inspector: &mut Inspector) -> (bool, bool, Vec<GuiEvent>) {
+    let GuiState { seed, seed_text, share, view_mode, debug_views, view_tuning, vector_style, effects, palette, textures, lighting, sketch_mode, season, stamp_tool, report, quality, river_cutoff,
+                   sea_level, levels, params, histograms, variant, blend_amount, blending, map_size, building_map, previous_session } = *state;
     let mut events = vec![];
     let mut show_gui = true;
 
     let mut pointer_over_gui = false;
+    let mut typing = false;
 
      // Process keys, mouse etc.
      egui_macroquad::ui(|egui_ctx| {
         egui::Window::new("Toolbox")
             .open(&mut show_gui)
             .show(egui_ctx, |ui| {
-                pointer_over_gui = egui_ctx.is_pointer_over_area();
-                ui.label(&format!("Seed: {}", seed));
+                match seed_text {
+                    Some(text) => ui.label(format!("Seed: {} (\"{}\")", seed, text)),
+                    None => ui.label(format!("Seed: {}", seed)),
+                };
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(seed_input);
+                    if ui.button("Use seed").clicked() && !seed_input.trim().is_empty() {
+                        events.push(GuiEvent::SetSeedText(seed_input.clone()))
+                    }
+                    if ui.button("Blend with seed").clicked() && !seed_input.trim().is_empty() {
+                        events.push(GuiEvent::BlendSeedText(seed_input.clone()))
+                    }
+                    if ui.button("Random").clicked() {
+                        events.push(GuiEvent::Regenerate(rand::thread_rng().gen()))
+                    }
+                });
+                ui.horizontal(|ui| {
+                    match variant {
+                        Some(variant) => ui.label(format!("Variant: {}", variant)),
+                        None => ui.label("Variant: none"),
+                    };
+                    if ui.button("New variant").clicked() {
+                        events.push(GuiEvent::SetVariant(Some(rand::random())))
+                    }
+                    if variant.is_some() && ui.button("Original").clicked() {
+                        events.push(GuiEvent::SetVariant(None))
+                    }
+                });
+                let mut amount = blend_amount;
+                let text = if blending { "Blend" } else { "Blend (no seed to blend with)" };
+                if ui.add(egui::Slider::new(&mut amount, 0.0..=1.0).text(text)).changed() {
+                    events.push(GuiEvent::SetBlendAmount(amount))
+                }
+                ui.horizontal(|ui| {
+                    ui.label(format!("Share: {}", share));
+                    if ui.button("Copy").clicked() {
+                        ui.output().copied_text = share.to_owned();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(share_input);
+                    if ui.button("Load shared").clicked() {
+                        events.push(GuiEvent::LoadShareString(share_input.clone()))
+                    }
+                });
                 ui.label(&format!("FPS: {}", mq::get_fps()));
                 ui.horizontal(|ui| {
-                    for mode in ViewMode::iter() {
+                    for mode in ViewMode::iter().filter(|mode| !mode.is_debug()) {
                         let selected = view_mode == mode;
                         let color = if selected { egui::Color32::RED } else { egui::Color32::WHITE };
                         if ui.add(egui::Button::new(mode.name()).text_color(color)).clicked() {
@@ -32,8 +197,290 @@ pub(crate) fn gui(seed:u64, view_mode: ViewMode) -> (bool, Vec<GuiEvent>) {
                         }
                     }
                 });
+                let mut show_debug = debug_views;
+                ui.checkbox(&mut show_debug, "Debug views");
+                if show_debug != debug_views {
+                    events.push(GuiEvent::SetDebugViews(show_debug))
+                }
+                if debug_views {
+                    ui.horizontal(|ui| {
+                        for mode in ViewMode::iter().filter(|mode| mode.is_debug()) {
+                            let selected = view_mode == mode;
+                            let color = if selected { egui::Color32::RED } else { egui::Color32::WHITE };
+                            if ui.add(egui::Button::new(mode.name()).text_color(color)).clicked() {
+                                events.push(GuiEvent::SetViewMode(mode))
+                            }
+                        }
+                    });
+                }
+                if view_mode.is_tunable() {
+                    egui::CollapsingHeader::new("View tuning").show(ui, |ui| {
+                        let mut tuning = view_tuning;
+                        ui.add(egui::Slider::new(&mut tuning.scale, 0.1..=10.0).logarithmic(true).text("Value scale"));
+                        ui.add(egui::Slider::new(&mut tuning.gamma, 0.2..=5.0).logarithmic(true).text("Gamma"));
+                        ui.horizontal(|ui| {
+                            ui.label("Curve:");
+                            for curve in AlphaCurve::ALL {
+                                ui.radio_value(&mut tuning.curve, curve, curve.name());
+                            }
+                        });
+                        if ui.button("Reset").clicked() {
+                            tuning = ViewTuning::default();
+                        }
+                        if tuning != view_tuning {
+                            events.push(GuiEvent::SetViewTuning(view_mode, tuning))
+                        }
+                    });
+                }
+                if view_mode == ViewMode::Temperature {
+                    ui.horizontal(|ui| {
+                        ui.label("Season:");
+                        let mut chosen = season;
+                        ui.radio_value(&mut chosen, None, "year");
+                        for each in Season::ALL {
+                            ui.radio_value(&mut chosen, Some(each), each.name());
+                        }
+                        if chosen != season {
+                            events.push(GuiEvent::SetSeason(chosen))
+                        }
+                    });
+                }
+                egui::CollapsingHeader::new("Vector fields").show(ui, |ui| {
+                    let mut style = vector_style;
+                    ui.add(egui::Slider::new(&mut style.spacing, 8.0..=100.0).text("Arrow spacing"));
+                    ui.checkbox(&mut style.scale_by_magnitude, "Length by magnitude");
+                    ui.checkbox(&mut style.color_by_magnitude, "Color by magnitude");
+                    ui.checkbox(&mut style.streamlines, "Streamlines");
+                    if style != vector_style {
+                        events.push(GuiEvent::SetVectorStyle(style))
+                    }
+                });
+                egui::CollapsingHeader::new("Effects").show(ui, |ui| {
+                    let mut chosen = effects;
+                    ui.checkbox(&mut chosen.water_shimmer, "Water shimmer");
+                    ui.checkbox(&mut chosen.cloud_shadows, "Cloud shadows");
+                    ui.checkbox(&mut chosen.fog, "Fog");
+                    ui.checkbox(&mut chosen.color_grade, "Climate color grade");
+                    ui.checkbox(&mut chosen.glyph_shadows, "Glyph shadows");
+                    if chosen != effects {
+                        events.push(GuiEvent::SetEffects(chosen))
+                    }
+                    let mut safe = palette == Palette::ColorBlindSafe;
+                    if ui.checkbox(&mut safe, "Color-blind safe classes").changed() {
+                        events.push(GuiEvent::SetColorBlindSafe(safe))
+                    }
+                    let mut chosen = textures;
+                    if ui.checkbox(&mut chosen, "Textures").changed() {
+                        events.push(GuiEvent::SetTextures(chosen))
+                    }
+                });
+                egui::CollapsingHeader::new("Lighting").show(ui, |ui| {
+                    let mut chosen = lighting;
+                    ui.checkbox(&mut chosen.enabled, "Preview lighting");
+                    ui.add(egui::Slider::new(&mut chosen.azimuth, 0.0..=360.0).text("Sun azimuth"));
+                    ui.add(egui::Slider::new(&mut chosen.elevation, 0.0..=90.0).text("Sun elevation"));
+                    ui.checkbox(&mut chosen.night, "Night");
+                    if chosen != lighting {
+                        events.push(GuiEvent::SetLighting(chosen))
+                    }
+                });
+                egui::CollapsingHeader::new("Thresholds").show(ui, |ui| {
+                    let heights = &histograms.heights;
+                    let mut level = [sea_level];
+                    let dragged = histogram::thresholds(ui, heights, &mut level);
+                    let info = Param::SeaLevel.info();
+                    if ui.add(egui::Slider::new(&mut level[0], info.min..=info.max).text(info.name)).changed() || dragged {
+                        events.push(GuiEvent::SetParam(Param::SeaLevel, level[0]))
+                    }
+                    ui.label(format!("{} sea cells, {} land cells", heights.below(sea_level), heights.above(sea_level)));
+
+                    let drainage = &histograms.drainage;
+                    let mut cutoff = [river_cutoff];
+                    let dragged = histogram::thresholds(ui, drainage, &mut cutoff);
+                    if ui.add(egui::Slider::new(&mut cutoff[0], 1.0..=100.0).logarithmic(true).text("River cutoff")).changed() || dragged {
+                        events.push(GuiEvent::SetRiverCutoff(cutoff[0]))
+                    }
+                    ui.label(format!("{} land cells drain enough for a river", drainage.above(river_cutoff)));
+
+                    let mut bounds = [levels.highland, levels.peak];
+                    let dragged = histogram::thresholds(ui, heights, &mut bounds);
+                    let highland_changed = ui.add(egui::Slider::new(&mut bounds[0], sea_level..=1.0).text("Highland")).changed();
+                    let peak_changed = ui.add(egui::Slider::new(&mut bounds[1], sea_level..=1.0).text("Peak")).changed();
+                    if dragged || highland_changed || peak_changed {
+                        // The peaks are within the highlands
+                        let (highland, peak) = (bounds[0].min(bounds[1]), bounds[0].max(bounds[1]));
+                        events.push(GuiEvent::SetTerrainLevels(TerrainLevels { highland, peak }))
+                    }
+                    ui.label(format!("{} highland cells, {} peak cells", heights.above(levels.highland), heights.above(levels.peak)));
+                });
+                egui::CollapsingHeader::new("Parameters").show(ui, |ui| {
+                    // The sea level has its slider with the thresholds
+                    for &(param, value) in params.iter().filter(|(param, _)| *param != Param::SeaLevel) {
+                        let info = param.info();
+                        let mut value = value;
+                        if ui.add(egui::Slider::new(&mut value, info.min..=info.max).logarithmic(info.logarithmic).text(info.name)).changed() {
+                            events.push(GuiEvent::SetParam(param, value))
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Sketch:");
+                    for mode in [None, Some(StrokeKind::Coastline), Some(StrokeKind::Ridge)] {
+                        let selected = sketch_mode == mode;
+                        let color = if selected { egui::Color32::RED } else { egui::Color32::WHITE };
+                        let name = mode.map(|kind| kind.name()).unwrap_or("Off");
+                        if ui.add(egui::Button::new(name).text_color(color)).clicked() {
+                            events.push(GuiEvent::SetSketchMode(mode))
+                        }
+                    }
+                    if ui.button("Apply").clicked() {
+                        events.push(GuiEvent::ApplySketch)
+                    }
+                    if ui.button("Clear").clicked() {
+                        events.push(GuiEvent::ClearSketch)
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Stamp:");
+                    let selected = stamp_tool.selected();
+                    let options = std::iter::once((None, "Off"))
+                        .chain(stamp_tool.names().into_iter().enumerate().map(|(idx, name)| (Some(idx), name)));
+                    for (option, name) in options {
+                        let color = if selected == option { egui::Color32::RED } else { egui::Color32::WHITE };
+                        if ui.add(egui::Button::new(name).text_color(color)).clicked() {
+                            events.push(GuiEvent::SelectStamp(option))
+                        }
+                    }
+                    let color = if stamp_tool.is_copying() { egui::Color32::RED } else { egui::Color32::WHITE };
+                    if ui.add(egui::Button::new("Copy region").text_color(color)).clicked() {
+                        events.push(GuiEvent::StartCopy)
+                    }
+                });
+                if stamp_tool.is_active() {
+                    let mut radius = stamp_tool.radius();
+                    if ui.add(egui::Slider::new(&mut radius, 20.0..=300.0).text("Radius")).changed() {
+                        events.push(GuiEvent::SetStampRadius(radius))
+                    }
+                    let mut rotation = stamp_tool.rotation();
+                    if ui.add(egui::Slider::new(&mut rotation, 0.0..=360.0).text("Rotation")).changed() {
+                        events.push(GuiEvent::SetStampRotation(rotation))
+                    }
+                }
+                if ui.button("Export tabletop files").clicked() {
+                    events.push(GuiEvent::Export)
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Civilization:");
+                    if ui.button("Export").clicked() {
+                        events.push(GuiEvent::ExportCivilization)
+                    }
+                    if ui.button("Import").clicked() {
+                        events.push(GuiEvent::ImportCivilization)
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("World:");
+                    if ui.button("Save").clicked() {
+                        events.push(GuiEvent::SaveWorld)
+                    }
+                    if ui.button("Load").clicked() {
+                        events.push(GuiEvent::LoadWorld)
+                    }
+                });
+                egui::CollapsingHeader::new("Map size").show(ui, |ui| {
+                    ui.label(format!("Current: {} x {}, {} cells", map_size.width, map_size.height, map_size.cells));
+                    ui.horizontal(|ui| {
+                        ui.label("Width");
+                        ui.add(egui::DragValue::new(&mut map_draft.width).clamp_range(100..=8000));
+                        ui.label("Height");
+                        ui.add(egui::DragValue::new(&mut map_draft.height).clamp_range(100..=8000));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Cells");
+                        ui.add(egui::DragValue::new(&mut map_draft.cells).clamp_range(16..=500000).speed(100.0));
+                    });
+                    if building_map {
+                        ui.label("Building...");
+                    } else {
+                        ui.horizontal(|ui| {
+                            if ui.button("Rebuild map").clicked() {
+                                events.push(GuiEvent::RebuildMap(*map_draft))
+                            }
+                            // Only onto a map of the same size, since the world is not stretched
+                            let same_size = map_draft.width == map_size.width && map_draft.height == map_size.height;
+                            if ui.add_enabled(same_size, egui::Button::new("Bake world")).clicked() {
+                                events.push(GuiEvent::BakeMap(*map_draft))
+                            }
+                        });
+                    }
+                });
+                egui::CollapsingHeader::new("Generation report").show(ui, |ui| {
+                    for stage in report.stages() {
+                        ui.label(format!("{}: {:.1} ms", stage.name, stage.duration.as_secs_f64() * 1000.0));
+                        for (counter, value) in stage.counters.iter() {
+                            ui.label(format!("    {}: {}", counter, value));
+                        }
+                    }
+                    ui.label(format!("Total: {:.1} ms", report.total().as_secs_f64() * 1000.0));
+                    ui.label(format!("Quality: {}", quality.name()));
+                });
             
             });
+
+        if let Some(session) = previous_session {
+            egui::Window::new("Restore session").show(egui_ctx, |ui| {
+                match session.seed_text.as_deref() {
+                    Some(text) => ui.label(format!("The last session was on seed {} (\"{}\").", session.seed, text)),
+                    None => ui.label(format!("The last session was on seed {}.", session.seed)),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        events.push(GuiEvent::RestoreSession)
+                    }
+                    if ui.button("Discard").clicked() {
+                        events.push(GuiEvent::DiscardSession)
+                    }
+                });
+            });
+        }
+
+        if inspector.cell().is_some() {
+            egui::Window::new("Inspector").show(egui_ctx, |ui| {
+                if let Some(cell) = inspector.cell() {
+                    ui.label(format!("Cell {}", cell.idx()));
+                }
+                for line in inspector.climate.iter() {
+                    ui.label(line);
+                }
+                for tag in inspector.tags.iter() {
+                    ui.label(tag);
+                }
+                egui::CollapsingHeader::new("Layers").show(ui, |ui| {
+                    for line in inspector.layers.iter() {
+                        ui.label(line);
+                    }
+                });
+                ui.label("Note:");
+                ui.text_edit_multiline(&mut inspector.note);
+                if ui.button("Save note").clicked() {
+                    events.push(GuiEvent::SetCellNote(inspector.note.clone()))
+                }
+                for feature in inspector.features.iter_mut() {
+                    ui.separator();
+                    ui.label(&feature.label);
+                    ui.text_edit_multiline(&mut feature.note);
+                    if ui.button("Save note").clicked() {
+                        events.push(GuiEvent::SetFeatureNote(feature.id, feature.note.clone()))
+                    }
+                }
+                ui.separator();
+                if ui.button("Close").clicked() {
+                    events.push(GuiEvent::CloseInspector)
+                }
+            });
+        }
+        pointer_over_gui = egui_ctx.is_pointer_over_area();
+        typing = egui_ctx.wants_keyboard_input();
     });
 
     // Draw things before egui
@@ -43,5 +490,5 @@ pub(crate) fn gui(seed:u64, view_mode: ViewMode) -> (bool, Vec<GuiEvent>) {
         events.push(GuiEvent::Close);
     }
 
-    (pointer_over_gui, events)
+    (pointer_over_gui, typing, events)
 }
@@ -0,0 +1,61 @@
+use world::model::Histogram;
+use world::World;
+
+const BINS: usize = 48;
+const HEIGHT: f32 = 60.0;
+
+// The fields behind the thresholds of the toolbox, counted again whenever the world changes
+pub(crate) struct Histograms {
+    pub heights: Histogram,
+    // Of the land only, on a log scale
+    pub drainage: Histogram,
+}
+
+impl Histograms {
+    pub fn of_world(world: &World) -> Self {
+        let poly = world.poly();
+        let sea_level = world.sea_level();
+        let land = poly.cells().map(|(id, _)| id).filter(|&id| world.heightmap()[id] >= sea_level);
+        Self {
+            heights: Histogram::new(poly.cells().map(|(id, _)| world.heightmap()[id]), BINS, false),
+            drainage: Histogram::new(land.map(|id| world.drainage()[id]), BINS, true),
+        }
+    }
+}
+
+// Draws the histogram with a line at each threshold. Clicking or dragging on it moves the
+// closest line there. Returns whether a line moved.
+pub(crate) fn thresholds(ui: &mut egui::Ui, histogram: &Histogram, thresholds: &mut [f64]) -> bool {
+    let size = egui::vec2(ui.available_width().min(320.0), HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(24));
+
+    let tallest = histogram.bins.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bin_width = rect.width() / histogram.bins.len() as f32;
+    for (idx, &count) in histogram.bins.iter().enumerate() {
+        let left = rect.left() + idx as f32 * bin_width;
+        let top = rect.bottom() - rect.height() * count as f32 / tallest;
+        let bar = egui::Rect::from_min_max(egui::pos2(left, top), egui::pos2(left + bin_width, rect.bottom()));
+        painter.rect_filled(bar, 0.0, egui::Color32::from_gray(160));
+    }
+
+    let x_of = |value: f64| rect.left() + rect.width() * histogram.position(value) as f32;
+    let mut moved = false;
+    if response.dragged() || response.clicked() {
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let closest = (0..thresholds.len())
+                .min_by(|&a, &b| (x_of(thresholds[a]) - pointer.x).abs().total_cmp(&(x_of(thresholds[b]) - pointer.x).abs()));
+            if let Some(idx) = closest {
+                let value = histogram.value_at(((pointer.x - rect.left()) / rect.width()) as f64);
+                moved = value != thresholds[idx];
+                thresholds[idx] = value;
+            }
+        }
+    }
+    for &threshold in thresholds.iter() {
+        let x = x_of(threshold);
+        painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], (2.0, egui::Color32::RED));
+    }
+    moved
+}
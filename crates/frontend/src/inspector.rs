@@ -0,0 +1,117 @@
+use polymap::CellId;
+use world::features::FeatureId;
+use world::layers::Domain;
+use world::season::Season;
+use world::tags::NOTE;
+use world::water::FreshWater;
+use world::{LandBiome, TerrainCategory, World};
+
+// A feature at the inspected cell, with the note being written for it
+pub(crate) struct FeatureEntry {
+    pub id: FeatureId,
+    pub label: String,
+    pub note: String,
+}
+
+// The cell picked on the map, and the drafts of its notes. Drafts are only written to the
+// world when saved.
+#[derive(Default)]
+pub(crate) struct Inspector {
+    cell: Option<CellId>,
+    // Calibrated values, one per line
+    pub climate: Vec<String>,
+    pub tags: Vec<String>,
+    // Every layer of the world, see World::layers
+    pub layers: Vec<String>,
+    pub note: String,
+    pub features: Vec<FeatureEntry>,
+}
+
+impl Inspector {
+    pub fn cell(&self) -> Option<CellId> { self.cell }
+
+    pub fn inspect(&mut self, world: &World, cell: Option<CellId>) {
+        *self = Self { cell, ..Self::default() };
+        let cell = match cell {
+            Some(cell) => cell,
+            None => return,
+        };
+        self.climate = vec![
+            format!("Elevation: {:.0} m", world.elevation(cell)),
+            format!("Temperature: {:.1} °C", world.celsius()[cell]),
+            format!("Summer / winter: {:.1} / {:.1} °C", world.cell_temperature_at(cell, Season::Summer), world.cell_temperature_at(cell, Season::Winter)),
+            format!("Rainfall: {:.0} mm/yr", world.rainfall_mm(cell)),
+            format!("Water balance: {:+.0} mm/yr", world.water_balance(cell)),
+        ];
+        let biome = world.land_biome()[cell];
+        if biome != LandBiome::None {
+            self.climate.push(format!("Biome: {}", biome.name()));
+            self.climate.push(format!("Fauna: {}", world.fauna()[cell].name()));
+        }
+        if let Some(lake) = world.lake_at(cell) {
+            self.climate.push(format!("Lake: {} cells, {} outflows", lake.cells.len(), lake.outflows.len()));
+        }
+        if let Some(river) = world.river_at(cell) {
+            let joins = match river.joins() {
+                Some(joined) => format!(", joins {}", joined.name().unwrap_or("another river")),
+                None => String::new(),
+            };
+            self.climate.push(format!(
+                "River: {}, {:.0} long, from {:.0} m, flux {:.1} at the mouth, {} tributaries{}",
+                river.name().unwrap_or("unnamed"), river.length(), river.source_elevation(),
+                river.mouth_flux(), river.tributaries().len(), joins,
+            ));
+        }
+        if let Some(source) = world.nearest_fresh_water(cell) {
+            let water = match source.water {
+                FreshWater::River(_) => "river",
+                FreshWater::Lake(_) => "lake",
+            };
+            let name = world.water_feature(&source)
+                .and_then(|id| world.features().get(id))
+                .and_then(|feature| feature.name.clone());
+            self.climate.push(match name {
+                Some(name) => format!("Fresh water: {} ({}), {:.0} away", name, water, source.distance),
+                None => format!("Fresh water: {}, {:.0} away", water, source.distance),
+            });
+        }
+        if world.terrain_category()[cell] != TerrainCategory::Sea {
+            self.climate.push(format!("Water table: {:.0} m, availability {:.2}", world.water_table()[cell], world.water_availability()[cell]));
+            self.climate.push(format!("Soil moisture: {:.2}", world.cell_moisture(cell)));
+        }
+        let hazards = world.hazards()[cell];
+        if !hazards.is_empty() {
+            self.climate.push(format!("Hazards: {}", hazards.names().join(", ")));
+        }
+        if world.harbor()[cell] > 0.0 {
+            self.climate.push(format!("Harbor: {:.2}", world.harbor()[cell]));
+        }
+        self.layers = world.layers().iter()
+            .filter(|layer| layer.domain == Domain::Cell)
+            .map(|layer| match layer.unit {
+                Some(unit) => format!("{}: {} {}", layer.name, layer.value(world, cell), unit),
+                None => format!("{}: {}", layer.name, layer.value(world, cell)),
+            })
+            .collect();
+        self.tags = world.tags().of_cell(cell)
+            .filter(|&(key, _)| key != NOTE.name())
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect();
+        if let Some(flavor) = world.weather_flavor(cell) {
+            self.tags.push(flavor.to_owned());
+        }
+        self.note = world.note(cell).unwrap_or_default();
+        self.features = world.features().at_cell(cell)
+            .map(|feature| FeatureEntry {
+                id: feature.id,
+                label: format!("{} {}", feature.kind.name(), feature.name.as_deref().unwrap_or("(unnamed)")),
+                note: feature.tags.get(NOTE.name()).map(|note| note.to_string()).unwrap_or_default(),
+            })
+            .collect();
+    }
+
+    // Reloads after the world changed under the inspector
+    pub fn refresh(&mut self, world: &World) {
+        self.inspect(world, self.cell)
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use macroquad::prelude as mq;
+
+enum Message<T, P> {
+    Progress(P),
+    Done(T),
+}
+
+// Work run on a thread of its own, so that the frames keep coming while it runs. The thread
+// reports its progress and then its result over a channel, which the frame loop polls.
+// Dropping the job discards its result and cancels it; the work stops at the next point it
+// checks, if it checks at all.
+pub(crate) struct Job<T, P> {
+    receiver: Receiver<Message<T, P>>,
+    progress: Option<P>,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+}
+
+// What the work is handed, to report its progress with and to learn that it is no longer wanted
+pub(crate) struct Worker<'a, P> {
+    report: &'a dyn Fn(P),
+    cancelled: &'a AtomicBool,
+}
+
+impl<P> Worker<'_, P> {
+    pub fn report(&self, progress: P) { (self.report)(progress) }
+
+    pub fn is_cancelled(&self) -> bool { self.cancelled.load(Ordering::Relaxed) }
+}
+
+impl<T: Send + 'static, P: Send + 'static> Job<T, P> {
+    pub fn spawn(work: impl FnOnce(&Worker<P>) -> T + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        std::thread::spawn(move || {
+            let report = |progress| {
+                let _ = sender.send(Message::Progress(progress));
+            };
+            let result = work(&Worker { report: &report, cancelled: &flag });
+            let _ = sender.send(Message::Done(result));
+        });
+        Self { receiver, progress: None, started: Instant::now(), cancelled }
+    }
+
+    // Asks the work to stop early; what it returns then is still its result
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // The last progress reported, as of the last poll
+    pub fn progress(&self) -> Option<&P> { self.progress.as_ref() }
+
+    pub fn elapsed(&self) -> Duration { self.started.elapsed() }
+
+    // The result once the work is done, only once
+    pub fn poll(&mut self) -> Option<Result<T, String>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Message::Progress(progress)) => self.progress = Some(progress),
+                Ok(Message::Done(result)) => return Some(Ok(result)),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => return Some(Err("The job stopped without a result".to_owned())),
+            }
+        }
+    }
+
+    // Blocks until the work is done
+    pub fn wait(self) -> Result<T, String> {
+        for message in self.receiver.iter() {
+            if let Message::Done(result) = message {
+                return Ok(result);
+            }
+        }
+        Err("The job stopped without a result".to_owned())
+    }
+}
+
+impl<T, P> Drop for Job<T, P> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+// Dots around a circle, the brightest going round, to show that work is under way
+pub(crate) fn draw_spinner(x: f32, y: f32, radius: f32) {
+    const DOTS: usize = 8;
+    let lead = (mq::get_time() * DOTS as f64) as usize % DOTS;
+    for dot in 0..DOTS {
+        let angle = dot as f32 / DOTS as f32 * std::f32::consts::TAU;
+        let behind = (lead + DOTS - dot) % DOTS;
+        let alpha = 1.0 - behind as f32 / DOTS as f32;
+        let color = mq::Color::new(1.0, 1.0, 1.0, alpha);
+        mq::draw_circle(x + radius * angle.cos(), y + radius * angle.sin(), radius * 0.2, color);
+    }
+}
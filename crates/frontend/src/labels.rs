@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use macroquad::prelude as mq;
+use world::{gazetteer, World};
+
+use crate::camera::MapCamera;
+use crate::lod::{LodClass, LodRules};
+
+// Width of a character of a label, in parts of its size, for telling which labels overlap
+const CHAR_WIDTH: f32 = 0.55;
+
+// The name of a feature, centered on its anchor. Positions are in map units with y downwards,
+// the size in screen pixels.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+    pub size: f32,
+}
+
+impl Label {
+    // Half the width and height, in map units
+    fn half_extent(&self, map_per_pixel: f32) -> (f64, f64) {
+        let width = CHAR_WIDTH * self.size * self.text.chars().count() as f32;
+        ((0.5 * width * map_per_pixel) as f64, (0.5 * self.size * map_per_pixel) as f64)
+    }
+}
+
+// The labels of the named features the rules show at the scale, broadest classes first. Of
+// labels which would overlap only the first is kept.
+pub fn labels(world: &World, rules: &LodRules, map_per_pixel: f32) -> Vec<Label> {
+    let mut candidates: Vec<_> = world.features().iter()
+        .filter_map(|feature| {
+            let class = LodClass::of_feature(feature)?;
+            let size = rules.label_size(class, map_per_pixel)?;
+            let text = feature.name.clone()?;
+            let (x, y) = world.poly()[gazetteer::anchor(world.poly(), feature)?].center();
+            Some((class as usize, Label { text, x, y, size }))
+        })
+        .collect();
+    candidates.sort_by_key(|&(class, _)| class);
+
+    let mut placed: Vec<Label> = vec![];
+    for (_, label) in candidates {
+        let (w, h) = label.half_extent(map_per_pixel);
+        let overlaps = placed.iter().any(|other| {
+            let (ow, oh) = other.half_extent(map_per_pixel);
+            (label.x - other.x).abs() < w + ow && (label.y - other.y).abs() < h + oh
+        });
+        if !overlaps {
+            placed.push(label);
+        }
+    }
+    placed
+}
+
+// On the screen, over the part of the map the camera shows
+pub fn draw(labels: &[Label], camera: &MapCamera) {
+    for label in labels {
+        let size = label.size as u16;
+        let dimensions = mq::measure_text(&label.text, None, size, 1.0);
+        let (x, y) = camera.to_screen(label.x as f32, label.y as f32);
+        let x = x - 0.5 * dimensions.width;
+        let y = y + 0.5 * dimensions.offset_y;
+        mq::draw_text(&label.text, x + 1.0, y + 1.0, label.size, mq::BLACK);
+        mq::draw_text(&label.text, x, y, label.size, mq::WHITE);
+    }
+}
+
+// SVG text elements, in map units like the glyphs
+pub fn write_svg(labels: &[Label], svg: &mut String, map_per_pixel: f32) {
+    for label in labels {
+        let _ = writeln!(svg, "  <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"middle\" fill=\"white\" stroke=\"black\" stroke-width=\"{:.2}\" paint-order=\"stroke\">{}</text>",
+            label.x, label.y, label.size * map_per_pixel, 0.3 * map_per_pixel, escape(&label.text));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,106 @@
+use macroquad::prelude as mq;
+use polymap::CellId;
+use world::detail::{self, DetailPoint};
+use world::seed::SeedTree;
+use world::World;
+
+use crate::painter::colors;
+
+// How many times larger than the map the lens shows the terrain
+const ZOOM: f32 = 6.0;
+// In screen pixels
+const RADIUS: f32 = 160.0;
+// Each triangle of a cell is cut in 4^LEVELS
+const LEVELS: usize = 3;
+// Light from the northwest, as east, north and up
+const LIGHT: (f32, f32, f32) = (-1.0, 1.0, 1.5);
+// How many times steeper than they are the slopes are shaded
+const EXAGGERATION: f32 = 400.0;
+
+// A magnifying glass over the map, with sub-cell terrain synthesized on the fly for the cells
+// under it. The synthesized terrain is kept while the lens stays over the same cell.
+pub(crate) struct DetailLens {
+    key: Option<(CellId, u64)>,
+    // In map units, y downwards like the cell centers
+    triangles: Vec<([mq::Vec2; 3], mq::Color)>,
+}
+
+impl DetailLens {
+    pub fn new() -> Self {
+        Self { key: None, triangles: vec![] }
+    }
+
+    // The world changed, so the synthesized terrain is stale
+    pub fn invalidate(&mut self) {
+        self.key = None;
+    }
+
+    // Synthesizes the terrain around the map point, unless it already was
+    pub fn update(&mut self, world: &World, seed: u64, x: f64, y: f64, map_per_pixel: f32) {
+        let center = match world.poly().cell_at(x, y) {
+            Some(cell) => cell,
+            None => return,
+        };
+        if self.key == Some((center, seed)) {
+            return;
+        }
+        self.key = Some((center, seed));
+
+        // Enough cells to fill the lens wherever the pointer is within the center cell
+        let reach = (RADIUS * map_per_pixel / ZOOM) as f64 * 1.5;
+        let (cx, cy) = world.poly()[center].center();
+        let seeds = SeedTree::new(seed);
+        let sea_level = world.sea_level();
+        let poly = world.poly();
+        self.triangles = poly.cells_in_rect((cx - reach, cy - reach), (cx + reach, cy + reach)).into_iter()
+            .filter(|&id| {
+                let (x, y) = poly[id].center();
+                (x - cx).powi(2) + (y - cy).powi(2) <= reach * reach
+            })
+            .flat_map(|id| detail::synthesize(world, id, &seeds, LEVELS))
+            .map(|triangle| {
+                let points = triangle.map(|(x, y, _)| mq::Vec2::new(x as f32, y as f32));
+                (points, shade(&triangle, sea_level))
+            })
+            .collect();
+    }
+
+    // Centered on the pointer, at the given screen position
+    pub fn draw(&self, screen: mq::Vec2, map: mq::Vec2, map_per_pixel: f32) {
+        mq::draw_circle(screen.x, screen.y, RADIUS, mq::BLACK);
+        let scale = ZOOM / map_per_pixel;
+        for (points, color) in self.triangles.iter() {
+            let points = points.map(|point| screen + (point - map) * scale);
+            let centroid = (points[0] + points[1] + points[2]) / 3.0;
+            if centroid.distance(screen) <= RADIUS {
+                mq::draw_triangle(points[0], points[1], points[2], *color);
+            }
+        }
+        mq::draw_circle_lines(screen.x, screen.y, RADIUS, 2.0, mq::WHITE);
+    }
+}
+
+// The colors of the geography view, lit by the slope of the triangle
+fn shade(triangle: &[DetailPoint; 3], sea_level: f64) -> mq::Color {
+    let height = triangle.iter().map(|&(_, _, h)| h).sum::<f64>() / 3.0;
+    let color = if height < sea_level {
+        colors::interpolate_colors(mq::DARKBLUE, mq::BLUE, (height / sea_level) as f32)
+    } else {
+        colors::interpolate_three_colors(mq::GREEN, mq::BROWN, mq::WHITE, ((height - 0.5) * 2.0) as f32)
+    };
+    if height < sea_level {
+        return color;
+    }
+
+    // The y of the points grows southwards
+    let [(x0, y0, h0), (x1, y1, h1), (x2, y2, h2)] = *triangle;
+    let u = mq::Vec3::new((x1 - x0) as f32, (y0 - y1) as f32, (h1 - h0) as f32 * EXAGGERATION);
+    let v = mq::Vec3::new((x2 - x0) as f32, (y0 - y2) as f32, (h2 - h0) as f32 * EXAGGERATION);
+    let mut normal = u.cross(v).normalize_or_zero();
+    if normal.z < 0.0 {
+        normal = -normal;
+    }
+    let light = mq::Vec3::new(LIGHT.0, LIGHT.1, LIGHT.2).normalize();
+    let brightness = 0.4 + 0.6 * normal.dot(light).max(0.0);
+    mq::Color::new(color.r * brightness, color.g * brightness, color.b * brightness, 1.0)
+}
@@ -1,106 +1,1167 @@
 use macroquad::prelude as mq;
 use macroquad::prelude::{KeyCode, MouseButton};
 
+use accessibility::Palette;
+use effects::Lighting;
+use color_grade::{Climate, ColorGrade};
 use gui::GuiEvent;
 use painter::ViewMode;
 use polymap::PolyMap;
-use rand::{Rng, SeedableRng};
+use rand::Rng;
+use share::ShareString;
+use strum::IntoEnumIterator;
+use world::constraint::ConstraintField;
+use world::grid::{self, GridSpec};
+use world::{gazetteer, sql, traversal};
+use world::hypsometry::{HypsometricCurve, Normalization};
+use world::mask::MaskImage;
+use world::model::Statistics;
+use world::params::Param;
+use world::summary::Summary;
+use world::generation::Generation;
+use world::seam::{Seam, Side};
+use world::seed::{self, SeedTree};
+use world::stamp::Stamp;
+use world::symmetry::Symmetry;
+use world::terrain_noise::NoiseLayer;
+use world_file::WorldFile;
 
+mod accessibility;
+mod bookmarks;
+mod camera;
+mod session;
+mod config;
+mod effects;
+mod glyphs;
+mod labels;
+mod lod;
+mod color_grade;
 mod gui;
-mod tessellation;
+mod histogram;
+mod inspector;
+mod jobs;
+mod lens;
+mod map_size;
+mod raster;
+pub mod tessellation;
+mod texture;
+mod vector_field;
+mod view_settings;
+mod mesh;
 mod painter;
+mod recorder;
+mod share;
+mod sketch;
+mod stamp_tool;
+mod tooltip;
+mod world_file;
 
 
 const WIDTH: i32 = 1600;
 const HEIGHT: i32 = 900;
+const CIVILIZATION_PATH: &str = "civilization.tsv";
+const BOOKMARKS_PATH: &str = "bookmarks.json";
+const SESSION_PATH: &str = "session.json";
+const WORLD_PATH: &str = "world.json";
+const BOOKMARK_KEYS: [KeyCode; bookmarks::SLOTS] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+    KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+// Distance between opposite sides of the exported hexes, in map units
+const HEX_SIZE: f64 = 48.0;
+const DEFAULT_SEED: u64 = 27049319951022;
+// The cells of the renders when neither the arguments nor the config pick them, so that a seed
+// always renders the same image
+const DEFAULT_MAP_SEED: u64 = 1;
 
 pub fn main() {
-    let mut config = mq::Conf::default();
-    config.high_dpi = true;
-    config.window_width = WIDTH;
-    config.window_height = HEIGHT;
-    config.window_title = "Worldgen".to_owned();
+    tracing_subscriber::fmt::init();
+    let arguments = parse_arguments();
+    if let Some(render) = arguments.render.as_ref() {
+        if let Err(err) = render_headless(&arguments, render) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
+    if let Some(replay) = arguments.replay.as_ref() {
+        if let Err(err) = replay_headless(&arguments, replay) {
+            eprintln!("{}", err);
+        }
+        return;
+    }
 
+    let config = config::Config::load("config.toml");
+    let mut conf = mq::Conf::default();
+    conf.high_dpi = true;
+    conf.window_width = WIDTH;
+    conf.window_height = HEIGHT;
+    // The window can't be renamed once open, so the title only has what stays the same: the
+    // config, with the seed in the share string of the toolbox
+    conf.window_title = format!("Worldgen - config {:08x}", config.hash);
 
-    macroquad::Window::from_config(config, async {
-        let mut seed = 27049319951022;
 
-        
-        let screen_scale_x = WIDTH as f32 / mq::screen_width();
-        let screen_scale_y = HEIGHT as f32 / mq::screen_height();
+    macroquad::Window::from_config(conf, async move {
+        let mut seed = arguments.seed.as_deref().map(seed::seed_from_text).unwrap_or(DEFAULT_SEED);
+        let mut seed_text = arguments.seed.as_deref().and_then(seed_words);
+
+        let mask = match config.mask.path.as_ref() {
+            Some(path) => match mq::load_file(path).await.map_err(|err| format!("{:?}", err))
+                .and_then(|bytes| raster::decode(&bytes))
+                .and_then(|image| MaskImage::from_rgba(image.width(), image.height(), &image.bytes)) {
+                Ok(mask) => Some(mask),
+                Err(err) => {
+                    eprintln!("Failed to load mask {}: {}", path, err);
+                    None
+                }
+            },
+            None => None,
+        };
 
-        let poly = PolyMap::new(1600, 900, 8.0);
-        let mut world = world::World::new(&poly);
-        world.generate(&mut rand::rngs::SmallRng::seed_from_u64(seed));
+        // Without a seed in the config the cells are new on every start, but the session
+        // still needs the seed to build them again
+        let mut map_seed = arguments.map_seed.or(config.world.map_seed).unwrap_or_else(|| rand::thread_rng().gen());
+        let mut map_size = arguments.size;
+        let built = map_size.build(map_seed).unwrap_or_else(|err| {
+            eprintln!("{}, using the default size", err);
+            map_size = map_size::MapSize::default();
+            map_size.build(map_seed).expect("The default map always has cells")
+        });
+        let mut map_draft = map_size;
+        let mut map_build: Option<map_size::MapBuild> = None;
+        // A generation asked for by the last frame, started on a worker thread at the next
+        let mut generating: Option<Generation> = None;
+        // The generation under way, with the stage it started at. It runs on a copy of the
+        // world, which replaces the world once done, so that the frames keep coming.
+        let mut regeneration: Option<(&'static str, WorldJob)> = None;
+        // Whether the map being built gets the current world, rather than a generated one
+        let mut baking = false;
+        // The world borrows its map for as long as it lives, from the slot
+        let mut maps = map_size::MapSlot::new(built);
+        let mut poly = maps.get();
+
+        // The whole map is stretched over the window until zoomed in
+        let mut camera = camera::MapCamera::new(poly);
+
+        let mut world = new_world(poly, &config, mask.as_ref());
+        world.generate(&SeedTree::new(seed));
+        // Share strings only carry the parameters moved away from these
+        let default_params = Param::ALL.map(|param| (param, world.param(param)));
+        // The world of another seed, which the world of the seed is blended with
+        // Another take on the world of the seed, with the same continents
+        let mut variant: Option<u64> = None;
+        let mut blend_partner: Option<world::World> = None;
+        let mut blend_amount = 0.5;
 
         let mut view_mode = ViewMode::Geography;
+        let mut debug_views = false;
+        let mut view_settings = view_settings::ViewSettings::new();
+        let mut vector_style = vector_field::VectorFieldStyle::default();
+        let mut effects = config.rendering.effects();
+        let mut palette = config.rendering.palette();
+        let mut grade = ColorGrade::NEUTRAL;
+        let mut lighting = Lighting::default();
+        let mut season = None;
+        let mut textures = config.rendering.textures;
         let mut dirty = true;
 
-        let mut painter = painter::Painter::new(&poly);
+        let mut histograms = histogram::Histograms::of_world(&world);
+        let mut labels = vec![];
+        let mut placed_glyphs = vec![];
+        // The labels and glyphs depend on the scale, so they are placed again on zooming too
+        let mut relabel = true;
+
+        let mut painter = painter::Painter::new(poly, config.rendering.tessellation(), config.rendering.cache.as_deref());
+        painter.set_textures(config.rendering.textures);
 
         let mut show_gui = false;
+        let mut share_input = String::new();
+        let mut seed_input = String::new();
+        let mut inspector = inspector::Inspector::default();
+        let mut lens = lens::DetailLens::new();
+        let mut tooltip = tooltip::Tooltip::new();
+        // Whether the toolbox took the keyboard on the last frame
+        let mut typing = false;
+        let mut bookmarks = bookmarks::Bookmarks::load(BOOKMARKS_PATH);
+        let mut sessions = session::SessionManager::new(SESSION_PATH);
+        if sessions.previous().is_some() {
+            show_gui = true;
+        }
+        // Quitting saves the session first
+        mq::prevent_quit();
+        let mut sketch = sketch::Sketch::new();
+        let mut stamp_tool = stamp_tool::StampTool::new(Stamp::builtins());
+        if let Some(dir) = config.stamps.path.as_ref() {
+            stamp_tool.load_content_pack(dir);
+        }
+
+        let mut recorder = config.recording.record.as_ref().and_then(|path| {
+            recorder::Recorder::create(path)
+                .map_err(|err| eprintln!("Failed to create recording {}: {}", path, err))
+                .ok()
+        });
+        let mut replay = config.recording.replay.as_ref().and_then(|path| {
+            recorder::Replay::load(path).map_err(|err| eprintln!("{}", err)).ok()
+        });
+        if let Some(dir) = config.recording.frames.as_ref() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                eprintln!("Failed to create {}: {}", dir, err);
+            }
+        }
 
 
         loop {
 
+            if let Some(build) = map_build.as_mut() {
+                match build.poll() {
+                    Some(Ok(built)) => {
+                        map_size = build.size();
+                        // Safety: the worlds on the old map are all gone before it is dropped below
+                        let old_map = unsafe { maps.replace(built) };
+                        poly = maps.get();
+                        camera = camera::MapCamera::new(poly);
+                        let mut built_world = new_world(poly, &config, mask.as_ref());
+                        if baking {
+                            built_world.bake(&world, config.world.bake_detail.unwrap_or(0.0), &SeedTree::new(seed));
+                        } else {
+                            generating = Some(generation(seed, variant));
+                        }
+                        world = built_world;
+                        // Whatever was under way was for the old map
+                        if let Some((_, job)) = regeneration.take() {
+                            job.cancel();
+                            let _ = job.wait();
+                        }
+                        blend_partner = None;
+                        drop(old_map);
+                        painter = painter::Painter::new(poly, config.rendering.tessellation(), config.rendering.cache.as_deref());
+                        painter.set_season(season);
+                        painter.set_textures(textures);
+                        inspector.inspect(&world, None);
+                        sketch.clear();
+                        map_build = None;
+                        dirty = true;
+                    }
+                    Some(Err(err)) => {
+                        eprintln!("{}", err);
+                        map_build = None;
+                    }
+                    None => {}
+                }
+            }
+
+            if let Some(mut staged) = generating.take() {
+                // The stages the generation under way was to run are run again by the new one, and
+                // dropping it cancels it
+                if let Some((first, _)) = regeneration.take() {
+                    staged.rewind(first);
+                }
+                regeneration = Some((staged.first_stage(), regenerate(&world, staged)));
+            }
+            if let Some((_, job)) = regeneration.as_mut() {
+                if let Some(done) = job.poll() {
+                    regeneration = None;
+                    match done {
+                        Ok(generated) => {
+                            world = generated;
+                            inspector.refresh(&world);
+                            dirty = true;
+                        }
+                        Err(err) => eprintln!("{}", err),
+                    }
+                }
+            }
+
             if dirty {
-                painter.update(&world, view_mode);
+                painter.update(&world, view_mode, &view_settings.get(view_mode), &vector_style, palette);
+                grade = config.grading.grade(Climate::of_world(&world));
+                histograms = histogram::Histograms::of_world(&world);
+                lens.invalidate();
                 dirty = false;
+                relabel = true;
+            }
+            if relabel {
+                labels = if view_mode.shows_glyphs() { labels::labels(&world, &config.lod, camera.scale().0) } else { vec![] };
+                placed_glyphs = if view_mode.shows_glyphs() { glyphs::place(&glyphs::glyphs(&world), &config.lod, camera.scale().0) } else { vec![] };
+                relabel = false;
             }
 
             mq::clear_background(mq::WHITE);
 
-            painter.draw();
+            painter.draw(&effects, &lighting, &grade, camera.visible());
+            glyphs::draw(&placed_glyphs, &camera, effects.glyph_shadows);
+            labels::draw(&labels, &camera);
+            if let Some(build) = map_build.as_ref() {
+                let text = format!("Building a map of {} cells... {:.1} s", build.size().cells, build.elapsed().as_secs_f32());
+                mq::draw_rectangle(10.0, 10.0, 420.0, 36.0, mq::Color::new(0.0, 0.0, 0.0, 0.6));
+                mq::draw_text(&text, 20.0, 34.0, 24.0, mq::WHITE);
+                jobs::draw_spinner(410.0, 28.0, 9.0);
+            }
+            if let Some((_, job)) = regeneration.as_ref() {
+                let (stage, progress) = job.progress().copied().unwrap_or(("world", 0.0));
+                let text = format!("Generating the {}... {:.1} s", stage, job.elapsed().as_secs_f32());
+                mq::draw_rectangle(10.0, 10.0, 420.0, 36.0, mq::Color::new(0.0, 0.0, 0.0, 0.6));
+                mq::draw_rectangle(10.0, 42.0, 420.0 * progress, 4.0, mq::WHITE);
+                mq::draw_text(&text, 20.0, 34.0, 24.0, mq::WHITE);
+                jobs::draw_spinner(410.0, 28.0, 9.0);
+            }
+            sketch.draw(&camera);
+            {
+                let (smx, smy) = mq::mouse_position();
+                let (scale_x, scale_y) = camera.scale();
+                stamp_tool.draw_preview(smx, smy, scale_x, scale_y);
+
+                // Holding Z looks closer at the terrain under the pointer
+                if !typing && mq::is_key_down(KeyCode::Z) {
+                    let (mx, my) = camera.to_map(smx, smy);
+                    lens.update(&world, seed, mx as f64, my as f64, scale_x);
+                    lens.draw(mq::Vec2::new(smx, smy), mq::Vec2::new(mx, my), scale_x);
+                } else {
+                    tooltip.draw(&world, view_mode, season, smx, smy);
+                }
+            }
 
 
+            let mut events = vec![];
             let mut block_clicks = false;
+            typing = false;
             if show_gui {
-                let (hovered, events) = gui::gui(seed, view_mode);
+                let params = Param::ALL.map(|param| (param, world.param(param)));
+                let share = ShareString::new(seed, config.hash, Some(world.stage_qualities()))
+                    .with_params(&params, &default_params)
+                    .encode();
+                let state = gui::GuiState {
+                    seed,
+                    seed_text: seed_text.as_deref(),
+                    map_size,
+                    building_map: map_build.is_some(),
+                    previous_session: sessions.previous(),
+                    share: &share,
+                    view_mode,
+                    debug_views,
+                    view_tuning: view_settings.get(view_mode),
+                    vector_style,
+                    effects,
+                    palette,
+                    lighting,
+                    sketch_mode: sketch.mode(),
+                    season,
+                    textures,
+                    stamp_tool: &stamp_tool,
+                    report: world.report(),
+                    quality: world.quality(),
+                    river_cutoff: world.river_cutoff(),
+                    sea_level: world.sea_level(),
+                    levels: world.levels(),
+                    params: &params,
+                    histograms: &histograms,
+                    variant,
+                    blend_amount,
+                    blending: blend_partner.is_some(),
+                };
+                let (hovered, keyboard, gui_events) = gui::gui(&state, &mut seed_input, &mut share_input, &mut map_draft, &mut inspector);
                 block_clicks = hovered;
-                for event in events {
-                    match event {
-                        GuiEvent::Close => {
-                            show_gui = false;
+                typing = keyboard;
+                events.extend(gui_events);
+            }
+
+            if !block_clicks {
+                if camera.handle_input() {
+                    relabel = true;
+                }
+                let (smx, smy) = mq::mouse_position();
+                let (mx, my) = camera.to_map(smx, smy);
+
+                let resting = sketch.mode().is_none() && !stamp_tool.is_active();
+                tooltip.hover(if resting { poly.cell_at(mx as f64, my as f64) } else { None });
+
+                if sketch.handle_mouse(mx as f64, my as f64) {
+                    // The sketching tool owns the mouse
+                } else if stamp_tool.is_copying() {
+                    if mq::is_mouse_button_pressed(MouseButton::Left) {
+                        events.push(GuiEvent::CopyRegion(mx as f64, my as f64));
+                    }
+                } else if stamp_tool.is_active() {
+                    if mq::is_mouse_button_pressed(MouseButton::Left) {
+                        events.push(GuiEvent::PlaceStamp(mx as f64, my as f64));
+                    }
+                } else if mq::is_mouse_button_pressed(MouseButton::Left) {
+                    events.push(GuiEvent::Inspect(mx as f64, my as f64));
+                }
+            } else {
+                tooltip.hover(None);
+            }
+
+            if !typing {
+                if mq::is_key_pressed(KeyCode::Space) {
+                    show_gui = !show_gui;
+                }    
+                
+                if mq::is_key_pressed(KeyCode::R) {
+                    events.push(GuiEvent::Regenerate(rand::thread_rng().gen()));
+                }        
+
+                if mq::is_key_pressed(KeyCode::V) {
+                    events.push(GuiEvent::SetVariant(Some(rand::thread_rng().gen())));
+                }
+
+                if mq::is_key_pressed(KeyCode::Key0) {
+                    camera.reset();
+                    relabel = true;
+                }
+
+                // A number key brings back the world, view and camera of its slot, with shift
+                // it saves them there
+                let shift = mq::is_key_down(KeyCode::LeftShift) || mq::is_key_down(KeyCode::RightShift);
+                for (slot, &key) in BOOKMARK_KEYS.iter().enumerate() {
+                    if !mq::is_key_pressed(key) {
+                        continue;
+                    }
+                    if shift {
+                        bookmarks.set(slot, bookmarks::Bookmark::new(seed, view_mode, camera.view()));
+                        println!("Bookmarked seed {} in slot {}", seed, slot + 1);
+                    } else if let Some(bookmark) = bookmarks.get(slot) {
+                        if bookmark.seed != seed {
+                            events.push(GuiEvent::Regenerate(bookmark.seed));
                         }
-                        GuiEvent::SetViewMode(mode) => {
-                            view_mode = mode;
-                            dirty = true;
+                        if let Some(mode) = bookmark.view_mode() {
+                            events.push(GuiEvent::SetViewMode(mode));
+                        }
+                        if let Some(view) = bookmark.camera {
+                            camera.set_view(view);
+                            relabel = true;
                         }
                     }
                 }
             }
 
-            if !block_clicks {
-                let (smx, smy) = mq::mouse_position();
-                // Scale th mouse coordinate appropriately
-                let mx = screen_scale_x * smx;
-                let my = screen_scale_y * smy;
+            // While replaying, the recording takes the place of the user. It holds off while a map
+            // is built, as the events after were recorded on the built map.
+            if let Some(replay) = replay.as_mut() {
+                events = if map_build.is_none() { replay.next_frame() } else { vec![] };
+            }
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&events, sketch.strokes());
+            }
 
-                if mq::is_mouse_button_pressed(MouseButton::Left) {
-                    if let Some(clicked_poly) = poly.cell_at(mx as f64, my as f64) {
-                        println!("Clicked cell:{}", clicked_poly.idx())
+            // Events acting on the world wait for the generation under way, so that they act on
+            // the world it makes
+            if events.iter().any(GuiEvent::acts_on_world) {
+                if let Some((_, job)) = regeneration.take() {
+                    match job.wait() {
+                        Ok(generated) => {
+                            world = generated;
+                            inspector.refresh(&world);
+                            dirty = true;
+                        }
+                        Err(err) => eprintln!("{}", err),
+                    }
+                }
+            }
+            for event in events {
+                match event {
+                    GuiEvent::Close => {
+                        show_gui = false;
+                    }
+                    GuiEvent::LoadShareString(text) => {
+                        match ShareString::decode(&text) {
+                            Ok(shared) => {
+                                for mismatch in shared.mismatches(config.hash) {
+                                    eprintln!("Warning: {}", mismatch);
+                                }
+                                seed = shared.seed;
+                                seed_text = None;
+                                variant = None;
+                                generating = Some(shared.generation(&mut world, &default_params));
+                            }
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
+                    GuiEvent::SetSeedText(text) => {
+                        seed = seed::seed_from_text(&text);
+                        seed_text = seed_words(&text);
+                        variant = None;
+                        generating = Some(generation(seed, variant));
+                    }
+                    GuiEvent::BlendSeedText(text) => {
+                        let mut partner = new_world(poly, &config, mask.as_ref());
+                        partner.generate(&SeedTree::new(seed::seed_from_text(&text)));
+                        world = blended(poly, &config, mask.as_ref(), seed, variant, &partner, blend_amount);
+                        blend_partner = Some(partner);
+                        inspector.refresh(&world);
+                        dirty = true;
+                    }
+                    GuiEvent::SetBlendAmount(amount) => {
+                        blend_amount = amount;
+                        if let Some(partner) = blend_partner.as_ref() {
+                            world = blended(poly, &config, mask.as_ref(), seed, variant, partner, blend_amount);
+                            inspector.refresh(&world);
+                            dirty = true;
+                        }
+                    }
+                    GuiEvent::RebuildMap(size) => {
+                        if map_build.is_none() {
+                            map_build = Some(map_size::MapBuild::start(size, map_seed));
+                            baking = false;
+                        }
+                    }
+                    GuiEvent::BakeMap(size) => {
+                        if map_build.is_none() {
+                            map_build = Some(map_size::MapBuild::start(size, map_seed));
+                            baking = true;
+                        }
+                    }
+                    GuiEvent::RestoreSession => {
+                        if let Some(previous) = sessions.take_previous() {
+                            for mismatch in ShareString::new(previous.seed, previous.config_hash, None).mismatches(config.hash) {
+                                eprintln!("Warning: {}", mismatch);
+                            }
+                            let mut old_map = None;
+                            if previous.map_size != map_size || previous.map_seed != map_seed {
+                                match previous.map_size.build(previous.map_seed) {
+                                    Ok(built) => {
+                                        map_size = previous.map_size;
+                                        map_draft = map_size;
+                                        map_seed = previous.map_seed;
+                                        // Safety: the worlds on the old map are all gone before it is dropped below
+                                        old_map = Some(unsafe { maps.replace(built) });
+                                        poly = maps.get();
+                                        camera = camera::MapCamera::new(poly);
+                                        painter = painter::Painter::new(poly, config.rendering.tessellation(), config.rendering.cache.as_deref());
+                                        painter.set_season(season);
+                                        painter.set_textures(textures);
+                                        sketch.clear();
+                                    }
+                                    Err(err) => eprintln!("{}", err),
+                                }
+                            }
+                            seed = previous.seed;
+                            seed_text = previous.seed_text.clone();
+                            variant = None;
+                            world = new_world(poly, &config, mask.as_ref());
+                            for (param, value) in previous.params() {
+                                world.set_param(param, value);
+                            }
+                            world.generate(&SeedTree::new(seed));
+                            if let Err(err) = world.import_civilization(&previous.civilization) {
+                                eprintln!("{}", err);
+                            }
+                            if let Some((_, job)) = regeneration.take() {
+                                job.cancel();
+                                let _ = job.wait();
+                            }
+                            blend_partner = None;
+                            drop(old_map);
+                            view_mode = previous.view_mode().unwrap_or(view_mode);
+                            debug_views = previous.debug_views;
+                            previous.apply_tunings(&mut view_settings);
+                            vector_style = previous.vector_style;
+                            effects = previous.effects;
+                            lighting = previous.lighting;
+                            if let Some(view) = previous.camera {
+                                camera.set_view(view);
+                                relabel = true;
+                            }
+                            inspector.inspect(&world, None);
+                            dirty = true;
+                        }
+                    }
+                    GuiEvent::DiscardSession => {
+                        sessions.take_previous();
+                    }
+                    GuiEvent::SetViewMode(mode) => {
+                        view_mode = mode;
+                        dirty = true;
+                    }
+                    GuiEvent::SetDebugViews(enabled) => {
+                        debug_views = enabled;
+                        if !debug_views && view_mode.is_debug() {
+                            view_mode = ViewMode::Geography;
+                            dirty = true;
+                        }
+                    }
+                    GuiEvent::SetViewTuning(mode, tuning) => {
+                        view_settings.set(mode, tuning);
+                        dirty = true;
+                    }
+                    GuiEvent::SetVectorStyle(style) => {
+                        vector_style = style;
+                        dirty = true;
+                    }
+                    GuiEvent::SetEffects(chosen) => {
+                        effects = chosen;
+                    }
+                    GuiEvent::SetColorBlindSafe(safe) => {
+                        palette = if safe { Palette::ColorBlindSafe } else { Palette::Standard };
+                        dirty = true;
+                    }
+                    GuiEvent::SetLighting(chosen) => {
+                        lighting = chosen;
+                    }
+                    GuiEvent::SetSketchMode(mode) => {
+                        sketch.set_mode(mode);
+                        stamp_tool.select(None);
+                    }
+                    GuiEvent::SetTextures(chosen) => {
+                        textures = chosen;
+                        painter.set_textures(textures);
+                        dirty = true;
+                    }
+                    GuiEvent::SetSeason(chosen) => {
+                        season = chosen;
+                        painter.set_season(season);
+                        dirty = true;
+                    }
+                    GuiEvent::SetSketch(strokes) => {
+                        sketch.set_strokes(strokes);
+                    }
+                    GuiEvent::ApplySketch => {
+                        world.set_constraints(ConstraintField::from_strokes(poly, sketch.strokes()));
+                        generating = Some(generation(seed, variant));
+                    }
+                    GuiEvent::ClearSketch => {
+                        sketch.clear();
+                    }
+                    GuiEvent::SelectStamp(selected) => {
+                        stamp_tool.select(selected);
+                        sketch.set_mode(None);
+                    }
+                    GuiEvent::StartCopy => {
+                        stamp_tool.start_copying();
+                        sketch.set_mode(None);
+                    }
+                    GuiEvent::CopyRegion(x, y) => {
+                        stamp_tool.add_copy(&world, x, y);
+                    }
+                    GuiEvent::SetTerrainLevels(levels) => {
+                        world.set_levels(levels);
+                        inspector.refresh(&world);
+                        dirty = true;
+                    }
+                    GuiEvent::SetRiverCutoff(cutoff) => {
+                        world.set_river_cutoff(cutoff);
+                        inspector.refresh(&world);
+                        dirty = true;
+                    }
+                    GuiEvent::SetStampRadius(radius) => {
+                        stamp_tool.set_radius(radius);
+                    }
+                    GuiEvent::SetStampRotation(rotation) => {
+                        stamp_tool.set_rotation(rotation);
+                    }
+                    GuiEvent::Export => {
+                        let bins = GridSpec::hex(HEX_SIZE).bin(&world);
+                        let places = gazetteer::entries(&world);
+                        let files = [
+                            ("hexes.csv", grid::to_csv(&bins)),
+                            ("hexes.json", grid::to_json(&bins)),
+                            ("travel.csv", traversal::travel_table_csv(&world)),
+                            ("gazetteer.csv", gazetteer::to_csv(&places)),
+                            ("gazetteer.json", gazetteer::to_json(&places)),
+                            ("statistics.txt", Statistics::of(&world).to_text()),
+                            ("summary.txt", Summary::of(&world).to_text()),
+                            ("world.sql", sql::to_sql(&world)),
+                            ("glyphs.svg", glyphs::to_svg(
+                                &glyphs::place(&glyphs::glyphs(&world), &config.lod, 1.0),
+                                &labels::labels(&world, &config.lod, 1.0),
+                                poly.width(), poly.height(), 1.0,
+                            )),
+                        ];
+                        let seams = Side::ALL.map(|side| (format!("seam-{}.txt", side.name()), world.export_seam(side).to_text()));
+                        let files = files.into_iter().map(|(path, contents)| (path.to_owned(), contents)).chain(seams);
+                        for (path, contents) in files {
+                            match std::fs::write(&path, contents) {
+                                Ok(()) => println!("Exported {}", path),
+                                Err(err) => eprintln!("Failed to write {}: {}", path, err),
+                            }
+                        }
+                        #[cfg(feature = "sqlite")]
+                        match sql::write_sqlite(&world, "world.sqlite") {
+                            Ok(()) => println!("Exported world.sqlite"),
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
+                    GuiEvent::ExportCivilization => {
+                        match std::fs::write(CIVILIZATION_PATH, world.export_civilization()) {
+                            Ok(()) => println!("Exported {}", CIVILIZATION_PATH),
+                            Err(err) => eprintln!("Failed to write {}: {}", CIVILIZATION_PATH, err),
+                        }
+                    }
+                    GuiEvent::ImportCivilization => {
+                        let imported = std::fs::read_to_string(CIVILIZATION_PATH)
+                            .map_err(|err| format!("Failed to read {}: {}", CIVILIZATION_PATH, err))
+                            .and_then(|text| world.import_civilization(&text));
+                        match imported {
+                            Ok(0) => {}
+                            Ok(lost) => eprintln!("Warning: {} rivers and passes are no longer in the world", lost),
+                            Err(err) => eprintln!("{}", err),
+                        }
+                        inspector.refresh(&world);
+                        dirty = true;
+                    }
+                    GuiEvent::SaveWorld => {
+                        let file = WorldFile::new(seed, config.hash, world.save());
+                        let saved = file.encode()
+                            .and_then(|json| std::fs::write(WORLD_PATH, json).map_err(|err| err.to_string()));
+                        match saved {
+                            Ok(()) => println!("Saved {}", WORLD_PATH),
+                            Err(err) => eprintln!("Failed to write {}: {}", WORLD_PATH, err),
+                        }
+                    }
+                    GuiEvent::LoadWorld => {
+                        let loaded = std::fs::read_to_string(WORLD_PATH)
+                            .map_err(|err| format!("Failed to read {}: {}", WORLD_PATH, err))
+                            .and_then(|json| WorldFile::decode(&json)
+                                .map_err(|err| format!("Failed to parse {}: {}", WORLD_PATH, err)))
+                            .and_then(|file| {
+                                for mismatch in file.mismatches(config.hash) {
+                                    eprintln!("Warning: {}", mismatch);
+                                }
+                                world.restore(file.world).map(|()| file.seed)
+                            });
+                        match loaded {
+                            Ok(loaded_seed) => {
+                                seed = loaded_seed;
+                                seed_text = None;
+                                variant = None;
+                                inspector.refresh(&world);
+                                dirty = true;
+                            }
+                            Err(err) => eprintln!("{}", err),
+                        }
+                    }
+                    GuiEvent::PlaceStamp(x, y) => {
+                        if let Some((stamp, placement)) = stamp_tool.placement_at(x, y) {
+                            world.apply_stamp(stamp, placement);
+                            inspector.refresh(&world);
+                            dirty = true;
+                        }
+                    }
+                    GuiEvent::SetVariant(new_variant) => {
+                        variant = new_variant;
+                        generating = Some(generation(seed, variant));
+                    }
+                    GuiEvent::SetParam(param, value) => {
+                        world.set_param(param, value);
+                        // Only the stages from that of the parameter on run again
+                        let stage = param.info().stage;
+                        match generating.as_mut() {
+                            Some(staged) => staged.rewind(stage),
+                            None => generating = Some(generation(seed, variant).starting_at(stage)),
+                        }
+                    }
+                    GuiEvent::Regenerate(new_seed) => {
+                        seed = new_seed;
+                        seed_text = None;
+                        variant = None;
+                        generating = Some(generation(seed, variant));
+                    }
+                    GuiEvent::Inspect(x, y) => {
+                        let clicked = poly.cell_at(x, y);
+                        inspector.inspect(&world, clicked);
+                    }
+                    GuiEvent::CloseInspector => {
+                        inspector.inspect(&world, None);
+                    }
+                    GuiEvent::SetCellNote(text) => {
+                        if let Some(cell) = inspector.cell() {
+                            world.set_note(cell, &text);
+                            inspector.refresh(&world);
+                            dirty = true;
+                        }
+                    }
+                    GuiEvent::SetFeatureNote(id, text) => {
+                        world.set_feature_note(id, &text);
+                        inspector.refresh(&world);
+                        dirty = true;
                     }
                 }
             }
 
-            if mq::is_key_pressed(KeyCode::Space) {
-                show_gui = !show_gui;
-            }    
-            
-            if mq::is_key_pressed(KeyCode::R) {
-                seed = rand::thread_rng().gen();
-                world.generate(&mut rand::rngs::SmallRng::seed_from_u64(seed));         
-                dirty = true;
-            }        
-                
+            if let Some(replay) = replay.as_ref() {
+                if let Some(dir) = config.recording.frames.as_ref() {
+                    let path = format!("{}/frame_{:05}.png", dir, replay.frame());
+                    if let Err(err) = raster::write_png(&mq::get_screen_data(), &path) {
+                        eprintln!("{}", err);
+                    }
+                }
+                if replay.is_finished() && config.recording.frames.is_some() {
+                    break;
+                }
+            }
+
             if !block_clicks {
                 if mq::is_mouse_button_pressed(MouseButton::Left) {
                     
                 }
             }
 
+            let quitting = mq::is_quit_requested();
+            if quitting || sessions.is_due() {
+                sessions.save(&session::Session {
+                    seed,
+                    seed_text: seed_text.clone(),
+                    config_hash: config.hash,
+                    map_size,
+                    map_seed,
+                    civilization: world.export_civilization(),
+                    params: Param::ALL.iter().map(|&param| (param.info().key.to_owned(), world.param(param))).collect(),
+                    camera: Some(camera.view()),
+                    view: view_mode.name().to_owned(),
+                    debug_views,
+                    view_tunings: view_settings.changed(),
+                    vector_style,
+                    effects,
+                    lighting,
+                });
+            }
+            if quitting {
+                break;
+            }
+
             mq::next_frame().await
         }
     });
 }
 
+type WorldJob = jobs::Job<world::World<'static>, (&'static str, f32)>;
+
+// Runs the generation on a copy of the world, reporting the stage it is at
+fn regenerate(world: &world::World<'static>, mut staged: Generation) -> WorldJob {
+    let mut world = world.clone();
+    jobs::Job::spawn(move |worker| {
+        // A superseded generation stops between stages
+        while let Some(stage) = staged.next_stage().filter(|_| !worker.is_cancelled()) {
+            worker.report((stage, staged.progress()));
+            staged.step(&mut world);
+        }
+        world
+    })
+}
+
+fn generation(seed: u64, variant: Option<u64>) -> Generation {
+    match variant {
+        Some(variant) => Generation::variant(SeedTree::new(seed), variant),
+        None => Generation::new(SeedTree::new(seed)),
+    }
+}
+
+// The world of the seed, blended towards the partner, generated again every time so that
+// the amount can be dragged back and forth
+fn blended<'a>(poly: &'a PolyMap, config: &config::Config, mask: Option<&MaskImage>, seed: u64, variant: Option<u64>,
+               partner: &world::World, amount: f64) -> world::World<'a> {
+    let mut world = new_world(poly, config, mask);
+    generation(seed, variant).finish(&mut world);
+    if let Err(err) = world.blend(partner, amount) {
+        eprintln!("{}", err);
+    }
+    world
+}
+
+fn new_world<'a>(poly: &'a PolyMap, config: &config::Config, mask: Option<&MaskImage>) -> world::World<'a> {
+    let symmetry = config.world.symmetry.as_deref()
+        .map(|name| name.parse().unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            Symmetry::None
+        }))
+        .unwrap_or_default();
+    let normalization = config.world.ocean_fraction
+        .map(Normalization::OceanFraction)
+        .unwrap_or_default();
+    let mut world = world::World::new(poly)
+        .with_symmetry(symmetry)
+        .with_normalization(normalization)
+        .with_relaxation(config.heightmap.relaxation())
+        .with_terracing(config.heightmap.terracing())
+        .with_classification_smoothing(config.world.classification_smoothing)
+        .with_sea_level(config.world.sea_level.unwrap_or(world::SEA_LEVEL))
+        .with_coast_band(config.world.coast_band.unwrap_or(1))
+        .with_quality(config.world.quality())
+        .with_temperature_scale(config.climate.temperature_scale())
+        .with_rainfall_scale(config.climate.rainfall_scale())
+        .with_wind(config.climate.wind_model(), config.climate.wind_direction)
+        .with_orbit(config.climate.orbit())
+        .with_river_cutoff(config.hydrology.river_cutoff.unwrap_or(world::RIVER_CUTOFF))
+        .with_noise(config.heightmap.low_noise.layer(NoiseLayer::LOW), config.heightmap.high_noise.layer(NoiseLayer::HIGH));
+    if let Some(bands) = config.world.hypsometry.as_ref() {
+        match HypsometricCurve::from_bands(bands) {
+            Ok(curve) => world = world.with_hypsometry(curve),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    if let Some(auto) = config.world.auto_levels.as_ref() {
+        match auto.levels() {
+            Ok(levels) => world = world.with_auto_levels(levels),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    if let Some(mask) = mask {
+        world = world.with_mask(mask);
+    }
+    let seams = config.world.seams.iter().flatten()
+        .filter_map(|path| {
+            std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to load seam {}: {}", path, err))
+                .and_then(|text| Seam::from_text(&text))
+                .map_err(|err| eprintln!("{}", err))
+                .ok()
+        })
+        .collect();
+    world.with_seams(seams)
+}
+
+// Generates the world and writes one view of it to a PNG image, without opening a window
+fn render_headless(arguments: &Arguments, render: &RenderArguments) -> Result<(), String> {
+    let mode = ViewMode::iter()
+        .find(|mode| mode.name().eq_ignore_ascii_case(&render.view))
+        .ok_or_else(|| format!("Unknown view '{}'", render.view))?;
+    let config = config::Config::load("config.toml");
+    let mask = headless_mask(&config)?;
+    let map_seed = arguments.map_seed.or(config.world.map_seed).unwrap_or(DEFAULT_MAP_SEED);
+    let poly = arguments.size.build(map_seed)?;
+    let mut world = new_world(&poly, &config, mask.as_ref());
+    let seed = arguments.seed.as_deref().map(seed::seed_from_text).unwrap_or(DEFAULT_SEED);
+    world.generate(&SeedTree::new(seed));
+
+    let width = render.width.unwrap_or(poly.width());
+    let height = render.height.unwrap_or(poly.height());
+    let image = raster::render(&world, mode, &view_settings::ViewTuning::default(), config.rendering.palette(), config.rendering.textures, width, height)?;
+    raster::write_png(&image, &render.path)?;
+    println!("Rendered {} of seed {} to {}", mode.name(), seed, render.path);
+    print!("{}", Summary::of(&world).to_text());
+    Ok(())
+}
+
+// Without the window, the mask file has to be there and be an image
+fn headless_mask(config: &config::Config) -> Result<Option<MaskImage>, String> {
+    match config.mask.path.as_ref() {
+        Some(path) => std::fs::read(path).map_err(|err| err.to_string())
+            .and_then(|bytes| raster::decode(&bytes))
+            .and_then(|image| MaskImage::from_rgba(image.width(), image.height(), &image.bytes))
+            .map(Some)
+            .map_err(|err| format!("Failed to load mask {}: {}", path, err)),
+        None => Ok(None),
+    }
+}
+
+// Plays a recording back without a window, saving an image per frame of the replay like the
+// application does, drawn like render_headless: the cells and the rivers of the view, without
+// the toolbox, the glyphs or the effects. The events which only change what the window shows,
+// or need it, are skipped and listed at the end.
+fn replay_headless(arguments: &Arguments, replay_arguments: &ReplayArguments) -> Result<(), String> {
+    let config = config::Config::load("config.toml");
+    let mut replay = recorder::Replay::load(&replay_arguments.path)?;
+    let frames = replay_arguments.frames.clone()
+        .or_else(|| config.recording.frames.clone())
+        .unwrap_or_else(|| "frames".to_owned());
+    std::fs::create_dir_all(&frames).map_err(|err| format!("Failed to create {}: {}", frames, err))?;
+
+    let mask = headless_mask(&config)?;
+    let map_seed = arguments.map_seed.or(config.world.map_seed).unwrap_or(DEFAULT_MAP_SEED);
+    let poly = arguments.size.build(map_seed)?;
+    let mut world = new_world(&poly, &config, mask.as_ref());
+    let mut seed = arguments.seed.as_deref().map(seed::seed_from_text).unwrap_or(DEFAULT_SEED);
+    let mut variant = None;
+    world.generate(&SeedTree::new(seed));
+    let default_params = Param::ALL.map(|param| (param, world.param(param)));
+
+    let mut view_mode = ViewMode::Geography;
+    let mut view_settings = view_settings::ViewSettings::new();
+    let mut palette = config.rendering.palette();
+    let mut textures = config.rendering.textures;
+    let mut stamp_tool = stamp_tool::StampTool::new(Stamp::builtins());
+    if let Some(dir) = config.stamps.path.as_ref() {
+        stamp_tool.load_content_pack(dir);
+    }
+    let mut strokes = vec![];
+    let mut skipped = std::collections::BTreeSet::new();
+
+    let width = replay_arguments.width.unwrap_or(poly.width());
+    let height = replay_arguments.height.unwrap_or(poly.height());
+    let mut image = None;
+    while !replay.is_finished() {
+        for event in replay.next_frame() {
+            let mut generating = None;
+            match event {
+                GuiEvent::LoadShareString(text) => {
+                    let shared = ShareString::decode(&text)?;
+                    for mismatch in shared.mismatches(config.hash) {
+                        eprintln!("Warning: {}", mismatch);
+                    }
+                    seed = shared.seed;
+                    variant = None;
+                    generating = Some(shared.generation(&mut world, &default_params));
+                }
+                GuiEvent::SetSeedText(text) => {
+                    seed = seed::seed_from_text(&text);
+                    variant = None;
+                    generating = Some(generation(seed, variant));
+                }
+                GuiEvent::Regenerate(new_seed) => {
+                    seed = new_seed;
+                    variant = None;
+                    generating = Some(generation(seed, variant));
+                }
+                GuiEvent::SetVariant(new_variant) => {
+                    variant = new_variant;
+                    generating = Some(generation(seed, variant));
+                }
+                GuiEvent::SetParam(param, value) => {
+                    world.set_param(param, value);
+                    generating = Some(generation(seed, variant).starting_at(param.info().stage));
+                }
+                GuiEvent::SetSketch(recorded) => strokes = recorded,
+                GuiEvent::ApplySketch => {
+                    world.set_constraints(ConstraintField::from_strokes(&poly, &strokes));
+                    generating = Some(generation(seed, variant));
+                }
+                GuiEvent::ClearSketch => strokes.clear(),
+                GuiEvent::SetViewMode(mode) => view_mode = mode,
+                GuiEvent::SetViewTuning(mode, tuning) => view_settings.set(mode, tuning),
+                GuiEvent::SetColorBlindSafe(safe) => {
+                    palette = if safe { Palette::ColorBlindSafe } else { Palette::Standard };
+                }
+                GuiEvent::SetTextures(chosen) => textures = chosen,
+                GuiEvent::SetTerrainLevels(levels) => world.set_levels(levels),
+                GuiEvent::SetRiverCutoff(cutoff) => world.set_river_cutoff(cutoff),
+                GuiEvent::SelectStamp(selected) => stamp_tool.select(selected),
+                GuiEvent::StartCopy => stamp_tool.start_copying(),
+                GuiEvent::CopyRegion(x, y) => stamp_tool.add_copy(&world, x, y),
+                GuiEvent::SetStampRadius(radius) => stamp_tool.set_radius(radius),
+                GuiEvent::SetStampRotation(rotation) => stamp_tool.set_rotation(rotation),
+                GuiEvent::PlaceStamp(x, y) => {
+                    if let Some((stamp, placement)) = stamp_tool.placement_at(x, y) {
+                        world.apply_stamp(stamp, placement);
+                    }
+                }
+                GuiEvent::SetFeatureNote(id, text) => world.set_feature_note(id, &text),
+                other => {
+                    skipped.insert(recorder::command(&other));
+                    continue;
+                }
+            }
+            if let Some(mut generation) = generating {
+                generation.finish(&mut world);
+            }
+            image = None;
+        }
+
+        let frame = match image.take() {
+            Some(frame) => frame,
+            None => raster::render(&world, view_mode, &view_settings.get(view_mode), palette, textures, width, height)?,
+        };
+        raster::write_png(&frame, &format!("{}/frame_{:05}.png", frames, replay.frame()))?;
+        image = Some(frame);
+    }
+    println!("Replayed {} frames of {} to {}", replay.frame(), replay_arguments.path, frames);
+    if !skipped.is_empty() {
+        let skipped: Vec<_> = skipped.into_iter().collect();
+        eprintln!("Skipped the events which need the window: {}", skipped.join(", "));
+    }
+    Ok(())
+}
+
+struct Arguments {
+    // A number or any words
+    seed: Option<String>,
+    // Of the cells of the map, instead of that of the config
+    map_seed: Option<u64>,
+    size: map_size::MapSize,
+    render: Option<RenderArguments>,
+    replay: Option<ReplayArguments>,
+}
+
+// A view to render to an image instead of starting the application
+struct RenderArguments {
+    view: String,
+    path: String,
+    // The size of the map when missing
+    width: Option<usize>,
+    height: Option<usize>,
+}
+
+// A recording to play back into frames instead of starting the application
+struct ReplayArguments {
+    path: String,
+    // The directory of the frames, else that of the config
+    frames: Option<String>,
+    width: Option<usize>,
+    height: Option<usize>,
+}
+
+// --seed "misty mountains" --map-seed 7 --width 1600 --height 900 --cells 16000
+// --render geography --output map.png --image-width 3200 --image-height 1800
+// --replay session.rec --frames frames
+fn parse_arguments() -> Arguments {
+    let mut arguments = Arguments { seed: None, map_seed: None, size: map_size::MapSize::default(), render: None, replay: None };
+    let mut render_view = None;
+    let mut replay_path = None;
+    let mut frames = None;
+    let mut render_path = None;
+    let mut render_width = None;
+    let mut render_height = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--seed" => {
+                arguments.seed = args.next();
+                continue;
+            }
+            "--map-seed" => {
+                arguments.map_seed = args.next().and_then(|value| value.parse().ok());
+                if arguments.map_seed.is_none() {
+                    eprintln!("{} needs a number", arg);
+                }
+                continue;
+            }
+            "--render" => {
+                render_view = args.next();
+                continue;
+            }
+            "--output" => {
+                render_path = args.next();
+                continue;
+            }
+            "--replay" => {
+                replay_path = args.next();
+                continue;
+            }
+            "--frames" => {
+                frames = args.next();
+                continue;
+            }
+            "--image-width" | "--image-height" => {
+                let value = args.next().and_then(|value| value.parse().ok());
+                if value.is_none() {
+                    eprintln!("{} needs a number", arg);
+                }
+                if arg == "--image-width" { render_width = value } else { render_height = value }
+                continue;
+            }
+            "--width" => &mut arguments.size.width,
+            "--height" => &mut arguments.size.height,
+            "--cells" => &mut arguments.size.cells,
+            other => {
+                eprintln!("Ignoring unknown argument {}", other);
+                continue;
+            }
+        };
+        match args.next().map(|value| value.parse()) {
+            Some(Ok(value)) => *target = value,
+            _ => eprintln!("{} needs a number", arg),
+        }
+    }
+    arguments.render = render_view.map(|view| RenderArguments {
+        view,
+        path: render_path.unwrap_or_else(|| "map.png".to_owned()),
+        width: render_width,
+        height: render_height,
+    });
+    arguments.replay = replay_path.map(|path| ReplayArguments { path, frames, width: render_width, height: render_height });
+    arguments
+}
+
+// The words shown next to a seed hashed from text, nothing for a plain number
+fn seed_words(text: &str) -> Option<String> {
+    let text = text.trim();
+    if text.parse::<u64>().is_ok() { None } else { Some(text.to_owned()) }
+}
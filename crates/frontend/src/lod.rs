@@ -0,0 +1,122 @@
+use serde_derive::Deserialize;
+use world::features::{Feature, FeatureKind};
+use world::tags::TagValue;
+
+// The classes of features on the map, from the broadest to the finest. The map has no
+// continents as features; archipelagos and regions are the broadest it has.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LodClass {
+    Archipelago,
+    Region,
+    Island,
+    City,
+    Town,
+    Village,
+    Volcano,
+    Poi,
+    Pass,
+}
+
+impl LodClass {
+    // Settlements by their "tier" tag, like their glyphs. Rivers, lakes and ranges are drawn
+    // with the cells, and habitats and migrations are not drawn; they have no class.
+    pub fn of_feature(feature: &Feature) -> Option<Self> {
+        let class = match feature.kind {
+            FeatureKind::Archipelago => LodClass::Archipelago,
+            FeatureKind::Region => LodClass::Region,
+            FeatureKind::Island => LodClass::Island,
+            FeatureKind::Settlement => match feature.tags.get("tier") {
+                Some(TagValue::Int(tier)) if *tier >= 3 => LodClass::City,
+                Some(TagValue::Int(tier)) if *tier <= 1 => LodClass::Village,
+                _ => LodClass::Town,
+            },
+            FeatureKind::Poi => match feature.tags.get("icon") {
+                Some(TagValue::Text(icon)) if icon == "volcano" => LodClass::Volcano,
+                _ => LodClass::Poi,
+            },
+            FeatureKind::Pass => LodClass::Pass,
+            FeatureKind::River | FeatureKind::Lake | FeatureKind::Range | FeatureKind::Habitat
+                | FeatureKind::Migration => return None,
+        };
+        Some(class)
+    }
+}
+
+// The scales, in map units per screen pixel, between which a class is shown, and the size of
+// its labels in pixels, without labels at 0
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct LodRule {
+    #[serde(default)]
+    pub min_scale: f32,
+    #[serde(default = "unbounded")]
+    pub max_scale: f32,
+    #[serde(default)]
+    pub label_size: f32,
+}
+
+fn unbounded() -> f32 { f32::MAX }
+
+impl LodRule {
+    const fn new(min_scale: f32, max_scale: f32, label_size: f32) -> Self {
+        Self { min_scale, max_scale, label_size }
+    }
+}
+
+// Which classes the map shows at which scale, on screen and in the exports. Zoomed out, the
+// islands give way to the names of their archipelagos and the small places are left out.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct LodRules {
+    pub archipelago: LodRule,
+    pub region: LodRule,
+    pub island: LodRule,
+    pub city: LodRule,
+    pub town: LodRule,
+    pub village: LodRule,
+    pub volcano: LodRule,
+    pub poi: LodRule,
+    pub pass: LodRule,
+}
+
+impl Default for LodRules {
+    fn default() -> Self {
+        Self {
+            archipelago: LodRule::new(1.0, f32::MAX, 16.0),
+            region: LodRule::new(0.0, f32::MAX, 18.0),
+            island: LodRule::new(0.0, 1.0, 12.0),
+            city: LodRule::new(0.0, f32::MAX, 14.0),
+            town: LodRule::new(0.0, f32::MAX, 12.0),
+            village: LodRule::new(0.0, f32::MAX, 0.0),
+            volcano: LodRule::new(0.0, f32::MAX, 0.0),
+            poi: LodRule::new(0.0, 2.0, 0.0),
+            pass: LodRule::new(0.0, 1.0, 0.0),
+        }
+    }
+}
+
+impl LodRules {
+    pub fn rule(&self, class: LodClass) -> LodRule {
+        match class {
+            LodClass::Archipelago => self.archipelago,
+            LodClass::Region => self.region,
+            LodClass::Island => self.island,
+            LodClass::City => self.city,
+            LodClass::Town => self.town,
+            LodClass::Village => self.village,
+            LodClass::Volcano => self.volcano,
+            LodClass::Poi => self.poi,
+            LodClass::Pass => self.pass,
+        }
+    }
+
+    pub fn shows(&self, class: LodClass, map_per_pixel: f32) -> bool {
+        let rule = self.rule(class);
+        map_per_pixel >= rule.min_scale && map_per_pixel <= rule.max_scale
+    }
+
+    // The size of the labels of the class at the scale, if it is shown with labels
+    pub fn label_size(&self, class: LodClass, map_per_pixel: f32) -> Option<f32> {
+        let size = self.rule(class).label_size;
+        (size > 0.0 && self.shows(class, map_per_pixel)).then_some(size)
+    }
+}
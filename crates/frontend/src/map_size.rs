@@ -0,0 +1,96 @@
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use polymap::PolyMap;
+use serde_derive::{Deserialize, Serialize};
+
+// The map the application always started with, which the cell counts are measured against
+const REFERENCE_WIDTH: usize = 1600;
+const REFERENCE_HEIGHT: usize = 900;
+const REFERENCE_RADIUS: f64 = 8.0;
+// Roughly how many cells the poisson disc sampling gives the reference map
+const REFERENCE_CELLS: usize = 15750;
+
+// Dimensions of the map, in map units, and how many cells it should be cut into
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MapSize {
+    pub width: usize,
+    pub height: usize,
+    pub cells: usize,
+}
+
+impl Default for MapSize {
+    fn default() -> Self {
+        Self { width: REFERENCE_WIDTH, height: REFERENCE_HEIGHT, cells: REFERENCE_CELLS }
+    }
+}
+
+impl MapSize {
+    // Cells take an area proportional to the square of the sampling radius. The reference
+    // map gets exactly the reference radius, so that its cells stay the same.
+    pub fn poisson_radius(&self) -> f64 {
+        let area = (self.width * self.height) as f64 / (REFERENCE_WIDTH * REFERENCE_HEIGHT) as f64;
+        let density = REFERENCE_CELLS as f64 / self.cells.max(1) as f64;
+        REFERENCE_RADIUS * (area * density).sqrt()
+    }
+
+    // Fails when the map is too small for a handful of cells
+    pub fn build(&self, map_seed: u64) -> Result<PolyMap, String> {
+        PolyMap::try_with_seed(self.width, self.height, self.poisson_radius(), map_seed)
+    }
+}
+
+// A map being built on another thread, since large maps take seconds
+pub(crate) struct MapBuild {
+    size: MapSize,
+    started: Instant,
+    handle: Option<JoinHandle<Result<PolyMap, String>>>,
+}
+
+impl MapBuild {
+    pub fn start(size: MapSize, map_seed: u64) -> Self {
+        Self {
+            size,
+            started: Instant::now(),
+            handle: Some(std::thread::spawn(move || size.build(map_seed))),
+        }
+    }
+
+    pub fn size(&self) -> MapSize { self.size }
+
+    pub fn elapsed(&self) -> Duration { self.started.elapsed() }
+
+    // The map once it is built, only once
+    pub fn poll(&mut self) -> Option<Result<PolyMap, String>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| Err(format!("Failed to build a map of {} cells", self.size.cells))))
+    }
+}
+
+// The map the worlds are made on. The worlds borrow it and are handed to worker threads, which
+// need borrows for as long as the program runs, so the slot gives out such borrows of a map it
+// owns. A replaced map is handed back rather than dropped, to be dropped once every world made
+// on it is gone.
+pub(crate) struct MapSlot {
+    map: &'static PolyMap,
+}
+
+impl MapSlot {
+    pub fn new(map: PolyMap) -> Self {
+        Self { map: Box::leak(Box::new(map)) }
+    }
+
+    pub fn get(&self) -> &'static PolyMap { self.map }
+
+    // Puts the new map in the slot and hands back the old one. The last map is never dropped,
+    // as a job may still be running on it when the program ends.
+    //
+    // Safety: the old map must outlive every world, and anything else, borrowing it from get.
+    pub unsafe fn replace(&mut self, map: PolyMap) -> Box<PolyMap> {
+        let old = std::mem::replace(&mut self.map, Box::leak(Box::new(map)));
+        Box::from_raw(old as *const PolyMap as *mut PolyMap)
+    }
+}
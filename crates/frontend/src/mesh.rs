@@ -0,0 +1,142 @@
+use std::ops::Range;
+
+use macroquad::prelude as mq;
+use macroquad::models::{Mesh, Vertex};
+use polymap::field::Field;
+use polymap::{CellId, PolyMap};
+
+use crate::tessellation::GridTessellation;
+
+// Macroquad clamps a single geometry call to 10000 vertices and 5000 indices
+const MAX_CHUNK_INDICES: usize = 4998;
+
+// All the cells of the map in a handful of meshes, with the colors kept per vertex so
+// that a new view only needs to rewrite the colors
+pub struct CellMesh {
+    chunks: Vec<Mesh>,
+    // The chunk of each cell, and the range of its vertices in there
+    cells: Vec<(usize, Range<usize>)>,
+}
+
+impl CellMesh {
+    pub fn new(poly: &PolyMap, tessellation: &GridTessellation) -> Self {
+        let mut chunks = vec![empty_mesh()];
+        let mut cells = vec![];
+        for (id, _) in poly.cells() {
+            let triangles = tessellation.polygon_of(id);
+            if chunks.last().unwrap().indices.len() + 3 * triangles.len() > MAX_CHUNK_INDICES {
+                chunks.push(empty_mesh());
+            }
+            let chunk_idx = chunks.len() - 1;
+            let chunk = chunks.last_mut().unwrap();
+
+            let start = chunk.vertices.len();
+            for vertex in triangles.iter().flatten() {
+                chunk.indices.push(chunk.vertices.len() as u16);
+                chunk.vertices.push(Vertex {
+                    position: mq::Vec3::new(vertex.x, vertex.y, 0.0),
+                    uv: mq::Vec2::ZERO,
+                    color: mq::BLACK,
+                });
+            }
+            cells.push((chunk_idx, start..chunk.vertices.len()));
+        }
+        Self { chunks, cells }
+    }
+
+    pub fn set_color(&mut self, cell: CellId, color: mq::Color) {
+        let (chunk, range) = &self.cells[cell.idx()];
+        for vertex in self.chunks[*chunk].vertices[range.clone()].iter_mut() {
+            vertex.color = color;
+        }
+    }
+
+    pub fn draw(&self) {
+        for chunk in self.chunks.iter() {
+            mq::draw_mesh(chunk);
+        }
+    }
+
+    // Draws only the given cells, over what is there
+    pub fn draw_cells(&self, cells: &[CellId]) {
+        let mut mesh = empty_mesh();
+        for &cell in cells {
+            let (chunk, range) = &self.cells[cell.idx()];
+            if mesh.indices.len() + range.len() > MAX_CHUNK_INDICES {
+                mq::draw_mesh(&mesh);
+                mesh = empty_mesh();
+            }
+            for vertex in self.chunks[*chunk].vertices[range.clone()].iter() {
+                mesh.indices.push(mesh.vertices.len() as u16);
+                mesh.vertices.push(*vertex);
+            }
+        }
+        if !mesh.indices.is_empty() {
+            mq::draw_mesh(&mesh);
+        }
+    }
+
+    pub fn paint(&self, repaint: &Repaint) {
+        match repaint {
+            Repaint::All => self.draw(),
+            Repaint::Cells(cells) => self.draw_cells(cells),
+        }
+    }
+}
+
+pub enum Repaint {
+    All,
+    Cells(Vec<CellId>),
+}
+
+// The colors a render target was last painted with, so that an update only paints again the
+// cells whose color changed. Editing tools and most tweaks only change a part of the map.
+// The colors must be opaque, or painting over the old ones would blend with them.
+#[derive(Default)]
+pub struct PaintedColors(Option<Field<mq::Color>>);
+
+impl PaintedColors {
+    // Sets the colors to paint on the mesh, and tells which cells to paint. When more than
+    // half of the cells changed, all of them are painted in one go.
+    pub fn repaint(&mut self, poly: &PolyMap, mesh: &mut CellMesh, colors: Field<mq::Color>) -> Repaint {
+        let changed: Option<Vec<_>> = self.0.as_ref().map(|painted| {
+            poly.cells().map(|(id, _)| id).filter(|&id| painted[id] != colors[id]).collect()
+        });
+        let repaint = match changed {
+            Some(cells) if cells.len() <= colors.len() / 2 => Repaint::Cells(cells),
+            _ => Repaint::All,
+        };
+        match &repaint {
+            Repaint::All => {
+                for (id, _) in poly.cells() {
+                    mesh.set_color(id, colors[id]);
+                }
+            }
+            Repaint::Cells(cells) => {
+                for &id in cells {
+                    mesh.set_color(id, colors[id]);
+                }
+            }
+        }
+        self.0 = Some(colors);
+        repaint
+    }
+}
+
+fn empty_mesh() -> Mesh {
+    Mesh {
+        vertices: vec![],
+        indices: vec![],
+        texture: None,
+    }
+}
+
+// The color of drawing `over` on top of `base` with alpha blending
+pub fn blend(base: mq::Color, over: mq::Color) -> mq::Color {
+    let alpha = over.a + base.a * (1.0 - over.a);
+    if alpha <= 0.0 {
+        return mq::Color::new(0.0, 0.0, 0.0, 0.0);
+    }
+    let mix = |b: f32, o: f32| (o * over.a + b * base.a * (1.0 - over.a)) / alpha;
+    mq::Color::new(mix(base.r, over.r), mix(base.g, over.g), mix(base.b, over.b), alpha)
+}
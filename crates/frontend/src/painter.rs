@@ -1,8 +1,20 @@
 use macroquad::prelude as mq;
 use polymap::*;
+use polymap::field::Field;
 use world::*;
-
-use crate::tessellation::{GridTessellation, PathTessellation};
+use world::marine::MarineBiome;
+use world::features::FeatureKind;
+use world::relief::{self, Landform};
+use world::season::Season;
+
+use crate::accessibility::{self, Palette, Pattern};
+use crate::color_grade::ColorGrade;
+use crate::effects::{Effects, EffectsPass, Lighting};
+use crate::mesh::{self, CellMesh, PaintedColors, Repaint};
+use crate::texture::{self, Texture};
+use crate::tessellation::{GridTessellation, PathTessellation, TessellationOptions};
+use crate::vector_field::{self, VectorFieldStyle};
+use crate::view_settings::ViewTuning;
 
 use strum_macros::EnumIter;
 
@@ -14,6 +26,15 @@ pub enum ViewMode {
     Precipitation,
     Drainage,
     Biome,
+    LandBiome,
+    Hazards,
+    DebugRawHeight,
+    DebugSlope,
+    DebugLowNoise,
+    DebugHighNoise,
+    DebugFilledDepressions,
+    DebugDescent,
+    DebugLandform,
 }
 
 
@@ -27,13 +48,113 @@ impl ViewMode {
             ViewMode::Precipitation => "Precipitation",
             ViewMode::Drainage => "Drainage",
             ViewMode::Biome => "Biome",
+            ViewMode::LandBiome => "Land biome",
+            ViewMode::Hazards => "Hazards",
+            ViewMode::DebugRawHeight => "Raw height",
+            ViewMode::DebugSlope => "Slope",
+            ViewMode::DebugLowNoise => "Low noise",
+            ViewMode::DebugHighNoise => "High noise",
+            ViewMode::DebugFilledDepressions => "Filled depressions",
+            ViewMode::DebugDescent => "Descent",
+            ViewMode::DebugLandform => "Landform",
+        }
+    }
+
+    pub fn is_debug(&self) -> bool {
+        matches!(self,
+            ViewMode::DebugRawHeight | ViewMode::DebugSlope | ViewMode::DebugLowNoise |
+            ViewMode::DebugHighNoise | ViewMode::DebugFilledDepressions | ViewMode::DebugDescent |
+            ViewMode::DebugLandform)
+    }
+
+    // The views of the ground, whose land can get texture fills
+    pub fn is_textured(&self) -> bool {
+        matches!(self, ViewMode::Geography | ViewMode::Biome | ViewMode::LandBiome)
+    }
+
+    // The texture fill of the cell in the view, if textures are on. The classes of the safe
+    // palette keep their patterns instead.
+    pub fn texture(&self, world: &World, cell: CellId, palette: Palette, textures: bool) -> Texture {
+        if !textures || !self.is_textured() || self.class_style(world, cell, palette).is_some() {
+            return Texture::Flat;
         }
+        Texture::of_cell(world, cell)
     }
 
-    fn draw_cell(&self, world:&World, cell: CellId) -> DrawCell {
+    // The views of a single value, whose rendering can be tuned
+    pub fn is_tunable(&self) -> bool {
+        matches!(self, ViewMode::Heightmap | ViewMode::Temperature | ViewMode::Precipitation | ViewMode::Drainage)
+            || self.is_scalar_debug()
+    }
+
+    fn is_scalar_debug(&self) -> bool {
+        matches!(self,
+            ViewMode::DebugRawHeight | ViewMode::DebugSlope | ViewMode::DebugLowNoise |
+            ViewMode::DebugHighNoise | ViewMode::DebugFilledDepressions)
+    }
+
+    // The intermediate field shown by the scalar debug views
+    pub(crate) fn debug_field<'w>(&self, world: &'w World) -> Option<&'w Field<f64>> {
+        let fields = world.debug_fields();
+        match self {
+            ViewMode::DebugRawHeight => Some(&fields.raw_heightmap),
+            ViewMode::DebugSlope => Some(&fields.slope),
+            ViewMode::DebugLowNoise => Some(&fields.low_noise),
+            ViewMode::DebugHighNoise => Some(&fields.high_noise),
+            ViewMode::DebugFilledDepressions => Some(&fields.filled_depressions),
+            _ => None,
+        }
+    }
+
+    // What the scalar debug views stretch over the full intensity
+    pub fn value_range(&self, world: &World) -> (f64, f64) {
+        self.debug_field(world).map(|field| field.range()).unwrap_or((0.0, 1.0))
+    }
+
+    // The color of a cell, without the arrows drawn over it. The temperature is the one over
+    // the year without a season.
+    pub fn cell_color(&self, world: &World, cell: CellId, range: (f64, f64), tuning: &ViewTuning, palette: Palette, season: Option<Season>) -> mq::Color {
+        self.draw_cell(world, cell, range, tuning, palette, season).blended()
+    }
+
+    // The color and pattern of the class of the cell, in the views of classes with the safe
+    // palette
+    fn class_style(&self, world: &World, cell: CellId, palette: Palette) -> Option<(mq::Color, Pattern)> {
+        if palette != Palette::ColorBlindSafe {
+            return None;
+        }
+        match self {
+            ViewMode::Biome => Some(accessibility::cover_style(world.cover(cell))),
+            ViewMode::LandBiome if world.is_lake(cell) => Some(accessibility::cover_style("Lake")),
+            ViewMode::LandBiome => Some(accessibility::land_biome_style(world.land_biome()[cell])),
+            ViewMode::DebugLandform if world.terrain_category()[cell] == TerrainCategory::Sea => Some(accessibility::sea_style()),
+            ViewMode::DebugLandform => Some(accessibility::landform_style(world.landform()[cell])),
+            _ => None,
+        }
+    }
+
+    fn draw_cell(&self, world:&World, cell: CellId, range: (f64, f64), tuning: &ViewTuning, palette: Palette, season: Option<Season>) -> DrawCell {
+        if let Some((color, _)) = self.class_style(world, cell, palette) {
+            return DrawCell {
+                color,
+                stack: vec![],
+                direction: None,
+            };
+        }
+
+        if let Some(field) = self.debug_field(world) {
+            let (min, max) = range;
+            let value = if max > min { tuning.apply((field[cell] - min) / (max - min)) } else { 0.0 };
+            return DrawCell {
+                color: mq::Color::new(value, value, value, 1.0),
+                stack: vec![],
+                direction: None,
+            };
+        }
+
         match self {
             &ViewMode::Heightmap => {
-                let height = world.heightmap()[cell] as f32;
+                let height = tuning.apply(world.heightmap()[cell]);
                 
                 let color = mq::Color::new(height,height, height, 1.0);
 
@@ -46,6 +167,7 @@ impl ViewMode {
             &ViewMode::Geography => {
                 let terrain_category = world.terrain_category()[cell];
                 let color = match terrain_category {
+                    _ if world.is_lake(cell) => mq::SKYBLUE,
                     TerrainCategory::Land => {
                         let t = (world.heightmap()[cell] - 0.5) * 2.0;
                         colors::interpolate_three_colors(mq::GREEN, mq::BROWN, mq::WHITE, t as f32)
@@ -60,7 +182,11 @@ impl ViewMode {
                 }
             }
             &ViewMode::Temperature => {
-                let temperature = world.temperature()[cell] as f32;
+                let temperature = match season {
+                    Some(season) => world.seasonal_temperature(cell, season),
+                    None => world.temperature()[cell],
+                };
+                let temperature = tuning.apply(temperature);
                 let color = colors::interpolate_three_colors(mq::BLUE, mq::YELLOW, mq::RED, temperature);
                 DrawCell {
                     color,
@@ -69,25 +195,18 @@ impl ViewMode {
                 }
             }
             &ViewMode::Precipitation => {
-                let rain = world::measure::DRAIN.normalize(world.rainfall()[cell]);
+                let rain = tuning.apply(world::measure::DRAIN.normalize(world.rainfall()[cell]));
                 
-                let color = mq::Color::new(0.0, 0.0, 1.0, rain as f32);
-
-                let wind_vector = &world.wind()[cell].to_polar().unwrap_or_default();
-                let direction = if wind_vector.r == 0.0 { None} else{ 
-                    let rain = world::measure::RAIN.normalize(wind_vector.r);
-                    let color = mq::Color::new(rain as f32, 0.0, 0.0, rain as f32);
-                    Some((color, wind_vector.theta)) 
-                };
+                let color = mq::Color::new(0.0, 0.0, 1.0, rain);
 
                 DrawCell {
                     color,
                     stack: vec![],
-                    direction,
+                    direction: None,
                 }
             }
             &ViewMode::Drainage => {
-                let drainage = world::measure::DRAIN.normalize(world.drainage()[cell]) as f32;
+                let drainage = tuning.apply(world::measure::DRAIN.normalize(world.drainage()[cell]));
                 let color = mq::Color::new(0.0, 0.0, 1.0, drainage);
 
                 let direction = if world.is_river(cell) {
@@ -110,6 +229,15 @@ impl ViewMode {
                 }
             }
             &ViewMode::Biome => {
+                let marine = world.marine()[cell];
+                if marine != MarineBiome::None {
+                    return DrawCell {
+                        color: colors::marine_biome(marine),
+                        stack: vec![],
+                        direction: None,
+                    };
+                }
+
                 let mut colors = vec![];
                 
                 {
@@ -151,12 +279,88 @@ impl ViewMode {
                     direction: None,
                 }
             }
+            &ViewMode::LandBiome => {
+                let color = if world.is_lake(cell) {
+                    mq::SKYBLUE
+                } else {
+                    colors::land_biome(world.land_biome()[cell])
+                };
+                DrawCell {
+                    color,
+                    stack: vec![],
+                    direction: None,
+                }
+            }
+            &ViewMode::Hazards => {
+                let color = match world.terrain_category()[cell] {
+                    TerrainCategory::Sea => mq::DARKGRAY,
+                    _ if world.is_lake(cell) => mq::DARKGRAY,
+                    _ => colors::hazards(world.hazards()[cell], world.heightmap()[cell] as f32),
+                };
+                DrawCell {
+                    color,
+                    stack: vec![],
+                    direction: None,
+                }
+            }
+            &ViewMode::DebugDescent => {
+                let height = world.heightmap()[cell] as f32;
+                let direction = match world.downhill()[cell] {
+                    CellVector::Stationary => None,
+                    CellVector::Towards(tgt, _) => {
+                        let angle = world.poly().angle_between_cells(cell, tgt);
+                        Some((mq::RED, angle))
+                    }
+                };
+                DrawCell {
+                    color: mq::Color::new(height, height, height, 1.0),
+                    stack: vec![],
+                    direction,
+                }
+            }
+            &ViewMode::DebugLandform => {
+                let color = match world.landform()[cell] {
+                    Landform::Flat => mq::Color::new(0.75, 0.85, 0.6, 1.0),
+                    Landform::Slope => mq::GRAY,
+                    Landform::Ridge => mq::ORANGE,
+                    Landform::Valley => mq::DARKBLUE,
+                };
+                let color = if world.terrain_category()[cell] == TerrainCategory::Sea { mq::BLACK } else { color };
+                DrawCell {
+                    color,
+                    stack: vec![],
+                    direction: None,
+                }
+            }
+            // Scalar debug views are handled above
+            _ => DrawCell {
+                color: mq::BLACK,
+                stack: vec![],
+                direction: None,
+            },
         }
     }
 
-    fn paths(&self, world:&World) -> Vec<(Vec<CellId>, mq::Color)> {
+    // The wind over the precipitation, and the surface currents over the seas. The currents
+    // follow the wind, as they do for the marine productivity.
+    fn vector_field(&self, world: &World) -> Option<Field<Vec2>> {
         match self {
-            ViewMode::Geography | ViewMode::Biome => {
+            ViewMode::Precipitation => Some(world.wind().clone()),
+            ViewMode::Biome => Some(Field::with_fn(world.poly(), |id, _| {
+                if world.marine()[id] != MarineBiome::None { world.wind()[id] } else { Vec2::ZERO }
+            })),
+            _ => None,
+        }
+    }
+
+    // Views of the land as a map, rather than of a single quantity
+    pub(crate) fn shows_glyphs(&self) -> bool {
+        matches!(self, ViewMode::Geography | ViewMode::Biome | ViewMode::LandBiome | ViewMode::Hazards)
+    }
+
+    pub(crate) fn paths(&self, world:&World) -> Vec<(Vec<CellId>, mq::Color)> {
+        match self {
+            ViewMode::Geography | ViewMode::Biome | ViewMode::LandBiome | ViewMode::Hazards => {
                 world.rivers().iter().map(|path| 
                     (path.cells().iter().copied().collect(), mq::BLUE)
                 ).collect()
@@ -177,63 +381,207 @@ struct DrawCell {
     direction: Option<(mq::Color, f64)>,
 }
 
+impl DrawCell {
+    fn blended(&self) -> mq::Color {
+        self.stack.iter().fold(self.color, |color, &over| mesh::blend(color, over))
+    }
+}
+
 pub struct Painter {
     target: mq::RenderTarget,
-    tessellation: GridTessellation,
+    // The cells of the view alone, which the target gets the overlays drawn over
+    cells: mq::RenderTarget,
+    mesh: CellMesh,
+    painted_view: PaintedColors,
+    painted_mask: PaintedColors,
+    painted_relief: PaintedColors,
+    options: TessellationOptions,
+    effects: EffectsPass,
+    season: Option<Season>,
+    textures: bool,
 }
 
 impl Painter {
-    pub fn new(poly: &PolyMap) -> Self {
+    pub fn new(poly: &PolyMap, options: TessellationOptions, cache_dir: Option<&str>) -> Self {
+        let tessellation = GridTessellation::cached(poly, &options, cache_dir);
         Self {
             target: mq::render_target(poly.width() as u32, poly.height() as u32),
-            tessellation: GridTessellation::new(poly),
+            cells: mq::render_target(poly.width() as u32, poly.height() as u32),
+            mesh: CellMesh::new(poly, &tessellation),
+            painted_view: PaintedColors::default(),
+            painted_mask: PaintedColors::default(),
+            painted_relief: PaintedColors::default(),
+            options,
+            effects: EffectsPass::new(poly.width() as u32, poly.height() as u32),
+            season: None,
+            textures: false,
         }
     }
 
-    pub fn update(&mut self, world: &World, mode: ViewMode) {
+    // The season the temperature view shows, or the whole year
+    pub fn set_season(&mut self, season: Option<Season>) {
+        self.season = season;
+    }
+
+    pub fn set_textures(&mut self, textures: bool) {
+        self.textures = textures;
+    }
+
+    pub fn update(&mut self, world: &World, mode: ViewMode, tuning: &ViewTuning, vector_style: &VectorFieldStyle, palette: Palette) {
         let display_rect = mq::Rect::new(0.0, 0.0, world.poly().width() as f32, world.poly().height() as f32);
         let mut camera = mq::Camera2D::from_display_rect(display_rect);
         camera.render_target = Some(self.target);
         mq::push_camera_state();
-        mq::set_camera(&camera);
 
-        mq::draw_rectangle(0.0,0.0, world.poly().width() as f32, world.poly().height() as f32, mq::BLACK);
-        
-        for (cell_id, cell) in world.poly().cells() {
-            let triangles = self.tessellation.polygon_of(cell_id);
-            let drawing = mode.draw_cell(world, cell_id);
-            for triangle in triangles {
-                mq::draw_triangle(triangle[0], triangle[1], triangle[2], drawing.color);
-                for &color in drawing.stack.iter() {
-                    mq::draw_triangle(triangle[0], triangle[1], triangle[2], color);
-                }
+        let poly = world.poly();
+        let paint = |target: mq::RenderTarget, mesh: &CellMesh, repaint: &Repaint| {
+            let mut layer_camera = mq::Camera2D::from_display_rect(display_rect);
+            layer_camera.render_target = Some(target);
+            mq::set_camera(&layer_camera);
+            if let Repaint::All = repaint {
+                mq::draw_rectangle(0.0, 0.0, display_rect.w, display_rect.h, mq::BLACK);
             }
+            mesh.paint(repaint);
+        };
+
+        // The mask of the effects goes first, since it borrows the mesh
+        let mask = Field::with_fn(poly, |id, _| effects_mask(world, id));
+        let repaint = self.painted_mask.repaint(poly, &mut self.mesh, mask);
+        paint(self.effects.mask(), &self.mesh, &repaint);
+
+        let lights = settlement_lights(world);
+        let relief = Field::with_fn(poly, |id, _| relief_color(world, id, lights[id]));
+        let repaint = self.painted_relief.repaint(poly, &mut self.mesh, relief);
+        paint(self.effects.relief(), &self.mesh, &repaint);
+
+        let range = mode.value_range(world);
+        let mut arrows = vec![];
+        let mut patterns = vec![];
+        let mut textured = vec![];
+        let mut view = Field::uniform(poly, mq::BLACK);
+        for (cell_id, cell) in poly.cells() {
+            let drawing = mode.draw_cell(world, cell_id, range, tuning, palette, self.season);
+            // Over black, as the full repaint draws them
+            view[cell_id] = mesh::blend(mq::BLACK, drawing.blended());
 
             if let Some((color, direction)) = drawing.direction {
-                let (cx, cy) = cell.center();
-                let triangle = rotated_triangle((cx, world.poly().height() as f64 - cy), 5.0, direction);
+                arrows.push((cell, color, direction));
+            }
+            if let Some((color, pattern)) = mode.class_style(world, cell_id, palette) {
+                patterns.push((cell_id, color, pattern));
+            }
+            let fill = mode.texture(world, cell_id, palette, self.textures);
+            if fill != Texture::Flat {
+                textured.push((cell_id, fill));
+            }
+        }
+        let repaint = self.painted_view.repaint(poly, &mut self.mesh, view);
+        paint(self.cells, &self.mesh, &repaint);
 
-                mq::draw_triangle(triangle[0], triangle[1], triangle[2], color)
+        // Everything over the cells is drawn again every time, over a copy of them
+        mq::set_camera(&camera);
+        let params = mq::DrawTextureParams {
+            dest_size: Some(mq::Vec2::new(display_rect.w, display_rect.h)),
+            // Between render targets, the copy would come out upside down
+            flip_y: true,
+            ..Default::default()
+        };
+        mq::draw_texture_ex(self.cells.texture, 0.0, 0.0, mq::WHITE, params);
+        texture::draw(world.poly(), &textured);
+        accessibility::draw_patterns(world.poly(), &patterns);
+
+        // Arrows go on top of the whole mesh, not just of their own cell
+        for (cell, color, direction) in arrows {
+            let (cx, cy) = cell.center();
+            let triangle = rotated_triangle((cx, world.poly().height() as f64 - cy), 5.0, direction);
+
+            mq::draw_triangle(triangle[0], triangle[1], triangle[2], color)
+        }
+
+        if mode == ViewMode::Geography {
+            for pass in world.passes() {
+                let (x, y) = world.poly()[pass.cell].center();
+                mq::draw_circle(x as f32, (world.poly().height() as f64 - y) as f32, 3.0, mq::BLACK);
             }
         }
+
+        if let Some(field) = mode.vector_field(world) {
+            let map_per_pixel = world.poly().width() as f32 / mq::screen_width();
+            vector_field::draw(world.poly(), &field, vector_style, map_per_pixel);
+        }
       
         for (path, color) in mode.paths(world) {
-            let tess = PathTessellation::path_of_cells(world.poly(), path.as_slice(), 2.0).unwrap();
+            let tess = match PathTessellation::path_of_cells(world.poly(), path.as_slice(), 2.0, self.options.stroke_tolerance) {
+                Some(tess) => tess,
+                None => continue,
+            };
             for triangle in tess.polygon() {
                 mq::draw_triangle(triangle[0], triangle[1], triangle[2], color)
             }
         }
+
+        // Notes are marked in every view
+        for cell in world.noted_cells() {
+            let (x, y) = world.poly()[cell].center();
+            let (x, y) = (x as f32, (world.poly().height() as f64 - y) as f32);
+            mq::draw_rectangle(x - 3.0, y - 3.0, 6.0, 6.0, mq::YELLOW);
+            mq::draw_rectangle_lines(x - 3.0, y - 3.0, 6.0, 6.0, 1.0, mq::BLACK);
+        }
     
         mq::pop_camera_state();
     }
 
-    pub fn draw(&mut self) {
-        let mut params = mq::DrawTextureParams::default();
-        params.dest_size = Some(mq::Vec2::new(mq::screen_width(), mq::screen_height()));
-        mq::draw_texture_ex(self.target.texture, 0.0, 0.0, mq::WHITE, params);
+    // The visible part of the map, in map units with y downwards, over the whole window
+    pub fn draw(&mut self, effects: &Effects, lighting: &Lighting, grade: &ColorGrade, visible: mq::Rect) {
+        let params = mq::DrawTextureParams {
+            dest_size: Some(mq::Vec2::new(mq::screen_width(), mq::screen_height())),
+            source: Some(visible),
+            ..Default::default()
+        };
+        if effects.any() || lighting.enabled {
+            self.effects.draw(self.target.texture, effects, lighting, grade, params);
+        } else {
+            mq::draw_texture_ex(self.target.texture, 0.0, 0.0, mq::WHITE, params);
+        }
     }
 }
 
+// Sea in red, the cloud cover, which follows the rain, in green, and the fog in blue
+fn effects_mask(world: &World, cell: CellId) -> mq::Color {
+    let sea = if world.terrain_category()[cell] == TerrainCategory::Sea { 1.0 } else { 0.0 };
+    let clouds = world::measure::DRAIN.normalize(world.rainfall()[cell]).min(1.0) as f32;
+    let fog = world.fog()[cell] as f32;
+    mq::Color::new(sea, clouds, fog, 1.0)
+}
+
+// How many times steeper than they are the slopes are lit, or the hillshading is too faint
+const RELIEF_EXAGGERATION: f64 = 200.0;
+// How many rings of cells around a settlement its lights reach
+const LIGHT_RINGS: usize = 3;
+
+// The surface normal, east in red and north in green, and the lights in blue. The sea is flat.
+fn relief_color(world: &World, cell: CellId, light: f32) -> mq::Color {
+    let (nx, ny) = if world.terrain_category()[cell] == TerrainCategory::Sea {
+        (0.0, 0.0)
+    } else {
+        // Cell centers have y downwards, the normals north
+        let (gx, gy) = relief::gradient(world.poly(), world.heightmap(), cell);
+        let (x, y) = (-gx * RELIEF_EXAGGERATION, gy * RELIEF_EXAGGERATION);
+        let length = (x * x + y * y + 1.0).sqrt();
+        (x / length, y / length)
+    };
+    mq::Color::new(nx as f32 * 0.5 + 0.5, ny as f32 * 0.5 + 0.5, light, 1.0)
+}
+
+fn settlement_lights(world: &World) -> Field<f32> {
+    let settlements = world.features().of_kind(FeatureKind::Settlement)
+        .flat_map(|feature| feature.geometry.cells().iter().copied());
+    let rings = world.poly().ring_distances(settlements);
+    Field::with_fn(world.poly(), |id, _| {
+        1.0 - (rings[id].min(LIGHT_RINGS) as f32 / LIGHT_RINGS as f32)
+    })
+}
+
 fn rotated_triangle(center:(f64, f64), height: f64, direction: f64) -> [mq::Vec2; 3] {
     let (cx, cy) = center;
     let h = height;
@@ -249,9 +597,64 @@ fn rotated_triangle(center:(f64, f64), height: f64, direction: f64) -> [mq::Vec2
     [p_top, p_left, p_right]
 }
 
- 
-mod colors {
+
+pub(crate) mod colors {
     use macroquad::prelude::*;
+    use world::hazard::HazardFlags;
+    use world::marine::MarineBiome;
+    use world::LandBiome;
+
+    pub fn marine_biome(biome: MarineBiome) -> Color {
+        match biome {
+            MarineBiome::None => BLUE,
+            MarineBiome::Reef => Color::new(0.0, 0.8, 0.8, 1.0),
+            MarineBiome::Kelp => Color::new(0.1, 0.4, 0.35, 1.0),
+            MarineBiome::Shelf => Color::new(0.3, 0.5, 0.9, 1.0),
+            MarineBiome::Ocean => Color::new(0.1, 0.25, 0.7, 1.0),
+            MarineBiome::Abyss => Color::new(0.02, 0.05, 0.3, 1.0),
+            MarineBiome::PolarSea => Color::new(0.8, 0.9, 1.0, 1.0),
+        }
+    }
+
+    // The mix of the colors of the hazards of a land cell, over gray shaded by its height
+    pub fn hazards(flags: HazardFlags, height: f32) -> Color {
+        let shade = 0.6 + 0.35 * height;
+        let inks: Vec<_> = HazardFlags::ALL.into_iter()
+            .filter(|&flag| flags.contains(flag))
+            .map(|flag| match flag {
+                HazardFlags::EROSION => ORANGE,
+                HazardFlags::LANDSLIDE => RED,
+                _ => BLUE,
+            })
+            .collect();
+        if inks.is_empty() {
+            return Color::new(shade, shade, shade, 1.0);
+        }
+        let n = inks.len() as f32;
+        Color::new(
+            inks.iter().map(|ink| ink.r).sum::<f32>() / n,
+            inks.iter().map(|ink| ink.g).sum::<f32>() / n,
+            inks.iter().map(|ink| ink.b).sum::<f32>() / n,
+            1.0,
+        )
+    }
+
+    pub fn land_biome(biome: LandBiome) -> Color {
+        match biome {
+            LandBiome::None => BLUE,
+            LandBiome::Ice => Color::new(0.95, 0.97, 1.0, 1.0),
+            LandBiome::Tundra => Color::new(0.65, 0.7, 0.6, 1.0),
+            LandBiome::Taiga => Color::new(0.2, 0.4, 0.3, 1.0),
+            LandBiome::ColdDesert => Color::new(0.75, 0.7, 0.6, 1.0),
+            LandBiome::Steppe => Color::new(0.75, 0.75, 0.4, 1.0),
+            LandBiome::TemperateForest => Color::new(0.2, 0.55, 0.2, 1.0),
+            LandBiome::TemperateRainforest => Color::new(0.05, 0.45, 0.3, 1.0),
+            LandBiome::Desert => Color::new(0.95, 0.85, 0.5, 1.0),
+            LandBiome::Savanna => Color::new(0.7, 0.65, 0.25, 1.0),
+            LandBiome::SeasonalForest => Color::new(0.45, 0.6, 0.1, 1.0),
+            LandBiome::TropicalRainforest => Color::new(0.0, 0.35, 0.05, 1.0),
+        }
+    }
 
     pub fn interpolate_three_colors(c1: Color, c2: Color, c3: Color, t: f32) -> Color {
         if t <= 0.5 {
@@ -0,0 +1,93 @@
+use macroquad::prelude as mq;
+use world::World;
+
+use crate::accessibility::Palette;
+use crate::painter::ViewMode;
+use crate::view_settings::ViewTuning;
+
+// Width of the rivers, in map units, as the painter draws them
+const RIVER_WIDTH: f64 = 2.0;
+
+// One view of the world drawn into an image on the CPU, so that it needs neither a window nor
+// a GPU. The map is stretched over the whole image. Only the colors of the cells and the
+// rivers are drawn: arrows, glyphs, effects and the patterns of the safe palette are left
+// out, but the texture fills are drawn when on.
+pub(crate) fn render(world: &World, mode: ViewMode, tuning: &ViewTuning, palette: Palette, textures: bool, width: usize, height: usize) -> Result<mq::Image, String> {
+    let (width16, height16) = match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+        _ => return Err(format!("Images must be between 1 and {} pixels wide and tall", u16::MAX)),
+    };
+    let poly = world.poly();
+    let range = mode.value_range(world);
+    let colors: Vec<_> = poly.cells().map(|(id, _)| mode.cell_color(world, id, range, tuning, palette, None)).collect();
+    let fills: Vec<_> = poly.cells().map(|(id, _)| mode.texture(world, id, palette, textures)).collect();
+
+    let mut image = mq::Image::gen_image_color(width16, height16, mq::BLACK);
+    let scale_x = poly.width() as f64 / width as f64;
+    let scale_y = poly.height() as f64 / height as f64;
+    // Each pixel takes the cell with the closest center, found walking from the cell of the
+    // pixel before it, which is usually the same one or a neighbor
+    let mut cell = match poly.cell_id(0) {
+        Some(cell) => cell,
+        None => return Ok(image),
+    };
+    for py in 0..height {
+        for px in 0..width {
+            let x = (px as f64 + 0.5) * scale_x;
+            let y = (py as f64 + 0.5) * scale_y;
+            cell = poly.closest_cell(cell, x, y);
+            // Image::export_png flips the rows, since screen captures are upside down
+            let color = fills[cell.idx()].apply(colors[cell.idx()], cell, x, y);
+            image.set_pixel(px as u32, (height - 1 - py) as u32, color);
+        }
+    }
+
+    // Straight between the cell centers, where the painter curves them
+    let radius = (0.5 * RIVER_WIDTH / scale_x.max(scale_y)).max(0.5);
+    for (cells, color) in mode.paths(world) {
+        for pair in cells.windows(2) {
+            let (x0, y0) = poly[pair[0]].center();
+            let (x1, y1) = poly[pair[1]].center();
+            draw_segment(&mut image, (x0 / scale_x, y0 / scale_y), (x1 / scale_x, y1 / scale_y), radius, color);
+        }
+    }
+    Ok(image)
+}
+
+// In pixels with y downwards, as discs along the segment
+fn draw_segment(image: &mut mq::Image, from: (f64, f64), to: (f64, f64), radius: f64, color: mq::Color) {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let steps = (2.0 * length).ceil().max(1.0) as usize;
+    let reach = radius.ceil() as i64;
+    for step in 0..=steps {
+        let t = step as f64 / steps as f64;
+        let (cx, cy) = (from.0 + t * (to.0 - from.0), from.1 + t * (to.1 - from.1));
+        for py in (cy as i64 - reach)..=(cy as i64 + reach) {
+            for px in (cx as i64 - reach)..=(cx as i64 + reach) {
+                let inside = (px as f64 + 0.5 - cx).powi(2) + (py as f64 + 0.5 - cy).powi(2) <= radius * radius;
+                if inside && (0..width).contains(&px) && (0..height).contains(&py) {
+                    image.set_pixel(px as u32, (height - 1 - py) as u32, color);
+                }
+            }
+        }
+    }
+}
+
+// Like Image::from_file_with_format, which panics on files that are not images
+pub(crate) fn decode(bytes: &[u8]) -> Result<mq::Image, String> {
+    let decoded = image::load_from_memory(bytes).map_err(|err| err.to_string())?.to_rgba8();
+    let (width, height) = match (u16::try_from(decoded.width()), u16::try_from(decoded.height())) {
+        (Ok(width), Ok(height)) => (width, height),
+        _ => return Err(format!("Images must be at most {} pixels wide and tall", u16::MAX)),
+    };
+    Ok(mq::Image { bytes: decoded.into_raw(), width, height })
+}
+
+// Like Image::export_png, rows flipped too, which panics when the file can't be written
+pub(crate) fn write_png(image: &mq::Image, path: &str) -> Result<(), String> {
+    let row = image.width().max(1) * 4;
+    let bytes: Vec<u8> = image.bytes.chunks(row).rev().flatten().copied().collect();
+    image::save_buffer(path, &bytes, image.width() as u32, image.height() as u32, image::ColorType::Rgba8)
+        .map_err(|err| format!("Failed to write {}: {}", path, err))
+}
@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+
+use strum::IntoEnumIterator;
+use world::constraint::{Stroke, StrokeKind};
+use world::features::FeatureId;
+use world::hypsometry::TerrainLevels;
+use world::params::Param;
+
+use crate::effects::{Effects, Lighting};
+use crate::gui::GuiEvent;
+use crate::map_size::MapSize;
+use crate::painter::ViewMode;
+use crate::vector_field::VectorFieldStyle;
+use crate::view_settings::{AlphaCurve, ViewTuning};
+
+// Replays advance by a fixed step per frame, so that the frame sequence does not depend on
+// how fast the machine is
+const REPLAY_FRAME_TIME: f64 = 1.0 / 60.0;
+
+// Writes every event to a file, one line per event, prefixed by the seconds since the
+// recording started
+pub struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            file: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    // The strokes of the sketch go along with apply-sketch, as the mouse drawing them is not
+    // recorded
+    pub fn record(&mut self, events: &[GuiEvent], strokes: &[Stroke]) {
+        if events.is_empty() {
+            return;
+        }
+        let time = self.start.elapsed().as_secs_f64();
+        let sketch = GuiEvent::SetSketch(strokes.to_vec());
+        let with_strokes = events.iter().flat_map(|event| match event {
+            GuiEvent::ApplySketch => vec![&sketch, event],
+            _ => vec![event],
+        });
+        for event in with_strokes {
+            if let Err(err) = writeln!(self.file, "{:.3} {}", time, encode(event)) {
+                eprintln!("Failed to record event: {}", err);
+            }
+        }
+        // The application is usually closed by killing the window, so don't hold on to anything
+        let _ = self.file.flush();
+    }
+}
+
+pub struct Replay {
+    events: VecDeque<(f64, GuiEvent)>,
+    frame: usize,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+        let mut events = VecDeque::new();
+        for (idx, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let parsed = line.split_once(' ')
+                .ok_or_else(|| "missing event".to_owned())
+                .and_then(|(time, event)| {
+                    let time = time.parse::<f64>().map_err(|err| err.to_string())?;
+                    Ok((time, decode(event)?))
+                });
+            match parsed {
+                Ok(entry) => events.push_back(entry),
+                Err(err) => return Err(format!("{}:{}: {}", path, idx + 1, err)),
+            }
+        }
+        Ok(Self { events, frame: 0 })
+    }
+
+    pub fn frame(&self) -> usize { self.frame }
+
+    pub fn is_finished(&self) -> bool { self.events.is_empty() }
+
+    // The events due by the end of the next frame
+    pub fn next_frame(&mut self) -> Vec<GuiEvent> {
+        self.frame += 1;
+        let time = self.frame as f64 * REPLAY_FRAME_TIME;
+        let mut due = vec![];
+        while self.events.front().is_some_and(|(event_time, _)| *event_time <= time) {
+            due.extend(self.events.pop_front().map(|(_, event)| event));
+        }
+        due
+    }
+}
+
+// The command of the event in recordings, without its arguments
+pub fn command(event: &GuiEvent) -> String {
+    let line = encode(event);
+    line.split(' ').next().unwrap_or_default().to_owned()
+}
+
+fn encode(event: &GuiEvent) -> String {
+    match event {
+        GuiEvent::Close => "close".to_owned(),
+        GuiEvent::LoadShareString(text) => format!("load-share {}", text),
+        GuiEvent::SetSeedText(text) => format!("seed-text {}", escape(text)),
+        GuiEvent::BlendSeedText(text) => format!("blend-seed-text {}", escape(text)),
+        GuiEvent::SetBlendAmount(amount) => format!("blend-amount {}", amount),
+        GuiEvent::SetVariant(Some(variant)) => format!("variant {}", variant),
+        GuiEvent::SetVariant(None) => "variant none".to_owned(),
+        GuiEvent::RebuildMap(size) => format!("rebuild-map {} {} {}", size.width, size.height, size.cells),
+        GuiEvent::BakeMap(size) => format!("bake-map {} {} {}", size.width, size.height, size.cells),
+        GuiEvent::RestoreSession => "restore-session".to_owned(),
+        GuiEvent::DiscardSession => "discard-session".to_owned(),
+        GuiEvent::SetViewMode(mode) => format!("view {}", mode.name()),
+        GuiEvent::SetDebugViews(enabled) => format!("debug-views {}", enabled),
+        GuiEvent::SetColorBlindSafe(safe) => format!("color-blind-safe {}", safe),
+        GuiEvent::SetTextures(textures) => format!("textures {}", textures),
+        // The name of the view last, since it may have spaces
+        GuiEvent::SetViewTuning(mode, tuning) => format!(
+            "view-tuning {} {} {} {}",
+            tuning.scale, tuning.gamma, tuning.curve.name(), mode.name()
+        ),
+        GuiEvent::SetVectorStyle(style) => format!(
+            "vector-style {} {} {} {}",
+            style.spacing, style.scale_by_magnitude, style.color_by_magnitude, style.streamlines
+        ),
+        GuiEvent::SetEffects(effects) => format!(
+            "effects {} {} {} {} {}",
+            effects.water_shimmer, effects.cloud_shadows, effects.fog, effects.color_grade, effects.glyph_shadows
+        ),
+        GuiEvent::SetLighting(lighting) => {
+            format!("lighting {} {} {} {}", lighting.enabled, lighting.azimuth, lighting.elevation, lighting.night)
+        }
+        GuiEvent::SetSketchMode(mode) => format!("sketch-mode {}", mode.map(|kind| kind.name()).unwrap_or("off")),
+        GuiEvent::SetSeason(season) => format!("season {}", season.map(|season| season.name()).unwrap_or("year")),
+        // The strokes one after the other, each its kind, its radius and its points
+        GuiEvent::SetSketch(strokes) => strokes.iter().fold("sketch".to_owned(), |mut line, stroke| {
+            line.push_str(&format!(" | {} {}", stroke.kind.name(), stroke.radius));
+            for (x, y) in stroke.points.iter() {
+                line.push_str(&format!(" {},{}", x, y));
+            }
+            line
+        }),
+        GuiEvent::ApplySketch => "apply-sketch".to_owned(),
+        GuiEvent::ClearSketch => "clear-sketch".to_owned(),
+        GuiEvent::SelectStamp(selected) => match selected {
+            Some(idx) => format!("stamp {}", idx),
+            None => "stamp off".to_owned(),
+        },
+        GuiEvent::SetRiverCutoff(cutoff) => format!("river-cutoff {}", cutoff),
+        GuiEvent::SetTerrainLevels(levels) => format!("terrain-levels {} {}", levels.highland, levels.peak),
+        GuiEvent::SetParam(param, value) => format!("param {} {}", param.info().key, value),
+        GuiEvent::SetStampRadius(radius) => format!("stamp-radius {}", radius),
+        GuiEvent::SetStampRotation(rotation) => format!("stamp-rotation {}", rotation),
+        GuiEvent::Export => "export".to_owned(),
+        GuiEvent::ExportCivilization => "export-civilization".to_owned(),
+        GuiEvent::ImportCivilization => "import-civilization".to_owned(),
+        GuiEvent::SaveWorld => "save-world".to_owned(),
+        GuiEvent::LoadWorld => "load-world".to_owned(),
+        GuiEvent::PlaceStamp(x, y) => format!("place-stamp {} {}", x, y),
+        GuiEvent::StartCopy => "start-copy".to_owned(),
+        GuiEvent::CopyRegion(x, y) => format!("copy-region {} {}", x, y),
+        GuiEvent::Regenerate(seed) => format!("regenerate {}", seed),
+        GuiEvent::Inspect(x, y) => format!("inspect {} {}", x, y),
+        GuiEvent::CloseInspector => "close-inspector".to_owned(),
+        GuiEvent::SetCellNote(text) => format!("cell-note {}", escape(text)),
+        GuiEvent::SetFeatureNote(id, text) => format!("feature-note {} {}", id.idx(), escape(text)),
+    }
+}
+
+fn decode(line: &str) -> Result<GuiEvent, String> {
+    let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+    let number = |text: &str| text.trim().parse::<f64>().map_err(|err| format!("{}: {}", command, err));
+
+    let event = match command {
+        "close" => GuiEvent::Close,
+        "load-share" => GuiEvent::LoadShareString(args.to_owned()),
+        "seed-text" => GuiEvent::SetSeedText(unescape(args)),
+        "blend-seed-text" => GuiEvent::BlendSeedText(unescape(args)),
+        "blend-amount" => GuiEvent::SetBlendAmount(number(args)?),
+        "variant" => match args.trim() {
+            "none" => GuiEvent::SetVariant(None),
+            variant => GuiEvent::SetVariant(Some(variant.parse().map_err(|_| format!("invalid variant {}", variant))?)),
+        },
+        "rebuild-map" | "bake-map" => {
+            let parts: Vec<_> = args.split(' ').collect();
+            if parts.len() != 3 {
+                return Err(format!("{} needs three values", command));
+            }
+            let size = MapSize {
+                width: number(parts[0])? as usize,
+                height: number(parts[1])? as usize,
+                cells: number(parts[2])? as usize,
+            };
+            if command == "bake-map" { GuiEvent::BakeMap(size) } else { GuiEvent::RebuildMap(size) }
+        }
+        "restore-session" => GuiEvent::RestoreSession,
+        "discard-session" => GuiEvent::DiscardSession,
+        "view" => {
+            let mode = ViewMode::iter().find(|mode| mode.name() == args)
+                .ok_or_else(|| format!("unknown view mode {}", args))?;
+            GuiEvent::SetViewMode(mode)
+        }
+        "debug-views" => GuiEvent::SetDebugViews(args == "true"),
+        "color-blind-safe" => GuiEvent::SetColorBlindSafe(args == "true"),
+        "textures" => GuiEvent::SetTextures(args == "true"),
+        "view-tuning" => {
+            let parts: Vec<_> = args.splitn(4, ' ').collect();
+            if parts.len() != 4 {
+                return Err("view-tuning needs four values".to_owned());
+            }
+            let curve = AlphaCurve::ALL.into_iter().find(|curve| curve.name() == parts[2])
+                .ok_or_else(|| format!("unknown curve {}", parts[2]))?;
+            let mode = ViewMode::iter().find(|mode| mode.name() == parts[3])
+                .ok_or_else(|| format!("unknown view mode {}", parts[3]))?;
+            GuiEvent::SetViewTuning(mode, ViewTuning {
+                scale: number(parts[0])? as f32,
+                gamma: number(parts[1])? as f32,
+                curve,
+            })
+        }
+        "vector-style" => {
+            let parts: Vec<_> = args.split(' ').collect();
+            if parts.len() != 4 {
+                return Err("vector-style needs four values".to_owned());
+            }
+            GuiEvent::SetVectorStyle(VectorFieldStyle {
+                spacing: number(parts[0])? as f32,
+                scale_by_magnitude: parts[1] == "true",
+                color_by_magnitude: parts[2] == "true",
+                streamlines: parts[3] == "true",
+            })
+        }
+        "effects" => {
+            // Recordings from before the fog, the color grade and the glyph shadows have fewer values
+            let flags: Vec<_> = args.split(' ').map(|flag| flag == "true").collect();
+            if !(2..=5).contains(&flags.len()) {
+                return Err("effects needs five values".to_owned());
+            }
+            GuiEvent::SetEffects(Effects {
+                water_shimmer: flags[0],
+                cloud_shadows: flags[1],
+                fog: flags.get(2).copied().unwrap_or(false),
+                color_grade: flags.get(3).copied().unwrap_or(false),
+                glyph_shadows: flags.get(4).copied().unwrap_or(false),
+            })
+        }
+        "lighting" => {
+            let parts: Vec<_> = args.split(' ').collect();
+            if parts.len() != 4 {
+                return Err("lighting needs four values".to_owned());
+            }
+            GuiEvent::SetLighting(Lighting {
+                enabled: parts[0] == "true",
+                azimuth: number(parts[1])? as f32,
+                elevation: number(parts[2])? as f32,
+                night: parts[3] == "true",
+            })
+        }
+        "sketch-mode" => {
+            let mode = [StrokeKind::Coastline, StrokeKind::Ridge].into_iter().find(|kind| kind.name() == args);
+            if mode.is_none() && args != "off" {
+                return Err(format!("unknown sketch mode {}", args));
+            }
+            GuiEvent::SetSketchMode(mode)
+        }
+        "season" => match args {
+            "year" => GuiEvent::SetSeason(None),
+            name => GuiEvent::SetSeason(Some(name.parse()?)),
+        },
+        "sketch" => {
+            let strokes = args.split('|').map(str::trim).filter(|stroke| !stroke.is_empty())
+                .map(|stroke| {
+                    let mut parts = stroke.split(' ');
+                    let kind = parts.next().unwrap_or("");
+                    let kind = [StrokeKind::Coastline, StrokeKind::Ridge].into_iter().find(|found| found.name() == kind)
+                        .ok_or_else(|| format!("unknown stroke kind {}", kind))?;
+                    let mut stroke = Stroke::new(kind, number(parts.next().unwrap_or(""))?);
+                    for point in parts {
+                        let (x, y) = point.split_once(',').ok_or_else(|| format!("invalid point {}", point))?;
+                        stroke.points.push((number(x)?, number(y)?));
+                    }
+                    Ok(stroke)
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            GuiEvent::SetSketch(strokes)
+        }
+        "apply-sketch" => GuiEvent::ApplySketch,
+        "clear-sketch" => GuiEvent::ClearSketch,
+        "stamp" => match args {
+            "off" => GuiEvent::SelectStamp(None),
+            idx => GuiEvent::SelectStamp(Some(idx.parse().map_err(|_| format!("invalid stamp {}", idx))?)),
+        },
+        "river-cutoff" => GuiEvent::SetRiverCutoff(number(args)?),
+        // Recordings from before the sea level was a parameter
+        "sea-level" => GuiEvent::SetParam(Param::SeaLevel, number(args)?),
+        "terrain-levels" => {
+            let parts: Vec<_> = args.split(' ').collect();
+            if parts.len() != 2 {
+                return Err("terrain-levels needs two values".to_owned());
+            }
+            GuiEvent::SetTerrainLevels(TerrainLevels { highland: number(parts[0])?, peak: number(parts[1])? })
+        }
+        "param" => {
+            let (key, value) = args.split_once(' ').ok_or_else(|| "param needs a name and a value".to_owned())?;
+            GuiEvent::SetParam(key.parse()?, number(value)?)
+        }
+        "stamp-radius" => GuiEvent::SetStampRadius(number(args)? as f32),
+        "stamp-rotation" => GuiEvent::SetStampRotation(number(args)? as f32),
+        // Recordings from before the export wrote the gazetteer too name it after the hex grid
+        "export" | "export-hex-grid" => GuiEvent::Export,
+        "export-civilization" => GuiEvent::ExportCivilization,
+        "import-civilization" => GuiEvent::ImportCivilization,
+        "save-world" => GuiEvent::SaveWorld,
+        "load-world" => GuiEvent::LoadWorld,
+        "place-stamp" => {
+            let (x, y) = args.split_once(' ').ok_or_else(|| "place-stamp needs two coordinates".to_owned())?;
+            GuiEvent::PlaceStamp(number(x)?, number(y)?)
+        }
+        "start-copy" => GuiEvent::StartCopy,
+        "copy-region" => {
+            let (x, y) = args.split_once(' ').ok_or_else(|| "copy-region needs two coordinates".to_owned())?;
+            GuiEvent::CopyRegion(number(x)?, number(y)?)
+        }
+        "regenerate" => GuiEvent::Regenerate(args.trim().parse().map_err(|_| format!("invalid seed {}", args))?),
+        "inspect" => {
+            let (x, y) = args.split_once(' ').ok_or_else(|| "inspect needs two coordinates".to_owned())?;
+            GuiEvent::Inspect(number(x)?, number(y)?)
+        }
+        "close-inspector" => GuiEvent::CloseInspector,
+        "cell-note" => GuiEvent::SetCellNote(unescape(args)),
+        "feature-note" => {
+            let (id, text) = args.split_once(' ').unwrap_or((args, ""));
+            let id = id.parse().map_err(|_| format!("invalid feature {}", id))?;
+            GuiEvent::SetFeatureNote(FeatureId::from_idx(id), unescape(text))
+        }
+        _ => return Err(format!("unknown event {}", command)),
+    };
+    Ok(event)
+}
+
+// Events are one per line, so line breaks in free text are escaped
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use world::params::Param;
+
+use crate::camera::CameraView;
+use crate::effects::{Effects, Lighting};
+use crate::map_size::MapSize;
+use crate::painter::ViewMode;
+use crate::vector_field::VectorFieldStyle;
+use crate::view_settings::{ViewSettings, ViewTuning};
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// What is needed to bring the application back as it was: the world, from its seed and map,
+// with what was made of it, and the state of the views
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub seed: u64,
+    pub seed_text: Option<String>,
+    pub config_hash: u32,
+    pub map_size: MapSize,
+    pub map_seed: u64,
+    // See World::export_civilization
+    pub civilization: String,
+    // The parameters of the sliders, the sea level among them, by key like the views
+    #[serde(default)]
+    pub params: Vec<(String, f64)>,
+    #[serde(default)]
+    pub camera: Option<CameraView>,
+    // Views by name, so that adding view modes does not break saved sessions
+    pub view: String,
+    pub debug_views: bool,
+    pub view_tunings: Vec<(String, ViewTuning)>,
+    pub vector_style: VectorFieldStyle,
+    pub effects: Effects,
+    pub lighting: Lighting,
+}
+
+impl Session {
+    // Those of the keys still known
+    pub fn params(&self) -> impl Iterator<Item = (Param, f64)> + '_ {
+        self.params.iter().filter_map(|(key, value)| Some((key.parse().ok()?, *value)))
+    }
+
+    pub fn view_mode(&self) -> Option<ViewMode> {
+        ViewMode::iter().find(|mode| mode.name() == self.view)
+    }
+
+    pub fn apply_tunings(&self, settings: &mut ViewSettings) {
+        *settings = ViewSettings::new();
+        for (name, tuning) in self.view_tunings.iter() {
+            if let Some(mode) = ViewMode::iter().find(|mode| mode.name() == name) {
+                settings.set(mode, *tuning);
+            }
+        }
+    }
+}
+
+// Saves the session every minute and on exit. A session left from the previous run is kept
+// aside in memory until it is restored or discarded, while the current one is saved over it.
+pub(crate) struct SessionManager {
+    path: String,
+    last_save: Instant,
+    previous: Option<Session>,
+}
+
+impl SessionManager {
+    // A missing session file means there is nothing to restore
+    pub fn new(path: &str) -> Self {
+        let previous = std::fs::read_to_string(path).ok().and_then(|text| {
+            serde_json::from_str(&text)
+                .map_err(|err| eprintln!("Failed to parse {}: {}", path, err))
+                .ok()
+        });
+        Self { path: path.to_owned(), last_save: Instant::now(), previous }
+    }
+
+    pub fn previous(&self) -> Option<&Session> {
+        self.previous.as_ref()
+    }
+
+    pub fn take_previous(&mut self) -> Option<Session> {
+        self.previous.take()
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_save.elapsed() >= AUTOSAVE_INTERVAL
+    }
+
+    // Written aside and then moved over the old file, so that a crash while saving never
+    // leaves half a session
+    pub fn save(&mut self, session: &Session) {
+        self.last_save = Instant::now();
+        let temporary = format!("{}.tmp", self.path);
+        let saved = serde_json::to_string(session)
+            .map_err(|err| err.to_string())
+            .and_then(|text| std::fs::write(&temporary, text).map_err(|err| err.to_string()))
+            .and_then(|()| std::fs::rename(&temporary, &self.path).map_err(|err| err.to_string()));
+        if let Err(err) = saved {
+            eprintln!("Failed to save {}: {}", self.path, err);
+        }
+    }
+}
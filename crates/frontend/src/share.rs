@@ -0,0 +1,172 @@
+use world::generation::Generation;
+use world::params::Param;
+use world::quality::StageQualities;
+use world::seed::SeedTree;
+use world::World;
+
+// Share strings pack everything needed to regenerate a world: the seed, a hash of the config
+// it was generated with, the version of the generator, the quality each stage ran at, which
+// the stage budget may have lowered, and the parameters moved away from those of the config.
+pub struct ShareString {
+    pub seed: u64,
+    pub config_hash: u32,
+    pub version: [u8; 3],
+    // Missing from the strings shared before they were kept
+    pub qualities: Option<StageQualities>,
+    // Those which are not in the list have the value of the config
+    pub params: Vec<(Param, f64)>,
+}
+
+// The seed, the config hash and the version, then the qualities, then a bit per parameter of
+// Param::ALL followed by the values of those set
+const UNQUALIFIED_LEN: usize = 8 + 4 + 3;
+const HEADER_LEN: usize = UNQUALIFIED_LEN + 2 + 2;
+
+impl ShareString {
+    pub fn new(seed: u64, config_hash: u32, qualities: Option<StageQualities>) -> Self {
+        Self {
+            seed,
+            config_hash,
+            version: current_version(),
+            qualities,
+            params: vec![],
+        }
+    }
+
+    // Keeps the parameters whose values are not those of the config
+    pub fn with_params(mut self, params: &[(Param, f64)], defaults: &[(Param, f64)]) -> Self {
+        self.params = params.iter().copied().filter(|param| !defaults.contains(param)).collect();
+        self
+    }
+
+    pub fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 8 * self.params.len());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.config_hash.to_le_bytes());
+        bytes.extend_from_slice(&self.version);
+        if let Some(qualities) = self.qualities {
+            bytes.extend_from_slice(&qualities.to_bits().to_le_bytes());
+            let set = |param: &Param| self.params.iter().find(|(set, _)| set == param).map(|&(_, value)| value);
+            let bits = Param::ALL.iter().enumerate()
+                .filter(|(_, param)| set(param).is_some())
+                .fold(0u16, |bits, (idx, _)| bits | 1 << idx);
+            bytes.extend_from_slice(&bits.to_le_bytes());
+            for value in Param::ALL.iter().filter_map(set) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let bytes = base64::decode_config(text.trim(), base64::URL_SAFE_NO_PAD)
+            .map_err(|err| format!("Invalid share string: {}", err))?;
+        if bytes.len() != UNQUALIFIED_LEN && bytes.len() < HEADER_LEN {
+            return Err(format!("Invalid share string: expected at least {} bytes, found {}", HEADER_LEN, bytes.len()));
+        }
+
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&bytes[0..8]);
+        let mut config_hash = [0; 4];
+        config_hash.copy_from_slice(&bytes[8..12]);
+        let mut version = [0; 3];
+        version.copy_from_slice(&bytes[12..15]);
+        let mut shared = Self {
+            seed: u64::from_le_bytes(seed),
+            config_hash: u32::from_le_bytes(config_hash),
+            version,
+            qualities: None,
+            params: vec![],
+        };
+        if bytes.len() == UNQUALIFIED_LEN {
+            return Ok(shared);
+        }
+
+        let qualities = StageQualities::from_bits(u16::from_le_bytes([bytes[15], bytes[16]]))
+            .ok_or_else(|| "Invalid share string: unknown stage qualities".to_owned())?;
+        shared.qualities = Some(qualities);
+        let bits = u16::from_le_bytes([bytes[17], bytes[18]]);
+        let params: Vec<_> = Param::ALL.iter().enumerate().filter(|(idx, _)| bits & 1 << idx != 0).map(|(_, &param)| param).collect();
+        if bits >> Param::ALL.len() != 0 || bytes.len() != HEADER_LEN + 8 * params.len() {
+            return Err("Invalid share string: the parameters do not match their values".to_owned());
+        }
+        for (param, value) in params.into_iter().zip(bytes[HEADER_LEN..].chunks_exact(8)) {
+            let mut le = [0; 8];
+            le.copy_from_slice(value);
+            shared.params.push((param, f64::from_le_bytes(le)));
+        }
+        Ok(shared)
+    }
+
+    // Sets the parameters of the world to the shared ones, the others to the defaults, and
+    // returns the generation of the shared world, at the quality its stages ran at
+    pub fn generation(&self, world: &mut World, defaults: &[(Param, f64)]) -> Generation {
+        for &(param, default) in defaults.iter() {
+            let shared = self.params.iter().find(|(shared, _)| *shared == param).map(|&(_, value)| value);
+            world.set_param(param, shared.unwrap_or(default));
+        }
+        let generation = Generation::new(SeedTree::new(self.seed));
+        match self.qualities {
+            Some(qualities) => generation.with_qualities(qualities),
+            None => {
+                eprintln!("Warning: the world was shared without the quality of its stages");
+                generation
+            }
+        }
+    }
+
+    // Describes why the world may not come out exactly as it was shared
+    pub fn mismatches(&self, config_hash: u32) -> Vec<String> {
+        let mut mismatches = vec![];
+        if self.config_hash != config_hash {
+            mismatches.push("the world was shared with a different config".to_owned());
+        }
+        if self.version != current_version() {
+            let [major, minor, patch] = self.version;
+            mismatches.push(format!("the world was shared from version {}.{}.{}", major, minor, patch));
+        }
+        mismatches
+    }
+}
+
+fn current_version() -> [u8; 3] {
+    let part = |s: &str| s.parse().unwrap_or(0);
+    [
+        part(env!("CARGO_PKG_VERSION_MAJOR")),
+        part(env!("CARGO_PKG_VERSION_MINOR")),
+        part(env!("CARGO_PKG_VERSION_PATCH")),
+    ]
+}
+
+// 32 bit FNV-1a, which unlike the std hashers is stable across platforms and releases
+pub fn stable_hash(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c9dc5, |hash, &byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+#[cfg(test)]
+mod tests {
+    use world::quality::Quality;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_with_the_changed_params() {
+        let qualities = StageQualities([Quality::Normal, Quality::Normal, Quality::Draft, Quality::Draft, Quality::Draft, Quality::Draft, Quality::Draft]);
+        let defaults = [(Param::LowIntensity, 1.0), (Param::AxialTilt, 23.5), (Param::SeaLevel, 0.3)];
+        let params = [(Param::LowIntensity, 1.0), (Param::AxialTilt, 40.0), (Param::SeaLevel, 0.45)];
+        let text = ShareString::new(7, 0xdeadbeef, Some(qualities)).with_params(&params, &defaults).encode();
+
+        let shared = ShareString::decode(&text).unwrap();
+        assert_eq!((shared.seed, shared.config_hash, shared.qualities), (7, 0xdeadbeef, Some(qualities)));
+        assert_eq!(shared.params, vec![(Param::AxialTilt, 40.0), (Param::SeaLevel, 0.45)]);
+        assert!(ShareString::decode(&text[..text.len() - 4]).is_err());
+    }
+
+    #[test]
+    fn reads_the_strings_shared_before_the_qualities() {
+        let text = base64::encode_config([42, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 0, 1, 0], base64::URL_SAFE_NO_PAD);
+        let shared = ShareString::decode(&text).unwrap();
+        assert_eq!((shared.seed, shared.config_hash, shared.qualities), (42, 0x04030201, None));
+        assert!(shared.params.is_empty());
+    }
+}
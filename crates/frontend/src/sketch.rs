@@ -0,0 +1,82 @@
+use macroquad::prelude as mq;
+use macroquad::prelude::MouseButton;
+use world::constraint::{Stroke, StrokeKind};
+
+use crate::camera::MapCamera;
+
+const STROKE_RADIUS: f64 = 40.0;
+
+pub struct Sketch {
+    mode: Option<StrokeKind>,
+    strokes: Vec<Stroke>,
+    current: Option<Stroke>,
+}
+
+impl Sketch {
+    pub fn new() -> Self {
+        Self {
+            mode: None,
+            strokes: vec![],
+            current: None,
+        }
+    }
+
+    pub fn mode(&self) -> Option<StrokeKind> { self.mode }
+
+    pub fn set_mode(&mut self, mode: Option<StrokeKind>) {
+        self.finish_stroke();
+        self.mode = mode;
+    }
+
+    pub fn strokes(&self) -> &[Stroke] { &self.strokes }
+
+    // Those of a recording, in place of the ones drawn
+    pub fn set_strokes(&mut self, strokes: Vec<Stroke>) {
+        self.strokes = strokes;
+        self.current = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.strokes.clear();
+        self.current = None;
+    }
+
+    // Records the stroke under the mouse (in map coordinates). Returns true if the
+    // mouse was consumed by the sketching tool.
+    pub fn handle_mouse(&mut self, x: f64, y: f64) -> bool {
+        let kind = match self.mode {
+            Some(kind) => kind,
+            None => return false,
+        };
+
+        if mq::is_mouse_button_down(MouseButton::Left) {
+            let stroke = self.current.get_or_insert_with(|| Stroke::new(kind, STROKE_RADIUS));
+            if stroke.points.last() != Some(&(x, y)) {
+                stroke.points.push((x, y));
+            }
+        } else {
+            self.finish_stroke();
+        }
+        true
+    }
+
+    fn finish_stroke(&mut self) {
+        if let Some(stroke) = self.current.take() {
+            self.strokes.push(stroke);
+        }
+    }
+
+    pub fn draw(&self, camera: &MapCamera) {
+        for stroke in self.strokes.iter().chain(self.current.iter()) {
+            let color = match stroke.kind {
+                StrokeKind::Coastline => mq::DARKBLUE,
+                StrokeKind::Ridge => mq::ORANGE,
+            };
+            for segment in stroke.points.windows(2) {
+                let (x1, y1) = camera.to_screen(segment[0].0 as f32, segment[0].1 as f32);
+                let (x2, y2) = camera.to_screen(segment[1].0 as f32, segment[1].1 as f32);
+                mq::draw_line(x1, y1, x2, y2, 3.0, color);
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use macroquad::prelude as mq;
+use world::stamp::{Placement, Stamp};
+use world::World;
+
+use crate::raster;
+
+pub struct StampTool {
+    library: Vec<Stamp>,
+    selected: Option<usize>,
+    // Whether the next click copies the region under the outline, rather than placing a stamp
+    copying: bool,
+    copies: usize,
+    radius: f32,
+    rotation: f32,
+}
+
+impl StampTool {
+    pub fn new(library: Vec<Stamp>) -> Self {
+        Self {
+            library,
+            selected: None,
+            copying: false,
+            copies: 0,
+            radius: 80.0,
+            rotation: 0.0,
+        }
+    }
+
+    // Loads every square PNG of a content pack directory as a stamp named after its file
+    pub fn load_content_pack(&mut self, dir: &str) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                eprintln!("Failed to open stamp directory {}: {}", dir, err);
+                return;
+            }
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            match std::fs::read(&path).map_err(|err| err.to_string()).and_then(|bytes| raster::decode(&bytes)) {
+                Ok(image) if image.width() == image.height() => {
+                    self.library.push(Stamp::from_rgba(&name, image.width(), &image.bytes));
+                }
+                Ok(_) => eprintln!("Stamp {} is not square, skipping it", path.display()),
+                Err(err) => eprintln!("Failed to load stamp {}: {}", path.display(), err),
+            }
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.library.iter().map(|stamp| stamp.name()).collect()
+    }
+
+    pub fn selected(&self) -> Option<usize> { self.selected }
+    pub fn select(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+        self.copying = false;
+    }
+
+    pub fn is_copying(&self) -> bool { self.copying }
+    pub fn start_copying(&mut self) {
+        self.selected = None;
+        self.copying = true;
+    }
+
+    // Adds the copy of a region to the library, selected so that the next click pastes it
+    pub fn add_copy(&mut self, world: &World, x: f64, y: f64) {
+        self.copies += 1;
+        let copy = Stamp::copy_of(world, &format!("Copy {}", self.copies), &self.region_at(x, y));
+        self.library.push(copy);
+        self.select(Some(self.library.len() - 1));
+    }
+
+    pub fn radius(&self) -> f32 { self.radius }
+    pub fn set_radius(&mut self, radius: f32) { self.radius = radius }
+
+    pub fn rotation(&self) -> f32 { self.rotation }
+    pub fn set_rotation(&mut self, rotation: f32) { self.rotation = rotation }
+
+    pub fn is_active(&self) -> bool { self.selected.is_some() || self.copying }
+
+    pub fn placement_at(&self, x: f64, y: f64) -> Option<(&Stamp, Placement)> {
+        let stamp = self.library.get(self.selected?)?;
+        Some((stamp, self.region_at(x, y)))
+    }
+
+    fn region_at(&self, x: f64, y: f64) -> Placement {
+        Placement {
+            x,
+            y,
+            rotation: (self.rotation as f64).to_radians(),
+            radius: self.radius as f64,
+        }
+    }
+
+    // Outline of the area the stamp would cover, or the copy take, centered on the mouse
+    pub fn draw_preview(&self, screen_x: f32, screen_y: f32, scale_x: f32, scale_y: f32) {
+        if !self.is_active() {
+            return;
+        }
+        let t = self.rotation.to_radians();
+        let corners: Vec<_> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter()
+            .map(|&(u, v): &(f32, f32)| {
+                let x = self.radius * (u * t.cos() - v * t.sin());
+                let y = self.radius * (u * t.sin() + v * t.cos());
+                (screen_x + x / scale_x, screen_y + y / scale_y)
+            })
+            .collect();
+        for i in 0..corners.len() {
+            let (x1, y1) = corners[i];
+            let (x2, y2) = corners[(i + 1) % corners.len()];
+            mq::draw_line(x1, y1, x2, y2, 2.0, if self.copying { mq::SKYBLUE } else { mq::RED });
+        }
+    }
+}
@@ -1,7 +1,30 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
 use lyon::{lyon_tessellation::VertexBuffers, geom::euclid::{Point2D, UnknownUnit}};
 use macroquad::prelude as mq;
-use polymap::{PolyMap, CellId};
+use lyon::tessellation::{FillOptions, FillTessellator};
+use polymap::field::Field;
+use polymap::{Cell, CellId, PolyMap};
+
+// Lower tolerances give smoother shapes, at the price of more triangles and slower tessellation
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TessellationOptions {
+    pub fill_tolerance: f32,
+    // Also decides how finely the curves of the rivers are subdivided
+    pub stroke_tolerance: f32,
+}
 
+impl Default for TessellationOptions {
+    fn default() -> Self {
+        Self {
+            fill_tolerance: 0.1,
+            stroke_tolerance: 0.1,
+        }
+    }
+}
+
+const CACHE_MAGIC: &[u8; 4] = b"TESS";
 
 pub type Triangle = [mq::Vec2; 3];
 pub struct GridTessellation {
@@ -9,52 +32,125 @@ pub struct GridTessellation {
 }
 
 impl GridTessellation {
-    pub fn new(poly: &PolyMap) -> Self {
+    // Loads the tessellation from the cache directory if a file for the same map and options
+    // exists, otherwise tessellates and stores the result there
+    pub fn cached(poly: &PolyMap, options: &TessellationOptions, cache_dir: Option<&str>) -> Self {
+        let dir = match cache_dir {
+            Some(dir) => dir,
+            None => return Self::new(poly, options),
+        };
+        let key = poly.fingerprint()
+            ^ (options.fill_tolerance.to_bits() as u64)
+            ^ ((options.stroke_tolerance.to_bits() as u64) << 32);
+        let path = format!("{}/{:016x}.tess", dir, key);
+
+        match Self::read(&path, poly) {
+            Ok(tessellation) => return tessellation,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("Ignoring tessellation cache {}: {}", path, err),
+        }
+
+        let tessellation = Self::new(poly, options);
+        let written = std::fs::create_dir_all(dir).and_then(|_| tessellation.write(&path));
+        if let Err(err) = written {
+            eprintln!("Failed to write tessellation cache {}: {}", path, err);
+        }
+        tessellation
+    }
+
+    // The cells are tessellated on their own, on every core with the parallel feature
+    pub fn new(poly: &PolyMap, options: &TessellationOptions) -> Self {
+        let options = FillOptions::tolerance(options.fill_tolerance);
+        let cells = Field::par_with_fn(poly, |_, cell| Self::tessellate_cell(poly, cell, &options));
+        Self { cells: cells.into_vec() }
+    }
+
+    fn tessellate_cell(poly: &PolyMap, cell: &Cell, options: &FillOptions) -> Vec<Triangle> {
         use lyon::math::Point;
         use lyon::path::builder::*;
         use lyon::tessellation::geometry_builder::simple_builder;
-        use lyon::tessellation::{FillOptions, FillTessellator};
 
-        let mut cells = vec![];
+        let points: Vec<_> = cell
+            .polygon()
+            .exterior()
+            .points_iter()
+            .map(|p| lyon::geom::point(p.x() as f32, poly.height() as f32 - p.y() as f32))
+            .collect();
+        let polygon = lyon::path::Polygon {
+            points: points.as_slice(),
+            closed: true,
+        };
+
         let mut geometry = VertexBuffers::<Point, u16>::new();
-        {
-            let options = FillOptions::tolerance(0.1);
-            let mut tessellator = FillTessellator::new();
-            for (_, cell) in poly.cells() {
-                let points: Vec<_> = cell
-                    .polygon()
-                    .exterior()
-                    .points_iter()
-                    .map(|p| lyon::geom::point(p.x() as f32, poly.height() as f32 - p.y() as f32))
-                    .collect();
-                let polygon = lyon::path::Polygon {
-                    points: points.as_slice(),
-                    closed: true,
+        let mut tessellator = FillTessellator::new();
+        let mut geometry_builder = simple_builder(&mut geometry);
+        let mut builder = tessellator.builder(options, &mut geometry_builder);
+        builder.add_polygon(polygon);
+        builder.build().unwrap();
+
+        geometry.indices.chunks(3)
+            .map(|triangle| {
+                let make_vertex = |idx: usize| {
+                    let v: &lyon::math::Point = &geometry.vertices[triangle[idx] as usize];
+                    mq::Vec2::new(v.x, v.y)
                 };
+                [make_vertex(0), make_vertex(1), make_vertex(2)]
+            })
+            .collect()
+    }
 
-                geometry.vertices.clear();
-                geometry.indices.clear();
-                let mut geometry_builder = simple_builder(&mut geometry);
-                let mut builder = tessellator.builder(&options, &mut geometry_builder);
-                builder.add_polygon(polygon);
-                builder.build().unwrap();
-
-                let mut triangles = vec![];
-                for triangle in geometry.indices.chunks(3) {
-                    let make_vertex = |idx| {
-                        let v: &lyon::math::Point = &geometry.vertices[triangle[idx] as usize];
-                        mq::Vec2::new(v.x, v.y)
-                    };
-                    triangles.push([make_vertex(0), make_vertex(1), make_vertex(2)]);
-                }
-                cells.push(triangles);
+    pub fn polygon_of(&self, id:CellId) -> &[Triangle] {
+        self.cells[id.idx()].as_slice()
+    }
+
+    // The format is the magic, the number of cells, and for each cell the number of triangles
+    // followed by their coordinates, all little endian
+    fn write(&self, path: &str) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(CACHE_MAGIC)?;
+        file.write_all(&(self.cells.len() as u32).to_le_bytes())?;
+        for triangles in self.cells.iter() {
+            file.write_all(&(triangles.len() as u32).to_le_bytes())?;
+            for vertex in triangles.iter().flatten() {
+                file.write_all(&vertex.x.to_le_bytes())?;
+                file.write_all(&vertex.y.to_le_bytes())?;
             }
         }
-        Self { cells }
+        file.flush()
     }
 
-    pub fn polygon_of(&self, id:CellId) -> &[Triangle] {
-        self.cells[id.idx()].as_slice()
+    fn read(path: &str, poly: &PolyMap) -> std::io::Result<Self> {
+        let invalid = |message| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut file = BufReader::new(File::open(path)?);
+        let mut word = [0; 4];
+        let mut read_u32 = |file: &mut BufReader<File>| file.read_exact(&mut word).map(|_| u32::from_le_bytes(word));
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != CACHE_MAGIC {
+            return Err(invalid("not a tessellation file"));
+        }
+        let len = read_u32(&mut file)? as usize;
+        if len != poly.cells().count() {
+            return Err(invalid("made for a different map"));
+        }
+
+        let mut cells = Vec::with_capacity(len);
+        for _ in 0..len {
+            let count = read_u32(&mut file)? as usize;
+            let mut triangles = Vec::with_capacity(count);
+            for _ in 0..count {
+                let mut triangle = [mq::Vec2::ZERO; 3];
+                for vertex in triangle.iter_mut() {
+                    let x = f32::from_bits(read_u32(&mut file)?);
+                    let y = f32::from_bits(read_u32(&mut file)?);
+                    *vertex = mq::Vec2::new(x, y);
+                }
+                triangles.push(triangle);
+            }
+            cells.push(triangles);
+        }
+        Ok(Self { cells })
     }
 }
 
@@ -74,7 +170,7 @@ pub struct PathTessellation {
 }
 
 impl PathTessellation {
-    pub fn with_points(points: &[(f64, f64)], thickness: f32, closed: bool) -> Option<Self>{
+    pub fn with_points(points: &[(f64, f64)], thickness: f32, closed: bool, tolerance: f32) -> Option<Self>{
         if points.len() < 2 {
             return None
         }
@@ -111,7 +207,7 @@ impl PathTessellation {
             let path = path_builder.build();
         
             let mut tessellator = StrokeTessellator::new();
-            let options = StrokeOptions::default().with_line_width(thickness);
+            let options = StrokeOptions::tolerance(tolerance).with_line_width(thickness);
             tessellator.tessellate_path(&path, &options, &mut geometry_builder).unwrap();
         }
 
@@ -119,16 +215,39 @@ impl PathTessellation {
         Some(Self { triangles })
     }
 
-    pub fn path_of_cells(poly: &PolyMap, cells: &[CellId], thickness: f32) -> Option<Self> {
+    pub fn path_of_cells(poly: &PolyMap, cells: &[CellId], thickness: f32, tolerance: f32) -> Option<Self> {
         let closed = cells.first() == cells.last();
         let centers:Vec<_> = cells.iter().map(|&id| {
             let (x, y) = poly[id].center();
             (x, poly.height() as f64 - y)
         }).collect();
-        Self::with_points(centers.as_slice(), thickness, closed)
+        Self::with_points(centers.as_slice(), thickness, closed, tolerance)
     }
 
     pub fn polygon(&self) -> &[Triangle] {
         &self.triangles
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // Like the tessellation fuzz target, on maps drawn from fixed seeds
+    #[test]
+    fn cells_tessellate_into_finite_triangles() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        for seed in 0..8 {
+            let poly = PolyMap::with_seed(rng.gen_range(32..288), rng.gen_range(32..288), rng.gen_range(4..17) as f64, seed);
+            let options = TessellationOptions { fill_tolerance: rng.gen_range(0.01..1.01), ..TessellationOptions::default() };
+            let tessellation = GridTessellation::new(&poly, &options);
+            for (id, _) in poly.cells() {
+                for triangle in tessellation.polygon_of(id) {
+                    assert!(triangle.iter().all(|point| point.x.is_finite() && point.y.is_finite()));
+                }
+            }
+        }
+    }
+}
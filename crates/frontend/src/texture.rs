@@ -0,0 +1,103 @@
+use macroquad::prelude as mq;
+use polymap::*;
+use world::seed::SeedTree;
+use world::{LandBiome, TerrainCategory, World};
+
+// Distance between the texels of the fills, in map units. Like the patterns, they sit on a
+// lattice over the whole map.
+const SPACING: f64 = 3.0;
+// How far the texels lighten or darken the color of the cell, at most
+const SPECKLE: f32 = 0.1;
+const RIPPLES: f32 = 0.08;
+const ROCK: f32 = 0.16;
+
+// A procedural fill drawn over the flat color of a cell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Texture {
+    Flat,
+    // Grass and forest
+    Speckle,
+    // Dunes
+    Ripples,
+    // Highlands
+    Rock,
+}
+
+impl Texture {
+    // The land of the views of the ground gets textures, the sea and the lakes stay flat
+    pub fn of_cell(world: &World, cell: CellId) -> Self {
+        if world.terrain_category()[cell] != TerrainCategory::Land || world.is_lake(cell) {
+            return Texture::Flat;
+        }
+        if world.heightmap()[cell] >= world.levels().highland {
+            return Texture::Rock;
+        }
+        match world.land_biome()[cell] {
+            LandBiome::Desert | LandBiome::ColdDesert => Texture::Ripples,
+            LandBiome::None | LandBiome::Ice => Texture::Flat,
+            _ => Texture::Speckle,
+        }
+    }
+
+    // Between -1, darkest, and 1, lightest, at a texel of the cell. The same cell always
+    // gets the same texels.
+    fn shade(&self, cell: CellId, i: i64, j: i64) -> f32 {
+        let noise = |scale: i64| {
+            let texel = SeedTree::new(i.div_euclid(scale) as u64).indexed(j.div_euclid(scale) as u64);
+            let seed = SeedTree::new(texel.seed()).child("texture").cell(cell).seed();
+            (seed >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+        };
+        match self {
+            Texture::Flat => 0.0,
+            Texture::Speckle => SPECKLE * noise(1),
+            Texture::Ripples => {
+                // Waves across the map, bent a little so that they read as sand
+                let (x, y) = (i as f64 * SPACING, j as f64 * SPACING);
+                RIPPLES * ((y + 4.0 * (x * 0.08).sin()) * 0.5).sin() as f32
+            }
+            Texture::Rock => ROCK * (0.6 * noise(3) + 0.4 * noise(1)),
+        }
+    }
+
+    // The color of the cell at a point, in map units with y downwards
+    pub fn apply(&self, color: mq::Color, cell: CellId, x: f64, y: f64) -> mq::Color {
+        let shade = self.shade(cell, (x / SPACING).floor() as i64, (y / SPACING).floor() as i64);
+        let ink = if shade > 0.0 { mq::WHITE } else { mq::BLACK };
+        let t = shade.abs();
+        mq::Color::new(
+            color.r + (ink.r - color.r) * t,
+            color.g + (ink.g - color.g) * t,
+            color.b + (ink.b - color.b) * t,
+            color.a,
+        )
+    }
+}
+
+// Draws the texels of each cell over it, as translucent squares which lighten or darken it
+// like Texture::apply. The map is drawn with y upwards.
+pub fn draw(poly: &PolyMap, cells: &[(CellId, Texture)]) {
+    let height = poly.height() as f64;
+    let size = SPACING as f32;
+    for &(id, texture) in cells {
+        if texture == Texture::Flat {
+            continue;
+        }
+        let cell = poly.cell(id);
+        let points = cell.polygon().exterior().points_iter().map(|p| (p.x(), p.y()));
+        let (min_x, max_x, min_y, max_y) = points.fold((f64::MAX, f64::MIN, f64::MAX, f64::MIN), |(x0, x1, y0, y1), (x, y)| {
+            (x0.min(x), x1.max(x), y0.min(y), y1.max(y))
+        });
+        for j in (min_y / SPACING).floor() as i64..=(max_y / SPACING).floor() as i64 {
+            for i in (min_x / SPACING).floor() as i64..=(max_x / SPACING).floor() as i64 {
+                let (x, y) = (i as f64 * SPACING, j as f64 * SPACING);
+                // A texel goes to the cell its center is in
+                if !cell.contains(x + 0.5 * SPACING, y + 0.5 * SPACING) {
+                    continue;
+                }
+                let shade = texture.shade(id, i, j);
+                let ink = if shade > 0.0 { mq::Color::new(1.0, 1.0, 1.0, shade) } else { mq::Color::new(0.0, 0.0, 0.0, -shade) };
+                mq::draw_rectangle(x as f32, (height - y) as f32 - size, size, size, ink);
+            }
+        }
+    }
+}
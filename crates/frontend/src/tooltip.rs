@@ -0,0 +1,112 @@
+use macroquad::prelude as mq;
+use polymap::CellId;
+use world::measure::DRAIN;
+use world::season::Season;
+use world::{LandBiome, TerrainCategory, World};
+
+use crate::painter::ViewMode;
+
+// How long the pointer rests on a cell before its tooltip shows, in seconds
+const DELAY: f64 = 0.5;
+const FONT_SIZE: f32 = 18.0;
+const LINE_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 6.0;
+// From the pointer to the corner of the tooltip, in screen pixels
+const OFFSET: f32 = 16.0;
+
+// The values of the view for the cell under the pointer, once the pointer rests on it
+pub(crate) struct Tooltip {
+    cell: Option<CellId>,
+    // When the pointer came over the cell
+    since: f64,
+}
+
+impl Tooltip {
+    pub fn new() -> Self {
+        Self { cell: None, since: 0.0 }
+    }
+
+    // The cell under the pointer, if the map has the pointer
+    pub fn hover(&mut self, cell: Option<CellId>) {
+        if cell != self.cell {
+            self.cell = cell;
+            self.since = mq::get_time();
+        }
+    }
+
+    pub fn draw(&self, world: &World, mode: ViewMode, season: Option<Season>, screen_x: f32, screen_y: f32) {
+        let cell = match self.cell {
+            Some(cell) if mq::get_time() - self.since >= DELAY => cell,
+            _ => return,
+        };
+        let lines = lines(world, mode, season, cell);
+        if lines.is_empty() {
+            return;
+        }
+        let width = lines.iter()
+            .map(|line| mq::measure_text(line, None, FONT_SIZE as u16, 1.0).width)
+            .fold(0.0, f32::max) + 2.0 * PADDING;
+        let height = LINE_HEIGHT * lines.len() as f32 + 2.0 * PADDING;
+        // Kept on the screen, flipped to the other side of the pointer near the edges
+        let x = if screen_x + OFFSET + width > mq::screen_width() { screen_x - OFFSET - width } else { screen_x + OFFSET };
+        let y = if screen_y + OFFSET + height > mq::screen_height() { screen_y - OFFSET - height } else { screen_y + OFFSET };
+        mq::draw_rectangle(x, y, width, height, mq::Color::new(0.0, 0.0, 0.0, 0.75));
+        for (idx, line) in lines.iter().enumerate() {
+            let baseline = y + PADDING + LINE_HEIGHT * (idx as f32 + 0.75);
+            mq::draw_text(line, x + PADDING, baseline, FONT_SIZE, mq::WHITE);
+        }
+    }
+}
+
+// The key values of the cell for what the view shows
+fn lines(world: &World, mode: ViewMode, season: Option<Season>, cell: CellId) -> Vec<String> {
+    let sea = world.terrain_category()[cell] == TerrainCategory::Sea;
+    match mode {
+        ViewMode::Heightmap | ViewMode::Geography => vec![
+            format!("Elevation: {:.0} m", world.elevation(cell)),
+            format!("Height: {:.3}", world.heightmap()[cell]),
+        ],
+        ViewMode::Temperature => match season {
+            Some(season) => vec![
+                format!("Temperature in {}: {:.1} °C", season.name(), world.cell_temperature_at(cell, season)),
+                format!("Over the year: {:.1} °C", world.celsius()[cell]),
+            ],
+            None => vec![format!("Temperature: {:.1} °C", world.celsius()[cell])],
+        },
+        ViewMode::Precipitation => vec![
+            format!("Rainfall: {:.0} mm/yr", world.rainfall_mm(cell)),
+            format!("Water balance: {:+.0} mm/yr", world.water_balance(cell)),
+        ],
+        ViewMode::Drainage => {
+            let mut lines = vec![DRAIN.format(world.drainage()[cell])];
+            if let Some(river) = world.river_at(cell) {
+                lines.push(format!("River: {}", river.name().unwrap_or("unnamed")));
+            }
+            lines
+        }
+        ViewMode::Biome => vec![format!("Cover: {}", world.cover(cell))],
+        ViewMode::LandBiome => {
+            let biome = world.land_biome()[cell];
+            if sea || biome == LandBiome::None {
+                return vec![];
+            }
+            vec![
+                format!("Biome: {}", biome.name()),
+                format!("Soil moisture: {:.2}", world.cell_moisture(cell)),
+                format!("Fauna: {}", world.fauna()[cell].name()),
+            ]
+        }
+        ViewMode::Hazards => {
+            let hazards = world.hazards()[cell];
+            if sea || hazards.is_empty() {
+                return vec![];
+            }
+            vec![format!("Hazards: {}", hazards.names().join(", "))]
+        }
+        ViewMode::DebugLandform if sea => vec![],
+        ViewMode::DebugLandform => vec![format!("Landform: {}", world.landform()[cell].name())],
+        _ => mode.debug_field(world)
+            .map(|field| vec![format!("{}: {:.3}", mode.name(), field[cell])])
+            .unwrap_or_default(),
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use macroquad::prelude as mq;
+use serde_derive::{Deserialize, Serialize};
+use polymap::field::Field;
+use polymap::{CellId, PolyMap};
+use world::Vec2;
+
+use crate::painter::colors;
+
+const STREAMLINE_STEPS: usize = 12;
+
+// How vector fields (wind, currents) are drawn over a view
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct VectorFieldStyle {
+    // Minimum distance between two arrows, in screen pixels
+    pub spacing: f32,
+    pub scale_by_magnitude: bool,
+    pub color_by_magnitude: bool,
+    pub streamlines: bool,
+}
+
+impl Default for VectorFieldStyle {
+    fn default() -> Self {
+        Self {
+            spacing: 24.0,
+            scale_by_magnitude: true,
+            color_by_magnitude: true,
+            streamlines: false,
+        }
+    }
+}
+
+// Draws the field in map space with the y axis pointing up, like the rest of the painter.
+// Vectors are expected in the same convention. `map_per_pixel` is how many map units a
+// screen pixel covers, so that the arrow density stays the same whatever the window size.
+pub fn draw(poly: &PolyMap, field: &Field<Vec2>, style: &VectorFieldStyle, map_per_pixel: f32) {
+    let spacing = (style.spacing * map_per_pixel).max(1.0) as f64;
+    let max_magnitude = poly.cells().map(|(id, _)| field[id].length()).fold(0.0, f64::max);
+    if max_magnitude <= 0.0 {
+        return;
+    }
+    let height = poly.height() as f64;
+
+    for id in decimate(poly, field, spacing) {
+        let vector = field[id];
+        let magnitude = (vector.length() / max_magnitude) as f32;
+        let color = if style.color_by_magnitude {
+            colors::interpolate_three_colors(mq::BLUE, mq::YELLOW, mq::RED, magnitude)
+        } else {
+            mq::Color::new(1.0, 0.0, 0.0, 1.0)
+        };
+        let (cx, cy) = poly[id].center();
+        let center = (cx, height - cy);
+
+        if style.streamlines {
+            draw_streamline(poly, field, id, center, spacing / 4.0, color);
+        }
+
+        let length = if style.scale_by_magnitude { spacing * 0.8 * magnitude as f64 } else { spacing * 0.5 };
+        let direction = vector.y.atan2(vector.x);
+        let (dx, dy) = (direction.cos() * length / 2.0, direction.sin() * length / 2.0);
+        let tail = mq::Vec2::new((center.0 - dx) as f32, (center.1 - dy) as f32);
+        let tip = mq::Vec2::new((center.0 + dx) as f32, (center.1 + dy) as f32);
+        mq::draw_line(tail.x, tail.y, tip.x, tip.y, 1.5, color);
+
+        let head = (length * 0.3).clamp(2.0, 5.0) as f32;
+        let left = direction + f64::to_radians(150.0);
+        let right = direction - f64::to_radians(150.0);
+        mq::draw_triangle(
+            tip,
+            tip + mq::Vec2::new(left.cos() as f32, left.sin() as f32) * head,
+            tip + mq::Vec2::new(right.cos() as f32, right.sin() as f32) * head,
+            color,
+        );
+    }
+}
+
+// Keeps the strongest vector in every square of the given size
+fn decimate(poly: &PolyMap, field: &Field<Vec2>, spacing: f64) -> Vec<CellId> {
+    let mut bins: HashMap<(i64, i64), CellId> = HashMap::new();
+    for (id, cell) in poly.cells() {
+        if field[id].length() <= 0.0 {
+            continue;
+        }
+        let (x, y) = cell.center();
+        let bin = ((x / spacing).floor() as i64, (y / spacing).floor() as i64);
+        let best = bins.entry(bin).or_insert(id);
+        if field[id].length() > field[*best].length() {
+            *best = id;
+        }
+    }
+    let mut cells: Vec<_> = bins.into_values().collect();
+    cells.sort();
+    cells
+}
+
+// Follows the field forward from the cell, a fixed step at a time
+fn draw_streamline(poly: &PolyMap, field: &Field<Vec2>, start: CellId, center: (f64, f64), step: f64, color: mq::Color) {
+    let height = poly.height() as f64;
+    let color = mq::Color::new(color.r, color.g, color.b, color.a * 0.5);
+    let mut cell = start;
+    let (mut x, mut y) = center;
+    for _ in 0..STREAMLINE_STEPS {
+        let vector = field[cell];
+        let length = vector.length();
+        if length <= 0.0 {
+            break;
+        }
+        let (nx, ny) = (x + vector.x / length * step, y + vector.y / length * step);
+        if nx < 0.0 || ny < 0.0 || nx > poly.width() as f64 || ny > height {
+            break;
+        }
+        mq::draw_line(x as f32, y as f32, nx as f32, ny as f32, 1.0, color);
+        x = nx;
+        y = ny;
+        cell = poly.closest_cell(cell, x, height - y);
+    }
+}
@@ -0,0 +1,88 @@
+use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::painter::ViewMode;
+
+// How a value of a view is turned into an intensity, after scaling it
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AlphaCurve {
+    Linear,
+    // Both lift the low values, the logarithm more
+    Sqrt,
+    Log,
+}
+
+impl AlphaCurve {
+    pub const ALL: [AlphaCurve; 3] = [AlphaCurve::Linear, AlphaCurve::Sqrt, AlphaCurve::Log];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlphaCurve::Linear => "linear",
+            AlphaCurve::Sqrt => "sqrt",
+            AlphaCurve::Log => "log",
+        }
+    }
+
+    // Keeps 0 at 0 and 1 at 1
+    fn apply(&self, x: f64) -> f64 {
+        match self {
+            AlphaCurve::Linear => x,
+            AlphaCurve::Sqrt => x.sqrt(),
+            AlphaCurve::Log => (1.0 + 9.0 * x).log10(),
+        }
+    }
+}
+
+// Rendering parameters of a view: values are multiplied by the scale, bent by the curve,
+// clamped to [0, 1] and gamma corrected. The defaults leave the values as they are.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ViewTuning {
+    pub scale: f32,
+    pub gamma: f32,
+    pub curve: AlphaCurve,
+}
+
+impl Default for ViewTuning {
+    fn default() -> Self {
+        Self { scale: 1.0, gamma: 1.0, curve: AlphaCurve::Linear }
+    }
+}
+
+impl ViewTuning {
+    pub fn apply(&self, value: f64) -> f32 {
+        let scaled = (value * self.scale as f64).max(0.0);
+        let curved = self.curve.apply(scaled).clamp(0.0, 1.0);
+        curved.powf(1.0 / self.gamma.max(0.01) as f64) as f32
+    }
+}
+
+// The tuning of every view, kept while switching between them
+pub(crate) struct ViewSettings {
+    tunings: Vec<ViewTuning>,
+}
+
+impl ViewSettings {
+    pub fn new() -> Self {
+        Self { tunings: ViewMode::iter().map(|_| ViewTuning::default()).collect() }
+    }
+
+    pub fn get(&self, mode: ViewMode) -> ViewTuning {
+        self.tunings[index(mode)]
+    }
+
+    pub fn set(&mut self, mode: ViewMode, tuning: ViewTuning) {
+        self.tunings[index(mode)] = tuning;
+    }
+
+    // The views whose tuning was changed, by name
+    pub fn changed(&self) -> Vec<(String, ViewTuning)> {
+        ViewMode::iter()
+            .map(|mode| (mode.name().to_owned(), self.get(mode)))
+            .filter(|(_, tuning)| *tuning != ViewTuning::default())
+            .collect()
+    }
+}
+
+fn index(mode: ViewMode) -> usize {
+    ViewMode::iter().position(|other| other == mode).unwrap_or(0)
+}
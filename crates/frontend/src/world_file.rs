@@ -0,0 +1,106 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+
+use world::save::SavedWorld;
+
+// Bumped, with a migration added below, whenever a change to SavedWorld or to this file would
+// make older files fail to parse or load wrong
+pub const FORMAT: u32 = 1;
+
+// A generated world, with the seed it was generated from and what it was saved by
+#[derive(Serialize, Deserialize)]
+pub struct WorldFile {
+    pub format: u32,
+    // The version of the application that saved it
+    pub version: String,
+    // Of the config it was generated with, if known
+    pub config_hash: Option<u32>,
+    pub seed: u64,
+    pub world: SavedWorld,
+}
+
+// Brings a file of the format before it to the format after it, in place
+type Migration = fn(&mut serde_json::Map<String, Value>) -> Result<(), String>;
+
+// The migration at each index goes from that format to the next one
+const MIGRATIONS: [Migration; FORMAT as usize] = [
+    unversioned,
+];
+
+impl WorldFile {
+    pub fn new(seed: u64, config_hash: u32, world: SavedWorld) -> Self {
+        Self { format: FORMAT, version: env!("CARGO_PKG_VERSION").to_owned(), config_hash: Some(config_hash), seed, world }
+    }
+
+    pub fn encode(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| err.to_string())
+    }
+
+    // Files of older formats are migrated to the current one; those of newer ones are refused,
+    // as there is no knowing what changed
+    pub fn decode(json: &str) -> Result<Self, String> {
+        let mut value: Value = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        let fields = value.as_object_mut().ok_or_else(|| "Not a saved world".to_owned())?;
+        // The files from before the format was versioned have no format field
+        let format = match fields.get("format") {
+            None => 0,
+            Some(found) => found.as_u64()
+                .and_then(|found| u32::try_from(found).ok())
+                .ok_or_else(|| format!("Invalid format {}", found))?,
+        };
+        if format > FORMAT {
+            return Err(format!("The world was saved in format {}, by a newer version; this one reads up to format {}", format, FORMAT));
+        }
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(format as usize) {
+            migration(fields).map_err(|err| format!("Failed to migrate from format {}: {}", from, err))?;
+            fields.insert("format".to_owned(), Value::from(from as u32 + 1));
+        }
+        serde_json::from_value(value).map_err(|err| err.to_string())
+    }
+
+    // Describes why the world may not be the one the current seed and config would generate
+    pub fn mismatches(&self, config_hash: u32) -> Vec<String> {
+        let mut mismatches = vec![];
+        match self.config_hash {
+            Some(hash) if hash != config_hash => mismatches.push("the world was generated with a different config".to_owned()),
+            None => mismatches.push("the world was saved without the hash of its config".to_owned()),
+            _ => {}
+        }
+        if self.version != env!("CARGO_PKG_VERSION") {
+            mismatches.push(format!("the world was saved by version {}", self.version));
+        }
+        mismatches
+    }
+}
+
+// The first files were only the seed and the world. The oldest of them were saved before the
+// lakes and the sea level setting: their worlds have no water depth, which is dry land
+// everywhere, and no sea level, which was the default one.
+fn unversioned(fields: &mut serde_json::Map<String, Value>) -> Result<(), String> {
+    fields.insert("version".to_owned(), Value::from("unknown"));
+    fields.insert("config_hash".to_owned(), Value::Null);
+    let world = fields.get_mut("world").and_then(Value::as_object_mut)
+        .ok_or_else(|| "No world".to_owned())?;
+    let cells = world.get("heightmap").and_then(Value::as_array).map(Vec::len)
+        .ok_or_else(|| "No heightmap".to_owned())?;
+    world.entry("water_depth").or_insert_with(|| Value::from(vec![0.0; cells]));
+    world.entry("sea_level").or_insert_with(|| Value::from(world::SEA_LEVEL));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A world saved before the file had a header, the lakes or the sea level setting
+    const FORMAT_0: &str = include_str!("../fixtures/world_format0.json");
+
+    #[test]
+    fn loads_format_0() {
+        let file = WorldFile::decode(FORMAT_0).unwrap();
+        assert_eq!(file.format, FORMAT);
+        assert_eq!(file.version, "unknown");
+        assert_eq!(file.config_hash, None);
+        assert_eq!(file.seed, 42);
+    }
+}
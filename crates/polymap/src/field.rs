@@ -1,5 +1,8 @@
+use serde_derive::{Deserialize, Serialize};
+
 use crate::*;
-pub struct Field<T>(Vec<T>);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Field<T>(pub(crate) Vec<T>);
 
 impl <T> std::ops::Index<CellId> for Field<T> {
     type Output = T;
@@ -16,6 +19,10 @@ impl <T> std::ops::IndexMut<CellId> for Field<T> {
 }
 
 impl <T> Field<T> {
+    pub fn len(&self) -> usize { self.0.len() }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
     pub fn with_fn(poly: &PolyMap, f: impl Fn(CellId, &Cell) -> T) -> Self {
         Self(poly.cells().map(|(id,cell)| f(id, cell)).collect())
     }
@@ -26,6 +33,8 @@ impl <T> Field<T> {
         }
     }
 
+    pub fn into_vec(self) -> Vec<T> { self.0 }
+
     pub fn sorted_order(&self, compare: impl Fn (&T,&T) -> std::cmp::Ordering) -> Vec<CellId> {
         let mut values:Vec<_> = (0..self.0.len()).map(|x| CellId(x)).collect();
         values.sort_by(|&id1, &id2| {
@@ -37,15 +46,47 @@ impl <T> Field<T> {
     }
 }
 
+// With the parallel feature, these run over the cells on every core, calling the function in
+// no particular order. Without it, they are the same as with_fn and update.
+impl <T:Send> Field<T> {
+    pub fn par_with_fn(poly: &PolyMap, f: impl Fn(CellId, &Cell) -> T + Sync) -> Self {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            Self(poly.cells.par_iter().enumerate().map(|(idx, cell)| f(CellId(idx), cell)).collect())
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            Self::with_fn(poly, f)
+        }
+    }
+
+    pub fn par_update(&mut self, f: impl Fn(CellId, &mut T) + Sync) {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            self.0.par_iter_mut().enumerate().for_each(|(idx, t)| f(CellId(idx), t));
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            self.update(f)
+        }
+    }
+}
+
 impl <T:Copy> Field<T> {
     pub fn uniform(poly: &PolyMap, x: T) -> Self {
         Self(poly.cells().map(|(_,_)| x).collect())
     }
 }
 
-pub trait Smoothable : Clone {
+pub trait Smoothable : Clone + Send + Sync {
     fn add(&mut self, x:&Self);
-    fn divide(&mut self, n: usize);
+    fn scale(&mut self, factor: f64);
+
+    fn divide(&mut self, n: usize) {
+        self.scale(1.0 / n as f64)
+    }
 }
 
 impl Smoothable for f64 {
@@ -53,6 +94,10 @@ impl Smoothable for f64 {
         *self += *x;
     }
 
+    fn scale(&mut self, factor: f64) {
+        *self *= factor
+    }
+
     fn divide(&mut self, n: usize) {
         *self = *self/(n as f64)
     }
@@ -67,7 +112,7 @@ impl <T:Smoothable> Field<T> {
     }
 
     fn smooth_once(&mut self, poly:&PolyMap,) {
-        let data = Field::with_fn(poly, |id, cell| {
+        let data = Field::par_with_fn(poly, |id, cell| {
             let mut count = 1;
             let mut val = self[id].clone();
             for &neighbor in cell.neighbors() {
@@ -79,12 +124,126 @@ impl <T:Smoothable> Field<T> {
         });
         self.0 = data.0;
     }
+
+    // Smooths only among the cells the predicate holds for. The others keep their value, and
+    // don't bleed into their neighbors either, e.g. to smooth over the land only.
+    pub fn smooth_where(&mut self, poly: &PolyMap, iterations: usize, include: impl Fn(CellId) -> bool + Sync) {
+        self.smooth_with(poly, iterations, |id, neighbor| {
+            if include(id) && include(neighbor) { 1.0 } else { 0.0 }
+        })
+    }
+
+    // Smooths more along the direction of each cell than across it, e.g. along the wind. With
+    // no anisotropy this is the same as smooth, with full anisotropy nothing diffuses across.
+    // Cells without a direction are smoothed evenly.
+    pub fn smooth_along(&mut self, poly: &PolyMap, iterations: usize, direction: &Field<(f64, f64)>, anisotropy: f64) {
+        let anisotropy = anisotropy.clamp(0.0, 1.0);
+        self.smooth_with(poly, iterations, |id, neighbor| {
+            match alignment(poly, id, neighbor, direction[id]) {
+                Some(alignment) => 1.0 - anisotropy + anisotropy * alignment,
+                None => 1.0,
+            }
+        })
+    }
+
+    // The most general smoothing: every cell becomes the weighted mean of itself, weighing 1,
+    // and its neighbors, weighing as given for the cell and the neighbor
+    pub fn smooth_with(&mut self, poly: &PolyMap, iterations: usize, weight: impl Fn(CellId, CellId) -> f64 + Sync) {
+        for _ in 0 .. iterations {
+            let data = Field::par_with_fn(poly, |id, cell| {
+                let mut total = 1.0;
+                let mut val = self[id].clone();
+                for &neighbor in cell.neighbors() {
+                    let weight = weight(id, neighbor).max(0.0);
+                    if weight > 0.0 {
+                        let mut contribution = self[neighbor].clone();
+                        contribution.scale(weight);
+                        val.add(&contribution);
+                        total += weight;
+                    }
+                }
+                val.scale(1.0 / total);
+                val
+            });
+            self.0 = data.0;
+        }
+    }
+
+    // Each cell moves towards the mean of its neighborhood by its own weight: 0 keeps the
+    // value, 1 is the same as smooth
+    pub fn smooth_weighted(&mut self, poly: &PolyMap, iterations: usize, weight: &Field<f64>) {
+        for _ in 0 .. iterations {
+            self.smooth_weighted_once(poly, weight)
+        }
+    }
+
+    fn smooth_weighted_once(&mut self, poly: &PolyMap, weight: &Field<f64>) {
+        let data = Field::par_with_fn(poly, |id, cell| {
+            let weight = weight[id].clamp(0.0, 1.0);
+            if weight <= 0.0 {
+                return self[id].clone();
+            }
+            let mut count = 1;
+            let mut mean = self[id].clone();
+            for &neighbor in cell.neighbors() {
+                mean.add(&self[neighbor]);
+                count += 1;
+            }
+            mean.divide(count);
+
+            let mut val = self[id].clone();
+            val.scale(1.0 - weight);
+            mean.scale(weight);
+            val.add(&mean);
+            val
+        });
+        self.0 = data.0;
+    }
 }
 
 impl Field<f64> {
+    // The same as smooth_with, run on the GPU with the gpu feature when there is one, at the
+    // precision of f32
+    pub fn gpu_smooth_with(&mut self, poly: &PolyMap, iterations: usize, weight: impl Fn(CellId, CellId) -> f64 + Sync) {
+        #[cfg(feature = "gpu")]
+        {
+            let stencil = crate::gpu::Stencil::new(poly, |id, cell| {
+                let mut stencil = vec![(id, 1.0)];
+                for &neighbor in cell.neighbors() {
+                    let weight = weight(id, neighbor).max(0.0);
+                    if weight > 0.0 {
+                        stencil.push((neighbor, weight));
+                    }
+                }
+                let total: f64 = stencil.iter().map(|&(_, weight)| weight).sum();
+                stencil.into_iter().map(|(cell, weight)| (cell, weight / total)).collect()
+            });
+            if stencil.apply(&mut self.0, iterations) {
+                return;
+            }
+        }
+        self.smooth_with(poly, iterations, weight)
+    }
 
-    
-    pub fn normalize(&mut self) {
+    // The same as smooth_weighted, like gpu_smooth_with
+    pub fn gpu_smooth_weighted(&mut self, poly: &PolyMap, iterations: usize, weight: &Field<f64>) {
+        #[cfg(feature = "gpu")]
+        {
+            let stencil = crate::gpu::Stencil::new(poly, |id, cell| {
+                let weight = weight[id].clamp(0.0, 1.0);
+                let share = weight / (cell.neighbors().len() + 1) as f64;
+                let mut stencil = vec![(id, 1.0 - weight + share)];
+                stencil.extend(cell.neighbors().iter().map(|&neighbor| (neighbor, share)));
+                stencil
+            });
+            if stencil.apply(&mut self.0, iterations) {
+                return;
+            }
+        }
+        self.smooth_weighted(poly, iterations, weight)
+    }
+
+    pub fn range(&self) -> (f64, f64) {
         let mut min = f64::MAX;
         let mut max = f64::MIN;
 
@@ -92,9 +251,43 @@ impl Field<f64> {
             min = min.min(x);
             max = max.max(x);
         }
+        (min, max)
+    }
+
+    // A flat field has no range to stretch, and becomes all zero
+    pub fn normalize(&mut self) {
+        let (min, max) = self.range();
+        if max <= min {
+            self.update(|_, x| *x = 0.0);
+            return;
+        }
 
         self.update(|_, x| *x = (*x - min)/(max - min));
     }
+
+    // The value below which the given fraction of the cells lies
+    pub fn quantile(&self, fraction: f64) -> f64 {
+        let order = self.ascending_order();
+        if order.is_empty() {
+            return 0.0;
+        }
+        let idx = ((fraction.clamp(0.0, 1.0) * order.len() as f64) as usize).min(order.len() - 1);
+        self[order[idx]]
+    }
+
+    // Like normalize, but stretches the two sides of the pivot separately so that
+    // the pivot ends up at the target value
+    pub fn normalize_around(&mut self, pivot: f64, target: f64) {
+        let (min, max) = self.range();
+
+        self.update(|_, x| {
+            *x = if *x <= pivot {
+                if pivot > min { (*x - min) / (pivot - min) * target } else { target }
+            } else {
+                target + (*x - pivot) / (max - pivot) * (1.0 - target)
+            }
+        });
+    }
     
     pub fn ascending_order(&self) -> Vec<CellId> {
         self.sorted_order(|&x,&y| 
@@ -103,4 +296,201 @@ impl Field<f64> {
                 else { std::cmp::Ordering::Greater }
         )
     }
-}
\ No newline at end of file
+}
+
+// Smoothing for categories, which can't be averaged: every cell takes the most common value
+// of its neighborhood. On a tie the cell keeps its own value if it is among the most common,
+// otherwise it takes the one of the first of its neighbors.
+impl <T:Copy + PartialEq> Field<T> {
+    pub fn majority_filter(&mut self, poly: &PolyMap, iterations: usize) {
+        self.majority_filter_where(poly, iterations, |_| true)
+    }
+
+    // Only the cells the predicate holds for change and vote
+    pub fn majority_filter_where(&mut self, poly: &PolyMap, iterations: usize, include: impl Fn(CellId) -> bool) {
+        for _ in 0 .. iterations {
+            let data = Field::with_fn(poly, |id, cell| {
+                if !include(id) {
+                    return self[id];
+                }
+                let mut counts: Vec<(T, usize)> = vec![(self[id], 1)];
+                for &neighbor in cell.neighbors().iter().filter(|&&n| include(n)) {
+                    match counts.iter_mut().find(|(value, _)| *value == self[neighbor]) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((self[neighbor], 1)),
+                    }
+                }
+                let most = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+                counts.iter().find(|&&(_, count)| count == most).map(|&(value, _)| value).unwrap_or(self[id])
+            });
+            self.0 = data.0;
+        }
+    }
+}
+
+// Morphology over the adjacency of the cells, for cleaning up classifications: opening takes
+// away specks and spurs narrower than the neighborhood, closing fills pinholes and gaps
+impl Field<bool> {
+    // A cell stays set only if its whole neighborhood is
+    pub fn erode(&mut self, poly: &PolyMap, iterations: usize) {
+        for _ in 0 .. iterations {
+            let data = Field::with_fn(poly, |id, cell| self[id] && cell.neighbors().iter().all(|&n| self[n]));
+            self.0 = data.0;
+        }
+    }
+
+    // A cell gets set if anything in its neighborhood is
+    pub fn dilate(&mut self, poly: &PolyMap, iterations: usize) {
+        for _ in 0 .. iterations {
+            let data = Field::with_fn(poly, |id, cell| self[id] || cell.neighbors().iter().any(|&n| self[n]));
+            self.0 = data.0;
+        }
+    }
+
+    pub fn open(&mut self, poly: &PolyMap, iterations: usize) {
+        self.erode(poly, iterations);
+        self.dilate(poly, iterations);
+    }
+
+    pub fn close(&mut self, poly: &PolyMap, iterations: usize) {
+        self.dilate(poly, iterations);
+        self.erode(poly, iterations);
+    }
+}
+
+// How well the step between two cells lines up with a direction in map coordinates, from 0
+// across to 1 along it either way. None when there is no direction.
+pub fn alignment(poly: &PolyMap, from: CellId, to: CellId, (dx, dy): (f64, f64)) -> Option<f64> {
+    let (fx, fy) = poly[from].center();
+    let (tx, ty) = poly[to].center();
+    let (sx, sy) = (tx - fx, ty - fy);
+    let lengths = (dx * dx + dy * dy) * (sx * sx + sy * sy);
+    if lengths <= 0.0 {
+        return None;
+    }
+    let dot = dx * sx + dy * sy;
+    Some(dot * dot / lengths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly() -> PolyMap {
+        PolyMap::with_seed(100, 60, 6.0, 3)
+    }
+
+    // A cell away from the edge of the map, with none of its neighbors on the edge either
+    fn inner_cell(poly: &PolyMap) -> CellId {
+        poly.cells()
+            .find(|(_, cell)| !cell.is_border() && cell.neighbors().iter().all(|&n| !poly[n].is_border()))
+            .map(|(id, _)| id)
+            .unwrap()
+    }
+
+    #[test]
+    fn open_removes_a_lone_cell() {
+        let poly = poly();
+        let lone = inner_cell(&poly);
+        let mut field = Field::with_fn(&poly, |id, _| id == lone);
+        field.open(&poly, 1);
+        assert!(field.0.iter().all(|&set| !set));
+    }
+
+    #[test]
+    fn close_fills_a_hole() {
+        let poly = poly();
+        let hole = inner_cell(&poly);
+        let mut field = Field::with_fn(&poly, |id, _| id != hole);
+        field.close(&poly, 1);
+        assert!(field.0.iter().all(|&set| set));
+    }
+
+    #[test]
+    fn majority_filter_takes_the_class_around_a_lone_cell() {
+        let poly = poly();
+        let lone = inner_cell(&poly);
+        let mut field = Field::with_fn(&poly, |id, _| if id == lone { 2u8 } else { 1 });
+        let unfiltered = field.clone();
+        field.majority_filter_where(&poly, 1, |id| id != lone);
+        assert_eq!(field.0, unfiltered.0);
+        field.majority_filter(&poly, 1);
+        assert!(field.0.iter().all(|&class| class == 1));
+    }
+
+    #[test]
+    fn no_iterations_change_nothing() {
+        let poly = poly();
+        let field = Field::with_fn(&poly, |id, _| id.idx() % 3 == 0);
+        let mut eroded = field.clone();
+        eroded.erode(&poly, 0);
+        assert_eq!(eroded.0, field.0);
+        let mut dilated = field.clone();
+        dilated.dilate(&poly, 0);
+        assert_eq!(dilated.0, field.0);
+    }
+
+    #[test]
+    fn normalize_flat_field() {
+        let poly = poly();
+        let mut field = Field::uniform(&poly, 0.7);
+        field.normalize();
+        assert!(field.0.iter().all(|&x| x == 0.0));
+    }
+}
+
+#[cfg(all(test, feature = "gpu"))]
+mod gpu_tests {
+    use super::*;
+
+    // The GPU runs at the precision of f32, and the error grows a little with every pass
+    const EPSILON: f64 = 1e-4;
+    const ITERATIONS: usize = 8;
+
+    fn setup() -> Option<(PolyMap, Field<f64>)> {
+        if !crate::gpu::available() {
+            eprintln!("skipped: no GPU adapter");
+            return None;
+        }
+        let poly = PolyMap::with_seed(200, 120, 6.0, 11);
+        let field = Field::with_fn(&poly, |_, cell| {
+            let (x, y) = cell.center();
+            (x * 0.13).sin() * (y * 0.07).cos()
+        });
+        Some((poly, field))
+    }
+
+    fn max_difference(a: &Field<f64>, b: &Field<f64>) -> f64 {
+        a.0.iter().zip(b.0.iter()).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn smooth_with_matches_cpu() {
+        let (poly, field) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let weight = |id: CellId, neighbor: CellId| ((id.idx() + neighbor.idx()) % 3) as f64 * 0.5;
+        let mut cpu = field.clone();
+        cpu.smooth_with(&poly, ITERATIONS, weight);
+        let mut gpu = field;
+        gpu.gpu_smooth_with(&poly, ITERATIONS, weight);
+        let difference = max_difference(&cpu, &gpu);
+        assert!(difference < EPSILON, "GPU and CPU differ by {}", difference);
+    }
+
+    #[test]
+    fn smooth_weighted_matches_cpu() {
+        let (poly, field) = match setup() {
+            Some(setup) => setup,
+            None => return,
+        };
+        let weight = Field::with_fn(&poly, |id, _| (id.idx() % 5) as f64 / 4.0);
+        let mut cpu = field.clone();
+        cpu.smooth_weighted(&poly, ITERATIONS, &weight);
+        let mut gpu = field;
+        gpu.gpu_smooth_weighted(&poly, ITERATIONS, &weight);
+        let difference = max_difference(&cpu, &gpu);
+        assert!(difference < EPSILON, "GPU and CPU differ by {}", difference);
+    }
+}
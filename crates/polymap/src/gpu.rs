@@ -0,0 +1,171 @@
+use std::sync::OnceLock;
+
+use wgpu::util::DeviceExt;
+
+use crate::{Cell, CellId, PolyMap};
+
+// The workgroup size of the shader
+const WORKGROUP: usize = 64;
+
+// One pass of a stencil: every cell becomes the sum of the cells of its stencil times their
+// weights. The adjacency starts with the offsets of the stencils of the cells, the one past
+// the last included, into the adjacency itself.
+const SHADER: &str = "
+@group(0) @binding(0) var<storage, read> adjacency: array<u32>;
+@group(0) @binding(1) var<storage, read> weights: array<f32>;
+@group(0) @binding(2) var<storage, read> input: array<f32>;
+@group(0) @binding(3) var<storage, read_write> output: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let cell = id.x;
+    if (cell >= arrayLength(&output)) {
+        return;
+    }
+    var value = 0.0;
+    for (var entry = adjacency[cell]; entry < adjacency[cell + 1u]; entry = entry + 1u) {
+        value = value + weights[entry - adjacency[0]] * input[adjacency[entry]];
+    }
+    output[cell] = value;
+}
+";
+
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    async fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions::default()).await?;
+        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("polymap"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::downlevel_defaults(),
+        }, None).await.ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("stencil"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("stencil"),
+            layout: None,
+            module: &module,
+            entry_point: "main",
+        });
+        Some(Self { device, queue, pipeline })
+    }
+}
+
+// Set up on first use, None on machines without an adapter
+fn gpu() -> Option<&'static Gpu> {
+    static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+    GPU.get_or_init(|| pollster::block_on(Gpu::new())).as_ref()
+}
+
+// Whether the passes run on the GPU, or fall back to the CPU
+pub fn available() -> bool {
+    gpu().is_some()
+}
+
+// A linear pass over the cells, run on the GPU at the precision of f32
+pub(crate) struct Stencil {
+    adjacency: Vec<u32>,
+    weights: Vec<f32>,
+}
+
+impl Stencil {
+    // The cells each cell is made of, itself included, with their weights
+    pub fn new(poly: &PolyMap, f: impl Fn(CellId, &Cell) -> Vec<(CellId, f64)>) -> Self {
+        let stencils: Vec<_> = poly.cells().map(|(id, cell)| f(id, cell)).collect();
+        let mut adjacency = Vec::with_capacity(stencils.len() + 1);
+        let mut offset = stencils.len() + 1;
+        for stencil in stencils.iter() {
+            adjacency.push(offset as u32);
+            offset += stencil.len();
+        }
+        adjacency.push(offset as u32);
+        let mut weights = vec![];
+        for (cell, weight) in stencils.into_iter().flatten() {
+            adjacency.push(cell.0 as u32);
+            weights.push(weight as f32);
+        }
+        Self { adjacency, weights }
+    }
+
+    // Returns false, leaving the values as they were, when there is no GPU to run on
+    pub fn apply(&self, values: &mut [f64], iterations: usize) -> bool {
+        let gpu = match gpu() {
+            Some(gpu) => gpu,
+            None => return false,
+        };
+        if values.is_empty() || iterations == 0 {
+            return true;
+        }
+        let device = &gpu.device;
+        let input: Vec<f32> = values.iter().map(|&value| value as f32).collect();
+        let buffer = |label, contents: &[u8], usage| device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage,
+        });
+        let adjacency = buffer("adjacency", bytemuck::cast_slice(&self.adjacency), wgpu::BufferUsages::STORAGE);
+        let weights = buffer("weights", bytemuck::cast_slice(&self.weights), wgpu::BufferUsages::STORAGE);
+        // Each pass reads one and writes the other
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let ping = buffer("ping", bytemuck::cast_slice(&input), usage);
+        let pong = buffer("pong", bytemuck::cast_slice(&input), usage);
+        let size = std::mem::size_of_val(input.as_slice()) as wgpu::BufferAddress;
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = gpu.pipeline.get_bind_group_layout(0);
+        let bind = |from: &wgpu::Buffer, to: &wgpu::Buffer| device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: adjacency.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: weights.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: from.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: to.as_entire_binding() },
+            ],
+        });
+        let groups = [bind(&ping, &pong), bind(&pong, &ping)];
+        let workgroups = values.len().div_ceil(WORKGROUP) as u32;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("stencil") });
+        for iteration in 0..iterations {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None, timestamp_writes: None });
+            pass.set_pipeline(&gpu.pipeline);
+            pass.set_bind_group(0, &groups[iteration % 2], &[]);
+            pass.dispatch_workgroups(workgroups, 1, 1);
+        }
+        let result = if iterations % 2 == 1 { &pong } else { &ping };
+        encoder.copy_buffer_to_buffer(result, 0, &readback, 0, size);
+        gpu.queue.submit(Some(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        let _ = device.poll(wgpu::Maintain::Wait);
+        if !matches!(receiver.recv(), Ok(Ok(()))) {
+            return false;
+        }
+        {
+            let mapped = slice.get_mapped_range();
+            let output: &[f32] = bytemuck::cast_slice(&mapped[..]);
+            for (value, &computed) in values.iter_mut().zip(output) {
+                *value = computed as f64;
+            }
+        }
+        readback.unmap();
+        true
+    }
+}
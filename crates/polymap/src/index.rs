@@ -0,0 +1,64 @@
+use crate::{Cell, CellId};
+
+// A uniform grid over the map, each bucket listing the cells whose bounding box overlaps it,
+// so that finding the cells around a point only looks at a handful of them
+pub(crate) struct CellIndex {
+    bucket_size: f64,
+    columns: usize,
+    rows: usize,
+    buckets: Vec<Vec<CellId>>,
+}
+
+// About this many cells for each bucket
+const CELLS_PER_BUCKET: f64 = 2.0;
+
+impl CellIndex {
+    pub(crate) fn new(width: usize, height: usize, cells: &[Cell]) -> Self {
+        let area = (width * height) as f64;
+        let bucket_size = (area * CELLS_PER_BUCKET / cells.len().max(1) as f64).sqrt().max(1.0);
+        let columns = (width as f64 / bucket_size).ceil().max(1.0) as usize;
+        let rows = (height as f64 / bucket_size).ceil().max(1.0) as usize;
+        let mut index = Self { bucket_size, columns, rows, buckets: vec![vec![]; columns * rows] };
+
+        for (idx, cell) in cells.iter().enumerate() {
+            let (min, max) = bounds(cell);
+            let (col0, row0) = index.bucket_of(min.0, min.1);
+            let (col1, row1) = index.bucket_of(max.0, max.1);
+            for row in row0..=row1 {
+                for col in col0..=col1 {
+                    index.buckets[row * columns + col].push(CellId(idx));
+                }
+            }
+        }
+        index
+    }
+
+    // The bucket a point falls in, the closest one for points off the map
+    fn bucket_of(&self, x: f64, y: f64) -> (usize, usize) {
+        let col = (x / self.bucket_size).floor().max(0.0) as usize;
+        let row = (y / self.bucket_size).floor().max(0.0) as usize;
+        (col.min(self.columns - 1), row.min(self.rows - 1))
+    }
+
+    // The cells that may reach into the rectangle, each once, in order
+    pub(crate) fn candidates(&self, min: (f64, f64), max: (f64, f64)) -> Vec<CellId> {
+        let (col0, row0) = self.bucket_of(min.0, min.1);
+        let (col1, row1) = self.bucket_of(max.0, max.1);
+        let mut cells = vec![];
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                cells.extend_from_slice(&self.buckets[row * self.columns + col]);
+            }
+        }
+        cells.sort();
+        cells.dedup();
+        cells
+    }
+}
+
+pub(crate) fn bounds(cell: &Cell) -> ((f64, f64), (f64, f64)) {
+    cell.polygon.exterior().points_iter().fold(
+        ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+        |((x0, y0), (x1, y1)), p| ((x0.min(p.x()), y0.min(p.y())), (x1.max(p.x()), y1.max(p.y()))),
+    )
+}
@@ -1,6 +1,15 @@
-use geo::{contains::Contains, Polygon};
+use std::collections::VecDeque;
+
+use geo::{area::Area, contains::Contains, intersects::Intersects, Polygon};
+use serde_derive::{Deserialize, Serialize};
 
 pub mod field;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+mod index;
+pub mod regions;
+pub mod resample;
+pub mod selection;
 
 #[derive(Clone, Copy, Debug, PartialOrd)]
 struct Location(f64, f64);
@@ -34,7 +43,7 @@ impl PartialEq for Location {
 
 impl Eq for Location {}
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct CellId(usize);
 
 impl CellId {
@@ -60,6 +69,10 @@ impl Cell {
 
     pub fn polygon(&self) -> &Polygon<f64> { &self.polygon }
 
+    pub fn area(&self) -> f64 { self.polygon.unsigned_area() }
+
+    pub fn contains(&self, px: f64, py: f64) -> bool { self.polygon.contains(&geo::Point::new(px, py)) }
+
     pub fn neighbors(&self) -> &[CellId] {
         self.neighbors.as_slice()
     }
@@ -72,23 +85,54 @@ pub struct PolyMap {
     height: usize,
     cells: Vec<Cell>,
     borders: Vec<CellId>,
+    index: index::CellIndex,
 }
 
+// Fewer centers than this can't be triangulated into a voronoi diagram
+const MIN_CELLS: usize = 3;
+
 impl PolyMap {
     pub fn new(width: usize, height: usize, poisson_radius: f64) -> Self {
-        let centers: Vec<_> = fast_poisson::Poisson2D::new()
+        Self::try_new(width, height, poisson_radius).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // The same seed always gives the same cells
+    pub fn with_seed(width: usize, height: usize, poisson_radius: f64, seed: u64) -> Self {
+        Self::try_with_seed(width, height, poisson_radius, seed).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Like new, but maps too small for their radius are an error rather than a panic
+    pub fn try_new(width: usize, height: usize, poisson_radius: f64) -> Result<Self, String> {
+        check_dimensions(width, height, poisson_radius)?;
+        let poisson = fast_poisson::Poisson2D::new()
+            .with_dimensions([width as f64, height as f64], poisson_radius);
+        Self::from_poisson(width, height, poisson)
+    }
+
+    pub fn try_with_seed(width: usize, height: usize, poisson_radius: f64, seed: u64) -> Result<Self, String> {
+        check_dimensions(width, height, poisson_radius)?;
+        let poisson = fast_poisson::Poisson2D::new()
             .with_dimensions([width as f64, height as f64], poisson_radius)
+            .with_seed(seed);
+        Self::from_poisson(width, height, poisson)
+    }
+
+    fn from_poisson(width: usize, height: usize, poisson: fast_poisson::Poisson2D) -> Result<Self, String> {
+        let centers: Vec<_> = poisson
             .generate()
             .into_iter()
             .map(|x| (x[0], x[1]))
             .collect();
+        if centers.len() < MIN_CELLS {
+            return Err(format!("A {} x {} map has room for only {} cells, at least {} are needed", width, height, centers.len(), MIN_CELLS));
+        }
 
         let voronoi = voronator::VoronoiDiagram::<voronator::delaunator::Point>::from_tuple(
             &(0.0, 0.0),
             &(width as f64, height as f64),
             &centers,
         )
-        .expect("Failed to build voronoi diagram");
+        .ok_or_else(|| format!("Failed to build voronoi diagram of {} cells", centers.len()))?;
 
 
         let cells:Vec<_> = voronoi.cells().iter().enumerate()
@@ -124,12 +168,33 @@ impl PolyMap {
             .collect();
         borders.sort();
 
-        PolyMap {
+        let index = index::CellIndex::new(width, height, &cells);
+        Ok(PolyMap {
             width,
             height,
             cells,
-            borders
+            borders,
+            index,
+        })
+    }
+
+    // FNV-1a hash of the geometry of the cells, stable across runs and platforms
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |value: u64| {
+            for byte in value.to_le_bytes() {
+                hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        };
+        feed(self.width as u64);
+        feed(self.height as u64);
+        for cell in self.cells.iter() {
+            for point in cell.polygon.exterior().points_iter() {
+                feed(point.x().to_bits());
+                feed(point.y().to_bits());
+            }
         }
+        hash
     }
 
     pub fn width(&self) -> usize {
@@ -143,6 +208,11 @@ impl PolyMap {
         &self.cells[id.0]
     }
 
+    // The inverse of CellId::idx, for ids read back from a file
+    pub fn cell_id(&self, idx: usize) -> Option<CellId> {
+        if idx < self.cells.len() { Some(CellId(idx)) } else { None }
+    }
+
     pub fn cell_at(&self, px: f64, py: f64) -> Option<CellId> {
         if px < 0.0 || py < 0.0 {
             return None;
@@ -150,9 +220,85 @@ impl PolyMap {
 
         let point = geo::Point::new(px, py);
 
+        self.index.candidates((px, py), (px, py)).into_iter()
+            .find(|&id| self.cells[id.0].polygon.contains(&point))
+    }
+
+    // The cells reaching into the rectangle between the two corners, in order
+    pub fn cells_in_rect(&self, min: (f64, f64), max: (f64, f64)) -> Vec<CellId> {
+        let rect = geo::Rect::new(geo::Coordinate { x: min.0, y: min.1 }, geo::Coordinate { x: max.0, y: max.1 });
+        self.index.candidates(min, max).into_iter()
+            .filter(|&id| {
+                let (low, high) = index::bounds(&self.cells[id.0]);
+                low.0 <= max.0 && low.1 <= max.1 && high.0 >= min.0 && high.1 >= min.1
+                    && self.cells[id.0].polygon.intersects(&rect)
+            })
+            .collect()
+    }
+
+    // Walks the neighbor graph from start towards the cell whose center is closest to the given point.
+    // Since the neighbors are the Delaunay neighbors, the walk always ends at the closest center.
+    pub fn closest_cell(&self, start: CellId, px: f64, py: f64) -> CellId {
+        let distance = |id: CellId| {
+            let (cx, cy) = self.cells[id.0].center();
+            (cx - px).powi(2) + (cy - py).powi(2)
+        };
+
+        let mut current = start;
+        loop {
+            let closest_neighbor = self.cells[current.0].neighbors().iter()
+                .map(|&id| (id, distance(id)))
+                .reduce(|(id1, d1), (id2, d2)| if d1 <= d2 { (id1, d1) } else { (id2, d2) });
+
+            match closest_neighbor {
+                Some((id, d)) if d < distance(current) => current = id,
+                _ => return current,
+            }
+        }
+    }
+
+    pub fn cells_in_polygon(&self, polygon: &Polygon<f64>) -> Vec<CellId> {
         self.cells()
-            .find(|&(_, cell)| cell.polygon.contains(&point))
+            .filter(|(_, cell)| {
+                let (cx, cy) = cell.center();
+                polygon.contains(&geo::Point::new(cx, cy))
+            })
             .map(|(id, _)| id)
+            .collect()
+    }
+
+    // The corners at the two ends of the side two neighboring cells share. Neighbors meeting at
+    // a single corner share no side.
+    pub fn shared_side(&self, a: CellId, b: CellId) -> Option<((f64, f64), (f64, f64))> {
+        let corners = |id: CellId| self.cells[id.0].polygon.exterior().points_iter().map(|p| (p.x(), p.y()));
+        let mut shared = corners(a).filter(|&(x, y)| corners(b).any(|(bx, by)| bx == x && by == y));
+        let first = shared.next()?;
+        // The ring is closed, so its first corner comes again at the end
+        let second = shared.find(|&corner| corner != first)?;
+        Some((first, second))
+    }
+
+    // Number of neighbor steps from each cell to the closest source, usize::MAX if unreachable
+    pub fn ring_distances(&self, sources: impl IntoIterator<Item = CellId>) -> field::Field<usize> {
+        let mut distances = field::Field::uniform(self, usize::MAX);
+        let mut queue = VecDeque::new();
+        for source in sources {
+            if distances[source] != 0 {
+                distances[source] = 0;
+                queue.push_back(source);
+            }
+        }
+
+        while let Some(id) = queue.pop_front() {
+            let distance = distances[id] + 1;
+            for &neighbor in self.cells[id.0].neighbors() {
+                if distances[neighbor] > distance {
+                    distances[neighbor] = distance;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        distances
     }
 
     pub fn cells(&self) -> impl Iterator<Item = (CellId, &Cell)> {
@@ -195,3 +341,105 @@ impl std::ops::Index<CellId> for PolyMap {
         &self.cells[index.0]
     }
 }
+
+fn check_dimensions(width: usize, height: usize, poisson_radius: f64) -> Result<(), String> {
+    if width == 0 || height == 0 {
+        return Err(format!("A map can't be {} x {}", width, height));
+    }
+    if !(poisson_radius.is_finite() && poisson_radius > 0.0) {
+        return Err(format!("The poisson radius must be positive, not {}", poisson_radius));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn too_few_cells_are_an_error() {
+        assert!(PolyMap::try_with_seed(1, 1, 10.0, 0).is_err());
+        assert!(PolyMap::try_with_seed(0, 10, 1.0, 0).is_err());
+        assert!(PolyMap::try_with_seed(10, 10, 0.0, 0).is_err());
+    }
+
+    // Growing a map from nothing, the first size that builds has only a few cells, and the one
+    // before it had too few
+    #[test]
+    fn smallest_map_builds() {
+        let (width, poly) = (1..200)
+            .find_map(|width| PolyMap::try_with_seed(width, 8, 4.0, 0).ok().map(|poly| (width, poly)))
+            .expect("No map up to 200 wide builds");
+        let cells = poly.cells().count();
+        assert!((MIN_CELLS..MIN_CELLS * 3).contains(&cells), "the smallest map has {} cells", cells);
+        assert!(PolyMap::try_with_seed(width - 1, 8, 4.0, 0).is_err());
+    }
+
+    // The properties of the fuzz targets, on maps and queries drawn from fixed seeds, so
+    // that they are checked by every test run
+    fn small_maps() -> impl Iterator<Item = (PolyMap, SmallRng)> {
+        (0..8).map(|seed| {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let width = rng.gen_range(32..288);
+            let height = rng.gen_range(32..288);
+            let radius = rng.gen_range(4..17) as f64;
+            (PolyMap::with_seed(width, height, radius, seed), rng)
+        })
+    }
+
+    #[test]
+    fn cell_at_is_the_cell_of_the_closest_center() {
+        for (poly, mut rng) in small_maps() {
+            for _ in 0..200 {
+                let x = rng.gen_range(0.001..1.0) * poly.width() as f64;
+                let y = rng.gen_range(0.001..1.0) * poly.height() as f64;
+                let cell = poly.cell_at(x, y).unwrap_or_else(|| panic!("no cell at ({}, {})", x, y));
+                let distance = |(cx, cy): (f64, f64)| (cx - x).powi(2) + (cy - y).powi(2);
+                let closest = poly.cells().map(|(_, other)| distance(other.center())).fold(f64::INFINITY, f64::min);
+                assert!(distance(poly[cell].center()) <= closest + 1e-6, "({}, {}) is in cell {} but closer to another center", x, y, cell.idx());
+            }
+        }
+    }
+
+    // Against a scan of every cell, with rectangles of any size, some reaching off the map
+    #[test]
+    fn cells_in_rect_are_those_whose_polygons_meet_the_rect() {
+        for (poly, mut rng) in small_maps() {
+            for _ in 0..50 {
+                let mut corner = || (rng.gen_range(-0.1..1.1) * poly.width() as f64, rng.gen_range(-0.1..1.1) * poly.height() as f64);
+                let ((x0, y0), (x1, y1)) = (corner(), corner());
+                let (min, max) = ((x0.min(x1), y0.min(y1)), (x0.max(x1), y0.max(y1)));
+                let rect = geo::Rect::new(geo::Coordinate { x: min.0, y: min.1 }, geo::Coordinate { x: max.0, y: max.1 });
+                let scanned: Vec<_> = poly.cells().filter(|(_, cell)| cell.polygon.intersects(&rect)).map(|(id, _)| id).collect();
+                assert_eq!(poly.cells_in_rect(min, max), scanned, "cells in {:?} to {:?}", min, max);
+            }
+        }
+    }
+
+    #[test]
+    fn neighbor_in_direction_is_the_closest_within_the_tolerance() {
+        let difference = |a: f64, b: f64| f64::atan2((b - a).sin(), (b - a).cos()).abs();
+        for (poly, mut rng) in small_maps() {
+            let cells = poly.cells().count();
+            for _ in 0..200 {
+                let cell = CellId(rng.gen_range(0..cells));
+                let direction = rng.gen_range(-PI..PI);
+                let tolerance = rng.gen_range(0.0..PI);
+                let off = |neighbor| difference(direction, poly.angle_between_cells(cell, neighbor));
+                match poly.neighbor_in_direction(cell, direction, tolerance) {
+                    Some(found) => {
+                        assert!(poly[cell].neighbors().contains(&found));
+                        assert!(off(found) < tolerance);
+                        assert!(poly[cell].neighbors().iter().all(|&other| off(found) <= off(other) + 1e-12));
+                    }
+                    None => assert!(poly[cell].neighbors().iter().all(|&other| off(other) >= tolerance)),
+                }
+            }
+        }
+    }
+}
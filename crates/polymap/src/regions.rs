@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::*;
+use crate::field::Field;
+
+type Point = (f64, f64);
+// Two neighboring cells and the side they share
+type Side = (CellId, CellId, Option<(Point, Point)>);
+
+// Numbers the connected groups of cells with the same key, in the order of their first cell.
+// Cells without a key are in no region.
+pub fn label<K: PartialEq>(poly: &PolyMap, key: impl Fn(CellId) -> Option<K>) -> Field<Option<usize>> {
+    let mut labels = Field::uniform(poly, None);
+    let mut count = 0;
+    for (start, _) in poly.cells() {
+        let start_key = match key(start) {
+            Some(start_key) if labels[start].is_none() => start_key,
+            _ => continue,
+        };
+        labels[start] = Some(count);
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            for &neighbor in poly.cells[id.0].neighbors() {
+                if labels[neighbor].is_none() && key(neighbor).as_ref() == Some(&start_key) {
+                    labels[neighbor] = Some(count);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        count += 1;
+    }
+    labels
+}
+
+// Where two regions meet: the pairs of neighboring cells on either side, the sides they share
+// joined into polylines, and the total length of these, in map units
+#[derive(Clone, Debug)]
+pub struct Border {
+    pub regions: (usize, usize),
+    // The first cell of each pair is in the first region
+    pub cells: Vec<(CellId, CellId)>,
+    pub chains: Vec<Vec<Point>>,
+    pub length: f64,
+}
+
+// Which regions of a labelling border which. Cells without a region, like the sea around
+// realms, border nothing.
+#[derive(Clone, Debug, Default)]
+pub struct RegionGraph {
+    // By the pair of regions, the lower first
+    borders: BTreeMap<(usize, usize), Border>,
+}
+
+impl RegionGraph {
+    pub fn new(poly: &PolyMap, labels: &Field<Option<usize>>) -> Self {
+        let mut sides: BTreeMap<(usize, usize), Vec<Side>> = BTreeMap::new();
+        for (id, cell) in poly.cells() {
+            let here = match labels[id] {
+                Some(here) => here,
+                None => continue,
+            };
+            for &neighbor in cell.neighbors() {
+                match labels[neighbor] {
+                    Some(there) if here < there => {
+                        sides.entry((here, there)).or_default().push((id, neighbor, poly.shared_side(id, neighbor)));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let borders = sides.into_iter()
+            .map(|(regions, sides)| {
+                let segments: Vec<_> = sides.iter().filter_map(|&(_, _, side)| side).collect();
+                let length = segments.iter().map(|&(a, b)| distance(a, b)).sum();
+                let border = Border {
+                    regions,
+                    cells: sides.iter().map(|&(a, b, _)| (a, b)).collect(),
+                    chains: chains(&segments),
+                    length,
+                };
+                (regions, border)
+            })
+            .collect();
+        Self { borders }
+    }
+
+    pub fn borders(&self) -> impl Iterator<Item = &Border> {
+        self.borders.values()
+    }
+
+    pub fn border(&self, a: usize, b: usize) -> Option<&Border> {
+        self.borders.get(&(a.min(b), a.max(b)))
+    }
+
+    pub fn neighbors(&self, region: usize) -> impl Iterator<Item = usize> + '_ {
+        self.borders.keys().filter_map(move |&(a, b)| {
+            if a == region { Some(b) } else if b == region { Some(a) } else { None }
+        })
+    }
+}
+
+fn distance((x1, y1): Point, (x2, y2): Point) -> f64 {
+    ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt()
+}
+
+// Joins segments sharing endpoints into polylines. Open chains are walked from their ends, so
+// that each comes out whole; what is left are closed loops, like the border of an enclave.
+fn chains(segments: &[(Point, Point)]) -> Vec<Vec<Point>> {
+    let key = |(x, y): Point| (x.to_bits(), y.to_bits());
+    let mut at: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (idx, &(a, b)) in segments.iter().enumerate() {
+        at.entry(key(a)).or_default().push(idx);
+        at.entry(key(b)).or_default().push(idx);
+    }
+
+    let ends = segments.iter().flat_map(|&(a, b)| [a, b]).filter(|&point| at[&key(point)].len() == 1);
+    let anywhere = segments.iter().map(|&(a, _)| a);
+    let mut used = vec![false; segments.len()];
+    let mut chains = vec![];
+    for start in ends.chain(anywhere).collect::<Vec<_>>() {
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(&idx) = at[&key(current)].iter().find(|&&idx| !used[idx]) {
+            used[idx] = true;
+            let (a, b) = segments[idx];
+            current = if key(a) == key(current) { b } else { a };
+            chain.push(current);
+        }
+        if chain.len() > 1 {
+            chains.push(chain);
+        }
+    }
+    chains
+}
@@ -0,0 +1,88 @@
+use std::f64::consts::TAU;
+
+use crate::*;
+use crate::field::Field;
+
+// How the cells of one map sit on another, for moving fields between maps of the same size cut
+// differently, e.g. onto a denser map. Each target cell takes the source cell with the closest
+// center, and the source triangle of Delaunay neighbors around its center with the barycentric
+// weights of the corners. Interpolating on the triangles keeps the slopes between the source
+// cells instead of averaging over a neighborhood, so the fields come out no blurrier than they
+// were. Outside the triangles, past the outermost centers, the closest cell is used.
+pub struct Resampling {
+    closest: Vec<CellId>,
+    triangles: Vec<Option<[(CellId, f64); 3]>>,
+}
+
+impl Resampling {
+    pub fn new(source: &PolyMap, target: &PolyMap) -> Self {
+        let mut closest = Vec::with_capacity(target.cells.len());
+        let mut triangles = Vec::with_capacity(target.cells.len());
+        let mut current = match source.cell_id(0) {
+            Some(cell) => cell,
+            None => return Self { closest, triangles },
+        };
+        for (_, cell) in target.cells() {
+            let (x, y) = cell.center();
+            // Neighboring target cells are usually close in the source too
+            current = source.closest_cell(current, x, y);
+            closest.push(current);
+            triangles.push(triangle(source, current, (x, y)));
+        }
+        Self { closest, triangles }
+    }
+
+    // The source cell with the center closest to the center of the target cell
+    pub fn closest(&self, target: CellId) -> CellId {
+        self.closest[target.0]
+    }
+
+    // For fields of categories, which do not mix
+    pub fn nearest<T: Clone>(&self, source: &Field<T>) -> Field<T> {
+        Field(self.closest.iter().map(|&id| source[id].clone()).collect())
+    }
+
+    pub fn interpolate(&self, source: &Field<f64>) -> Field<f64> {
+        let values = self.closest.iter().zip(self.triangles.iter())
+            .map(|(&closest, triangle)| match triangle {
+                Some(corners) => corners.iter().map(|&(id, weight)| source[id] * weight).sum(),
+                None => source[closest],
+            })
+            .collect();
+        Field(values)
+    }
+}
+
+// The triangle made by the cell and two of its neighbors next to each other around it that
+// holds the point, with the weight of each corner
+fn triangle(poly: &PolyMap, cell: CellId, point: (f64, f64)) -> Option<[(CellId, f64); 3]> {
+    let center = poly.cells[cell.0].center();
+    let angle = |id: CellId| {
+        let (x, y) = poly.cells[id.0].center();
+        (y - center.1).atan2(x - center.0).rem_euclid(TAU)
+    };
+    let mut around = poly.cells[cell.0].neighbors().to_vec();
+    around.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+
+    (0..around.len()).find_map(|idx| {
+        let (a, b) = (around[idx], around[(idx + 1) % around.len()]);
+        let weights = barycentric(center, poly.cells[a.0].center(), poly.cells[b.0].center(), point)?;
+        Some([(cell, weights[0]), (a, weights[1]), (b, weights[2])])
+    })
+}
+
+// None when the point is outside the triangle, or the triangle is flat
+fn barycentric(a: (f64, f64), b: (f64, f64), c: (f64, f64), p: (f64, f64)) -> Option<[f64; 3]> {
+    const TOLERANCE: f64 = 1e-9;
+    let det = (b.1 - c.1) * (a.0 - c.0) + (c.0 - b.0) * (a.1 - c.1);
+    if det.abs() < TOLERANCE {
+        return None;
+    }
+    let wa = ((b.1 - c.1) * (p.0 - c.0) + (c.0 - b.0) * (p.1 - c.1)) / det;
+    let wb = ((c.1 - a.1) * (p.0 - c.0) + (a.0 - c.0) * (p.1 - c.1)) / det;
+    let wc = 1.0 - wa - wb;
+    if wa < -TOLERANCE || wb < -TOLERANCE || wc < -TOLERANCE {
+        return None;
+    }
+    Some([wa, wb, wc])
+}
@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+
+use crate::*;
+
+// A set of cells of a PolyMap
+#[derive(Clone, PartialEq, Eq)]
+pub struct ElementSet {
+    members: Vec<bool>,
+    len: usize,
+}
+
+impl ElementSet {
+    pub fn empty(poly: &PolyMap) -> Self {
+        Self {
+            members: vec![false; poly.cells.len()],
+            len: 0,
+        }
+    }
+
+    pub fn from_cells(poly: &PolyMap, cells: impl IntoIterator<Item = CellId>) -> Self {
+        let mut set = Self::empty(poly);
+        for cell in cells {
+            set.insert(cell);
+        }
+        set
+    }
+
+    pub fn select_by(poly: &PolyMap, predicate: impl Fn(CellId, &Cell) -> bool) -> Self {
+        Self::from_cells(poly, poly.cells().filter(|&(id, cell)| predicate(id, cell)).map(|(id, _)| id))
+    }
+
+    // All the cells connected to start through cells satisfying the predicate
+    pub fn flood_fill(poly: &PolyMap, start: CellId, predicate: impl Fn(CellId) -> bool) -> Self {
+        let mut set = Self::empty(poly);
+        if !predicate(start) {
+            return set;
+        }
+
+        let mut queue = VecDeque::new();
+        set.insert(start);
+        queue.push_back(start);
+        while let Some(id) = queue.pop_front() {
+            for &neighbor in poly.cells[id.0].neighbors() {
+                if !set.contains(neighbor) && predicate(neighbor) {
+                    set.insert(neighbor);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        set
+    }
+
+    pub fn contains(&self, id: CellId) -> bool {
+        self.members[id.0]
+    }
+
+    pub fn insert(&mut self, id: CellId) -> bool {
+        let inserted = !self.members[id.0];
+        if inserted {
+            self.members[id.0] = true;
+            self.len += 1;
+        }
+        inserted
+    }
+
+    pub fn remove(&mut self, id: CellId) -> bool {
+        let removed = self.members[id.0];
+        if removed {
+            self.members[id.0] = false;
+            self.len -= 1;
+        }
+        removed
+    }
+
+    pub fn len(&self) -> usize { self.len }
+    pub fn is_empty(&self) -> bool { self.len == 0 }
+
+    pub fn iter(&self) -> impl Iterator<Item = CellId> + '_ {
+        self.members.iter().enumerate().filter(|(_, &member)| member).map(|(idx, _)| CellId(idx))
+    }
+
+    pub fn complement(&self) -> Self {
+        let members: Vec<_> = self.members.iter().map(|&member| !member).collect();
+        Self { len: members.len() - self.len, members }
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a || b)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && b)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    fn combine(&self, other: &Self, op: impl Fn(bool, bool) -> bool) -> Self {
+        let members: Vec<_> = self.members.iter().zip(other.members.iter()).map(|(&a, &b)| op(a, b)).collect();
+        Self { len: members.iter().filter(|&&member| member).count(), members }
+    }
+
+    // Adds all the cells within the given number of rings around the set
+    pub fn grow(&self, poly: &PolyMap, rings: usize) -> Self {
+        let distances = poly.ring_distances(self.iter());
+        Self::select_by(poly, |id, _| distances[id] <= rings)
+    }
+
+    // Removes all the cells within the given number of rings from the outside of the set
+    pub fn shrink(&self, poly: &PolyMap, rings: usize) -> Self {
+        self.complement().grow(poly, rings).complement()
+    }
+}
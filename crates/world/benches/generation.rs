@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use polymap::PolyMap;
+use world::seed::SeedTree;
+use world::World;
+
+const SEED: u64 = 27049319951022;
+const MAP_SEED: u64 = 1;
+const POISSON_RADIUS: f64 = 8.0;
+// About 3500, 14000 and 56000 cells; the large one is the size the frontend opens with
+const SIZES: [(&str, usize, usize); 3] = [
+    ("small", 400, 225),
+    ("medium", 800, 450),
+    ("large", 1600, 900),
+];
+
+fn polymap(c: &mut Criterion) {
+    let mut group = c.benchmark_group("polymap");
+    group.sample_size(10);
+    for (name, width, height) in SIZES {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED))
+        });
+    }
+    group.finish();
+}
+
+// The time spent in one stage over a number of full generations, as the generation report
+// measured it
+fn stage_time(world: &mut World, stage: &str, iterations: u64) -> Duration {
+    (0..iterations)
+        .map(|_| {
+            world.generate(&SeedTree::new(SEED));
+            world.report().stages().iter()
+                .filter(|report| report.name == stage)
+                .map(|report| report.duration)
+                .sum::<Duration>()
+        })
+        .sum()
+}
+
+fn heightmap(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heightmap");
+    group.sample_size(10);
+    for (name, width, height) in SIZES {
+        let poly = PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED);
+        let mut world = World::new(&poly);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_custom(|iterations| stage_time(&mut world, "heightmap", iterations))
+        });
+    }
+    group.finish();
+}
+
+fn planchon_darboux(c: &mut Criterion) {
+    let mut group = c.benchmark_group("planchon_darboux");
+    group.sample_size(10);
+    for (name, width, height) in SIZES {
+        let poly = PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(SEED));
+        let raw = world.debug_fields().raw_heightmap.clone();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_batched(
+                || raw.clone(),
+                |mut heightmap| world::planchon_darboux(&mut heightmap, &poly, |_| false),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// Everything downstream of the heightmap: drainage, rivers and the climate that follows them
+fn hydrology(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hydrology");
+    group.sample_size(10);
+    for (name, width, height) in SIZES {
+        let poly = PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(SEED));
+        group.bench_function(BenchmarkId::from_parameter(name), |b| b.iter(|| world.reflow()));
+    }
+    group.finish();
+}
+
+fn generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    for (name, width, height) in SIZES {
+        let poly = PolyMap::with_seed(width, height, POISSON_RADIUS, MAP_SEED);
+        let mut world = World::new(&poly);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| world.generate(&SeedTree::new(SEED)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, polymap, heightmap, planchon_darboux, hydrology, generate);
+criterion_main!(benches);
@@ -1,9 +1,10 @@
 use polymap::field::Smoothable;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::TerrainCategory;
 use crate::measure;
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Ground {
     pub water: f64,
     pub sand: f64,
@@ -12,13 +13,13 @@ pub struct Ground {
 }
 
 impl Ground {
-    pub fn new(terrain_category: TerrainCategory, rain:f64, drain: f64, height: f64) -> Self {
+    pub fn new(terrain_category: TerrainCategory, humidity: f64, drain: f64, height: f64) -> Self {
         if terrain_category == TerrainCategory::Sea {
             Ground {
                 water: 1.0, sand: 0.0, soil: 0.0, rock: 0.0,
             }
         } else {
-            let wetness = measure::DRAIN.normalize(drain).min(1.0) + measure::RAIN.normalize(rain);
+            let wetness = measure::DRAIN.normalize(drain).min(1.0) + humidity;
             Ground {
                 water: 0.0,
                 sand: 0.2 * (1.0 - wetness).max(0.0) * (1.0 - height).max(0.0),
@@ -28,6 +29,18 @@ impl Ground {
         }
     }
 
+    pub fn lake() -> Ground {
+        Ground { water: 1.0, sand: 0.0, soil: 0.0, rock: 0.0 }
+    }
+
+    pub fn desert() -> Ground {
+        Ground { water: 0.0, sand: 0.8, soil: 0.1, rock: 0.1 }
+    }
+
+    pub fn mountain() -> Ground {
+        Ground { water: 0.0, sand: 0.0, soil: 0.2, rock: 0.8 }
+    }
+
     pub fn normalize(self) -> Ground {
         let total = self.water + self.sand + self.soil + self.rock;
         if total == 0.0 { self } else {
@@ -49,6 +62,13 @@ impl Smoothable for Ground {
         self.rock += x.rock;
     }
 
+    fn scale(&mut self, factor: f64) {
+        self.water *= factor;
+        self.sand  *= factor;
+        self.soil  *= factor;
+        self.rock  *= factor;
+    }
+
     fn divide(&mut self, n: usize) {
         let n = n as f64;
         self.water /= n;
@@ -57,7 +77,7 @@ impl Smoothable for Ground {
         self.rock  /= n;
     }
 }
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Vegetation {
     pub none: f64,
     pub deciduous: f64,
@@ -74,18 +94,39 @@ impl Default for Vegetation {
 
 impl Vegetation {
 
-    pub fn new(terrain_category: TerrainCategory, rain: f64, temperature: f64, height: f64) -> Vegetation {
+    pub fn new(terrain_category: TerrainCategory, humidity: f64, temperature: f64, height: f64) -> Vegetation {
         
         match terrain_category {
             TerrainCategory::Sea => Vegetation::default(),
             _ => Vegetation {
-                none: (1.0 - measure::RAIN.normalize(rain)).max(0.0),
+                none: (1.0 - humidity).max(0.0),
                 deciduous: if height > 0.8 { 0.0 } else { 10.0 * (0.3 - (0.5 - temperature).abs().min(0.3)) },
                 boreal:  if height > 0.9 { 0.0 } else { 10.0 * (0.3 - (0.2 - temperature).abs().min(0.3) * height) },
             }.normalize()
         }
     }
 
+    // Same mix of trees, but without any bare ground
+    pub fn forested(self) -> Self {
+        if self.deciduous + self.boreal == 0.0 {
+            Vegetation { none: 0.0, deciduous: 1.0, boreal: 0.0 }
+        } else {
+            Vegetation { none: 0.0, ..self }.normalize()
+        }
+    }
+
+    // Trees cover most of the ground
+    pub fn is_forest(&self) -> bool {
+        self.none < 0.5
+    }
+
+    pub fn mean(vegetations: impl Iterator<Item = Vegetation>) -> Self {
+        let (sum, count) = vegetations.fold((Vegetation { none: 0.0, deciduous: 0.0, boreal: 0.0 }, 0), |(sum, count), x| {
+            (Vegetation { none: sum.none + x.none, deciduous: sum.deciduous + x.deciduous, boreal: sum.boreal + x.boreal }, count + 1)
+        });
+        if count == 0 { Vegetation::default() } else { sum.normalize() }
+    }
+
     pub fn normalize(self) -> Self {
         let total = self.none + self.deciduous + self.boreal;
         if total == 0.0 { self } else {
@@ -96,4 +137,102 @@ impl Vegetation {
             }
         }
     }
-}
\ No newline at end of file
+}
+// The climate zone of a land cell, after Whittaker: the mean temperature sets the band, and how
+// humid the climate is within it sets how much grows there
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum LandBiome {
+    // Not a land cell, or under a lake
+    #[default]
+    None,
+    Ice,
+    Tundra,
+    Taiga,
+    ColdDesert,
+    Steppe,
+    TemperateForest,
+    TemperateRainforest,
+    Desert,
+    Savanna,
+    SeasonalForest,
+    TropicalRainforest,
+}
+
+impl LandBiome {
+    // Celsius is the mean temperature of the cell, humidity the usual one, from 0 without rain
+    // to 1 where it rains twice as much as can evaporate
+    pub fn new(terrain_category: TerrainCategory, celsius: f64, humidity: f64) -> Self {
+        if terrain_category == TerrainCategory::Sea {
+            return LandBiome::None;
+        }
+
+        if celsius < -12.0 {
+            LandBiome::Ice
+        } else if celsius < -4.0 {
+            LandBiome::Tundra
+        } else if celsius < 4.0 {
+            if humidity < 0.2 { LandBiome::ColdDesert } else if humidity < 0.4 { LandBiome::Tundra } else { LandBiome::Taiga }
+        } else if celsius < 18.0 {
+            if humidity < 0.2 {
+                LandBiome::ColdDesert
+            } else if humidity < 0.45 {
+                LandBiome::Steppe
+            } else if humidity < 0.85 {
+                LandBiome::TemperateForest
+            } else {
+                LandBiome::TemperateRainforest
+            }
+        } else if humidity < 0.2 {
+            LandBiome::Desert
+        } else if humidity < 0.5 {
+            LandBiome::Savanna
+        } else if humidity < 0.85 {
+            LandBiome::SeasonalForest
+        } else {
+            LandBiome::TropicalRainforest
+        }
+    }
+
+    // The biome of the same climate, had it been dry or wooded, for the cells the mask makes
+    // deserts or forests
+    pub fn dried(self) -> Self {
+        match self {
+            LandBiome::None | LandBiome::Ice => self,
+            LandBiome::Tundra | LandBiome::Taiga | LandBiome::ColdDesert | LandBiome::Steppe
+            | LandBiome::TemperateForest | LandBiome::TemperateRainforest => LandBiome::ColdDesert,
+            _ => LandBiome::Desert,
+        }
+    }
+
+    pub fn forested(self) -> Self {
+        match self {
+            LandBiome::Tundra | LandBiome::ColdDesert => LandBiome::Taiga,
+            LandBiome::Steppe => LandBiome::TemperateForest,
+            LandBiome::Desert | LandBiome::Savanna => LandBiome::SeasonalForest,
+            _ => self,
+        }
+    }
+
+    pub fn is_forest(&self) -> bool {
+        matches!(self,
+            LandBiome::Taiga | LandBiome::TemperateForest | LandBiome::TemperateRainforest |
+            LandBiome::SeasonalForest | LandBiome::TropicalRainforest)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LandBiome::None => "None",
+            LandBiome::Ice => "Ice",
+            LandBiome::Tundra => "Tundra",
+            LandBiome::Taiga => "Taiga",
+            LandBiome::ColdDesert => "Cold desert",
+            LandBiome::Steppe => "Steppe",
+            LandBiome::TemperateForest => "Temperate forest",
+            LandBiome::TemperateRainforest => "Temperate rainforest",
+            LandBiome::Desert => "Desert",
+            LandBiome::Savanna => "Savanna",
+            LandBiome::SeasonalForest => "Seasonal forest",
+            LandBiome::TropicalRainforest => "Tropical rainforest",
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{Vec2, World};
+
+impl World<'_> {
+    // Moves this world t of the way towards another generated on the same map, from 0 for this
+    // world to 1 for the other, e.g. to morph between two seeds or to average two good ones.
+    // The heights, the rain, the wind and the water in the lakes are interpolated, and the rest
+    // derived again from them. The mask, the pins and the civilization stay those of this world.
+    pub fn blend(&mut self, other: &World, t: f64) -> Result<(), String> {
+        if self.poly.fingerprint() != other.poly.fingerprint() {
+            return Err("Only worlds on the same map can be blended".to_owned());
+        }
+        let _span = tracing::info_span!("blend", t).entered();
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        self.start_run();
+
+        self.stage("heightmap", |world| {
+            world.heightmap.update(|id, height| *height = lerp(*height, other.heightmap[id]));
+            world.water_depth.update(|id, depth| *depth = lerp(*depth, other.water_depth[id]));
+            world.apply_mask_to_heightmap();
+            world.apply_pins();
+            // The valleys of one world can dam those of the other
+            world.fill_around_pins();
+            world.compute_downhill();
+        });
+
+        self.stage("terrain", |world| {
+            world.assign_terrain_types();
+            world.analyze_relief();
+        });
+
+        self.stage("temperature", |world| world.generate_temperature());
+
+        self.stage("rainfall", |world| {
+            world.rainfall.update(|id, rain| *rain = lerp(*rain, other.rainfall[id]));
+            world.wind.update(|id, wind| {
+                let towards = other.wind[id];
+                *wind = Vec2 { x: lerp(wind.x, towards.x), y: lerp(wind.y, towards.y) };
+            });
+        });
+
+        self.stage("rivers", |world| world.generate_rivers());
+
+        self.stage("biomes", |world| world.generate_biomes());
+        Ok(())
+    }
+}
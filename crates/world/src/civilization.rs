@@ -0,0 +1,285 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::culture::Culture;
+use crate::features::{Feature, FeatureId, FeatureKind, Features, Geometry};
+use crate::seed::SeedTree;
+use crate::tags::{TagValue, Tags};
+use crate::World;
+
+const VERSION: u32 = 1;
+
+// Everything people made of a world, as opposed to what the generator made of it: the
+// features and their names and tags, the cell tags, the cultures and the realms. It is
+// exported on its own so that it can be put back on the same map after the physical world
+// was generated again.
+pub struct Civilization {
+    map: u64,
+    features: Vec<Feature>,
+    tags: Vec<(CellId, String, TagValue)>,
+    cultures: Vec<(CellId, u64)>,
+    culture: Vec<Option<usize>>,
+    territory: Vec<Option<usize>>,
+}
+
+impl Civilization {
+    pub fn of_world(world: &World) -> Self {
+        let poly = world.poly();
+        let tags = world.tags();
+        let mut cells: Vec<_> = tags.tagged_cells().collect();
+        cells.sort_unstable();
+        Self {
+            map: poly.fingerprint(),
+            features: world.features().iter().cloned().collect(),
+            tags: cells.into_iter()
+                .flat_map(|cell| tags.of_cell(cell).map(move |(key, value)| (cell, key.to_owned(), value.clone())))
+                .collect(),
+            cultures: world.cultures().iter().map(|culture| (culture.origin, culture.seed())).collect(),
+            culture: poly.cells().map(|(id, _)| world.culture()[id]).collect(),
+            territory: poly.cells().map(|(id, _)| world.territory()[id]).collect(),
+        }
+    }
+
+    // A text file, one record per line with tab separated fields. Names, tags and notes
+    // follow the feature they belong to.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "civilization\t{}", VERSION);
+        let _ = writeln!(text, "map\t{:016x}", self.map);
+        for feature in self.features.iter() {
+            let (shape, cells) = match &feature.geometry {
+                Geometry::Point(cell) => ("point", std::slice::from_ref(cell)),
+                Geometry::Path(cells) => ("path", cells.as_slice()),
+                Geometry::Area(cells) => ("area", cells.as_slice()),
+            };
+            let cells: Vec<_> = cells.iter().map(|cell| cell.idx().to_string()).collect();
+            let _ = writeln!(text, "feature\t{}\t{}\t{}\t{}", feature.id.idx(), feature.kind.name(), shape, cells.join(","));
+            if let Some(name) = feature.name.as_ref() {
+                let _ = writeln!(text, "name\t{}", escape(name));
+            }
+            for (key, value) in feature.tags.iter() {
+                let _ = writeln!(text, "tag\t{}\t{}", escape(key), tag_to_text(value));
+            }
+        }
+        for (cell, key, value) in self.tags.iter() {
+            let _ = writeln!(text, "cell-tag\t{}\t{}\t{}", cell.idx(), escape(key), tag_to_text(value));
+        }
+        for (origin, seed) in self.cultures.iter() {
+            let _ = writeln!(text, "culture\t{}\t{}", origin.idx(), seed);
+        }
+        let _ = writeln!(text, "culture-map\t{}", owners_to_text(&self.culture));
+        let _ = writeln!(text, "territory\t{}", owners_to_text(&self.territory));
+        text
+    }
+
+    pub fn from_text(poly: &PolyMap, text: &str) -> Result<Self, String> {
+        let mut civilization = Self {
+            map: 0,
+            features: vec![],
+            tags: vec![],
+            cultures: vec![],
+            culture: vec![None; poly.cells().count()],
+            territory: vec![None; poly.cells().count()],
+        };
+
+        for (number, line) in text.lines().enumerate() {
+            let fields: Vec<_> = line.split('\t').collect();
+            let error = |message: &str| format!("line {}: {}", number + 1, message);
+            let field = |idx: usize| fields.get(idx).copied().ok_or_else(|| error("missing field"));
+            let cell = |text: &str| text.parse().ok().and_then(|idx| poly.cell_id(idx))
+                .ok_or_else(|| error(&format!("invalid cell {}", text)));
+
+            match fields[0] {
+                "" => {}
+                "civilization" => {
+                    if field(1)? != VERSION.to_string() {
+                        return Err(error(&format!("unsupported version {}", field(1)?)));
+                    }
+                }
+                "map" => {
+                    civilization.map = u64::from_str_radix(field(1)?, 16).map_err(|_| error("invalid map"))?;
+                    if civilization.map != poly.fingerprint() {
+                        return Err("the civilization was exported from a different map".to_owned());
+                    }
+                }
+                "feature" => {
+                    let id = field(1)?.parse().map_err(|_| error("invalid feature id"))?;
+                    let kind_name = field(2)?;
+                    let kind = FeatureKind::ALL.into_iter().find(|kind| kind.name() == kind_name)
+                        .ok_or_else(|| error(&format!("unknown feature kind {}", kind_name)))?;
+                    let cells = field(4)?.split(',').filter(|text| !text.is_empty())
+                        .map(cell)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let geometry = match (field(3)?, cells.first()) {
+                        ("point", Some(&first)) => Geometry::Point(first),
+                        ("path", _) => Geometry::Path(cells),
+                        ("area", _) => Geometry::Area(cells),
+                        (shape, _) => return Err(error(&format!("invalid geometry {}", shape))),
+                    };
+                    civilization.features.push(Feature {
+                        id: FeatureId::from_idx(id),
+                        kind,
+                        name: None,
+                        geometry,
+                        tags: BTreeMap::new(),
+                    });
+                }
+                "name" => {
+                    let feature = civilization.features.last_mut().ok_or_else(|| error("name before any feature"))?;
+                    feature.name = Some(unescape(field(1)?));
+                }
+                "tag" => {
+                    let value = tag_from_text(field(2)?, field(3)?).ok_or_else(|| error("invalid tag"))?;
+                    let feature = civilization.features.last_mut().ok_or_else(|| error("tag before any feature"))?;
+                    feature.tags.insert(unescape(field(1)?), value);
+                }
+                "cell-tag" => {
+                    let value = tag_from_text(field(3)?, field(4)?).ok_or_else(|| error("invalid tag"))?;
+                    civilization.tags.push((cell(field(1)?)?, unescape(field(2)?), value));
+                }
+                "culture" => {
+                    let seed = field(2)?.parse().map_err(|_| error("invalid seed"))?;
+                    civilization.cultures.push((cell(field(1)?)?, seed));
+                }
+                "culture-map" => {
+                    civilization.culture = owners_from_text(field(1)?, poly).ok_or_else(|| error("invalid culture map"))?;
+                }
+                "territory" => {
+                    civilization.territory = owners_from_text(field(1)?, poly).ok_or_else(|| error("invalid territory"))?;
+                }
+                other => return Err(error(&format!("unknown record {}", other))),
+            }
+        }
+        Ok(civilization)
+    }
+
+    // The features as they were exported, for a world put back as it was saved
+    pub fn features(&self) -> Features {
+        let mut features = Features::default();
+        for feature in self.features.iter() {
+            features.insert(feature.clone());
+        }
+        features
+    }
+
+    pub fn tags(&self) -> Tags {
+        let mut tags = Tags::default();
+        for (cell, key, value) in self.tags.iter() {
+            tags.set(*cell, key, value.clone());
+        }
+        tags
+    }
+
+    pub fn cultures(&self) -> Vec<Culture> {
+        self.cultures.iter().map(|&(origin, seed)| Culture::new(origin, SeedTree::new(seed))).collect()
+    }
+
+    pub fn culture(&self, poly: &PolyMap) -> Field<Option<usize>> {
+        Field::with_fn(poly, |id, _| self.culture[id.idx()])
+    }
+
+    pub fn territory(&self, poly: &PolyMap) -> Field<Option<usize>> {
+        Field::with_fn(poly, |id, _| self.territory[id.idx()])
+    }
+
+    // The imported features merged with those generated for the current world. Features
+    // placed by hand come back as they were. Generated ones are matched by their key cell and
+    // take back their id, name and tags; the generated features that match nothing keep
+    // their own. Returns the registry, and the number of exported generated features which
+    // are no longer in the world.
+    pub fn merge_features(&self, current: &Features) -> (Features, usize) {
+        let mut generated: HashMap<(FeatureKind, CellId), Feature> = current.iter()
+            .filter(|feature| feature.kind.is_generated())
+            .filter_map(|feature| Some(((feature.kind, feature.geometry.key_cell()?), feature.clone())))
+            .collect();
+
+        let mut features = Features::default();
+        let mut lost = 0;
+        for imported in self.features.iter() {
+            if !imported.kind.is_generated() {
+                features.insert(imported.clone());
+                continue;
+            }
+            let found = imported.geometry.key_cell().and_then(|cell| generated.remove(&(imported.kind, cell)));
+            match found {
+                Some(mut feature) => {
+                    feature.id = imported.id;
+                    feature.name = imported.name.clone();
+                    feature.tags.extend(imported.tags.iter().map(|(key, value)| (key.clone(), value.clone())));
+                    features.insert(feature);
+                }
+                None => lost += 1,
+            }
+        }
+
+        let mut remaining: Vec<_> = generated.into_values().collect();
+        remaining.sort_by_key(|feature| feature.id);
+        for feature in remaining {
+            let id = features.add(feature.kind, feature.geometry);
+            if let Some(added) = features.get_mut(id) {
+                added.name = feature.name;
+                added.tags = feature.tags;
+            }
+        }
+        (features, lost)
+    }
+}
+
+fn tag_to_text(value: &TagValue) -> String {
+    match value {
+        TagValue::Bool(x) => format!("bool\t{}", x),
+        TagValue::Int(x) => format!("int\t{}", x),
+        TagValue::Float(x) => format!("float\t{}", x),
+        TagValue::Text(x) => format!("text\t{}", escape(x)),
+    }
+}
+
+fn tag_from_text(kind: &str, text: &str) -> Option<TagValue> {
+    match kind {
+        "bool" => text.parse().ok().map(TagValue::Bool),
+        "int" => text.parse().ok().map(TagValue::Int),
+        "float" => text.parse().ok().map(TagValue::Float),
+        "text" => Some(TagValue::Text(unescape(text))),
+        _ => None,
+    }
+}
+
+fn owners_to_text(owners: &[Option<usize>]) -> String {
+    let owners: Vec<_> = owners.iter()
+        .map(|owner| owner.map(|idx| idx.to_string()).unwrap_or_else(|| "-".to_owned()))
+        .collect();
+    owners.join(",")
+}
+
+fn owners_from_text(text: &str, poly: &PolyMap) -> Option<Vec<Option<usize>>> {
+    let owners = text.split(',')
+        .map(|owner| if owner == "-" { Some(None) } else { owner.parse().ok().map(Some) })
+        .collect::<Option<Vec<_>>>()?;
+    if owners.len() == poly.cells().count() { Some(owners) } else { None }
+}
+
+// Free text may hold tabs and line breaks, which separate fields and records
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
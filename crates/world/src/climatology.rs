@@ -0,0 +1,175 @@
+use polymap::*;
+use polymap::field::Field;
+use rand::Rng;
+
+use crate::{PolarVec2, TerrainCategory, Vec2, World};
+
+// The vapor the wind brings in over the edge of the map, and picks up over each sea cell
+const START_VAPOR: f64 = 10.0;
+const SEA_PICKUP: f64 = 0.1;
+// Of the vapor carried, the share each land cell gets as rain, in the lowlands and highlands
+const LOWLAND_RAIN: f64 = 0.01;
+const HIGHLAND_RAIN: f64 = 0.02;
+// Rain wrung out of the air by each unit of height it is pushed up, on top of the above
+const OROGRAPHIC_RAIN: f64 = 2.0;
+
+// How the wind brings the rain over the land
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WindModel {
+    // Clouds blown in from every border, drifting at random as they go
+    #[default]
+    Clouds,
+    // A steady wind over the whole map, passing its vapor on from cell to cell downwind.
+    // Slopes facing the wind get the rain, and leave the lands behind them in its shadow.
+    Prevailing,
+}
+
+impl std::str::FromStr for WindModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clouds" => Ok(WindModel::Clouds),
+            "prevailing" => Ok(WindModel::Prevailing),
+            _ => Err(format!("Unknown wind model '{}', expected one of clouds, prevailing", s)),
+        }
+    }
+}
+
+impl World<'_> {
+    // Sets the wind and drops the rain it carries. The direction is the configured one, or
+    // drawn from the seeds.
+    pub(crate) fn blow_wind(&mut self, rng: &mut impl Rng) {
+        let drawn = (rng.gen_range(0..=359) as f64).to_radians();
+        let direction = self.wind_direction.map(f64::to_radians).unwrap_or(drawn);
+        self.wind.update(|_, x| *x = Vec2::ZERO);
+        match self.wind_model {
+            WindModel::Clouds => self.blow_clouds(direction, rng),
+            WindModel::Prevailing => self.blow_prevailing(direction),
+        }
+    }
+
+    fn blow_clouds(&mut self, wind_direction: f64, rng: &mut impl Rng) {
+        self.report.count("clouds", self.poly().borders().count());
+
+        // For each border tile, we spawn a cloud
+        // TODO: Do not just pick up any border, but just the borders which are opposite to
+        // the wind-blowing direction
+        for (mut cloud_cell, _) in self.poly().borders() {
+            let mut vapor = START_VAPOR;
+            let mut direction = wind_direction;
+            let mut stop = false;
+            let mut visited = Field::uniform(self.poly(), false);
+            // Randomly walk the cell through the world
+            loop {
+                visited[cloud_cell] = true;
+                // If the cell is over water, pick up vapor, but if it's over land, drop some vapor.
+                // Lose all vapour if over mountain
+                let terrain_category = self.terrain_category[cloud_cell];
+                match terrain_category {
+                    TerrainCategory::Sea => vapor += SEA_PICKUP,
+                    TerrainCategory::Coast => {},
+                    TerrainCategory::Land => {
+                        let height = self.heightmap[cloud_cell];
+                        let rain_rate = if height < self.levels.highland {
+                            LOWLAND_RAIN
+                        } else {
+                            HIGHLAND_RAIN
+                        };
+                        let rain = if height < self.levels.peak {
+                            vapor * rain_rate
+                        } else {
+                            stop = true;
+                            vapor
+                        };
+                        vapor -= rain;
+                        self.rainfall[cloud_cell] += rain;
+                    }
+                }
+
+                // Broken by a high peak
+                if stop {
+                    break;
+                }
+
+                // Add a random drift
+                let change_magnitude = 2.5;
+                let direction_change = f64::to_radians(rng.gen_range(-change_magnitude..change_magnitude));
+                direction += direction_change;
+                // Record the path of the cell in the wind table
+                self.wind[cloud_cell] += PolarVec2 { r: vapor, theta: direction}.to_cartesian();
+
+                match self.poly().neighbor_in_direction(cloud_cell, direction, 40.0) {
+                    Some(x) => {
+                        if visited[x] {
+                            break;
+                        } else {
+                            cloud_cell = x
+                        }
+                    },
+                    None => break
+                }
+            }
+        }
+    }
+
+    // Sweeps the map from upwind to downwind. Each cell takes in the vapor of the cells upwind
+    // of it, weighted by how squarely they face the wind, and rains out a share of it: more in
+    // the highlands, more again the higher the air is pushed over the cells upwind, and all of
+    // it over the peaks.
+    fn blow_prevailing(&mut self, direction: f64) {
+        let poly = self.poly;
+        // The angles go counterclockwise with y up, the map has y down
+        let (dx, dy) = (direction.cos(), -direction.sin());
+        let along = |id: CellId| {
+            let (x, y) = poly[id].center();
+            x * dx + y * dy
+        };
+        let mut order: Vec<_> = poly.cells().map(|(id, _)| id).collect();
+        order.sort_by(|&a, &b| along(a).total_cmp(&along(b)));
+
+        let mut vapor = Field::uniform(poly, 0.0);
+        let mut shadowed = 0;
+        for id in order {
+            let (cx, cy) = poly[id].center();
+            let (mut incoming, mut upwind_height, mut weight) = (0.0, 0.0, 0.0);
+            for &neighbor in poly[id].neighbors() {
+                let (nx, ny) = poly[neighbor].center();
+                let (sx, sy) = (cx - nx, cy - ny);
+                let facing = (sx * dx + sy * dy) / (sx * sx + sy * sy).sqrt().max(f64::EPSILON);
+                if facing > 0.0 {
+                    incoming += facing * vapor[neighbor];
+                    upwind_height += facing * self.heightmap[neighbor];
+                    weight += facing;
+                }
+            }
+
+            let height = self.heightmap[id];
+            let (mut carried, rise) = if weight > 0.0 {
+                (incoming / weight, (height - upwind_height / weight).max(0.0))
+            } else {
+                (START_VAPOR, 0.0)
+            };
+            match self.terrain_category[id] {
+                TerrainCategory::Sea => carried += SEA_PICKUP,
+                TerrainCategory::Coast => {}
+                TerrainCategory::Land => {
+                    let rate = if height < self.levels.highland { LOWLAND_RAIN } else { HIGHLAND_RAIN };
+                    let rain = if height < self.levels.peak {
+                        carried * (rate + OROGRAPHIC_RAIN * rise).min(1.0)
+                    } else {
+                        carried
+                    };
+                    if carried < 0.5 * START_VAPOR {
+                        shadowed += 1;
+                    }
+                    carried -= rain;
+                    self.rainfall[id] += rain;
+                }
+            }
+            vapor[id] = carried;
+            self.wind[id] = PolarVec2 { r: carried, theta: direction }.to_cartesian();
+        }
+        self.report.count("rain shadow cells", shadowed);
+    }
+}
@@ -0,0 +1,107 @@
+use polymap::*;
+use polymap::field::Field;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StrokeKind {
+    Coastline,
+    Ridge,
+}
+
+impl StrokeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StrokeKind::Coastline => "Coastline",
+            StrokeKind::Ridge => "Ridge",
+        }
+    }
+
+    pub fn target_height(&self) -> f64 {
+        match self {
+            StrokeKind::Coastline => 0.3,
+            StrokeKind::Ridge => 0.85,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Stroke {
+    pub kind: StrokeKind,
+    pub points: Vec<(f64, f64)>,
+    pub radius: f64,
+}
+
+impl Stroke {
+    pub fn new(kind: StrokeKind, radius: f64) -> Self {
+        Self { kind, points: vec![], radius }
+    }
+
+    pub fn distance_to(&self, x: f64, y: f64) -> f64 {
+        match self.points.as_slice() {
+            [] => f64::INFINITY,
+            &[(px, py)] => ((x - px).powi(2) + (y - py).powi(2)).sqrt(),
+            points => points.windows(2)
+                .map(|segment| distance_to_segment((x, y), segment[0], segment[1]))
+                .fold(f64::INFINITY, f64::min),
+        }
+    }
+}
+
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HeightConstraint {
+    pub target: f64,
+    pub weight: f64,
+}
+
+impl HeightConstraint {
+    pub fn blend(&self, height: f64) -> f64 {
+        height * (1.0 - self.weight) + self.target * self.weight
+    }
+}
+
+#[derive(Clone)]
+pub struct ConstraintField(Field<Option<HeightConstraint>>);
+
+impl ConstraintField {
+    pub fn empty(poly: &PolyMap) -> Self {
+        Self(Field::uniform(poly, None))
+    }
+
+    // Each cell takes the constraint of the closest stroke, fading out linearly with the distance
+    pub fn from_strokes(poly: &PolyMap, strokes: &[Stroke]) -> Self {
+        Self(Field::with_fn(poly, |_, cell| {
+            let (x, y) = cell.center();
+            strokes.iter()
+                .filter(|stroke| stroke.radius > 0.0)
+                .map(|stroke| HeightConstraint {
+                    target: stroke.kind.target_height(),
+                    weight: 1.0 - stroke.distance_to(x, y) / stroke.radius,
+                })
+                .filter(|constraint| constraint.weight > 0.0)
+                .reduce(|c1, c2| if c1.weight >= c2.weight { c1 } else { c2 })
+        }))
+    }
+
+    pub fn get(&self, id: CellId) -> Option<HeightConstraint> {
+        self.0[id]
+    }
+
+    pub fn blend_into(&self, heightmap: &mut Field<f64>) {
+        heightmap.update(|id, height| {
+            if let Some(constraint) = self.0[id] {
+                *height = constraint.blend(*height)
+            }
+        })
+    }
+}
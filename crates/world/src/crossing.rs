@@ -0,0 +1,28 @@
+use polymap::CellId;
+
+// Rivers narrower than this can be forded
+const MAX_FORD_WIDTH: f64 = 2.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CrossingKind {
+    Ford,
+    Bridge,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Crossing {
+    pub cell: CellId,
+    // Index of the river in World::rivers
+    pub river: usize,
+    pub width: f64,
+    pub kind: CrossingKind,
+}
+
+impl Crossing {
+    // The width of the river grows with the square root of the water flowing through it
+    pub fn new(cell: CellId, river: usize, drainage: f64) -> Self {
+        let width = drainage.max(0.0).sqrt() * 0.5;
+        let kind = if width <= MAX_FORD_WIDTH { CrossingKind::Ford } else { CrossingKind::Bridge };
+        Self { cell, river, width, kind }
+    }
+}
@@ -0,0 +1,143 @@
+use polymap::CellId;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::features::FeatureKind;
+use crate::seed::SeedTree;
+use crate::{TerrainCategory, World};
+
+// How many cultures a generated world starts with, fewer on maps with little land
+const CULTURES: usize = 5;
+
+// Every culture speaks with a subset of these
+const ONSETS: [&str; 28] = [
+    "b", "c", "d", "f", "g", "h", "k", "l", "m", "n", "p", "r", "s", "t", "v", "z",
+    "br", "dr", "gr", "kh", "st", "th", "tr", "sh", "ch", "kw", "gl", "",
+];
+const VOWELS: [&str; 12] = ["a", "e", "i", "o", "u", "ae", "ai", "ei", "ou", "ia", "y", "oo"];
+const CODAS: [&str; 16] = ["", "", "n", "r", "l", "s", "th", "nd", "rn", "sk", "m", "x", "st", "ng", "rd", "k"];
+
+// The sounds a culture builds its names from
+#[derive(Clone, Debug)]
+pub struct Phonemes {
+    pub onsets: Vec<&'static str>,
+    pub vowels: Vec<&'static str>,
+    pub codas: Vec<&'static str>,
+    pub min_syllables: usize,
+    pub max_syllables: usize,
+}
+
+impl Phonemes {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let mut pick = |inventory: &[&'static str], count: usize| {
+            let mut chosen: Vec<_> = inventory.choose_multiple(rng, count).copied().collect();
+            chosen.sort_unstable();
+            chosen
+        };
+        let onsets = pick(&ONSETS, 10);
+        let vowels = pick(&VOWELS, 5);
+        let codas = pick(&CODAS, 5);
+        let min_syllables = rng.gen_range(1..=2);
+        let max_syllables = min_syllables + rng.gen_range(1..=2);
+        Self { onsets, vowels, codas, min_syllables, max_syllables }
+    }
+
+    pub fn word(&self, rng: &mut impl Rng) -> String {
+        let syllables = rng.gen_range(self.min_syllables..=self.max_syllables);
+        let mut word = String::new();
+        for idx in 0..syllables {
+            word.push_str(self.onsets.choose(rng).unwrap_or(&""));
+            word.push_str(self.vowels.choose(rng).unwrap_or(&"a"));
+            if idx + 1 == syllables || rng.gen_bool(0.3) {
+                word.push_str(self.codas.choose(rng).unwrap_or(&""));
+            }
+        }
+        capitalize(&word)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Culture {
+    pub name: String,
+    pub origin: CellId,
+    pub phonemes: Phonemes,
+    seeds: SeedTree,
+}
+
+impl Culture {
+    pub fn new(origin: CellId, seeds: SeedTree) -> Self {
+        let phonemes = Phonemes::random(&mut seeds.child("phonemes").rng());
+        let name = phonemes.word(&mut seeds.child("name").rng());
+        Self { name, origin, phonemes, seeds }
+    }
+
+    // Together with the origin, all it takes to create the culture again
+    pub fn seed(&self) -> u64 { self.seeds.seed() }
+
+    // The same key always gives the same name; `attempt` gives alternatives for it
+    pub fn name_for(&self, kind: FeatureKind, key: u64, attempt: u64) -> String {
+        let mut rng = self.seeds.child(kind.name()).indexed(key).indexed(attempt).rng();
+        let word = self.phonemes.word(&mut rng);
+        match kind {
+            FeatureKind::River => format!("{} River", word),
+            FeatureKind::Lake => format!("Lake {}", word),
+            FeatureKind::Range => format!("{} Mountains", word),
+            FeatureKind::Pass => format!("{} Pass", word),
+            FeatureKind::Island => format!("{} Island", word),
+            FeatureKind::Archipelago => format!("{} Islands", word),
+            FeatureKind::Habitat => format!("{} Wilds", word),
+            FeatureKind::Migration => format!("{} Trail", word),
+            FeatureKind::Region | FeatureKind::Settlement | FeatureKind::Poi => word,
+        }
+    }
+}
+
+// Where the cultures of a generated world start: the settlements placed by hand, then open
+// land as far as can be from the origins picked before, the first one drawn from the seeds
+pub fn origins(world: &World, seeds: &SeedTree) -> Vec<CellId> {
+    let poly = world.poly();
+    let land: Vec<_> = poly.cells()
+        .map(|(id, _)| id)
+        .filter(|&id| world.terrain_category()[id] != TerrainCategory::Sea && world.difficulty()[id].is_finite())
+        .collect();
+    let mut origins: Vec<CellId> = world.features().of_kind(FeatureKind::Settlement)
+        .filter_map(|feature| feature.geometry.cells().first().copied())
+        .take(CULTURES)
+        .collect();
+    if origins.is_empty() {
+        match land.choose(&mut seeds.child("first").rng()) {
+            Some(&first) => origins.push(first),
+            None => return origins,
+        }
+    }
+
+    let distance = |from: CellId, to: CellId| {
+        let ((fx, fy), (tx, ty)) = (poly[from].center(), poly[to].center());
+        (tx - fx).powi(2) + (ty - fy).powi(2)
+    };
+    let mut nearest: Vec<f64> = land.iter()
+        .map(|&cell| origins.iter().map(|&origin| distance(origin, cell)).fold(f64::MAX, f64::min))
+        .collect();
+    while origins.len() < CULTURES.min(land.len()) {
+        let farthest = nearest.iter().enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(idx, _)| idx);
+        let origin = match farthest {
+            Some(idx) if nearest[idx] > 0.0 => land[idx],
+            _ => break,
+        };
+        origins.push(origin);
+        for (value, &cell) in nearest.iter_mut().zip(land.iter()) {
+            *value = value.min(distance(origin, cell));
+        }
+    }
+    origins
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,142 @@
+use polymap::*;
+use polymap::field::Field;
+
+use crate::spatial_function::SpatialFunction;
+
+// Intermediate data of the last generation, kept around to be inspected
+#[derive(Clone)]
+pub struct DebugFields {
+    // The sum of all the noise contributions, before filling and normalization
+    pub raw_heightmap: Field<f64>,
+    pub slope: Field<f64>,
+    pub low_noise: Field<f64>,
+    pub high_noise: Field<f64>,
+    // How much Planchon-Darboux raised each cell, in raw heightmap units
+    pub filled_depressions: Field<f64>,
+}
+
+impl DebugFields {
+    pub fn new(poly: &PolyMap) -> Self {
+        Self {
+            raw_heightmap: Field::uniform(poly, 0.0),
+            slope: Field::uniform(poly, 0.0),
+            low_noise: Field::uniform(poly, 0.0),
+            high_noise: Field::uniform(poly, 0.0),
+            filled_depressions: Field::uniform(poly, 0.0),
+        }
+    }
+}
+
+// Rainfall on every cell of a debug world, enough for rivers to form after a dozen cells
+pub(crate) const UNIFORM_RAINFALL: f64 = 1.0;
+
+// Analytic worlds with known shapes, used to check hydrology and thermology against expected outputs
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DebugPreset {
+    // A single circular island peaking at the map center: rivers run straight down its flanks
+    ConeIsland,
+    // A plane descending from the west edge into a sea on the east side
+    TiltedPlane,
+    // Two parallel east-west ridges enclosing a valley which drains to the east
+    TwoRidgeValley,
+}
+
+impl DebugPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DebugPreset::ConeIsland => "Cone island",
+            DebugPreset::TiltedPlane => "Tilted plane",
+            DebugPreset::TwoRidgeValley => "Two-ridge valley",
+        }
+    }
+
+    pub(crate) fn heights(self, width: f64, height: f64) -> PresetHeights {
+        PresetHeights { preset: self, width, height }
+    }
+}
+
+pub(crate) struct PresetHeights {
+    preset: DebugPreset,
+    width: f64,
+    height: f64,
+}
+
+impl SpatialFunction for PresetHeights {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (w, h) = (self.width, self.height);
+        match self.preset {
+            DebugPreset::ConeIsland => {
+                let radius = w.min(h) / 2.0;
+                let distance = ((x - w / 2.0).powi(2) + (y - h / 2.0).powi(2)).sqrt();
+                (1.0 - distance / radius).max(0.0)
+            }
+            DebugPreset::TiltedPlane => 1.0 - x / w,
+            DebugPreset::TwoRidgeValley => {
+                let ridge = |center: f64| (1.0 - (y - center).abs() / (h / 4.0)).max(0.0);
+                let ridges = ridge(h / 4.0).max(ridge(3.0 * h / 4.0));
+                0.5 * ridges + 0.5 * (1.0 - x / w)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World;
+
+    // The cells differ with the sampling of the map, so the counts are bounds around those
+    // of the analytic shapes rather than exact
+    fn map() -> PolyMap {
+        PolyMap::with_seed(400, 240, 6.0, 1)
+    }
+
+    fn center(poly: &PolyMap, cell: CellId) -> (f64, f64) { poly.cell(cell).center() }
+
+    #[test]
+    fn cone_island_rivers_run_down_the_flanks() {
+        let poly = map();
+        let world = World::debug_world(&poly, DebugPreset::ConeIsland);
+        let summit = poly.cell_at(200.0, 120.0).unwrap();
+        // Nothing flows onto the peak
+        assert_eq!(world.drainage()[summit], UNIFORM_RAINFALL);
+        let rivers: Vec<_> = world.river_list().collect();
+        assert!((2..=12).contains(&rivers.len()), "{} rivers", rivers.len());
+        let distance = |cell| {
+            let (x, y) = center(&poly, cell);
+            ((x - 200.0).powi(2) + (y - 120.0).powi(2)).sqrt()
+        };
+        for river in rivers {
+            assert!(distance(river.mouth()) > distance(river.source()));
+            assert!(world.heightmap()[river.mouth()] < world.heightmap()[river.source()]);
+        }
+    }
+
+    #[test]
+    fn tilted_plane_drains_east() {
+        let poly = map();
+        let world = World::debug_world(&poly, DebugPreset::TiltedPlane);
+        let west = poly.cell_at(5.0, 120.0).unwrap();
+        assert!(world.drainage()[west] <= 3.0 * UNIFORM_RAINFALL, "{}", world.drainage()[west]);
+        let rivers: Vec<_> = world.river_list().collect();
+        assert!((10..=60).contains(&rivers.len()), "{} rivers", rivers.len());
+        for river in rivers {
+            assert!(center(&poly, river.mouth()).0 > center(&poly, river.source()).0);
+        }
+    }
+
+    #[test]
+    fn two_ridge_valley_gathers_the_biggest_river() {
+        let poly = map();
+        let world = World::debug_world(&poly, DebugPreset::TwoRidgeValley);
+        // A crest is a divide
+        let crest = poly.cell_at(200.0, 60.0).unwrap();
+        assert!(world.drainage()[crest] <= 3.0 * UNIFORM_RAINFALL, "{}", world.drainage()[crest]);
+        let biggest = world.river_list()
+            .max_by(|a, b| a.mouth_flux().total_cmp(&b.mouth_flux()))
+            .unwrap();
+        let (_, y) = center(&poly, biggest.mouth());
+        assert!((y - 120.0).abs() < 30.0, "the biggest river ends at y {}", y);
+        assert!(biggest.mouth_flux() > 100.0 * UNIFORM_RAINFALL, "{}", biggest.mouth_flux());
+    }
+}
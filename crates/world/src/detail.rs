@@ -0,0 +1,116 @@
+use std::f64::consts::TAU;
+
+use polymap::*;
+use rand::Rng;
+
+use crate::seed::SeedTree;
+use crate::{TerrainCategory, World};
+
+// Octaves of the sub-cell relief, each half as wide and half as high as the one before
+const OCTAVES: usize = 4;
+// Height of the sub-cell relief of the roughest cells, in heightmap units
+const RELIEF: f64 = 0.03;
+
+// A point of the synthesized terrain: x and y in map units, like the cell centers, and height
+pub type DetailPoint = (f64, f64, f64);
+
+// Plausible terrain inside a cell, for drawing it much larger than it is. Nothing of it is
+// stored in the world. The cell is cut in a fan of triangles from its center, each subdivided
+// `levels` times. Heights are interpolated between the center and the corners of the cell,
+// whose heights are shared with the neighbors, so that neighboring cells meet without seams,
+// and relief rolled from the seeds of the cell is added towards the center. The same seeds
+// always give the same terrain for the same cell.
+pub fn synthesize(world: &World, cell: CellId, seeds: &SeedTree, levels: usize) -> Vec<[DetailPoint; 3]> {
+    let poly = world.poly();
+    let heightmap = world.heightmap();
+    let (cx, cy) = poly[cell].center();
+
+    let mut corners: Vec<_> = poly[cell].polygon().exterior().points_iter().map(|p| (p.x(), p.y())).collect();
+    // The ring is closed
+    corners.pop();
+    if corners.len() < 3 {
+        return vec![];
+    }
+    let corner_heights: Vec<_> = corners.iter().map(|&corner| corner_height(poly, heightmap, cell, corner)).collect();
+
+    let radius = corners.iter().map(|&(x, y)| ((x - cx).powi(2) + (y - cy).powi(2)).sqrt()).sum::<f64>() / corners.len() as f64;
+    let roughness = match world.terrain_category()[cell] {
+        TerrainCategory::Sea => 0.1,
+        TerrainCategory::Coast | TerrainCategory::Land => 0.25 + 0.75 * (world.slope()[cell] / 2.0).clamp(0.0, 1.0),
+    };
+    let relief = Relief::roll(&mut seeds.child("detail").cell(cell).rng(), radius, RELIEF * roughness);
+
+    let center = heightmap[cell];
+    let point = |(a, b): (f64, f64), first: usize, second: usize| -> DetailPoint {
+        let (x1, y1) = corners[first];
+        let (x2, y2) = corners[second];
+        let central = 1.0 - a - b;
+        let x = central * cx + a * x1 + b * x2;
+        let y = central * cy + a * y1 + b * y2;
+        let height = central * center + a * corner_heights[first] + b * corner_heights[second]
+            + central * relief.at(x - cx, y - cy);
+        (x, y, height)
+    };
+
+    let steps = 1 << levels;
+    let step = 1.0 / steps as f64;
+    let mut triangles = vec![];
+    for first in 0..corners.len() {
+        let second = (first + 1) % corners.len();
+        for i in 0..steps {
+            for j in 0..steps - i {
+                let (a, b) = (i as f64 * step, j as f64 * step);
+                triangles.push([
+                    point((a, b), first, second),
+                    point((a + step, b), first, second),
+                    point((a, b + step), first, second),
+                ]);
+                if i + j + 1 < steps {
+                    triangles.push([
+                        point((a + step, b), first, second),
+                        point((a + step, b + step), first, second),
+                        point((a, b + step), first, second),
+                    ]);
+                }
+            }
+        }
+    }
+    triangles
+}
+
+// The mean height of the cells meeting at a corner. Corners of neighboring cells are the
+// same points, so each of them finds the same cells.
+fn corner_height(poly: &PolyMap, heightmap: &field::Field<f64>, cell: CellId, (x, y): (f64, f64)) -> f64 {
+    let touching = poly[cell].neighbors().iter().copied()
+        .filter(|&neighbor| poly[neighbor].polygon().exterior().points_iter()
+            .any(|p| (p.x() - x).abs() < 1e-6 && (p.y() - y).abs() < 1e-6));
+    let (sum, count) = std::iter::once(cell).chain(touching)
+        .fold((0.0, 0), |(sum, count), id| (sum + heightmap[id], count + 1));
+    sum / count as f64
+}
+
+// A few crossing waves, each half the size of the one before
+struct Relief {
+    // Wave numbers along x and y, phase and amplitude
+    waves: Vec<(f64, f64, f64, f64)>,
+}
+
+impl Relief {
+    fn roll(rng: &mut impl Rng, radius: f64, height: f64) -> Self {
+        let mut waves = vec![];
+        let mut wavelength = radius.max(f64::EPSILON);
+        let mut amplitude = height / 2.0;
+        for _ in 0..OCTAVES {
+            let direction = rng.gen_range(0.0..TAU);
+            let number = TAU / wavelength;
+            waves.push((number * direction.cos(), number * direction.sin(), rng.gen_range(0.0..TAU), amplitude));
+            wavelength /= 2.0;
+            amplitude /= 2.0;
+        }
+        Self { waves }
+    }
+
+    fn at(&self, dx: f64, dy: f64) -> f64 {
+        self.waves.iter().map(|&(kx, ky, phase, amplitude)| amplitude * (kx * dx + ky * dy + phase).sin()).sum()
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+
+use polymap::CellId;
+
+use crate::tags::TagValue;
+
+// Never reused within a world, even after the feature is removed
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct FeatureId(usize);
+
+impl FeatureId {
+    pub fn idx(&self) -> usize { self.0 }
+    pub fn from_idx(idx: usize) -> Self { Self(idx) }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FeatureKind {
+    River,
+    Lake,
+    Range,
+    Region,
+    Settlement,
+    Pass,
+    Poi,
+    Island,
+    // A group of islands close together, holding all of their cells
+    Archipelago,
+    // A stretch of land with the same fauna, tagged with it
+    Habitat,
+    // The corridor of migrating herds, from their winter range to their summer range
+    Migration,
+}
+
+impl FeatureKind {
+    pub const ALL: [FeatureKind; 11] = [
+        FeatureKind::River, FeatureKind::Lake, FeatureKind::Range, FeatureKind::Region,
+        FeatureKind::Settlement, FeatureKind::Pass, FeatureKind::Poi, FeatureKind::Island,
+        FeatureKind::Archipelago, FeatureKind::Habitat, FeatureKind::Migration,
+    ];
+
+    // Registered by the generator from the terrain, rather than placed by hand
+    pub fn is_generated(&self) -> bool {
+        matches!(self, FeatureKind::River | FeatureKind::Pass | FeatureKind::Island | FeatureKind::Archipelago
+            | FeatureKind::Habitat | FeatureKind::Migration)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FeatureKind::River => "River",
+            FeatureKind::Lake => "Lake",
+            FeatureKind::Range => "Range",
+            FeatureKind::Region => "Region",
+            FeatureKind::Settlement => "Settlement",
+            FeatureKind::Pass => "Pass",
+            FeatureKind::Poi => "Point of interest",
+            FeatureKind::Island => "Island",
+            FeatureKind::Archipelago => "Archipelago",
+            FeatureKind::Habitat => "Habitat",
+            FeatureKind::Migration => "Migration",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Geometry {
+    Point(CellId),
+    // An ordered sequence of adjacent cells, like a river from source to mouth
+    Path(Vec<CellId>),
+    // An unordered set of cells, like a lake or a region
+    Area(Vec<CellId>),
+}
+
+impl Geometry {
+    pub fn cells(&self) -> &[CellId] {
+        match self {
+            Geometry::Point(cell) => std::slice::from_ref(cell),
+            Geometry::Path(cells) | Geometry::Area(cells) => cells,
+        }
+    }
+
+    pub fn contains(&self, cell: CellId) -> bool {
+        self.cells().contains(&cell)
+    }
+
+    // The cell that identifies a generated feature across regenerations: the last cell of a
+    // river before its mouth, rather than its source which moves with the drainage threshold.
+    // Not the mouth itself, as tributaries end on the river they join, several of them on the
+    // same cell at times, while the cells before are on one river only.
+    pub fn key_cell(&self) -> Option<CellId> {
+        match self {
+            Geometry::Point(cell) => Some(*cell),
+            Geometry::Path(cells) => cells.len().checked_sub(2).and_then(|idx| cells.get(idx)).or(cells.last()).copied(),
+            Geometry::Area(cells) => cells.first().copied(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Feature {
+    pub id: FeatureId,
+    pub kind: FeatureKind,
+    pub name: Option<String>,
+    pub geometry: Geometry,
+    pub tags: BTreeMap<String, TagValue>,
+}
+
+// The one registry of the named geographic entities of a world
+#[derive(Clone, Default)]
+pub struct Features {
+    features: BTreeMap<FeatureId, Feature>,
+    next_id: usize,
+}
+
+impl Features {
+    pub fn add(&mut self, kind: FeatureKind, geometry: Geometry) -> FeatureId {
+        let id = FeatureId(self.next_id);
+        self.next_id += 1;
+        self.features.insert(id, Feature {
+            id,
+            kind,
+            name: None,
+            geometry,
+            tags: BTreeMap::new(),
+        });
+        id
+    }
+
+    pub fn get(&self, id: FeatureId) -> Option<&Feature> {
+        self.features.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: FeatureId) -> Option<&mut Feature> {
+        self.features.get_mut(&id)
+    }
+
+    pub fn remove(&mut self, id: FeatureId) -> Option<Feature> {
+        self.features.remove(&id)
+    }
+
+    pub fn len(&self) -> usize { self.features.len() }
+    pub fn is_empty(&self) -> bool { self.features.is_empty() }
+
+    // In order of creation
+    pub fn iter(&self) -> impl Iterator<Item = &Feature> {
+        self.features.values()
+    }
+
+    pub fn of_kind(&self, kind: FeatureKind) -> impl Iterator<Item = &Feature> {
+        self.iter().filter(move |feature| feature.kind == kind)
+    }
+
+    pub fn at_cell(&self, cell: CellId) -> impl Iterator<Item = &Feature> {
+        self.iter().filter(move |feature| feature.geometry.contains(cell))
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&Feature> {
+        self.iter().find(|feature| feature.name.as_deref() == Some(name))
+    }
+
+    // The archipelago an island belongs to. The hierarchy follows from the cells rather than
+    // the ids, so that it holds across regenerations and imports.
+    pub fn archipelago_of(&self, island: FeatureId) -> Option<&Feature> {
+        let cell = self.get(island).filter(|feature| feature.kind == FeatureKind::Island)?.geometry.key_cell()?;
+        self.of_kind(FeatureKind::Archipelago).find(|archipelago| archipelago.geometry.contains(cell))
+    }
+
+    pub fn islands_of(&self, archipelago: FeatureId) -> impl Iterator<Item = &Feature> {
+        let geometry = self.get(archipelago)
+            .filter(|feature| feature.kind == FeatureKind::Archipelago)
+            .map(|feature| &feature.geometry);
+        self.of_kind(FeatureKind::Island).filter(move |island| {
+            matches!((geometry, island.geometry.key_cell()), (Some(geometry), Some(cell)) if geometry.contains(cell))
+        })
+    }
+
+    pub fn set_name(&mut self, id: FeatureId, name: impl Into<String>) {
+        if let Some(feature) = self.features.get_mut(&id) {
+            feature.name = Some(name.into());
+        }
+    }
+
+    pub fn set_tag(&mut self, id: FeatureId, key: &str, value: impl Into<TagValue>) {
+        if let Some(feature) = self.features.get_mut(&id) {
+            feature.tags.insert(key.to_owned(), value.into());
+        }
+    }
+
+    pub fn remove_tag(&mut self, id: FeatureId, key: &str) -> Option<TagValue> {
+        self.features.get_mut(&id).and_then(|feature| feature.tags.remove(key))
+    }
+
+    // Registers the features of a generated kind again. Those found at the same key cell as
+    // before keep their id, name and tags, so that curated names survive regenerating.
+    pub(crate) fn register_generated(&mut self, kind: FeatureKind, geometries: Vec<Geometry>) -> Vec<FeatureId> {
+        let mut previous: HashMap<CellId, Feature> = HashMap::new();
+        let ids: Vec<_> = self.of_kind(kind).map(|feature| feature.id).collect();
+        for id in ids {
+            if let Some(feature) = self.features.remove(&id) {
+                if let Some(cell) = feature.geometry.key_cell() {
+                    previous.entry(cell).or_insert(feature);
+                }
+            }
+        }
+
+        geometries.into_iter()
+            .map(|geometry| match geometry.key_cell().and_then(|cell| previous.remove(&cell)) {
+                Some(mut feature) => {
+                    feature.geometry = geometry;
+                    let id = feature.id;
+                    self.features.insert(id, feature);
+                    id
+                }
+                None => self.add(kind, geometry),
+            })
+            .collect()
+    }
+
+    // Puts back a feature with its own id, as read from an export
+    pub(crate) fn insert(&mut self, feature: Feature) {
+        self.next_id = self.next_id.max(feature.id.idx() + 1);
+        self.features.insert(feature.id, feature);
+    }
+}
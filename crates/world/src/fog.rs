@@ -0,0 +1,45 @@
+use polymap::*;
+use polymap::field::Field;
+
+use crate::relief::Landform;
+
+// How many rings around a cell make up the surroundings cold air drains from
+const POOL_RINGS: usize = 3;
+// A cell this far below its surroundings, in height units, is a full pool of cold air
+const POOL_DEPTH: f64 = 0.03;
+// Fog this thick or more counts as misty
+pub const MISTY: f64 = 0.4;
+
+// How thick the fog or low clouds over every cell are, in [0, 1]. Fog needs moist air, and
+// settles where the cold air pools at night: in the hollows and valleys below the surrounding
+// terrain, where the air is colder than above it. Cold seas are covered too.
+pub fn fog(poly: &PolyMap, heightmap: &Field<f64>, temperature: &Field<f64>, humidity: &Field<f64>,
+           landform: &Field<Landform>, sea_level: f64) -> Field<f64> {
+    let mut surroundings = heightmap.clone();
+    surroundings.smooth(poly, POOL_RINGS);
+
+    Field::with_fn(poly, |id, _| {
+        let coldness = (1.0 - temperature[id]).clamp(0.0, 1.0);
+        if heightmap[id] < sea_level {
+            return 0.5 * coldness * coldness;
+        }
+        let humidity = humidity[id];
+        let mut pooling = ((surroundings[id] - heightmap[id]) / POOL_DEPTH).clamp(0.0, 1.0);
+        if landform[id] == Landform::Valley {
+            pooling = pooling.max(0.5);
+        }
+        (humidity * (0.2 + 0.8 * pooling) * (0.5 + 0.5 * coldness)).clamp(0.0, 1.0)
+    })
+}
+
+// A word on the weather of a cell, for descriptions of the map
+pub fn flavor(fog: f64, landform: Landform) -> Option<&'static str> {
+    if fog < MISTY {
+        return None;
+    }
+    Some(match landform {
+        Landform::Valley => "Misty valley",
+        Landform::Ridge => "Cloud-capped ridge",
+        Landform::Flat | Landform::Slope => "Misty",
+    })
+}
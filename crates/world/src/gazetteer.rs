@@ -0,0 +1,115 @@
+use std::fmt::Write;
+
+use polymap::{CellId, PolyMap};
+
+use crate::features::{Feature, FeatureKind, Geometry};
+use crate::tags::TagValue;
+use crate::World;
+
+// One named place of the world, for reference from outside tools
+pub struct Entry {
+    pub name: String,
+    pub kind: FeatureKind,
+    pub x: f64,
+    pub y: f64,
+    pub region: Option<String>,
+}
+
+// All the named features, in order of creation
+pub fn entries(world: &World) -> Vec<Entry> {
+    world.features().iter()
+        .filter_map(|feature| {
+            let name = feature.name.clone()?;
+            let anchor = anchor(world.poly(), feature)?;
+            let (x, y) = world.poly()[anchor].center();
+            Some(Entry { name, kind: feature.kind, x, y, region: region_of(world, feature, anchor) })
+        })
+        .collect()
+}
+
+pub fn to_csv(entries: &[Entry]) -> String {
+    let mut csv = String::from("name,type,x,y,region\n");
+    for entry in entries {
+        let _ = writeln!(csv, "{},{},{:.1},{:.1},{}",
+            csv_field(&entry.name), csv_field(entry.kind.name()), entry.x, entry.y,
+            csv_field(entry.region.as_deref().unwrap_or("")));
+    }
+    csv
+}
+
+pub fn to_json(entries: &[Entry]) -> String {
+    let mut json = String::from("[\n");
+    for (idx, entry) in entries.iter().enumerate() {
+        let separator = if idx + 1 < entries.len() { "," } else { "" };
+        let region = match entry.region.as_deref() {
+            Some(region) => json_string(region),
+            None => "null".to_owned(),
+        };
+        let _ = writeln!(json, "  {{\"name\": {}, \"type\": {}, \"x\": {:.1}, \"y\": {:.1}, \"region\": {}}}{}",
+            json_string(&entry.name), json_string(entry.kind.name()), entry.x, entry.y, region, separator);
+    }
+    json.push(']');
+    json
+}
+
+// The cell that stands for the feature on a map: the middle of a path, or the cell of an
+// area closest to its centroid
+pub fn anchor(poly: &PolyMap, feature: &Feature) -> Option<CellId> {
+    match &feature.geometry {
+        Geometry::Point(cell) => Some(*cell),
+        Geometry::Path(cells) => cells.get(cells.len() / 2).copied(),
+        Geometry::Area(cells) => {
+            let count = cells.len() as f64;
+            let (sx, sy) = cells.iter().fold((0.0, 0.0), |(sx, sy), &id| {
+                let (x, y) = poly[id].center();
+                (sx + x, sy + y)
+            });
+            let (cx, cy) = (sx / count, sy / count);
+            cells.iter().copied().min_by(|&a, &b| {
+                let (ax, ay) = poly[a].center();
+                let (bx, by) = poly[b].center();
+                let da = (ax - cx).powi(2) + (ay - cy).powi(2);
+                let db = (bx - cx).powi(2) + (by - cy).powi(2);
+                da.total_cmp(&db)
+            })
+        }
+    }
+}
+
+fn region_of(world: &World, feature: &Feature, anchor: CellId) -> Option<String> {
+    if feature.kind == FeatureKind::Region {
+        return None;
+    }
+    match world.tag(anchor, "region") {
+        Some(TagValue::Text(region)) => Some(region.clone()),
+        _ => world.features().of_kind(FeatureKind::Region)
+            .find(|region| region.geometry.contains(anchor))
+            .and_then(|region| region.name.clone()),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+pub(crate) fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,112 @@
+use crate::quality::StageQualities;
+use crate::seed::SeedTree;
+use crate::World;
+
+// The stages of a generation from the seeds, in the order they run
+pub const STAGES: [&str; 7] = ["heightmap", "terrain", "temperature", "rainfall", "rivers", "biomes", "cultures"];
+
+// A generation from the seeds, run one stage at a time, so that a caller can show the world
+// taking shape and keep responding in between. The world is only whole once every stage ran;
+// until then the later fields are those of the previous world.
+#[derive(Clone, Copy, Debug)]
+pub struct Generation {
+    seeds: SeedTree,
+    // The seeds of the high noise, those of a variant or the seeds themselves
+    detail: SeedTree,
+    // The stage it started at, after those of the last generation it builds on
+    first: usize,
+    next: usize,
+    // Those the stages ran at where the world was generated first, in place of the budget
+    qualities: Option<StageQualities>,
+}
+
+impl Generation {
+    pub fn new(seeds: SeedTree) -> Self {
+        Self::with_detail(seeds, seeds)
+    }
+
+    // Like World::generate_variant
+    pub fn variant(seeds: SeedTree, variant: u64) -> Self {
+        Self::with_detail(seeds, seeds.child("variant").indexed(variant))
+    }
+
+    pub(crate) fn with_detail(seeds: SeedTree, detail: SeedTree) -> Self {
+        Self { seeds, detail, first: 0, next: 0, qualities: None }
+    }
+
+    // Runs each stage at the quality it ran at before, for the same world as then
+    pub fn with_qualities(mut self, qualities: StageQualities) -> Self {
+        self.qualities = Some(qualities);
+        self
+    }
+
+    // Runs only the named stage and those after it, over what the earlier stages of the last
+    // generation of the world left, for changes which leave those as they were
+    pub fn starting_at(mut self, stage: &str) -> Self {
+        if let Some(idx) = STAGES.iter().position(|&name| name == stage) {
+            self.first = idx;
+            self.next = idx;
+        }
+        self
+    }
+
+    // Goes back to the named stage, if it already ran, so that it runs again with the ones after
+    pub fn rewind(&mut self, stage: &str) {
+        if let Some(idx) = STAGES.iter().position(|&name| name == stage) {
+            self.first = self.first.min(idx);
+            self.next = self.next.min(idx);
+        }
+    }
+
+    // The share of the stages to run that ran, between 0 and 1
+    pub fn progress(&self) -> f32 { (self.next - self.first) as f32 / (STAGES.len() - self.first) as f32 }
+
+    // The stage it started at, or went back to
+    pub fn first_stage(&self) -> &'static str { STAGES[self.first] }
+
+    // The stage the next step runs, None once done
+    pub fn next_stage(&self) -> Option<&'static str> { STAGES.get(self.next).copied() }
+
+    pub fn is_done(&self) -> bool { self.next >= STAGES.len() }
+
+    // Runs the next stage on the world. Returns its name and the progress after it, None once
+    // every stage ran.
+    pub fn step(&mut self, world: &mut World) -> Option<(&'static str, f32)> {
+        let name = self.next_stage()?;
+        let (seeds, detail) = (self.seeds, self.detail);
+        if self.next == self.first {
+            world.start_run();
+        }
+        let quality = self.qualities.map(|qualities| qualities.0[self.next]);
+        match self.next {
+            0 => world.stage_at(name, quality, |world| world.generate_heightmap(&seeds.child("heightmap"), &detail.child("heightmap"))),
+            1 => world.stage_at(name, quality, |world| {
+                world.assign_terrain_types();
+                world.analyze_relief();
+            }),
+            2 => world.stage_at(name, quality, |world| world.generate_temperature()),
+            3 => world.stage_at(name, quality, |world| world.generate_rainfall(&seeds)),
+            4 => world.stage_at(name, quality, |world| world.generate_rivers()),
+            5 => world.stage_at(name, quality, |world| world.generate_biomes()),
+            _ => world.stage_at(name, quality, |world| world.generate_cultures(&seeds.child("cultures"))),
+        }
+        self.next += 1;
+        Some((name, self.progress()))
+    }
+
+    // Runs the stages left
+    pub fn finish(&mut self, world: &mut World) {
+        while self.step(world).is_some() {}
+    }
+}
+
+impl World<'_> {
+    // Like generate, calling back after each stage with its name and the progress so far
+    pub fn generate_with_progress(&mut self, seeds: &SeedTree, progress: &mut impl FnMut(&'static str, f32)) {
+        let _span = tracing::info_span!("generate", seed = seeds.seed()).entered();
+        let mut generation = Generation::new(*seeds);
+        while let Some((stage, done)) = generation.step(self) {
+            progress(stage, done);
+        }
+    }
+}
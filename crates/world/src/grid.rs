@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use polymap::CellId;
+
+use crate::marine::MarineBiome;
+use crate::World;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GridShape {
+    Square,
+    // Pointy-topped hexes, in rows where the odd ones are shifted right by half a hex
+    Hex,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GridSpec {
+    pub shape: GridShape,
+    // Side of the squares, or distance between opposite sides of the hexes, in map units
+    pub size: f64,
+}
+
+// The cells of the world whose centers fall in one tile of the grid, summarized
+#[derive(Clone, Debug)]
+pub struct GridBin {
+    pub col: i64,
+    pub row: i64,
+    pub center: (f64, f64),
+    pub cells: usize,
+    pub mean_height: f64,
+    pub sea_fraction: f64,
+    pub river: bool,
+    // Mean traversal difficulty of the land cells, 0 for all-sea bins
+    pub difficulty: f64,
+    // The most common land cover, or marine biome for mostly sea bins
+    pub cover: &'static str,
+}
+
+impl GridSpec {
+    pub fn square(size: f64) -> Self {
+        Self { shape: GridShape::Square, size }
+    }
+
+    pub fn hex(size: f64) -> Self {
+        Self { shape: GridShape::Hex, size }
+    }
+
+    // The (col, row) of the tile containing the point
+    pub fn tile_at(&self, x: f64, y: f64) -> (i64, i64) {
+        match self.shape {
+            GridShape::Square => ((x / self.size).floor() as i64, (y / self.size).floor() as i64),
+            GridShape::Hex => {
+                let radius = self.size / 3f64.sqrt();
+                let q = (3f64.sqrt() / 3.0 * x - y / 3.0) / radius;
+                let r = (2.0 / 3.0 * y) / radius;
+                let (q, r) = round_axial(q, r);
+                (q + (r - (r & 1)) / 2, r)
+            }
+        }
+    }
+
+    pub fn tile_center(&self, col: i64, row: i64) -> (f64, f64) {
+        match self.shape {
+            GridShape::Square => ((col as f64 + 0.5) * self.size, (row as f64 + 0.5) * self.size),
+            GridShape::Hex => {
+                let radius = self.size / 3f64.sqrt();
+                let shift = if row & 1 == 1 { 0.5 } else { 0.0 };
+                ((col as f64 + shift) * self.size, row as f64 * radius * 1.5)
+            }
+        }
+    }
+
+    // Bins are sorted by row, then column
+    pub fn bin(&self, world: &World) -> Vec<GridBin> {
+        let mut tiles: BTreeMap<(i64, i64), Vec<CellId>> = BTreeMap::new();
+        for (id, cell) in world.poly().cells() {
+            let (x, y) = cell.center();
+            let (col, row) = self.tile_at(x, y);
+            tiles.entry((row, col)).or_default().push(id);
+        }
+
+        tiles.into_iter().map(|((row, col), cells)| {
+            let count = cells.len() as f64;
+            let mean_height = cells.iter().map(|&id| world.heightmap()[id]).sum::<f64>() / count;
+            let sea = cells.iter().filter(|&&id| world.marine()[id] != MarineBiome::None).count();
+            let sea_fraction = sea as f64 / count;
+
+            let mut covers: HashMap<&'static str, usize> = HashMap::new();
+            for &id in cells.iter() {
+                let is_sea = world.marine()[id] != MarineBiome::None;
+                if is_sea == (sea_fraction > 0.5) {
+                    *covers.entry(world.cover(id)).or_default() += 1;
+                }
+            }
+            let cover = covers.into_iter()
+                .max_by(|(name1, n1), (name2, n2)| n1.cmp(n2).then(name2.cmp(name1)))
+                .map(|(name, _)| name)
+                .unwrap_or("None");
+
+            let land: Vec<_> = cells.iter().map(|&id| world.difficulty()[id]).filter(|x| x.is_finite()).collect();
+            let difficulty = if land.is_empty() { 0.0 } else { land.iter().sum::<f64>() / land.len() as f64 };
+
+            GridBin {
+                col,
+                row,
+                center: self.tile_center(col, row),
+                cells: cells.len(),
+                mean_height,
+                sea_fraction,
+                river: cells.iter().any(|&id| world.is_river(id)),
+                difficulty,
+                cover,
+            }
+        }).collect()
+    }
+}
+
+pub fn to_csv(bins: &[GridBin]) -> String {
+    let mut csv = String::from("col,row,x,y,cells,mean_height,sea_fraction,river,difficulty,cover\n");
+    for bin in bins {
+        let _ = writeln!(csv, "{},{},{:.1},{:.1},{},{:.4},{:.4},{},{:.2},{}",
+            bin.col, bin.row, bin.center.0, bin.center.1, bin.cells,
+            bin.mean_height, bin.sea_fraction, bin.river, bin.difficulty, bin.cover);
+    }
+    csv
+}
+
+pub fn to_json(bins: &[GridBin]) -> String {
+    let mut json = String::from("[\n");
+    for (idx, bin) in bins.iter().enumerate() {
+        let separator = if idx + 1 < bins.len() { "," } else { "" };
+        let _ = writeln!(json,
+            "  {{\"col\": {}, \"row\": {}, \"x\": {:.1}, \"y\": {:.1}, \"cells\": {}, \"mean_height\": {:.4}, \"sea_fraction\": {:.4}, \"river\": {}, \"difficulty\": {:.2}, \"cover\": \"{}\"}}{}",
+            bin.col, bin.row, bin.center.0, bin.center.1, bin.cells,
+            bin.mean_height, bin.sea_fraction, bin.river, bin.difficulty, bin.cover, separator);
+    }
+    json.push(']');
+    json
+}
+
+// Rounds fractional axial coordinates to the hex that contains them
+fn round_axial(q: f64, r: f64) -> (i64, i64) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i64, rr as i64)
+}
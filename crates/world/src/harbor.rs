@@ -0,0 +1,79 @@
+use std::collections::{HashSet, VecDeque};
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::{CellVector, TerrainCategory, World};
+
+// How far around a cell the shelter is judged, in rings of cells
+const SHELTER_RINGS: usize = 3;
+// The share of land around a cell on a straight coast, and around one deep in a bay
+const OPEN_COAST: f64 = 0.45;
+const CLOSED_BAY: f64 = 0.8;
+// Water this deep next to the shore, as a fraction of the sea level, takes any ship
+const DEEP_WATER: f64 = 0.3;
+// A river mouth this many rings away is half as good as one at the harbor
+const MOUTH_REACH: f64 = 2.0;
+
+// Harbors at least this good are counted in the report
+pub const GOOD: f64 = 0.6;
+
+// The cells within the rings around a cell, itself included
+fn neighborhood(poly: &PolyMap, center: CellId, rings: usize) -> Vec<CellId> {
+    let mut seen = HashSet::from([center]);
+    let mut queue = VecDeque::from([(center, 0)]);
+    let mut cells = vec![];
+    while let Some((id, ring)) = queue.pop_front() {
+        cells.push(id);
+        if ring == rings {
+            continue;
+        }
+        for &neighbor in poly[id].neighbors() {
+            if seen.insert(neighbor) {
+                queue.push_back((neighbor, ring + 1));
+            }
+        }
+    }
+    cells
+}
+
+// How good a harbor each land cell on the sea would make, between 0 and 1, and 0 for the
+// others: best in a bay, sheltered by the land around it on most sides, with deep water off
+// the shore and a river mouth nearby to bring the trade down from inland
+pub fn suitability(world: &World) -> Field<f64> {
+    let poly = world.poly();
+    let sea_level = world.sea_level();
+    let is_sea = |id: CellId| world.terrain_category()[id] == TerrainCategory::Sea;
+
+    let mouths = world.rivers().iter()
+        .filter_map(|river| river.cells().last().copied())
+        .filter(|&mouth| match world.downhill()[mouth] {
+            CellVector::Towards(outlet, _) => is_sea(outlet),
+            CellVector::Stationary => false,
+        });
+    let mouth_distance = poly.ring_distances(mouths);
+
+    Field::with_fn(poly, |id, cell| {
+        if is_sea(id) || world.is_lake(id) {
+            return 0.0;
+        }
+        let deepest = cell.neighbors().iter()
+            .filter(|&&neighbor| is_sea(neighbor))
+            .map(|&neighbor| sea_level - world.heightmap()[neighbor])
+            .reduce(f64::max);
+        let deepest = match deepest {
+            Some(depth) => depth,
+            None => return 0.0,
+        };
+
+        let around = neighborhood(poly, id, SHELTER_RINGS);
+        let land = around.iter().filter(|&&other| !is_sea(other)).count() as f64 / around.len() as f64;
+        let shelter = ((land - OPEN_COAST) / (CLOSED_BAY - OPEN_COAST)).clamp(0.0, 1.0);
+        let depth = (deepest / (DEEP_WATER * sea_level)).clamp(0.0, 1.0);
+        let river = match mouth_distance[id] {
+            usize::MAX => 0.0,
+            rings => 0.5f64.powf(rings as f64 / MOUTH_REACH),
+        };
+        0.5 * shelter + 0.25 * depth + 0.25 * river
+    })
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::relief::Landform;
+use crate::{measure, TerrainCategory, World};
+
+// A risk this high or more flags the cell
+const FLAGGED: f64 = 0.4;
+// Slopes this steep or more, in the units of relief::slope, are as steep as it gets
+const STEEP: f64 = 4.0;
+// How far from a river its floods reach, in rings of cells, and how high above it, in m
+const FLOOD_RINGS: usize = 2;
+const FLOOD_RISE: f64 = 20.0;
+
+// The natural hazards of a cell, as a set of flags
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct HazardFlags(u8);
+
+impl HazardFlags {
+    pub const NONE: HazardFlags = HazardFlags(0);
+    // Loose soil washed away by the rain
+    pub const EROSION: HazardFlags = HazardFlags(1);
+    // Steep soaked slopes giving way
+    pub const LANDSLIDE: HazardFlags = HazardFlags(2);
+    // Low ground along a river, under water when it rises
+    pub const FLOOD: HazardFlags = HazardFlags(4);
+
+    pub const ALL: [HazardFlags; 3] = [HazardFlags::EROSION, HazardFlags::LANDSLIDE, HazardFlags::FLOOD];
+
+    pub fn contains(&self, other: HazardFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: HazardFlags) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    // Of the single hazards
+    pub fn name(&self) -> &'static str {
+        match *self {
+            HazardFlags::EROSION => "Erosion",
+            HazardFlags::LANDSLIDE => "Landslide",
+            HazardFlags::FLOOD => "Flood",
+            _ => "Hazards",
+        }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        HazardFlags::ALL.into_iter().filter(|&flag| self.contains(flag)).map(|flag| flag.name()).collect()
+    }
+}
+
+// The hazards of the land. Erosion takes loose soil and sand under heavy rain where little grows
+// to hold them, the more the steeper; landslides need steep slopes soaked by rain or wet soil
+// without the roots of trees, and bare rock slides less than soil. Floods reach the flats and
+// valleys along the rivers, and the lower and closer to a large river, the worse.
+pub fn hazards(world: &World) -> Field<HazardFlags> {
+    let flood = flood_risk(world);
+    Field::with_fn(world.poly(), |id, _| {
+        let mut flags = HazardFlags::NONE;
+        if world.terrain_category()[id] == TerrainCategory::Sea || world.is_lake(id) {
+            return flags;
+        }
+        let steepness = (world.slope()[id] / STEEP).min(1.0);
+        let rain = measure::DRAIN.normalize(world.rainfall()[id]).clamp(0.0, 1.0);
+        let vegetation = world.vegetation()[id];
+        let cover = (vegetation.deciduous + vegetation.boreal).min(1.0);
+        let ground = world.ground()[id];
+        let loose = (ground.soil + ground.sand).min(1.0);
+
+        let erosion = rain * (0.3 + 0.7 * steepness) * (1.0 - cover) * loose;
+        let wetness = 0.5 * rain + 0.5 * world.cell_moisture(id);
+        let landslide = steepness * wetness * (1.0 - 0.7 * cover) * (1.0 - 0.5 * ground.rock);
+        if erosion >= FLAGGED {
+            flags.insert(HazardFlags::EROSION);
+        }
+        if landslide >= FLAGGED {
+            flags.insert(HazardFlags::LANDSLIDE);
+        }
+        if flood[id] >= FLAGGED {
+            flags.insert(HazardFlags::FLOOD);
+        }
+        flags
+    })
+}
+
+// Spreads out from the river cells over the flats and valleys close to them and not much higher
+fn flood_risk(world: &World) -> Field<f64> {
+    let poly = world.poly();
+    let mut risk = Field::uniform(poly, 0.0);
+    let floodplain = |id: CellId| {
+        world.terrain_category()[id] != TerrainCategory::Sea
+            && matches!(world.landform()[id], Landform::Flat | Landform::Valley)
+    };
+    for river in world.rivers() {
+        for &source in river.cells() {
+            let strength = measure::DRAIN.normalize(world.drainage()[source]).clamp(0.0, 1.0);
+            let level = world.elevation(source);
+            let mut seen = vec![source];
+            let mut queue = VecDeque::from([(source, 0)]);
+            while let Some((id, ring)) = queue.pop_front() {
+                let rise = (world.elevation(id) - level).max(0.0);
+                let cell_risk = strength * (1.0 - rise / FLOOD_RISE) * (1.0 - ring as f64 / (FLOOD_RINGS + 1) as f64);
+                risk[id] = f64::max(risk[id], cell_risk);
+                if ring == FLOOD_RINGS {
+                    continue;
+                }
+                for &neighbor in poly[id].neighbors() {
+                    if !seen.contains(&neighbor) && floodplain(neighbor) && world.elevation(neighbor) - level < FLOOD_RISE {
+                        seen.push(neighbor);
+                        queue.push_back((neighbor, ring + 1));
+                    }
+                }
+            }
+        }
+    }
+    risk
+}
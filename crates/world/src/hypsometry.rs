@@ -0,0 +1,156 @@
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+// How the raw heights are brought into [0, 1]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Normalization {
+    // Stretch the heights to the full range, leaving the amount of sea to the noise
+    #[default]
+    Stretch,
+    // Put the sea level where the given fraction of the cells is under water
+    OceanFraction(f64),
+}
+
+impl Normalization {
+    // The raw height which apply will bring to the sea level
+    pub(crate) fn raw_sea_level(&self, heightmap: &Field<f64>, sea_level: f64) -> f64 {
+        match *self {
+            Normalization::Stretch => {
+                let (min, max) = heightmap.range();
+                min + sea_level * (max - min)
+            }
+            Normalization::OceanFraction(fraction) => heightmap.quantile(fraction),
+        }
+    }
+
+    pub(crate) fn apply(&self, heightmap: &mut Field<f64>, sea_level: f64) {
+        match *self {
+            Normalization::Stretch => heightmap.normalize(),
+            Normalization::OceanFraction(fraction) => {
+                let pivot = heightmap.quantile(fraction);
+                heightmap.normalize_around(pivot, sea_level);
+            }
+        }
+    }
+}
+
+// The heights where the land becomes highland, which is colder and rainier, and where the peaks
+// rise so high that they stop the clouds
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TerrainLevels {
+    pub highland: f64,
+    pub peak: f64,
+}
+
+impl Default for TerrainLevels {
+    fn default() -> Self {
+        Self { highland: 0.6, peak: 0.95 }
+    }
+}
+
+// Terrain levels picked from the generated heights rather than fixed, as fractions of the map
+// area: the sea covers the lowest `water`, the highlands and the peaks the highest ones. Every
+// noise then gives about as much sea, highland and peaks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AutoLevels {
+    pub water: f64,
+    pub highland: f64,
+    pub peak: f64,
+}
+
+impl Default for AutoLevels {
+    fn default() -> Self {
+        Self { water: 0.55, highland: 0.15, peak: 0.03 }
+    }
+}
+
+impl AutoLevels {
+    pub fn new(water: f64, highland: f64, peak: f64) -> Result<Self, String> {
+        let valid = |fraction: f64| (0.0..=1.0).contains(&fraction);
+        if !valid(water) || !valid(highland) || !valid(peak) {
+            return Err("Terrain level fractions must be between 0 and 1".to_owned());
+        }
+        if water + highland > 1.0 || peak > highland {
+            return Err("The highlands must fit above the sea, and the peaks within the highlands".to_owned());
+        }
+        Ok(Self { water, highland, peak })
+    }
+
+    // The levels are kept above the sea even where the mask or the pins flood the highest cells
+    pub(crate) fn levels(&self, heightmap: &Field<f64>, sea_level: f64) -> TerrainLevels {
+        let highland = heightmap.quantile(1.0 - self.highland).max(sea_level);
+        let peak = heightmap.quantile(1.0 - self.peak).max(highland);
+        TerrainLevels { highland, peak }
+    }
+}
+
+// Target distribution of the land area across equally tall elevation bands, from the coast
+// up to the highest peaks. Real terrain has a lot of lowland and few peaks.
+#[derive(Clone, PartialEq, Debug)]
+pub struct HypsometricCurve {
+    // Fraction of the land area below the top of each band
+    cumulative: Vec<f64>,
+}
+
+impl HypsometricCurve {
+    // The weights don't need to sum to one
+    pub fn from_bands(weights: &[f64]) -> Result<Self, String> {
+        if weights.is_empty() || weights.iter().any(|&weight| weight < 0.0) {
+            return Err("Hypsometric bands must be a non-empty list of non-negative weights".to_owned());
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("Hypsometric bands must not all be zero".to_owned());
+        }
+
+        let mut below = 0.0;
+        let cumulative = weights.iter().map(|weight| {
+            below += weight / total;
+            below
+        }).collect();
+        Ok(Self { cumulative })
+    }
+
+    pub fn earthlike() -> Self {
+        Self::from_bands(&[0.4, 0.25, 0.15, 0.1, 0.06, 0.04]).unwrap()
+    }
+
+    // Height in [0, 1] above the coast of the land at the given area fraction
+    fn height_at(&self, fraction: f64) -> f64 {
+        let bands = self.cumulative.len() as f64;
+        let mut below = 0.0;
+        for (band, &top) in self.cumulative.iter().enumerate() {
+            if fraction <= top {
+                let within = if top > below { (fraction - below) / (top - below) } else { 0.0 };
+                return (band as f64 + within) / bands;
+            }
+            below = top;
+        }
+        1.0
+    }
+
+    // Remaps the land heights by their rank, so that the land area follows the curve. Cells
+    // of equal height stay equal, so no depressions are dug into flat areas.
+    pub(crate) fn apply(&self, heightmap: &mut Field<f64>, sea_level: f64) {
+        let land: Vec<_> = heightmap.ascending_order().into_iter()
+            .filter(|&id| heightmap[id] >= sea_level)
+            .collect();
+        if land.len() < 2 {
+            return;
+        }
+
+        let last = (land.len() - 1) as f64;
+        let mut rank = 0;
+        let mut remapped = Vec::with_capacity(land.len());
+        for (idx, &id) in land.iter().enumerate() {
+            if heightmap[id] != heightmap[land[rank]] {
+                rank = idx;
+            }
+            let height = self.height_at(rank as f64 / last);
+            remapped.push((id, sea_level + height * (1.0 - sea_level)));
+        }
+        for (id, height) in remapped {
+            heightmap[id] = height;
+        }
+    }
+}
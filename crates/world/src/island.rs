@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::{TerrainCategory, World};
+
+// Landmasses with fewer than this share of the cells of the map are islands
+const ISLAND_SHARE: f64 = 0.02;
+// Islands at most this many sea cells apart belong to the same archipelago
+const ARCHIPELAGO_GAP: usize = 6;
+
+// The cells of each connected piece of land, sorted
+pub(crate) fn landmasses(world: &World) -> Vec<Vec<CellId>> {
+    let poly = world.poly();
+    let is_sea = |id: CellId| world.terrain_category()[id] == TerrainCategory::Sea;
+
+    let mut seen = Field::uniform(poly, false);
+    let mut landmasses = vec![];
+    for (start, _) in poly.cells() {
+        if seen[start] || is_sea(start) {
+            continue;
+        }
+        seen[start] = true;
+        let mut landmass = vec![];
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            landmass.push(id);
+            for &neighbor in poly[id].neighbors() {
+                if !seen[neighbor] && !is_sea(neighbor) {
+                    seen[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        landmass.sort();
+        landmasses.push(landmass);
+    }
+    landmasses
+}
+
+pub(crate) fn is_island(world: &World, landmass: &[CellId]) -> bool {
+    (landmass.len() as f64) < ISLAND_SHARE * world.poly().cells().count() as f64
+}
+
+// The cells of each island, sorted, and the indices of the islands making up each
+// archipelago. Islands standing alone belong to none.
+pub(crate) fn find(world: &World) -> (Vec<Vec<CellId>>, Vec<Vec<usize>>) {
+    let poly = world.poly();
+    let is_sea = |id: CellId| world.terrain_category()[id] == TerrainCategory::Sea;
+    let islands: Vec<_> = landmasses(world).into_iter().filter(|landmass| is_island(world, landmass)).collect();
+
+    // Grows every island out over the sea at once. Where the waters of two islands meet, the
+    // sea cells between them are those each crossed to get there.
+    let mut group: Vec<usize> = (0..islands.len()).collect();
+    let mut owner: Field<Option<(usize, usize)>> = Field::uniform(poly, None);
+    let mut queue = VecDeque::new();
+    let meet = |group: &mut Vec<usize>, a: usize, b: usize, gap: usize| {
+        if gap <= ARCHIPELAGO_GAP {
+            let (a, b) = (root(group, a), root(group, b));
+            group[a.max(b)] = a.min(b);
+        }
+    };
+    for (island, cells) in islands.iter().enumerate() {
+        for &id in cells {
+            for &neighbor in poly[id].neighbors().iter().filter(|&&neighbor| is_sea(neighbor)) {
+                match owner[neighbor] {
+                    None => {
+                        owner[neighbor] = Some((island, 1));
+                        queue.push_back(neighbor);
+                    }
+                    Some((other, crossed)) if other != island => meet(&mut group, island, other, crossed),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    while let Some(id) = queue.pop_front() {
+        let (island, crossed) = owner[id].unwrap();
+        for &neighbor in poly[id].neighbors().iter().filter(|&&neighbor| is_sea(neighbor)) {
+            match owner[neighbor] {
+                None => {
+                    owner[neighbor] = Some((island, crossed + 1));
+                    queue.push_back(neighbor);
+                }
+                Some((other, other_crossed)) if other != island => meet(&mut group, island, other, crossed + other_crossed),
+                Some(_) => {}
+            }
+        }
+    }
+
+    let mut archipelagos: Vec<Vec<usize>> = vec![vec![]; islands.len()];
+    for island in 0..islands.len() {
+        let root = root(&mut group, island);
+        archipelagos[root].push(island);
+    }
+    archipelagos.retain(|members| members.len() > 1);
+    (islands, archipelagos)
+}
+
+fn root(group: &mut [usize], mut idx: usize) -> usize {
+    while group[idx] != idx {
+        group[idx] = group[group[idx]];
+        idx = group[idx];
+    }
+    idx
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use polymap::*;
+use polymap::field::Field;
+use polymap::regions;
+
+use crate::CellVector;
+
+// Water deeper than this over the ground, in heightmap units, is a lake. Filling leaves the
+// depressions with a slight tilt towards their outlet, a fraction of this.
+pub const LAKE_DEPTH: f64 = 0.002;
+// Smaller groups of flooded cells are puddles, left out
+pub const LAKE_CELLS: usize = 3;
+
+// The water standing in a closed depression, up to the level where it spills over its rim
+#[derive(Clone, Debug)]
+pub struct Lake {
+    pub cells: Vec<CellId>,
+    // The height of the water, the highest of the filled cells
+    pub surface: f64,
+    // In square map units
+    pub area: f64,
+    // Where the water leaves the lake: a cell of the lake and the cell past the rim it flows
+    // to. Depressions spill over a single cell, unless two parts of the rim are just as high.
+    pub outflows: Vec<(CellId, CellId)>,
+}
+
+// The lakes of a world, and which lake holds each cell
+#[derive(Clone)]
+pub struct Lakes {
+    lake: Field<Option<usize>>,
+    lakes: Vec<Lake>,
+}
+
+impl Lakes {
+    pub fn none(poly: &PolyMap) -> Self {
+        Self { lake: Field::uniform(poly, None), lakes: vec![] }
+    }
+
+    // The connected groups of cells with water deep enough over them, off the sea
+    pub fn detect(poly: &PolyMap, water_depth: &Field<f64>, heightmap: &Field<f64>, downhill: &Field<CellVector<f64>>,
+                  is_sea: impl Fn(CellId) -> bool) -> Self {
+        let labels = regions::label(poly, |id| (water_depth[id] > LAKE_DEPTH && !is_sea(id)).then_some(()));
+        let mut groups: BTreeMap<usize, Vec<CellId>> = BTreeMap::new();
+        for (id, _) in poly.cells() {
+            if let Some(label) = labels[id] {
+                groups.entry(label).or_default().push(id);
+            }
+        }
+
+        let mut lake = Field::uniform(poly, None);
+        let mut lakes = vec![];
+        for cells in groups.into_values().filter(|cells| cells.len() >= LAKE_CELLS) {
+            let idx = lakes.len();
+            for &id in cells.iter() {
+                lake[id] = Some(idx);
+            }
+            let outflows = cells.iter()
+                .filter_map(|&id| match downhill[id] {
+                    CellVector::Towards(target, _) if lake[target] != Some(idx) => Some((id, target)),
+                    _ => None,
+                })
+                .collect();
+            lakes.push(Lake {
+                surface: cells.iter().map(|&id| heightmap[id]).fold(f64::MIN, f64::max),
+                area: cells.iter().map(|&id| poly.cell(id).area()).sum(),
+                outflows,
+                cells,
+            });
+        }
+        Self { lake, lakes }
+    }
+
+    pub fn is_lake(&self, cell: CellId) -> bool { self.lake[cell].is_some() }
+
+    pub fn lake_at(&self, cell: CellId) -> Option<&Lake> {
+        self.lake[cell].map(|idx| &self.lakes[idx])
+    }
+
+    pub fn lakes(&self) -> &[Lake] { &self.lakes }
+}
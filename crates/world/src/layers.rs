@@ -0,0 +1,119 @@
+use std::fmt;
+
+use polymap::CellId;
+
+use crate::{TerrainCategory, World};
+
+// What the values of a layer are
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerKind {
+    Scalar,
+    // Named classes
+    Categorical,
+    Flag,
+    // Across and down, in map units
+    Vector,
+}
+
+// Where the values of a layer are
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Domain {
+    Cell,
+    // The corners of the cells, shared between the cells meeting there; none of the layers
+    // are there yet
+    Vertex,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum LayerValue {
+    Scalar(f64),
+    Category(String),
+    Flag(bool),
+    Vector(f64, f64),
+}
+
+impl fmt::Display for LayerValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerValue::Scalar(value) => write!(f, "{:.3}", value),
+            LayerValue::Category(name) => write!(f, "{}", name),
+            LayerValue::Flag(value) => write!(f, "{}", if *value { "yes" } else { "no" }),
+            LayerValue::Vector(x, y) => write!(f, "({:.3}, {:.3})", x, y),
+        }
+    }
+}
+
+// One of the values the generation makes for every cell, with how to read it, so that the
+// exporters and the views can go through all of them without naming each. New layers of the
+// world are added to LAYERS.
+pub struct Layer {
+    // Lower case with underscores, as in the columns of the SQL export
+    pub name: &'static str,
+    pub kind: LayerKind,
+    pub domain: Domain,
+    pub unit: Option<&'static str>,
+    value: fn(&World, CellId) -> LayerValue,
+}
+
+impl Layer {
+    pub fn value(&self, world: &World, cell: CellId) -> LayerValue {
+        (self.value)(world, cell)
+    }
+}
+
+const fn scalar(name: &'static str, unit: Option<&'static str>, value: fn(&World, CellId) -> LayerValue) -> Layer {
+    Layer { name, kind: LayerKind::Scalar, domain: Domain::Cell, unit, value }
+}
+
+const fn categorical(name: &'static str, value: fn(&World, CellId) -> LayerValue) -> Layer {
+    Layer { name, kind: LayerKind::Categorical, domain: Domain::Cell, unit: None, value }
+}
+
+const fn flag(name: &'static str, value: fn(&World, CellId) -> LayerValue) -> Layer {
+    Layer { name, kind: LayerKind::Flag, domain: Domain::Cell, unit: None, value }
+}
+
+// The first ones in the order of the columns of the SQL export from before the layers
+pub const LAYERS: [Layer; 30] = [
+    scalar("height", None, |world, id| LayerValue::Scalar(world.heightmap()[id])),
+    scalar("temperature", None, |world, id| LayerValue::Scalar(world.temperature()[id])),
+    scalar("celsius", Some("°C"), |world, id| LayerValue::Scalar(world.celsius()[id])),
+    scalar("elevation", Some("m"), |world, id| LayerValue::Scalar(world.elevation(id))),
+    scalar("rainfall", None, |world, id| LayerValue::Scalar(world.rainfall()[id])),
+    scalar("rainfall_mm", Some("mm/yr"), |world, id| LayerValue::Scalar(world.rainfall_mm(id))),
+    scalar("water_balance", Some("mm/yr"), |world, id| LayerValue::Scalar(world.water_balance(id))),
+    scalar("drainage", None, |world, id| LayerValue::Scalar(world.drainage()[id])),
+    categorical("terrain", |world, id| LayerValue::Category(match world.terrain_category()[id] {
+        TerrainCategory::Sea => "sea",
+        TerrainCategory::Coast => "coast",
+        TerrainCategory::Land => "land",
+    }.to_owned())),
+    categorical("cover", |world, id| LayerValue::Category(world.cover(id).to_owned())),
+    flag("is_river", |world, id| LayerValue::Flag(world.is_river(id))),
+    flag("is_lake", |world, id| LayerValue::Flag(world.is_lake(id))),
+    scalar("slope", None, |world, id| LayerValue::Scalar(world.slope()[id])),
+    scalar("aspect", Some("rad"), |world, id| LayerValue::Scalar(world.aspect()[id])),
+    categorical("landform", |world, id| LayerValue::Category(world.landform()[id].name().to_owned())),
+    Layer {
+        name: "wind", kind: LayerKind::Vector, domain: Domain::Cell, unit: None,
+        value: |world, id| LayerValue::Vector(world.wind()[id].x, world.wind()[id].y),
+    },
+    scalar("humidity", None, |world, id| LayerValue::Scalar(world.humidity()[id])),
+    scalar("evapotranspiration", Some("mm/yr"), |world, id| LayerValue::Scalar(world.evapotranspiration()[id])),
+    scalar("water_depth", None, |world, id| LayerValue::Scalar(world.water_depth()[id])),
+    scalar("water_table", Some("m"), |world, id| LayerValue::Scalar(world.water_table()[id])),
+    scalar("water_availability", None, |world, id| LayerValue::Scalar(world.water_availability()[id])),
+    scalar("soil_moisture", None, |world, id| LayerValue::Scalar(world.soil_moisture()[id])),
+    categorical("land_biome", |world, id| LayerValue::Category(world.land_biome()[id].name().to_owned())),
+    categorical("marine_biome", |world, id| LayerValue::Category(world.marine()[id].name().to_owned())),
+    scalar("marine_productivity", None, |world, id| LayerValue::Scalar(world.marine_productivity()[id])),
+    scalar("fog", None, |world, id| LayerValue::Scalar(world.fog()[id])),
+    scalar("difficulty", None, |world, id| LayerValue::Scalar(world.difficulty()[id])),
+    scalar("harbor", None, |world, id| LayerValue::Scalar(world.harbor()[id])),
+    categorical("fauna", |world, id| LayerValue::Category(world.fauna()[id].name().to_owned())),
+    categorical("hazards", |world, id| LayerValue::Category(world.hazards()[id].names().join(", "))),
+];
+
+impl World<'_> {
+    pub fn layers(&self) -> &'static [Layer] { &LAYERS }
+}
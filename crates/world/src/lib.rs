@@ -1,35 +1,190 @@
 pub mod measure;
+pub mod mask;
+pub mod constraint;
+pub mod symmetry;
+pub mod debug;
+pub mod report;
+pub mod stamp;
+pub mod tags;
+pub mod marine;
+pub mod crossing;
+pub mod river;
+pub mod lake;
+pub mod layers;
+pub mod model;
+pub mod seed;
+pub mod hypsometry;
+pub mod quality;
+pub mod relax;
+pub mod terrace;
+pub mod terrain_noise;
+pub mod relief;
+pub mod fog;
+pub mod detail;
+pub mod pass;
+pub mod features;
+pub mod grid;
+pub mod traversal;
+pub mod water;
+pub mod harbor;
+pub mod hazard;
+pub mod visibility;
+pub mod territory;
+pub mod culture;
+pub mod gazetteer;
+pub mod sql;
+pub mod save;
+pub mod upscale;
+pub mod blend;
+pub mod climatology;
+pub mod generation;
+pub mod params;
+pub mod seam;
+pub mod season;
+pub mod summary;
+pub mod wildlife;
+pub mod civilization;
 mod biome;
+mod island;
 
-pub use biome::Ground;
+pub use biome::{Ground, LandBiome};
 use biome::Vegetation;
+use mask::{MaskClass, MaskImage};
+use measure::{RainfallScale, TemperatureScale};
+use constraint::ConstraintField;
+use seam::Seam;
+use season::Orbit;
+use symmetry::Symmetry;
+use debug::{DebugFields, DebugPreset};
+use report::GenerationReport;
+use stamp::{Placement, Stamp};
+use tags::{TagValue, Tags, NOTE};
+use marine::MarineBiome;
+use crossing::Crossing;
+use river::River;
+use lake::{Lake, Lakes};
+use seed::SeedTree;
+use hypsometry::{AutoLevels, HypsometricCurve, Normalization, TerrainLevels};
+use quality::{Quality, QualitySettings, StageQualities};
+use relax::Relaxation;
+use terrace::Terracing;
+use terrain_noise::NoiseLayer;
+use relief::{Curvature, Landform};
+use pass::Pass;
+use hazard::HazardFlags;
+use water::WaterSource;
+use wildlife::Fauna;
+use climatology::WindModel;
+use generation::{Generation, STAGES};
+use features::{FeatureId, FeatureKind, Features, Geometry};
+use territory::{Realm, TerritoryOptions};
+use culture::Culture;
+use civilization::Civilization;
 
-use std::{collections::HashSet};
+use std::{collections::{HashMap, HashSet}, time::Instant};
 
 use polymap::*;
 use polymap::field::*;
+use polymap::regions::RegionGraph;
+use polymap::selection::ElementSet;
 
 mod spatial_function;
 
 use rand::Rng;
-use spatial_function::{PerlinField, SpatialFunction, Slope};
+use serde_derive::{Deserialize, Serialize};
+use spatial_function::{NoiseField, SpatialFunction, Slope};
+// The heights under which the cells are sea, by default and at the extremes
+pub const SEA_LEVEL: f64 = 0.3;
+const SEA_LEVELS: (f64, f64) = (0.05, 0.95);
+// How much more the rain spreads along the wind than across it
+const RAIN_ANISOTROPY: f64 = 0.7;
+// Cells draining more than this are rivers
+pub const RIVER_CUTOFF: f64 = 10.0;
+
+#[derive(Clone)]
 pub struct World<'a> {
     poly: &'a PolyMap,
     heightmap: Field<f64>,
     downhill: Field<CellVector<f64>>,
     height_sorted: Vec<CellId>,
     terrain_category: Field<TerrainCategory>,
+    slope: Field<f64>,
+    aspect: Field<f64>,
+    curvature: Field<Curvature>,
+    landform: Field<Landform>,
+    passes: Vec<Pass>,
     temperature: Field<f64>,
+    celsius: Field<f64>,
 
     wind: Field<Vec2>,
+    wind_model: WindModel,
+    // In degrees, counterclockwise from east, or drawn from the seeds when None
+    wind_direction: Option<f64>,
+    seams: Vec<Seam>,
 
     rainfall: Field<f64>,
+    // Both in mm per year
+    evapotranspiration: Field<f64>,
+    humidity: Field<f64>,
     drainage: Field<f64>,
+    // How deep the water filling the depressions stands over the ground, in heightmap units
+    water_depth: Field<f64>,
+    lakes: Lakes,
     rivers: Vec<Path>,
     is_river: Field<bool>,
+    // The index of the river carrying the water on from each cell
+    river_of: Field<Option<usize>>,
 
     ground: Field<Ground>,
     vegetation: Field<Vegetation>,
+    land_biome: Field<LandBiome>,
+    marine: Field<MarineBiome>,
+    marine_productivity: Field<f64>,
+    fog: Field<f64>,
+    difficulty: Field<f64>,
+    fresh_water: Field<Option<WaterSource>>,
+    water_table: Field<f64>,
+    water_availability: Field<f64>,
+    soil_moisture: Field<f64>,
+    harbor: Field<f64>,
+    hazards: Field<HazardFlags>,
+    fauna: Field<Fauna>,
+    territory: Field<Option<usize>>,
+    cultures: Vec<Culture>,
+    culture: Field<Option<usize>>,
+
+    mask: Field<MaskClass>,
+    constraints: ConstraintField,
+
+    symmetry: Symmetry,
+    mirror: Field<CellId>,
+    normalization: Normalization,
+    hypsometry: Option<HypsometricCurve>,
+    auto_levels: Option<AutoLevels>,
+    levels: TerrainLevels,
+    relaxation: Relaxation,
+    terracing: Terracing,
+    classification_smoothing: usize,
+    river_cutoff: f64,
+    sea_level: f64,
+    // How many rings of land cells around the sea are coast
+    coast_band: usize,
+    temperature_scale: TemperatureScale,
+    orbit: Orbit,
+    rainfall_scale: RainfallScale,
+    quality: QualitySettings,
+    // Lowered from the asked quality by the stages running over their budget
+    current_quality: Quality,
+    stage_qualities: StageQualities,
+    low_noise: NoiseLayer,
+    high_noise: NoiseLayer,
+
+    pins: Field<Option<f64>>,
+    tags: Tags,
+    features: Features,
+
+    debug: DebugFields,
+    report: GenerationReport,
 }
 
 impl <'a> World<'a> {
@@ -40,78 +195,633 @@ impl <'a> World<'a> {
             downhill: Field::uniform(poly, CellVector::Stationary),
             height_sorted: vec![],
             terrain_category: Field::uniform(poly, TerrainCategory::Land),
+            slope: Field::uniform(poly, 0.0),
+            aspect: Field::uniform(poly, 0.0),
+            curvature: Field::uniform(poly, Curvature::default()),
+            landform: Field::uniform(poly, Landform::Flat),
+            passes: vec![],
             temperature: Field::uniform(poly, 0.0),
+            celsius: Field::uniform(poly, 0.0),
             wind: Field::uniform(poly, Vec2::ZERO),
+            wind_model: WindModel::Clouds,
+            wind_direction: None,
+            seams: vec![],
             rainfall: Field::uniform(poly, 0.0),
+            evapotranspiration: Field::uniform(poly, 0.0),
+            humidity: Field::uniform(poly, 0.0),
             drainage: Field::uniform(poly, 0.0),
+            water_depth: Field::uniform(poly, 0.0),
+            lakes: Lakes::none(poly),
             rivers: vec![],
             is_river: Field::uniform(poly, false),
+            river_of: Field::uniform(poly, None),
 
             ground: Field::uniform(poly, Ground::default()),
             vegetation: Field::uniform(poly, Vegetation::default()),
+            land_biome: Field::uniform(poly, LandBiome::None),
+            marine: Field::uniform(poly, MarineBiome::None),
+            marine_productivity: Field::uniform(poly, 0.0),
+            fog: Field::uniform(poly, 0.0),
+            difficulty: Field::uniform(poly, 1.0),
+            fresh_water: Field::uniform(poly, None),
+            water_table: Field::uniform(poly, 0.0),
+            water_availability: Field::uniform(poly, 0.0),
+            soil_moisture: Field::uniform(poly, 0.0),
+            harbor: Field::uniform(poly, 0.0),
+            hazards: Field::uniform(poly, HazardFlags::NONE),
+            fauna: Field::uniform(poly, Fauna::None),
+            territory: Field::uniform(poly, None),
+            cultures: vec![],
+            culture: Field::uniform(poly, None),
+
+            mask: Field::uniform(poly, MaskClass::Free),
+            constraints: ConstraintField::empty(poly),
+
+            symmetry: Symmetry::None,
+            mirror: Symmetry::None.mirror_cells(poly),
+            normalization: Normalization::default(),
+            hypsometry: None,
+            auto_levels: None,
+            levels: TerrainLevels::default(),
+            relaxation: Relaxation::default(),
+            terracing: Terracing::default(),
+            classification_smoothing: 0,
+            river_cutoff: RIVER_CUTOFF,
+            sea_level: SEA_LEVEL,
+            coast_band: 1,
+            temperature_scale: TemperatureScale::default(),
+            orbit: Orbit::default(),
+            rainfall_scale: RainfallScale::default(),
+            quality: QualitySettings::default(),
+            current_quality: Quality::default(),
+            stage_qualities: StageQualities::default(),
+            low_noise: NoiseLayer::LOW,
+            high_noise: NoiseLayer::HIGH,
+
+            pins: Field::uniform(poly, None),
+            tags: Tags::default(),
+            features: Features::default(),
+
+            debug: DebugFields::new(poly),
+            report: GenerationReport::default(),
         }
     }
 
-    pub fn generate(&mut self, rng: &mut impl Rng) {
-        let width = self.poly.width() as f64;
-        let height = self.poly.height() as f64;
+    // The heights and the rain come out symmetric up to the irregular cells. The rivers only
+    // roughly so: the water gathers over cells of other sizes and shapes on either side, and
+    // crosses the drainage threshold in other places.
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self.mirror = symmetry.mirror_cells(self.poly);
+        self
+    }
+
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
 
-        self.generate_heightmap(rng);
+    // Also puts the sea level under the water fraction of the map, overriding the normalization
+    pub fn with_auto_levels(mut self, auto: AutoLevels) -> Self {
+        self.normalization = Normalization::OceanFraction(auto.water);
+        self.auto_levels = Some(auto);
+        self
+    }
 
-        self.assign_terrain_types();
+    pub fn with_relaxation(mut self, relaxation: Relaxation) -> Self {
+        self.relaxation = relaxation;
+        self
+    }
 
-        self.temperature = Field::uniform(&self.poly, 0.0);
-        spatial_function::Band::new(width/2.0, height/2.0, 0.0, height/2.0)
-            .add_to_field(&self.poly, &mut self.temperature);
+    pub fn with_terracing(mut self, terracing: Terracing) -> Self {
+        self.terracing = terracing;
+        self
+    }
 
-        self.temperature.update(|id, temperature| {
-            // If height > 0.6, proportionally scale down the temperature
-            let height = self.heightmap[id];
-            if height >= 0.6 {
-                let penalty = (height - 0.6)/(1.0 - 0.6);
-                *temperature = *temperature * (1.2 - penalty).min(1.0);
-            }
+    // Passes of a majority filter over the terrain categories, the land and marine biomes and the
+    // landforms, cleaning up single cells of a class within another
+    pub fn with_classification_smoothing(mut self, iterations: usize) -> Self {
+        self.classification_smoothing = iterations;
+        self
+    }
+
+    // The latitudes the map spans and the tilt of the seasons
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        self.orbit = orbit;
+        self
+    }
+
+    pub fn with_temperature_scale(mut self, scale: TemperatureScale) -> Self {
+        self.temperature_scale = scale;
+        self
+    }
+
+    pub fn with_wind(mut self, model: WindModel, direction: Option<f64>) -> Self {
+        self.wind_model = model;
+        self.wind_direction = direction;
+        self
+    }
+
+    // Seams exported from the maps around this one. Each meets the opposite side of this map,
+    // whose heights are pulled to those of the seam, and whose border cells take in the water
+    // of its rivers.
+    pub fn with_seams(mut self, seams: Vec<Seam>) -> Self {
+        self.seams = seams;
+        self
+    }
+
+    pub fn with_rainfall_scale(mut self, scale: RainfallScale) -> Self {
+        self.rainfall_scale = scale;
+        self
+    }
+
+    pub fn with_quality(mut self, settings: QualitySettings) -> Self {
+        self.quality = settings;
+        self.current_quality = settings.quality;
+        self
+    }
+
+    pub fn with_river_cutoff(mut self, cutoff: f64) -> Self {
+        self.river_cutoff = cutoff;
+        self
+    }
+
+    // Only the rivers are traced again, from the drainage of the last generation, and what
+    // depends on them. Nothing upstream of the drainage changes with the cutoff.
+    pub fn set_river_cutoff(&mut self, cutoff: f64) {
+        if cutoff == self.river_cutoff {
+            return;
+        }
+        self.river_cutoff = cutoff;
+        self.start_run();
+        self.stage("rivers", |world| {
+            world.trace_rivers();
+            world.difficulty = traversal::difficulty(world);
+            world.find_fresh_water();
+            world.classify_land_biomes();
+            world.harbor = harbor::suitability(world);
+            world.hazards = hazard::hazards(world);
+            world.register_wildlife();
         });
+    }
 
+    pub fn with_sea_level(mut self, sea_level: f64) -> Self {
+        self.sea_level = sea_level.clamp(SEA_LEVELS.0, SEA_LEVELS.1);
+        self
+    }
+
+    pub fn with_coast_band(mut self, rings: usize) -> Self {
+        self.coast_band = rings;
+        self
+    }
+
+    // Fixes the terrain levels, instead of picking them from the heights, and derives the
+    // temperatures and the biomes again. The rain, held back by the old peaks, stays.
+    pub fn set_levels(&mut self, levels: TerrainLevels) {
+        if levels == self.levels && self.auto_levels.is_none() {
+            return;
+        }
+        self.levels = levels;
+        self.auto_levels = None;
+        self.start_run();
+
+        self.stage("temperature", |world| world.generate_temperature());
+
+        self.stage("biomes", |world| world.generate_biomes());
+    }
+
+    pub fn with_noise(mut self, low: NoiseLayer, high: NoiseLayer) -> Self {
+        self.low_noise = low;
+        self.high_noise = high;
+        self
+    }
+
+    pub fn with_hypsometry(mut self, curve: HypsometricCurve) -> Self {
+        self.hypsometry = Some(curve);
+        self
+    }
+
+    pub fn with_mask(mut self, mask: &MaskImage) -> Self {
+        self.mask = mask.to_field(self.poly);
+        self
+    }
+
+    pub fn set_constraints(&mut self, constraints: ConstraintField) {
+        self.constraints = constraints;
+    }
+
+    // Every stage gets its own child of the seed, so the stages don't depend on each other's
+    // consumption of randomness
+    pub fn generate(&mut self, seeds: &SeedTree) {
+        let _span = tracing::info_span!("generate", seed = seeds.seed()).entered();
+        self.generate_with_detail(seeds, seeds);
+    }
+
+    // Another take on the world of the seeds, with its continents where they were: the slope
+    // and the low noise come from the seeds, the high noise from the variant, and the rivers
+    // and biomes follow the new hills. The wind stays that of the seeds.
+    pub fn generate_variant(&mut self, seeds: &SeedTree, variant: u64) {
+        let _span = tracing::info_span!("generate_variant", seed = seeds.seed(), variant).entered();
+        self.generate_with_detail(seeds, &seeds.child("variant").indexed(variant));
+    }
+
+    fn generate_with_detail(&mut self, seeds: &SeedTree, detail: &SeedTree) {
+        Generation::with_detail(*seeds, *detail).finish(self);
+    }
+
+    fn generate_rainfall(&mut self, seeds: &SeedTree) {
         self.rainfall.update(|_, x| *x = 0.00);
-        self.blow_wind(rng);
-        self.rainfall.smooth(&self.poly, 3);
+        self.blow_wind(&mut seeds.child("climate").child("wind").rng());
+        symmetry::symmetrize(&mut self.rainfall, self.poly, &self.mirror);
+        // Along the wind, which draws out the rain shadows, and over the land only, so that
+        // the coasts keep the rain they got
+        let poly = self.poly;
+        let category = &self.terrain_category;
+        let wind = &self.wind;
+        let passes = self.current_quality.passes(3);
+        self.rainfall.gpu_smooth_with(poly, passes, |id, neighbor| {
+            if category[id] == TerrainCategory::Sea || category[neighbor] == TerrainCategory::Sea {
+                return 0.0;
+            }
+            // The wind points up for a positive y, the map down
+            match field::alignment(poly, id, neighbor, (wind[id].x, -wind[id].y)) {
+                Some(alignment) => 1.0 - RAIN_ANISOTROPY + RAIN_ANISOTROPY * alignment,
+                None => 1.0,
+            }
+        });
+    }
 
-        self.generate_rivers();
+    fn start_run(&mut self) {
+        self.report.clear();
+        self.current_quality = self.quality.quality;
+    }
 
-        self.ground.update(|id, ground| {
-            *ground = Ground::new(
-                self.terrain_category[id], 
-                self.rainfall[id], 
-                self.drainage[id], 
-                self.heightmap[id]
-            )
+    // A stage over its budget lowers the quality of the stages after it
+    fn stage(&mut self, name: &'static str, f: impl FnOnce(&mut Self)) {
+        self.stage_at(name, None, f);
+    }
+
+    // Runs the stage at the given quality, as it ran somewhere else, rather than at the one
+    // the budget left. The budget is not checked then.
+    fn stage_at(&mut self, name: &'static str, quality: Option<Quality>, f: impl FnOnce(&mut Self)) {
+        let _span = tracing::info_span!("stage", name).entered();
+        if let Some(quality) = quality {
+            self.current_quality = quality;
+        }
+        if let Some(idx) = STAGES.iter().position(|&stage| stage == name) {
+            self.stage_qualities.0[idx] = self.current_quality;
+        }
+        let start = Instant::now();
+        self.report.begin(name);
+        f(self);
+        let elapsed = start.elapsed();
+        let over_budget = self.quality.stage_budget.filter(|_| quality.is_none()).map(|budget| elapsed > budget);
+        if over_budget == Some(true) {
+            self.report.count("over budget ms", (elapsed.as_secs_f64() * 1000.0) as usize);
+            if let Some(lower) = self.current_quality.lower() {
+                self.current_quality = lower;
+            }
+        }
+        self.report.finish(elapsed);
+    }
+
+    // The least drop of the filled cells at the current quality
+    fn fill_epsilon(&self) -> f64 {
+        self.current_quality.fill_epsilon()
+    }
+
+    fn relaxation(&self) -> Relaxation {
+        Relaxation { iterations: self.current_quality.passes(self.relaxation.iterations), ..self.relaxation }
+    }
+
+    pub fn debug_world(poly: &'a PolyMap, preset: DebugPreset) -> Self {
+        let mut world = Self::new(poly);
+        world.generate_debug(preset);
+        world
+    }
+
+    // Generates an analytic world without any randomness, with no wind and uniform rainfall.
+    pub fn generate_debug(&mut self, preset: DebugPreset) {
+        let _span = tracing::info_span!("generate_debug", preset = preset.name()).entered();
+        self.start_run();
+
+        self.stage("heightmap", |world| {
+            let width = world.poly.width() as f64;
+            let height = world.poly.height() as f64;
+
+            world.heightmap = Field::uniform(world.poly, 0.0);
+            preset.heights(width, height).add_to_field(world.poly, &mut world.heightmap);
+            world.debug = DebugFields::new(world.poly);
+            world.finish_heightmap();
         });
-        self.ground.smooth(&self.poly, 2);
 
-        self.vegetation.update(|id, vegetation| {
-            *vegetation = Vegetation::new(
-                self.terrain_category[id], 
-                self.rainfall[id], 
-                self.temperature[id], 
-                self.heightmap[id]
-            )
+        self.stage("terrain", |world| {
+            world.assign_terrain_types();
+            world.analyze_relief();
         });
+
+        self.stage("temperature", |world| world.generate_temperature());
+
+        self.stage("rainfall", |world| {
+            world.wind = Field::uniform(world.poly, Vec2::ZERO);
+            world.rainfall = Field::uniform(world.poly, debug::UNIFORM_RAINFALL);
+        });
+
+        self.stage("rivers", |world| world.generate_rivers());
+
+        self.stage("biomes", |world| world.generate_biomes());
     }
 
-    fn generate_heightmap(&mut self, rng: &mut impl Rng) {
+    fn generate_heightmap(&mut self, seeds: &SeedTree, detail: &SeedTree) {
+        self.heightmap = self.roll_noise(seeds, detail);
+        self.finish_heightmap();
+    }
+
+    // Sums all the noise contributions into a raw, unnormalized heightmap. The high noise is
+    // rolled from the detail seeds, the rest from the seeds.
+    fn roll_noise(&mut self, seeds: &SeedTree, detail: &SeedTree) -> Field<f64> {
         let width = self.poly.width() as f64;
         let height = self.poly.height() as f64;
 
-        Slope::with_rng(width, height, rng)
+        let symmetry = self.symmetry;
+        let (low, high) = (self.low_noise, self.high_noise);
+
+        // Each contribution is kept separately for debugging
+        let mut slope = Field::uniform(self.poly, 0.0);
+        Slope::with_rng(width, height, &mut seeds.child("slope").rng())
             .scale(0.00025)
-            .add_to_field(self.poly, &mut self.heightmap);
-        PerlinField::with_rng(0.001, rng).scale(1.0).add_to_field(self.poly, &mut self.heightmap);
-        PerlinField::with_rng(0.01, rng).scale(0.2).add_to_field(self.poly, &mut self.heightmap);
-        
-        planchon_darboux(&mut self.heightmap, &self.poly);
-        self.heightmap.normalize();
+            .symmetric(symmetry, width, height)
+            .add_to_field(self.poly, &mut slope);
+        let mut low_noise = Field::uniform(self.poly, 0.0);
+        NoiseField::with_rng(low.kind, low.frequency, &mut seeds.child("low_noise").rng()).scale(low.intensity)
+            .symmetric(symmetry, width, height)
+            .add_to_field(self.poly, &mut low_noise);
+        let mut high_noise = Field::uniform(self.poly, 0.0);
+        NoiseField::with_rng(high.kind, high.frequency, &mut detail.child("high_noise").rng()).scale(high.intensity)
+            .symmetric(symmetry, width, height)
+            .add_to_field(self.poly, &mut high_noise);
+
+        let mut heightmap = Field::with_fn(self.poly, |id, _| slope[id] + low_noise[id] + high_noise[id]);
+        self.debug.slope = slope;
+        self.debug.low_noise = low_noise;
+        self.debug.high_noise = high_noise;
+        symmetry::symmetrize(&mut heightmap, self.poly, &self.mirror);
+        heightmap
+    }
+
+    // Re-rolls the terrain inside the region only. The new terrain fades in over the outermost
+    // rings of the region, so that it blends with the untouched terrain around it.
+    pub fn regenerate_region(&mut self, region: &ElementSet, seeds: &SeedTree) {
+        const BLEND_RINGS: usize = 4;
+
+        let _span = tracing::info_span!("regenerate_region", cells = region.len()).entered();
+        self.start_run();
+
+        let rings = self.poly.ring_distances(region.complement().iter());
+        let weight = Field::with_fn(self.poly, |id, _| {
+            if region.contains(id) {
+                rings[id].min(BLEND_RINGS) as f64 / BLEND_RINGS as f64
+            } else {
+                0.0
+            }
+        });
+
+        self.stage("heightmap", |world| {
+            let heightmap_seeds = seeds.child("heightmap");
+            let mut fresh = world.roll_noise(&heightmap_seeds, &heightmap_seeds);
+            fresh.normalize();
+            world.relaxation().apply(world.poly, &mut fresh, world.sea_level);
+            // The only terracing of the new terrain: the rest of the heightmap was terraced when
+            // it was generated, and this does not go through finish_heightmap
+            world.terracing.apply(&mut fresh, &world.mask, world.sea_level);
+            world.heightmap.update(|id, height| {
+                *height = *height * (1.0 - weight[id]) + fresh[id] * weight[id];
+            });
+            world.constraints.blend_into(&mut world.heightmap);
+            world.apply_mask_to_heightmap();
+            world.apply_pins();
+
+            let before = world.heightmap.clone();
+            let pins = &world.pins;
+            let mask = &world.mask;
+            let epsilon = world.fill_epsilon();
+            let iterations = fill_depressions(&mut world.heightmap, world.poly, epsilon, |id| {
+                !region.contains(id) || pins[id].is_some() || mask[id] == MaskClass::Sea
+            });
+            world.water_depth.update(|id, depth| {
+                if region.contains(id) {
+                    *depth = world.heightmap[id] - before[id];
+                }
+            });
+            world.report.count("regenerated cells", region.len());
+            world.report.count("planchon-darboux iterations", iterations);
+            world.compute_downhill();
+        });
+
+        self.stage("terrain", |world| {
+            world.assign_terrain_types();
+            world.analyze_relief();
+        });
+
+        self.stage("temperature", |world| world.generate_temperature());
+
+        self.stage("rivers", |world| world.generate_rivers());
+
+        self.stage("biomes", |world| world.generate_biomes());
+    }
+
+    // Relaxes and fills the depressions of the raw heightmap, terraces it, applies the user
+    // constraints and derives the downhill directions
+    fn finish_heightmap(&mut self) {
+        let sea_level = self.normalization.raw_sea_level(&self.heightmap, self.sea_level);
+        self.relaxation().apply(self.poly, &mut self.heightmap, sea_level);
+        self.debug.raw_heightmap = self.heightmap.clone();
+        let epsilon = self.fill_epsilon();
+        let iterations = fill_depressions(&mut self.heightmap, self.poly, epsilon, |_| false);
+        self.report.count("cells", self.poly.cells().count());
+        self.report.count("planchon-darboux iterations", iterations);
+        self.debug.filled_depressions = Field::with_fn(self.poly, |id, _| {
+            self.heightmap[id] - self.debug.raw_heightmap[id]
+        });
+        // Over the raw relief, which the normalization stretches to about the unit range
+        let (min, max) = self.debug.raw_heightmap.range();
+        let relief = (max - min).max(f64::EPSILON);
+        self.water_depth = Field::with_fn(self.poly, |id, _| self.debug.filled_depressions[id] / relief);
+        self.normalization.apply(&mut self.heightmap, self.sea_level);
+        if let Some(curve) = self.hypsometry.as_ref() {
+            curve.apply(&mut self.heightmap, self.sea_level);
+        }
+        if self.terracing.is_enabled() {
+            self.terrace();
+        }
+        if self.symmetry != Symmetry::None {
+            self.mirror_heightmap();
+        }
+        self.constraints.blend_into(&mut self.heightmap);
+        self.blend_seams();
+        self.apply_mask_to_heightmap();
+
+        if self.has_pins() {
+            self.apply_pins();
+            self.fill_around_pins();
+        }
+
+        self.compute_downhill();
+    }
+
+    // The flat steps would stop the rivers, so they are given back a slight tilt towards the sea
+    fn terrace(&mut self) {
+        let terracing = self.terracing;
+        let sea_level = self.sea_level;
+        let terraced = self.poly.cells()
+            .filter(|&(id, _)| terracing.covers(self.heightmap[id], self.mask[id], sea_level))
+            .count();
+        terracing.apply(&mut self.heightmap, &self.mask, sea_level);
+        let epsilon = self.fill_epsilon();
+        let iterations = fill_depressions(&mut self.heightmap, self.poly, epsilon, |_| false);
+        self.report.count("terraced cells", terraced);
+        self.report.count("terrace planchon-darboux iterations", iterations);
+    }
+
+    // Filling the depressions and terracing break the symmetry of the noise a little, and
+    // with it that of the rivers, so the heights are mirrored once more after them. The copies
+    // can leave pits along the cells of the mirrored half, which are filled again.
+    fn mirror_heightmap(&mut self) {
+        symmetry::copy_fundamental(&mut self.heightmap, self.poly, self.symmetry, &self.mirror);
+        let epsilon = self.fill_epsilon();
+        let iterations = fill_depressions(&mut self.heightmap, self.poly, epsilon, |_| false);
+        self.report.count("symmetry planchon-darboux iterations", iterations);
+    }
+
+    // Pinned heights survive every following generation and reflow
+    pub fn pin_height(&mut self, cell: CellId, height: f64) {
+        self.pins[cell] = Some(height);
+        self.heightmap[cell] = height;
+    }
+
+    pub fn unpin(&mut self, cell: CellId) {
+        self.pins[cell] = None;
+    }
+
+    pub fn clear_pins(&mut self) {
+        self.pins = Field::uniform(self.poly, None);
+    }
+
+    pub fn is_pinned(&self, cell: CellId) -> bool {
+        self.pins[cell].is_some()
+    }
+
+    fn has_pins(&self) -> bool {
+        self.poly.cells().any(|(id, _)| self.pins[id].is_some())
+    }
+
+    fn apply_pins(&mut self) {
+        self.heightmap.update(|id, height| {
+            if let Some(pinned) = self.pins[id] {
+                *height = pinned
+            }
+        });
+    }
+
+    // Removes the depressions introduced by the edits, keeping the pinned cells (and the sea
+    // painted in the mask) as fixed boundary conditions
+    fn fill_around_pins(&mut self) {
+        let before = self.heightmap.clone();
+        let pins = &self.pins;
+        let mask = &self.mask;
+        let epsilon = self.fill_epsilon();
+        let iterations = fill_depressions(&mut self.heightmap, self.poly, epsilon, |id| {
+            pins[id].is_some() || mask[id] == MaskClass::Sea
+        });
+        // The pinned cells are ground, even where they were under water
+        self.water_depth.update(|id, depth| {
+            *depth = if pins[id].is_some() { 0.0 } else { *depth + self.heightmap[id] - before[id] };
+        });
+        self.report.count("pinned cells", self.poly.cells().filter(|&(id, _)| pins[id].is_some()).count());
+        self.report.count("pinned planchon-darboux iterations", iterations);
+    }
+
+    // Blends the stamp into the heightmap, records its biome overrides in the mask, and
+    // reflows the world around it
+    pub fn apply_stamp(&mut self, stamp: &Stamp, placement: Placement) {
+        let covered: Vec<_> = stamp.covered_cells(self.poly, &placement).collect();
+        for (id, height, alpha, biome) in covered {
+            self.heightmap[id] = self.heightmap[id] * (1.0 - alpha) + height * alpha;
+            if biome != MaskClass::Free && alpha >= 0.5 {
+                self.mask[id] = biome;
+            }
+        }
+        self.reflow();
+    }
+
+    // Forces the selected cells to a terrain class, as if painted in the mask
+    pub fn paint_mask(&mut self, selection: &ElementSet, class: MaskClass) {
+        for id in selection.iter() {
+            self.mask[id] = class;
+        }
+        self.apply_mask_to_heightmap();
+        self.reflow();
+    }
+
+    pub fn raise_height(&mut self, selection: &ElementSet, amount: f64) {
+        for id in selection.iter() {
+            self.heightmap[id] = (self.heightmap[id] + amount).clamp(0.0, 1.0);
+        }
+        self.reflow();
+    }
+
+    pub fn assign_region(&mut self, selection: &ElementSet, region: &str) {
+        for id in selection.iter() {
+            self.tags.set(id, "region", region);
+        }
+
+        // Regions are registered as features too, by name
+        let value = TagValue::from(region);
+        let cells: Vec<_> = self.tags.cells_with("region").into_iter()
+            .filter(|&(_, tag)| *tag == value)
+            .map(|(id, _)| id)
+            .collect();
+        let existing = self.features.of_kind(FeatureKind::Region)
+            .find(|feature| feature.name.as_deref() == Some(region))
+            .map(|feature| feature.id);
+        match existing {
+            Some(id) => {
+                if let Some(feature) = self.features.get_mut(id) {
+                    feature.geometry = Geometry::Area(cells);
+                }
+            }
+            None => {
+                let id = self.features.add(FeatureKind::Region, Geometry::Area(cells));
+                self.features.set_name(id, region);
+            }
+        }
+    }
+
+    // Recomputes everything that depends on the heightmap after it was edited by hand,
+    // without rolling new noise or wind
+    pub fn reflow(&mut self) {
+        let _span = tracing::info_span!("reflow").entered();
+        self.start_run();
+
+        self.stage("heightmap", |world| {
+            world.apply_pins();
+            world.fill_around_pins();
+            world.compute_downhill();
+        });
+
+        self.stage("terrain", |world| {
+            world.assign_terrain_types();
+            world.analyze_relief();
+        });
+
+        self.stage("temperature", |world| world.generate_temperature());
 
+        self.stage("rivers", |world| world.generate_rivers());
+
+        self.stage("biomes", |world| world.generate_biomes());
+    }
+
+    fn compute_downhill(&mut self) {
         self.downhill.update(|id, slope| {
             let my_height = self.heightmap[id];
             // Find the neighbor with minimum height, if any
@@ -128,103 +838,288 @@ impl <'a> World<'a> {
         self.height_sorted = self.heightmap.ascending_order();
     }
 
-    fn assign_terrain_types(&mut self) {
-        self.terrain_category.update(|id, category| {
+    fn generate_temperature(&mut self) {
+        let (poly, orbit) = (self.poly, self.orbit);
+        self.temperature = Field::with_fn(poly, |_, cell| Orbit::warmth(orbit.latitude(poly, cell.center().1)));
+        let scale = self.temperature_scale;
+        self.celsius = Field::with_fn(self.poly, |id, _| scale.celsius(self.temperature[id], self.heightmap[id], self.sea_level));
+
+        self.temperature.update(|id, temperature| {
+            // Above the highland level, proportionally scale down the temperature
             let height = self.heightmap[id];
-            *category = if height < 0.3 {
-                TerrainCategory::Sea
-            } else {
-                TerrainCategory::Land
+            let highland = self.levels.highland;
+            if height >= highland {
+                let penalty = (height - highland)/(1.0 - highland).max(f64::EPSILON);
+                *temperature = *temperature * (1.2 - penalty).min(1.0);
             }
         });
-        
-        {
-            let mut out_to_be_coast = vec![];
-            for (cell_id, cell) in self.poly.cells() {
-                if self.terrain_category[cell_id] == TerrainCategory::Land {
-                    if cell.neighbors().iter().any(|&neighbor| self.terrain_category[neighbor] == TerrainCategory::Sea) {
-                        out_to_be_coast.push(cell_id)
-                    }
-                }
+    }
+
+    fn generate_biomes(&mut self) {
+        self.balance_water();
+        self.find_fresh_water();
+        self.ground.update(|id, ground| {
+            *ground = Ground::new(
+                self.terrain_category[id], 
+                self.humidity[id], 
+                self.drainage[id], 
+                self.heightmap[id]
+            )
+        });
+        self.ground.smooth(&self.poly, self.current_quality.passes(2));
+        self.ground.update(|id, ground| {
+            match self.mask[id] {
+                MaskClass::Desert => *ground = Ground::desert(),
+                MaskClass::Mountain => *ground = Ground::mountain(),
+                _ => {}
             }
-            for cell in out_to_be_coast {
-                self.terrain_category[cell] = TerrainCategory::Coast;
+            if self.lakes.is_lake(id) {
+                *ground = Ground::lake();
             }
+        });
+
+        self.vegetation.update(|id, vegetation| {
+            *vegetation = Vegetation::new(
+                self.terrain_category[id], 
+                self.humidity[id], 
+                self.temperature[id], 
+                self.heightmap[id]
+            );
+        });
+        self.clean_up_forests();
+        self.vegetation.update(|id, vegetation| {
+            match self.mask[id] {
+                MaskClass::Desert => *vegetation = Vegetation::default(),
+                MaskClass::Forest => *vegetation = vegetation.forested(),
+                _ => {}
+            }
+            if self.lakes.is_lake(id) {
+                *vegetation = Vegetation::default();
+            }
+        });
+        self.classify_land_biomes();
+
+        self.marine.update(|id, marine| {
+            let depth = ((self.sea_level - self.heightmap[id]) / self.sea_level).clamp(0.0, 1.0);
+            // Surface currents are driven by the wind blowing over the sea
+            let current = measure::VAPOR.normalize(self.wind[id].length()).min(1.0);
+            *marine = MarineBiome::new(self.terrain_category[id], depth, self.temperature[id], current)
+        });
+        let category = &self.terrain_category;
+        self.marine.majority_filter_where(self.poly, self.current_quality.passes(self.classification_smoothing), |id| category[id] == TerrainCategory::Sea);
+        self.marine_productivity = marine::productivity(self.poly, &self.marine, &self.wind);
+        self.fog = fog::fog(self.poly, &self.heightmap, &self.temperature, &self.humidity, &self.landform, self.sea_level);
+        self.report.count("misty cells", self.poly.cells().filter(|&(id, _)| self.fog[id] >= fog::MISTY).count());
+        self.difficulty = traversal::difficulty(self);
+        self.harbor = harbor::suitability(self);
+        self.report.count("harbor cells", self.poly.cells().filter(|&(id, _)| self.harbor[id] >= harbor::GOOD).count());
+        self.hazards = hazard::hazards(self);
+        let counters = [
+            (HazardFlags::EROSION, "erosion hazard cells"),
+            (HazardFlags::LANDSLIDE, "landslide hazard cells"),
+            (HazardFlags::FLOOD, "flood hazard cells"),
+        ];
+        for (flag, counter) in counters {
+            self.report.count(counter, self.poly.cells().filter(|&(id, _)| self.hazards[id].contains(flag)).count());
         }
+        self.register_wildlife();
     }
 
-    fn blow_wind(&mut self, rng: &mut impl Rng) {
-        
-        let wind_direction = (rng.gen_range(0..=359) as f64).to_radians();
+    // The fauna of the land, with its habitats and the corridors of the migrating herds as
+    // features
+    fn register_wildlife(&mut self) {
+        self.fauna = wildlife::fauna(self);
+        let habitats = wildlife::habitats(self, &self.fauna);
+        let migrations: Vec<_> = habitats.iter()
+            .filter_map(|habitat| Some((habitat.fauna, wildlife::migration(self, habitat)?)))
+            .collect();
+        self.report.count("habitats", habitats.len());
+        self.report.count("migrations", migrations.len());
+        let kinds: Vec<_> = habitats.iter().map(|habitat| habitat.fauna).collect();
+        let ids = self.features.register_generated(FeatureKind::Habitat, habitats.into_iter().map(|habitat| Geometry::Area(habitat.cells)).collect());
+        for (id, fauna) in ids.into_iter().zip(kinds) {
+            self.features.set_tag(id, "fauna", fauna.name());
+        }
+        let kinds: Vec<_> = migrations.iter().map(|&(fauna, _)| fauna).collect();
+        let ids = self.features.register_generated(FeatureKind::Migration, migrations.into_iter().map(|(_, path)| Geometry::Path(path)).collect());
+        for (id, fauna) in ids.into_iter().zip(kinds) {
+            self.features.set_tag(id, "fauna", fauna.name());
+        }
+    }
 
-        // Reset the winds
-        self.wind.update(|_, x| *x = Vec2::ZERO);
-        
-        // For each border tile, we spawn a cloud
-        // TODO: Do not just pick up any border, but just the borders which are opposite to 
-        // the wind-blowing direction
-        for (mut cloud_cell, _) in self.poly().borders() {
-            let mut vapor = 10.0;
-            let mut direction = wind_direction;
-            let mut stop = false;
-            let mut visited = Field::uniform(self.poly(), false);
-            // Randomly walk the cell through the world
-            loop {
-                visited[cloud_cell] = true;
-                // If the cell is over water, pick up vapor, but if it's over land, drop some vapor.
-                // Lose all vapour if over mountain
-                let terrain_category = self.terrain_category[cloud_cell];
-                match terrain_category {
-                    TerrainCategory::Sea => vapor += 0.1,
-                    TerrainCategory::Coast => {},
-                    TerrainCategory::Land => {
-                        let height = self.heightmap[cloud_cell];
-                        let rain_rate = if height < 0.6 {
-                            0.01
-                        } else {
-                            0.02
-                        };
-                        let rain = if height < 0.95 {
-                            vapor * rain_rate
-                        } else {
-                            stop = true;
-                            vapor
-                        };
-                        vapor -= rain;
-                        self.rainfall[cloud_cell] += rain;
-                    }
-                }
-                
-                // Broken by a high peak
-                if stop {
-                    break;
-                }
+    // Where the people of each cell would draw their water from, and how easily, and how wet
+    // that water keeps the soil
+    fn find_fresh_water(&mut self) {
+        self.fresh_water = water::nearest_sources(self);
+        self.water_table = water::water_table(self, &self.fresh_water);
+        self.water_availability = water::availability(self, &self.fresh_water, &self.water_table);
+        self.soil_moisture = water::soil_moisture(self, &self.fresh_water, &self.water_table);
+        let dry = self.poly.cells()
+            .filter(|&(id, _)| self.terrain_category[id] != TerrainCategory::Sea && self.fresh_water[id].is_none())
+            .count();
+        self.report.count("cells without fresh water", dry);
+    }
 
-                // Add a random drift
-                let change_magnitude = 2.5;
-                let direction_change = f64::to_radians(rng.gen_range(-change_magnitude..change_magnitude));
-                direction += direction_change;
-                // Record the path of the cell in the wind table
-                self.wind[cloud_cell] += PolarVec2 { r: vapor, theta: direction}.to_cartesian();
-
-                match self.poly().neighbor_in_direction(cloud_cell, direction, 40.0) {
-                    Some(x) => { 
-                        if visited[x] {
-                            break;
-                        } else {
-                            cloud_cell = x 
-                        }
-                    },
-                    None => break
-                }
+    // The climate zones are derived from the calibrated climate alone, so restoring a world
+    // classifies them again rather than saving them
+    fn classify_land_biomes(&mut self) {
+        self.land_biome.update(|id, biome| {
+            *biome = LandBiome::new(self.terrain_category[id], self.celsius[id], self.soil_moisture[id]);
+            match self.mask[id] {
+                MaskClass::Desert => *biome = biome.dried(),
+                MaskClass::Forest => *biome = biome.forested(),
+                _ => {}
             }
+            if self.lakes.is_lake(id) {
+                *biome = LandBiome::None;
+            }
+        });
+        let category = &self.terrain_category;
+        let lakes = &self.lakes;
+        self.land_biome.majority_filter_where(self.poly, self.current_quality.passes(self.classification_smoothing), |id| {
+            category[id] != TerrainCategory::Sea && !lakes.is_lake(id)
+        });
+        self.report.count("forest biome cells", self.poly.cells().filter(|&(id, _)| self.land_biome[id].is_forest()).count());
+    }
+
+    // How much of the rain could evaporate, and how humid that leaves the climate, for the
+    // covers to tell the arid from the humid
+    fn balance_water(&mut self) {
+        self.evapotranspiration = Field::with_fn(self.poly, |id, _| measure::potential_evapotranspiration(self.celsius[id]));
+        let scale = self.rainfall_scale;
+        self.humidity = Field::with_fn(self.poly, |id, _| {
+            measure::humidity(scale.mm(self.rainfall[id]), self.evapotranspiration[id])
+        });
+        let arid = self.poly.cells()
+            .filter(|&(id, _)| self.heightmap[id] >= self.sea_level && self.water_balance(id) < 0.0)
+            .count();
+        self.report.count("arid cells", arid);
+    }
+
+    // Forests too thin to be woods, and clearings too small to be clearings, are specks of the
+    // climate noise. They take the vegetation around them.
+    fn clean_up_forests(&mut self) {
+        let forest = Field::with_fn(self.poly, |id, _| self.vegetation[id].is_forest());
+        let mut cleaned = forest.clone();
+        cleaned.open(self.poly, 1);
+        cleaned.close(self.poly, 1);
+
+        let specks: Vec<_> = self.poly.cells()
+            .filter(|&(id, _)| forest[id] != cleaned[id] && self.terrain_category[id] != TerrainCategory::Sea)
+            .map(|(id, cell)| (id, Vegetation::mean(cell.neighbors().iter().map(|&n| self.vegetation[n]))))
+            .collect();
+        self.report.count("forest specks", specks.len());
+        for (id, vegetation) in specks {
+            self.vegetation[id] = vegetation;
+        }
+    }
+
+    fn apply_mask_to_heightmap(&mut self) {
+        // Just under and over the sea level, whatever it is
+        let (sea, land) = ((self.sea_level - 0.05).max(0.0), self.sea_level + 0.05);
+        self.heightmap.update(|id, height| {
+            *height = match self.mask[id] {
+                MaskClass::Free => *height,
+                MaskClass::Sea => height.min(sea),
+                MaskClass::Mountain => height.max(0.75),
+                MaskClass::Land | MaskClass::Desert | MaskClass::Forest => height.max(land),
+            }
+        });
+    }
+
+    fn analyze_relief(&mut self) {
+        self.slope = relief::slope(self.poly, &self.heightmap);
+        self.aspect = relief::aspect(self.poly, &self.heightmap);
+        self.curvature = relief::curvature(self.poly, &self.heightmap);
+        self.landform = relief::landforms(self.poly, &self.heightmap, self.sea_level, &self.slope, &self.curvature);
+        let heightmap = &self.heightmap;
+        let sea_level = self.sea_level;
+        self.landform.majority_filter_where(self.poly, self.current_quality.passes(self.classification_smoothing), |id| heightmap[id] >= sea_level);
+        self.report.count("ridge cells", self.poly.cells().filter(|&(id, _)| self.landform[id] == Landform::Ridge).count());
+        self.report.count("valley cells", self.poly.cells().filter(|&(id, _)| self.landform[id] == Landform::Valley).count());
+        self.passes = pass::find_passes(self.poly, &self.heightmap, self.sea_level);
+        self.report.count("passes", self.passes.len());
+        let geometries = self.passes.iter().map(|pass| Geometry::Point(pass.cell)).collect();
+        let ids = self.features.register_generated(FeatureKind::Pass, geometries);
+        for (&id, pass) in ids.iter().zip(self.passes.iter()) {
+            self.features.set_tag(id, "elevation", pass.elevation);
+        }
+        self.register_islands();
+    }
+
+    fn register_islands(&mut self) {
+        let (islands, archipelagos) = island::find(self);
+        self.report.count("islands", islands.len());
+        self.report.count("archipelagos", archipelagos.len());
+        let geometries = archipelagos.iter()
+            .map(|members| {
+                let mut cells: Vec<_> = members.iter().flat_map(|&island| islands[island].iter().copied()).collect();
+                cells.sort();
+                Geometry::Area(cells)
+            })
+            .collect();
+        self.features.register_generated(FeatureKind::Archipelago, geometries);
+        let sizes: Vec<_> = islands.iter().map(|cells| cells.len()).collect();
+        let ids = self.features.register_generated(FeatureKind::Island, islands.into_iter().map(Geometry::Area).collect());
+        for (id, size) in ids.into_iter().zip(sizes) {
+            self.features.set_tag(id, "cells", size as i64);
         }
     }
 
+    fn assign_terrain_types(&mut self) {
+        if let Some(auto) = self.auto_levels {
+            self.levels = auto.levels(&self.heightmap, self.sea_level);
+            let levels = self.levels;
+            let heightmap = &self.heightmap;
+            self.report.count("highland cells", self.poly.cells().filter(|&(id, _)| heightmap[id] >= levels.highland).count());
+            self.report.count("peak cells", self.poly.cells().filter(|&(id, _)| heightmap[id] >= levels.peak).count());
+        }
+        self.terrain_category.update(|id, category| {
+            let height = self.heightmap[id];
+            *category = if height < self.sea_level {
+                TerrainCategory::Sea
+            } else {
+                TerrainCategory::Land
+            }
+        });
+        
+        // The land within the coast band of the sea, in rings of cells
+        let sea = self.poly.cells().filter(|&(id, _)| self.terrain_category[id] == TerrainCategory::Sea).map(|(id, _)| id);
+        let rings = self.poly.ring_distances(sea);
+        let coast_band = self.coast_band;
+        self.terrain_category.update(|id, category| {
+            if *category == TerrainCategory::Land && rings[id] <= coast_band {
+                *category = TerrainCategory::Coast;
+            }
+        });
+        // The sea stays where the heights put it, only the coast band is smoothed
+        self.terrain_category.majority_filter_where(self.poly, self.current_quality.passes(self.classification_smoothing), |id| {
+            rings[id] > 0
+        });
+        let coast_cells = self.poly.cells().filter(|&(id, _)| self.terrain_category[id] == TerrainCategory::Coast).count();
+        self.report.count("coast cells", coast_cells);
+    }
+
     fn generate_rivers(&mut self) {
+        self.detect_lakes();
+        self.compute_drainage();
+        self.trace_rivers();
+    }
+
+    fn detect_lakes(&mut self) {
+        let category = &self.terrain_category;
+        self.lakes = Lakes::detect(self.poly, &self.water_depth, &self.heightmap, &self.downhill, |id| {
+            category[id] == TerrainCategory::Sea
+        });
+        self.report.count("lakes", self.lakes.lakes().len());
+        self.report.count("lake cells", self.lakes.lakes().iter().map(|lake| lake.cells.len()).sum());
+    }
+
+    fn compute_drainage(&mut self) {
 
+        let inflow = self.seam_inflow();
         self.drainage.update(|id, drainage| {
-            let mut total = 0.0;
+            let mut total = inflow[id];
             total += self.rainfall[id];
             if self.terrain_category[id] == TerrainCategory::Coast {
                 total += 0.3;
@@ -248,61 +1143,403 @@ impl <'a> World<'a> {
                 *drainage = 0.0;
             }
         });
-    
+    }
+
+    fn trace_rivers(&mut self) {
         // TODO: Detect rivers while doing drainage, detect joinpoints as well
-        self.rivers = Path::paths_cascading(
-            &|id| self.drainage[id] > 10.0, 
-            &|id| match self.downhill[id] {
-                CellVector::Stationary => None,
-                CellVector::Towards(tgt, _) => Some(tgt),
-            }, self.height_sorted.iter().rev().cloned())
-            .into_iter().filter(|p| p.cells().len() > 2)
+        let next = |id| match self.downhill[id] {
+            CellVector::Stationary => None,
+            CellVector::Towards(tgt, _) => Some(tgt),
+        };
+        // The water crosses the lakes without a river, which ends at the shore and starts again
+        // from the outflow
+        let mut rivers = Path::paths_cascading(
+            &|id| self.drainage[id] > self.river_cutoff && !self.lakes.is_lake(id),
+            &next, self.height_sorted.iter().rev().cloned());
+        let outflows: HashMap<CellId, CellId> = self.lakes.lakes().iter()
+            .flat_map(|lake| lake.outflows.iter().map(|&(from, to)| (to, from)))
             .collect();
+        for river in rivers.iter_mut() {
+            if let Some(&from) = outflows.get(&river.0[0]) {
+                river.0.insert(0, from);
+            }
+            if let Some(inflow) = river.0.last().and_then(|&id| next(id)).filter(|&id| self.lakes.is_lake(id)) {
+                river.0.push(inflow);
+            }
+        }
+        self.rivers = rivers.into_iter().filter(|p| p.cells().len() > 2).collect();
+        self.river_of = river::index(self.poly, &self.rivers);
 
         self.is_river = Field::uniform(self.poly(), false);
 
+        let mut river_cells = 0;
         for river in self.rivers.iter() {
-            for &cell in river.cells().iter() {
+            for &cell in river.cells().iter().filter(|&&cell| !self.lakes.is_lake(cell)) {
                 self.is_river[cell] = true;
+                river_cells += 1;
             }
         }
+        self.report.count("rivers", self.rivers.len());
+        self.report.count("river cells", river_cells);
+
+        let geometries = self.rivers.iter().map(|river| Geometry::Path(river.cells().to_vec())).collect();
+        let ids = self.features.register_generated(FeatureKind::River, geometries);
+        for (&id, river) in ids.iter().zip(self.rivers.iter()) {
+            self.features.set_tag(id, "length", river.cells().len() as i64);
+        }
     }
     
     pub fn poly(&self) -> &'a PolyMap { self.poly }
+    pub fn sea_level(&self) -> f64 { self.sea_level }
+    pub fn coast_band(&self) -> usize { self.coast_band }
+
+    // The quality of the last stage, lower than asked if stages ran over their budget
+    pub fn quality(&self) -> Quality { self.current_quality }
+    // What each stage of the last generations ran at, to generate the same world again
+    pub fn stage_qualities(&self) -> StageQualities { self.stage_qualities }
+
+    pub fn levels(&self) -> TerrainLevels { self.levels }
     pub fn heightmap(&self) -> &Field<f64> { &self.heightmap }
     pub fn downhill(&self) -> &Field<CellVector<f64>> { &self.downhill }
 
     pub fn terrain_category(&self) -> &Field<TerrainCategory> { &self.terrain_category }
+    pub fn slope(&self) -> &Field<f64> { &self.slope }
+    pub fn aspect(&self) -> &Field<f64> { &self.aspect }
+    pub fn curvature(&self) -> &Field<Curvature> { &self.curvature }
+    pub fn landform(&self) -> &Field<Landform> { &self.landform }
+    pub fn passes(&self) -> &[Pass] { &self.passes }
     pub fn temperature(&self) -> &Field<f64> { &self.temperature }
 
+    // The temperature in °C, by the temperature scale
+    pub fn celsius(&self) -> &Field<f64> { &self.celsius }
+
+    // In m above the sea, by the temperature scale
+    pub fn elevation(&self, cell: CellId) -> f64 {
+        self.temperature_scale.elevation(self.heightmap[cell], self.sea_level)
+    }
+
     pub fn wind(&self) -> &Field<Vec2> { &self.wind }
 
     pub fn rainfall(&self) -> &Field<f64> { &self.rainfall }
 
+    // In mm per year, by the rainfall scale
+    pub fn rainfall_mm(&self, cell: CellId) -> f64 {
+        self.rainfall_scale.mm(self.rainfall[cell])
+    }
+
+    // The potential evapotranspiration, in mm per year
+    pub fn evapotranspiration(&self) -> &Field<f64> { &self.evapotranspiration }
+
+    // Rainfall less potential evapotranspiration, in mm per year, negative where arid
+    pub fn water_balance(&self, cell: CellId) -> f64 {
+        self.rainfall_mm(cell) - self.evapotranspiration[cell]
+    }
+
+    // In [0, 1], 0.5 on the boundary between arid and humid
+    pub fn humidity(&self) -> &Field<f64> { &self.humidity }
+
     pub fn drainage(&self) -> &Field<f64> { &self.drainage }
     pub fn rivers(&self) -> &[Path] { &self.rivers }
+    pub fn river_cutoff(&self) -> f64 { self.river_cutoff }
+    pub fn river(&self, idx: usize) -> Option<River<'_>> {
+        (idx < self.rivers.len()).then(|| River::new(self, idx))
+    }
+    pub fn river_list(&self) -> impl Iterator<Item = River<'_>> + '_ {
+        (0..self.rivers.len()).map(move |idx| River::new(self, idx))
+    }
+    // The river flowing through the cell. Where a tributary joins, the river it joins.
+    pub fn river_at(&self, cell: CellId) -> Option<River<'_>> {
+        self.river_of[cell].map(|idx| River::new(self, idx))
+    }
+
+    // Where a route (e.g. a road) crosses the rivers. Adjacent cells form a planar graph, so
+    // two paths can only cross on a shared cell; a route running along a river for a while
+    // counts as a single crossing, at its widest point.
+    pub fn river_crossings(&self, route: &[CellId]) -> Vec<Crossing> {
+        let mut river_of = std::collections::HashMap::new();
+        for (idx, river) in self.rivers.iter().enumerate() {
+            for &cell in river.cells() {
+                river_of.entry(cell).or_insert(idx);
+            }
+        }
+
+        let mut crossings: Vec<Crossing> = vec![];
+        let mut previous_river = None;
+        for &cell in route {
+            let river = river_of.get(&cell).copied();
+            if let Some(river) = river {
+                let crossing = Crossing::new(cell, river, self.drainage[cell]);
+                match crossings.last_mut() {
+                    Some(last) if previous_river == Some(river) => {
+                        if crossing.width > last.width {
+                            *last = crossing;
+                        }
+                    }
+                    _ => crossings.push(crossing),
+                }
+            }
+            previous_river = river;
+        }
+        crossings
+    }
     pub fn is_river(&self, cell: CellId) -> bool { self.is_river[cell] }
 
+    pub fn water_depth(&self) -> &Field<f64> { &self.water_depth }
+    pub fn is_lake(&self, cell: CellId) -> bool { self.lakes.is_lake(cell) }
+    pub fn lake_at(&self, cell: CellId) -> Option<&Lake> { self.lakes.lake_at(cell) }
+    pub fn lakes(&self) -> &[Lake] { self.lakes.lakes() }
+
     pub fn ground(&self) -> &Field<Ground> { &self.ground }
     pub fn vegetation(&self) -> &Field<Vegetation> { &self.vegetation }
+    pub fn marine(&self) -> &Field<MarineBiome> { &self.marine }
+    pub fn land_biome(&self) -> &Field<LandBiome> { &self.land_biome }
+
+    // The main land cover of a cell, or its marine biome
+    pub fn cover(&self, cell: CellId) -> &'static str {
+        let marine = self.marine[cell];
+        if marine != MarineBiome::None {
+            return marine.name();
+        }
+        if self.lakes.is_lake(cell) {
+            return "Lake";
+        }
+        let vegetation = self.vegetation[cell];
+        if vegetation.deciduous.max(vegetation.boreal) > vegetation.none {
+            return if vegetation.deciduous >= vegetation.boreal { "Deciduous forest" } else { "Boreal forest" };
+        }
+        let ground = self.ground[cell];
+        if ground.rock >= ground.sand && ground.rock >= ground.soil {
+            "Rock"
+        } else if ground.sand >= ground.soil {
+            "Sand"
+        } else {
+            "Grassland"
+        }
+    }
+    pub fn marine_productivity(&self) -> &Field<f64> { &self.marine_productivity }
+    pub fn fog(&self) -> &Field<f64> { &self.fog }
+    pub fn weather_flavor(&self, cell: CellId) -> Option<&'static str> { fog::flavor(self.fog[cell], self.landform[cell]) }
+    pub fn difficulty(&self) -> &Field<f64> { &self.difficulty }
+
+    // The closest river or lake, overland, if any can be reached
+    pub fn nearest_fresh_water(&self, cell: CellId) -> Option<WaterSource> { self.fresh_water[cell] }
+    // Meters below the ground
+    pub fn water_table(&self) -> &Field<f64> { &self.water_table }
+    pub fn water_availability(&self) -> &Field<f64> { &self.water_availability }
+    // How wet the soil stays, between 0 and 1, the moisture the land biomes are classified by
+    pub fn soil_moisture(&self) -> &Field<f64> { &self.soil_moisture }
+    pub fn cell_moisture(&self, cell: CellId) -> f64 { self.soil_moisture[cell] }
+    // How good a harbor each cell would make, 0 off the coast
+    pub fn harbor(&self) -> &Field<f64> { &self.harbor }
+    // The erosion, landslide and flood hazards of each cell
+    pub fn hazards(&self) -> &Field<HazardFlags> { &self.hazards }
+    // The animals living off the land of each cell
+    pub fn fauna(&self) -> &Field<Fauna> { &self.fauna }
+
+    // The river or lake feature holding the source, when one was registered
+    pub fn water_feature(&self, source: &WaterSource) -> Option<FeatureId> {
+        self.features.iter()
+            .find(|feature| matches!(feature.kind, FeatureKind::River | FeatureKind::Lake) && feature.geometry.contains(source.cell))
+            .map(|feature| feature.id)
+    }
+
+    pub fn route(&self, from: CellId, to: CellId) -> Option<Vec<CellId>> {
+        traversal::route(self, from, to)
+    }
+
+    // The peoples of a generated world: cultures spread from their origins, realms of a
+    // somewhat random strength grow from the same cells, and the features within reach of a
+    // culture are named in its language
+    fn generate_cultures(&mut self, seeds: &SeedTree) {
+        let origins = culture::origins(self, seeds);
+        self.report.count("cultures", origins.len());
+        let options = TerritoryOptions::default();
+        self.spread_cultures(&origins, &options, seeds);
+        let mut rng = seeds.child("realms").rng();
+        let realms: Vec<_> = origins.iter()
+            .map(|&capital| Realm { capital, strength: rng.gen_range(0.75..1.25) })
+            .collect();
+        self.expand_territories(&realms, &options, &seeds.child("territory"));
+        self.name_features();
+    }
+
+    // Replaces the territories with those grown from the given realms
+    pub fn expand_territories(&mut self, realms: &[Realm], options: &TerritoryOptions, seeds: &SeedTree) {
+        let _span = tracing::info_span!("expand_territories", realms = realms.len()).entered();
+        self.territory = territory::expand(self, realms, options, seeds);
+    }
+
+    // The index of the realm holding each cell
+    pub fn territory(&self) -> &Field<Option<usize>> { &self.territory }
+
+    // Which realms border which, and where
+    pub fn realm_borders(&self) -> RegionGraph {
+        RegionGraph::new(self.poly, &self.territory)
+    }
+
+    // Replaces the cultures with new ones spreading from the origins like realms do
+    pub fn spread_cultures(&mut self, origins: &[CellId], options: &TerritoryOptions, seeds: &SeedTree) {
+        let _span = tracing::info_span!("spread_cultures", cultures = origins.len()).entered();
+        self.cultures = origins.iter().enumerate()
+            .map(|(idx, &origin)| Culture::new(origin, seeds.child("culture").indexed(idx as u64)))
+            .collect();
+        let realms: Vec<_> = origins.iter().map(|&capital| Realm { capital, strength: 1.0 }).collect();
+        self.culture = territory::expand(self, &realms, options, &seeds.child("spread"));
+
+        let assigned: Vec<_> = self.features.iter()
+            .filter(|feature| matches!(feature.kind, FeatureKind::Region | FeatureKind::Settlement))
+            .filter_map(|feature| {
+                let culture = feature.geometry.cells().first().and_then(|&cell| self.culture[cell])?;
+                Some((feature.id, culture))
+            })
+            .collect();
+        for (id, culture) in assigned {
+            self.features.set_tag(id, "culture", culture as i64);
+        }
+    }
+
+    pub fn cultures(&self) -> &[Culture] { &self.cultures }
+    pub fn culture(&self) -> &Field<Option<usize>> { &self.culture }
+
+    // Names every unnamed feature in the language of the culture at its first cell. Names are
+    // unique within each kind of feature: on a collision the next alternative of the culture is
+    // tried, and after that a number is appended. Features outside of every culture, like
+    // islands out at sea, are named by the culture starting nearest to them.
+    pub fn name_features(&mut self) {
+        const ATTEMPTS: u64 = 32;
+
+        let mut taken: HashSet<(FeatureKind, String)> = self.features.iter()
+            .filter_map(|feature| feature.name.clone().map(|name| (feature.kind, name)))
+            .collect();
+        let mut names = vec![];
+        for feature in self.features.iter().filter(|feature| feature.name.is_none()) {
+            let first = match feature.geometry.cells().first() {
+                Some(&cell) => cell,
+                None => continue,
+            };
+            let culture = match self.culture[first].and_then(|idx| self.cultures.get(idx)).or_else(|| self.nearest_culture(first)) {
+                Some(culture) => culture,
+                None => continue,
+            };
+
+            let key = first.idx() as u64;
+            let name = (0..ATTEMPTS)
+                .map(|attempt| culture.name_for(feature.kind, key, attempt))
+                .find(|name| !taken.contains(&(feature.kind, name.clone())))
+                .unwrap_or_else(|| {
+                    let base = culture.name_for(feature.kind, key, 0);
+                    (2..).map(|n| format!("{} {}", base, n))
+                        .find(|name| !taken.contains(&(feature.kind, name.clone())))
+                        .unwrap()
+                });
+            taken.insert((feature.kind, name.clone()));
+            names.push((feature.id, name));
+        }
+        for (id, name) in names {
+            self.features.set_name(id, name);
+        }
+    }
+
+    fn nearest_culture(&self, cell: CellId) -> Option<&Culture> {
+        let (x, y) = self.poly[cell].center();
+        let distance = |culture: &&Culture| {
+            let (ox, oy) = self.poly[culture.origin].center();
+            (ox - x).powi(2) + (oy - y).powi(2)
+        };
+        self.cultures.iter().min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+
+    pub fn export_civilization(&self) -> String {
+        Civilization::of_world(self).to_text()
+    }
+
+    // Puts back a civilization exported from a world on the same map, usually after the
+    // physical world was generated again. Returns how many of the exported rivers and passes
+    // could not be found any more; their names and notes are dropped.
+    pub fn import_civilization(&mut self, text: &str) -> Result<usize, String> {
+        let civilization = Civilization::from_text(self.poly, text)?;
+        let (features, lost) = civilization.merge_features(&self.features);
+        self.features = features;
+        self.tags = civilization.tags();
+        self.cultures = civilization.cultures();
+        self.culture = civilization.culture(self.poly);
+        self.territory = civilization.territory(self.poly);
+        Ok(lost)
+    }
+
+    // Heights of observers are in heightmap units
+    pub fn viewshed(&self, cell: CellId, observer_height: f64) -> ElementSet {
+        visibility::viewshed(self.poly, &self.heightmap, self.sea_level, cell, observer_height)
+    }
+
+    pub fn line_of_sight(&self, a: CellId, b: CellId, observer_height: f64) -> bool {
+        visibility::line_of_sight(self.poly, &self.heightmap, self.sea_level, a, b, observer_height)
+    }
+
+    pub fn features(&self) -> &Features { &self.features }
+    pub fn features_mut(&mut self) -> &mut Features { &mut self.features }
+
+    pub fn tags(&self) -> &Tags { &self.tags }
+    pub fn tags_mut(&mut self) -> &mut Tags { &mut self.tags }
+    pub fn set_tag(&mut self, cell: CellId, key: &str, value: impl Into<TagValue>) { self.tags.set(cell, key, value) }
+    pub fn tag(&self, cell: CellId, key: &str) -> Option<&TagValue> { self.tags.get(cell, key) }
+
+    pub fn note(&self, cell: CellId) -> Option<String> { self.tags.get_typed(cell, &NOTE) }
 
+    // An empty note removes the note
+    pub fn set_note(&mut self, cell: CellId, text: &str) {
+        if text.trim().is_empty() {
+            self.tags.remove(cell, NOTE.name());
+        } else {
+            self.tags.set_typed(cell, &NOTE, text.to_owned());
+        }
+    }
+
+    pub fn set_feature_note(&mut self, id: FeatureId, text: &str) {
+        if text.trim().is_empty() {
+            self.features.remove_tag(id, NOTE.name());
+        } else {
+            self.features.set_tag(id, NOTE.name(), text);
+        }
+    }
+
+    // Where to mark the notes on the map: the noted cells, and the first cell of noted features
+    pub fn noted_cells(&self) -> Vec<CellId> {
+        let mut cells: Vec<_> = self.tags.cells_with(NOTE.name()).into_iter().map(|(cell, _)| cell).collect();
+        cells.extend(self.features.iter()
+            .filter(|feature| feature.tags.contains_key(NOTE.name()))
+            .filter_map(|feature| feature.geometry.cells().first().copied()));
+        cells
+    }
+
+    pub fn debug_fields(&self) -> &DebugFields { &self.debug }
+    pub fn report(&self) -> &GenerationReport { &self.report }
+
+}
+
+// Fixed cells keep their height, like the map border. Returns the number of sweeps
+// needed to fill all the depressions.
+pub fn planchon_darboux(heightmap:&mut Field<f64>, poly_map: &PolyMap, is_fixed: impl Fn(CellId) -> bool) -> usize {
+    fill_depressions(heightmap, poly_map, 0.001, is_fixed)
 }
 
- fn planchon_darboux(heightmap:&mut Field<f64>, poly_map: &PolyMap) {
-    let epsilon = 0.001;
+// Planchon-Darboux with the given least drop between cells
+pub fn fill_depressions(heightmap: &mut Field<f64>, poly_map: &PolyMap, epsilon: f64, is_fixed: impl Fn(CellId) -> bool) -> usize {
     let h = heightmap;
 
     let mut new_h = Field::with_fn(poly_map, |id, cell| {
-        if cell.is_border() {
+        if cell.is_border() || is_fixed(id) {
             h[id]
         } else {
             100.0
         }
     });
 
+    let mut iterations = 0;
     let mut changed = true;
     while changed {
         changed = false;
+        iterations += 1;
         for (id, corner) in poly_map.cells() {
             if new_h[id] == h[id] {
                 continue;
@@ -323,9 +1560,10 @@ impl <'a> World<'a> {
     }
 
     std::mem::swap(&mut new_h, h);
+    iterations
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TerrainCategory {
     Sea,
     Coast,
@@ -338,6 +1576,7 @@ pub enum CellVector<T> {
     Towards(CellId, T),
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Path(Vec<CellId>);
 
 impl Path {
@@ -356,7 +1595,12 @@ impl Path {
            if !visited.contains(&cell) {
                visited.insert(cell);
                 if property(cell) {
-                    let path = Self::path_while(cell, property, next);
+                    let mut path = Self::path_while(cell, property, next);
+                    // A path running into an earlier one ends there, on the cell where it
+                    // joins, so that a tributary ends on the river it flows into
+                    if let Some(joined) = path.iter().skip(1).position(|cell| visited.contains(cell)) {
+                        path.truncate(joined + 2);
+                    }
                     visited.extend(path.iter().copied());
                     paths.push(Path(path))
                 }
@@ -365,17 +1609,20 @@ impl Path {
         paths
     } 
 
+    // Stops before going around a cycle of `next`, so that it ends for any function
     pub fn path_while(mut cell: CellId, 
         property: impl Fn(CellId) -> bool,
         next: impl Fn(CellId) -> Option<CellId>) -> Vec<CellId> {
         
             let mut path = vec![];
+            let mut seen = HashSet::new();
             loop {
                 path.push(cell);
+                seen.insert(cell);
                 match next(cell) {
                     None => return path,
                     Some(next_cell) => {
-                        if !property(next_cell) { 
+                        if !property(next_cell) || seen.contains(&next_cell) { 
                             return path
                         } else {
                             cell = next_cell
@@ -388,14 +1635,14 @@ impl Path {
 
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f64,
     pub y: f64,
 }
 
 impl Vec2 {
-    const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
+    pub const ZERO: Vec2 = Vec2 { x: 0.0, y: 0.0 };
 
     pub fn to_polar(&self) -> Option<PolarVec2> {
         if self.x == 0.0 {
@@ -406,6 +1653,10 @@ impl Vec2 {
             Some(PolarVec2 { r, theta })
         }
     }
+
+    pub fn length(&self) -> f64 {
+        (self.x.powi(2) + self.y.powi(2)).sqrt()
+    }
 }
 
 impl std::ops::AddAssign<Vec2> for Vec2{
@@ -428,4 +1679,127 @@ impl PolarVec2 {
             y: self.r * self.theta.sin()
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::params::Param;
+
+    fn generates(poly: &PolyMap) {
+        let mut world = World::new(poly);
+        world.generate(&SeedTree::new(5));
+        for (id, _) in poly.cells() {
+            assert!(world.heightmap()[id].is_finite(), "cell {} has no height", id.idx());
+        }
+    }
+
+    #[test]
+    fn generates_tiny_map() {
+        generates(&PolyMap::with_seed(24, 16, 4.0, 1));
+    }
+
+    #[test]
+    fn generates_huge_radius_map() {
+        generates(&PolyMap::with_seed(400, 225, 60.0, 1));
+    }
+
+    // Like the tiny_worlds fuzz target: maps down to nothing either fail to build, or give
+    // whole worlds
+    #[test]
+    fn generates_worlds_of_a_few_cells() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut built = 0;
+        for map_seed in 0..24 {
+            let radius = 0.5 + rng.gen_range(0..256) as f64 / 2.0;
+            if let Ok(poly) = PolyMap::try_with_seed(rng.gen_range(0..256), rng.gen_range(0..256), radius, map_seed) {
+                let mut world = World::new(&poly);
+                world.generate(&SeedTree::new(rng.gen()));
+                assert!(poly.cells().all(|(id, _)| world.heightmap()[id].is_finite()));
+                built += 1;
+            }
+        }
+        assert!(built > 0, "no map built");
+    }
+
+    // Like the paths_cascading fuzz target, on flows drawn from fixed seeds, cycles included
+    #[test]
+    fn cascading_paths_follow_the_flow_and_only_join() {
+        let poly = PolyMap::with_seed(64, 64, 8.0, 0);
+        let count = poly.cells().count();
+        for seed in 0..32 {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let flow: Vec<(bool, Option<CellId>)> = (0..count)
+                .map(|_| (rng.gen_bool(0.8), poly.cell_id(rng.gen_range(0..count + count / 4))))
+                .collect();
+            let property = |cell: CellId| flow[cell.idx()].0;
+            let next = |cell: CellId| flow[cell.idx()].1;
+            let order: Vec<_> = (0..count).filter_map(|_| poly.cell_id(rng.gen_range(0..count))).collect();
+
+            let paths = Path::paths_cascading(&property, &next, order.iter().copied());
+
+            let mut covered = HashSet::new();
+            for path in paths.iter() {
+                let cells = path.cells();
+                assert!(!cells.is_empty());
+                assert_eq!(cells.iter().collect::<HashSet<_>>().len(), cells.len(), "a path visits a cell twice");
+                assert!(cells.windows(2).all(|pair| next(pair[0]) == Some(pair[1])), "a path leaves the flow");
+                assert!(cells.iter().all(|&cell| property(cell)), "a path leaves the property");
+                let upstream = &cells[..cells.len() - 1];
+                assert!(upstream.iter().all(|cell| !covered.contains(cell)), "a path runs on along an earlier one");
+                covered.extend(cells.iter().copied());
+            }
+            assert!(order.iter().filter(|&&cell| property(cell)).all(|cell| covered.contains(cell)));
+        }
+    }
+
+    #[test]
+    fn raising_the_sea_level_drowns_the_realms() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let seeds = SeedTree::new(42);
+        let mut world = World::new(&poly);
+        world.generate(&seeds);
+        world.set_param(Param::SeaLevel, 0.6);
+        Generation::new(seeds).starting_at(Param::SeaLevel.info().stage).finish(&mut world);
+        assert_eq!(world.sea_level(), 0.6);
+        for (id, _) in poly.cells().filter(|&(id, _)| world.heightmap()[id] < 0.6) {
+            assert!(world.terrain_category()[id] == TerrainCategory::Sea);
+            assert_eq!(world.culture()[id], None, "cell {} is sea", id.idx());
+            assert_eq!(world.territory()[id], None, "cell {} is sea", id.idx());
+        }
+    }
+
+    #[test]
+    fn classification_smoothing_keeps_the_coastline() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let mut rough = World::new(&poly);
+        rough.generate(&SeedTree::new(42));
+        let mut smooth = World::new(&poly).with_classification_smoothing(3);
+        smooth.generate(&SeedTree::new(42));
+        let is_sea = |world: &World, id| world.terrain_category()[id] == TerrainCategory::Sea;
+        assert!(poly.cells().all(|(id, _)| is_sea(&rough, id) == is_sea(&smooth, id)));
+        let changed = |field: fn(&World, CellId) -> u8| poly.cells().any(|(id, _)| field(&rough, id) != field(&smooth, id));
+        assert!(changed(|world, id| world.terrain_category()[id] as u8));
+        assert!(changed(|world, id| world.land_biome()[id] as u8));
+    }
+
+    #[test]
+    fn stage_qualities_replay_a_world_generated_over_budget() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        // Every stage runs over a budget of nothing, lowering the quality after it
+        let budget = QualitySettings { quality: Quality::High, stage_budget: Some(std::time::Duration::ZERO) };
+        let mut rushed = World::new(&poly).with_quality(budget);
+        rushed.generate(&SeedTree::new(42));
+        let qualities = rushed.stage_qualities();
+        assert_eq!(qualities.0[..3], [Quality::High, Quality::Normal, Quality::Draft]);
+
+        let mut replayed = World::new(&poly).with_quality(QualitySettings { quality: Quality::High, stage_budget: None });
+        Generation::new(SeedTree::new(42)).with_qualities(qualities).finish(&mut replayed);
+        assert_eq!(replayed.stage_qualities(), qualities);
+        assert!(poly.cells().all(|(id, _)| replayed.heightmap()[id] == rushed.heightmap()[id]));
+        assert!(poly.cells().all(|(id, _)| replayed.land_biome()[id] == rushed.land_biome()[id]));
+    }
+}
@@ -0,0 +1,113 @@
+use polymap::*;
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{TerrainCategory, Vec2};
+
+// Upwelling only happens this many rings away from the coast
+const UPWELLING_RINGS: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum MarineBiome {
+    // Not a sea cell
+    #[default]
+    None,
+    Reef,
+    Kelp,
+    Shelf,
+    Ocean,
+    Abyss,
+    PolarSea,
+}
+
+impl MarineBiome {
+    // Depth goes from 0 at sea level to 1 at the bottom of the map, temperature is the usual
+    // normalized temperature, and current is the normalized strength of the surface currents
+    pub fn new(terrain_category: TerrainCategory, depth: f64, temperature: f64, current: f64) -> Self {
+        if terrain_category != TerrainCategory::Sea {
+            return MarineBiome::None;
+        }
+
+        if temperature < 0.2 {
+            MarineBiome::PolarSea
+        } else if depth < 0.25 && temperature > 0.6 && current < 0.5 {
+            MarineBiome::Reef
+        } else if depth < 0.35 && temperature <= 0.6 && current >= 0.3 {
+            MarineBiome::Kelp
+        } else if depth < 0.35 {
+            MarineBiome::Shelf
+        } else if depth < 0.7 {
+            MarineBiome::Ocean
+        } else {
+            MarineBiome::Abyss
+        }
+    }
+
+    // How much marine life the biome supports on its own, between 0 and 1
+    pub fn base_productivity(&self) -> f64 {
+        match self {
+            MarineBiome::None => 0.0,
+            MarineBiome::Reef => 0.8,
+            MarineBiome::Kelp => 0.7,
+            MarineBiome::Shelf => 0.5,
+            MarineBiome::PolarSea => 0.4,
+            MarineBiome::Ocean => 0.2,
+            MarineBiome::Abyss => 0.05,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarineBiome::None => "None",
+            MarineBiome::Reef => "Reef",
+            MarineBiome::Kelp => "Kelp forest",
+            MarineBiome::Shelf => "Continental shelf",
+            MarineBiome::Ocean => "Open ocean",
+            MarineBiome::Abyss => "Abyss",
+            MarineBiome::PolarSea => "Polar sea",
+        }
+    }
+}
+
+// Fish stock of every sea cell, between 0 and 1 (0 on land). On top of the biome base value,
+// coastal waters where the currents diverge get nutrients from upwelling, and waters next to
+// reefs get a bonus.
+pub fn productivity(poly: &PolyMap, marine: &Field<MarineBiome>, currents: &Field<Vec2>) -> Field<f64> {
+    let land = poly.cells().map(|(id, _)| id).filter(|&id| marine[id] == MarineBiome::None);
+    let coast_distance = poly.ring_distances(land);
+
+    Field::with_fn(poly, |id, cell| {
+        let biome = marine[id];
+        if biome == MarineBiome::None {
+            return 0.0;
+        }
+
+        let upwelling = if coast_distance[id] <= UPWELLING_RINGS {
+            divergence(poly, currents, id).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let next_to_reef = cell.neighbors().iter().any(|&neighbor| marine[neighbor] == MarineBiome::Reef);
+        let reef_bonus = if next_to_reef && biome != MarineBiome::Reef { 0.2 } else { 0.0 };
+
+        (biome.base_productivity() + 0.5 * upwelling + reef_bonus).min(1.0)
+    })
+}
+
+// Average of the current differences along the directions towards each neighbor,
+// positive where the water spreads out
+fn divergence(poly: &PolyMap, currents: &Field<Vec2>, id: CellId) -> f64 {
+    let neighbors = poly[id].neighbors();
+    if neighbors.is_empty() {
+        return 0.0;
+    }
+    let here = currents[id];
+    let total: f64 = neighbors.iter()
+        .map(|&neighbor| {
+            let angle = poly.angle_between_cells(id, neighbor);
+            let there = currents[neighbor];
+            (there.x - here.x) * angle.cos() + (there.y - here.y) * angle.sin()
+        })
+        .sum();
+    crate::measure::VAPOR.normalize(total / neighbors.len() as f64)
+}
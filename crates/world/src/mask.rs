@@ -0,0 +1,105 @@
+use polymap::*;
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+// Colour coding of mask images. A pixel is assigned the class of the closest palette colour,
+// pixels that are far from every colour (e.g. black or transparent) leave the generator free.
+const PALETTE: [([u8; 3], MaskClass); 5] = [
+    ([0, 0, 255], MaskClass::Sea),
+    ([0, 255, 0], MaskClass::Land),
+    ([128, 128, 128], MaskClass::Mountain),
+    ([255, 255, 0], MaskClass::Desert),
+    ([0, 100, 0], MaskClass::Forest),
+];
+
+const MAX_COLOR_DISTANCE: u32 = 48 * 48 * 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MaskClass {
+    Free,
+    Sea,
+    Land,
+    Mountain,
+    Desert,
+    Forest,
+}
+
+impl std::str::FromStr for MaskClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sea" => Ok(MaskClass::Sea),
+            "land" => Ok(MaskClass::Land),
+            "mountain" => Ok(MaskClass::Mountain),
+            "desert" => Ok(MaskClass::Desert),
+            "forest" => Ok(MaskClass::Forest),
+            _ => Err(format!("Unknown mask class '{}', expected one of sea, land, mountain, desert, forest", s)),
+        }
+    }
+}
+
+impl MaskClass {
+    pub fn from_rgba(rgba: [u8; 4]) -> Self {
+        if rgba[3] < 128 {
+            return MaskClass::Free;
+        }
+
+        PALETTE.iter()
+            .map(|&(color, class)| {
+                let distance: u32 = (0..3)
+                    .map(|i| (color[i] as i32 - rgba[i] as i32).pow(2) as u32)
+                    .sum();
+                (class, distance)
+            })
+            .filter(|&(_, distance)| distance <= MAX_COLOR_DISTANCE)
+            .min_by_key(|&(_, distance)| distance)
+            .map(|(class, _)| class)
+            .unwrap_or(MaskClass::Free)
+    }
+
+    pub fn is_land(&self) -> bool {
+        matches!(self, MaskClass::Land | MaskClass::Mountain | MaskClass::Desert | MaskClass::Forest)
+    }
+}
+
+pub struct MaskImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<MaskClass>,
+}
+
+impl MaskImage {
+    // Builds a mask from tightly packed RGBA8 rows, top row first
+    pub fn from_rgba(width: usize, height: usize, data: &[u8]) -> Result<Self, String> {
+        if data.len() != width * height * 4 {
+            return Err(format!("The mask has {} bytes, not those of {}x{} RGBA pixels", data.len(), width, height));
+        }
+        let pixels = data.chunks_exact(4)
+            .map(|px| MaskClass::from_rgba([px[0], px[1], px[2], px[3]]))
+            .collect();
+        Ok(Self { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize { self.width }
+    pub fn height(&self) -> usize { self.height }
+
+    // The mask is stretched over the whole map, whatever its resolution
+    pub fn class_at(&self, poly: &PolyMap, x: f64, y: f64) -> MaskClass {
+        if self.width == 0 || self.height == 0 {
+            return MaskClass::Free;
+        }
+        let px = ((x / poly.width() as f64) * self.width as f64) as usize;
+        let py = ((y / poly.height() as f64) * self.height as f64) as usize;
+        let px = px.min(self.width - 1);
+        let py = py.min(self.height - 1);
+        self.pixels[py * self.width + px]
+    }
+
+    pub fn to_field(&self, poly: &PolyMap) -> Field<MaskClass> {
+        Field::with_fn(poly, |_, cell| {
+            let (x, y) = cell.center();
+            self.class_at(poly, x, y)
+        })
+    }
+}
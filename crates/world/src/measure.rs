@@ -9,11 +9,17 @@ impl Measure {
     pub fn normalize(&self, x: f64) -> f64 {
         (x - self.min)/(self.max - self.min)
     }
+
+    // A value with its name and symbol, for tooltips and reports
+    pub fn format(&self, x: f64) -> String {
+        format!("{}: {:.2} {}", self.name, x, self.symbol)
+    }
 }
 
-pub const RAIN: Measure = Measure {
-    name: "Rainfall",
-    symbol: "rain",
+// The vapor the winds carry, as dropped by the clouds
+pub const VAPOR: Measure = Measure {
+    name: "Vapor",
+    symbol: "vapor",
     min: 0.0,
     max: 50.0
 };
@@ -23,4 +29,68 @@ pub const DRAIN: Measure = Measure {
     symbol: "drainage",
     min: 0.0,
     max: 1.0
-};
\ No newline at end of file
+};
+// Real temperatures, in °C. At sea level they go from the poles to the equator like the
+// normalized ones, and they fall by the lapse rate with the elevation.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TemperatureScale {
+    pub equator: f64,
+    pub poles: f64,
+    // °C per 1000 m
+    pub lapse_rate: f64,
+    // Elevation of the highest land, in m
+    pub peak_elevation: f64,
+}
+
+impl Default for TemperatureScale {
+    fn default() -> Self {
+        Self { equator: 27.0, poles: -25.0, lapse_rate: 6.5, peak_elevation: 4000.0 }
+    }
+}
+
+impl TemperatureScale {
+    // Of the latitude, 0 at the poles and 1 at the equator, and the height
+    pub fn celsius(&self, latitude: f64, height: f64, sea_level: f64) -> f64 {
+        let sea_level_temperature = self.poles + latitude * (self.equator - self.poles);
+        sea_level_temperature - self.lapse_rate * self.elevation(height, sea_level) / 1000.0
+    }
+
+    // In m above the sea, 0 under it
+    pub fn elevation(&self, height: f64, sea_level: f64) -> f64 {
+        ((height - sea_level) / (1.0 - sea_level)).max(0.0) * self.peak_elevation
+    }
+}
+
+// Real rainfall, in mm per year, of the vapor the clouds drop
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RainfallScale {
+    pub mm_per_unit: f64,
+}
+
+impl Default for RainfallScale {
+    fn default() -> Self {
+        Self { mm_per_unit: 40.0 }
+    }
+}
+
+impl RainfallScale {
+    pub fn mm(&self, rainfall: f64) -> f64 {
+        rainfall * self.mm_per_unit
+    }
+}
+
+// The water a well watered cover would give off in a year at the temperature, in mm, after
+// Holdridge: proportional to the temperature between 0 and 30 °C
+pub fn potential_evapotranspiration(celsius: f64) -> f64 {
+    58.93 * celsius.clamp(0.0, 30.0)
+}
+
+// How humid a climate is, in [0, 1], by the ratio of the rainfall to the potential
+// evapotranspiration: 0 without rain, 0.5 on the boundary between arid and humid where the
+// two are even, and 1 where it rains twice as much as can evaporate
+pub fn humidity(rainfall_mm: f64, evapotranspiration: f64) -> f64 {
+    if evapotranspiration <= 0.0 {
+        return if rainfall_mm > 0.0 { 1.0 } else { 0.0 };
+    }
+    (rainfall_mm / evapotranspiration / 2.0).clamp(0.0, 1.0)
+}
@@ -0,0 +1,174 @@
+use polymap::*;
+
+use crate::World;
+
+// What every world model offers per cell, so that views, exporters and statistics can work
+// against any of them, and different models can be compared on the same map
+pub trait WorldModel {
+    fn poly(&self) -> &PolyMap;
+    fn sea_level(&self) -> f64;
+    fn height_at(&self, cell: CellId) -> f64;
+    // Normalized, 0 is the coldest and 1 the warmest
+    fn temperature_at(&self, cell: CellId) -> f64;
+    // In °C
+    fn celsius_at(&self, cell: CellId) -> f64;
+    fn rainfall_at(&self, cell: CellId) -> f64;
+    // Both in mm per year
+    fn rainfall_mm_at(&self, cell: CellId) -> f64;
+    fn water_balance_at(&self, cell: CellId) -> f64;
+    // The cells of each river, from the source downstream
+    fn river_paths(&self) -> Vec<&[CellId]>;
+    // The land cover, or the marine biome at sea
+    fn biome_at(&self, cell: CellId) -> &'static str;
+
+    fn is_land(&self, cell: CellId) -> bool {
+        self.height_at(cell) >= self.sea_level()
+    }
+}
+
+impl WorldModel for World<'_> {
+    fn poly(&self) -> &PolyMap { World::poly(self) }
+    fn sea_level(&self) -> f64 { World::sea_level(self) }
+    fn height_at(&self, cell: CellId) -> f64 { self.heightmap()[cell] }
+    fn temperature_at(&self, cell: CellId) -> f64 { self.temperature()[cell] }
+    fn celsius_at(&self, cell: CellId) -> f64 { self.celsius()[cell] }
+    fn rainfall_at(&self, cell: CellId) -> f64 { self.rainfall()[cell] }
+    fn rainfall_mm_at(&self, cell: CellId) -> f64 { self.rainfall_mm(cell) }
+    fn water_balance_at(&self, cell: CellId) -> f64 { self.water_balance(cell) }
+    fn river_paths(&self) -> Vec<&[CellId]> { self.rivers().iter().map(|river| river.cells()).collect() }
+    fn biome_at(&self, cell: CellId) -> &'static str { self.cover(cell) }
+}
+
+// Summary of a world model, for comparing models side by side
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    pub cells: usize,
+    pub land_fraction: f64,
+    // Means over the land cells
+    pub mean_height: f64,
+    pub mean_temperature: f64,
+    pub mean_celsius: f64,
+    pub mean_rainfall: f64,
+    pub mean_rainfall_mm: f64,
+    pub mean_water_balance: f64,
+    // Of the land, where less rain falls than could evaporate
+    pub arid_fraction: f64,
+    pub rivers: usize,
+    pub river_cells: usize,
+    // Cell counts, most common first
+    pub biomes: Vec<(&'static str, usize)>,
+}
+
+impl Statistics {
+    pub fn of(model: &dyn WorldModel) -> Self {
+        let poly = model.poly();
+        let land: Vec<_> = poly.cells().map(|(id, _)| id).filter(|&id| model.is_land(id)).collect();
+        let mean = |f: &dyn Fn(CellId) -> f64| {
+            if land.is_empty() { 0.0 } else { land.iter().map(|&id| f(id)).sum::<f64>() / land.len() as f64 }
+        };
+
+        let mut biomes: Vec<(&'static str, usize)> = vec![];
+        for (id, _) in poly.cells() {
+            let biome = model.biome_at(id);
+            match biomes.iter_mut().find(|(name, _)| *name == biome) {
+                Some((_, count)) => *count += 1,
+                None => biomes.push((biome, 1)),
+            }
+        }
+        biomes.sort_by(|(name1, n1), (name2, n2)| n2.cmp(n1).then(name1.cmp(name2)));
+
+        let rivers = model.river_paths();
+        let cells = poly.cells().count();
+        Self {
+            cells,
+            land_fraction: if cells == 0 { 0.0 } else { land.len() as f64 / cells as f64 },
+            mean_height: mean(&|id| model.height_at(id)),
+            mean_temperature: mean(&|id| model.temperature_at(id)),
+            mean_celsius: mean(&|id| model.celsius_at(id)),
+            mean_rainfall: mean(&|id| model.rainfall_at(id)),
+            mean_rainfall_mm: mean(&|id| model.rainfall_mm_at(id)),
+            mean_water_balance: mean(&|id| model.water_balance_at(id)),
+            arid_fraction: mean(&|id| if model.water_balance_at(id) < 0.0 { 1.0 } else { 0.0 }),
+            rivers: rivers.len(),
+            river_cells: rivers.iter().map(|river| river.len()).sum(),
+            biomes,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "cells: {}\nland: {:.1}%\nmean land height: {:.3}\nmean land temperature: {:.3} ({:.1} °C)\nmean land rainfall: {:.2} ({:.0} mm/yr)\nmean land water balance: {:.0} mm/yr\narid land: {:.1}%\nrivers: {} ({} cells)\n",
+            self.cells, self.land_fraction * 100.0, self.mean_height, self.mean_temperature, self.mean_celsius, self.mean_rainfall,
+            self.mean_rainfall_mm, self.mean_water_balance, self.arid_fraction * 100.0,
+            self.rivers, self.river_cells
+        );
+        for (name, count) in self.biomes.iter() {
+            text.push_str(&format!("{}: {}\n", name, count));
+        }
+        text
+    }
+}
+
+// How the values of a field spread over their range, in bins of equal width, or of equal ratio
+// on a log scale, where the values that are not positive are left out. The values are kept
+// sorted, so that those on either side of any threshold are counted exactly.
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub bins: Vec<usize>,
+    pub range: (f64, f64),
+    pub log: bool,
+    sorted: Vec<f64>,
+}
+
+impl Histogram {
+    pub fn new(values: impl IntoIterator<Item = f64>, bins: usize, log: bool) -> Self {
+        let mut sorted: Vec<_> = values.into_iter().filter(|x| x.is_finite() && (!log || *x > 0.0)).collect();
+        sorted.sort_by(f64::total_cmp);
+        let range = match (sorted.first(), sorted.last()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ if log => (1.0, 10.0),
+            _ => (0.0, 1.0),
+        };
+        let mut histogram = Self { bins: vec![], range, log, sorted };
+        let mut counts = vec![0; bins.max(1)];
+        for &value in histogram.sorted.iter() {
+            let bin = (histogram.position(value) * counts.len() as f64) as usize;
+            counts[bin.min(bins.max(1) - 1)] += 1;
+        }
+        histogram.bins = counts;
+        histogram
+    }
+
+    pub fn len(&self) -> usize { self.sorted.len() }
+
+    pub fn is_empty(&self) -> bool { self.sorted.is_empty() }
+
+    // How many values are under the threshold, and how many at it or over
+    pub fn below(&self, threshold: f64) -> usize { self.sorted.partition_point(|&x| x < threshold) }
+
+    pub fn above(&self, threshold: f64) -> usize { self.len() - self.below(threshold) }
+
+    // Where the value falls along the range, from 0 to 1
+    pub fn position(&self, value: f64) -> f64 {
+        let (min, max) = self.scaled_range();
+        if max <= min {
+            return 0.0;
+        }
+        ((self.scale(value) - min) / (max - min)).clamp(0.0, 1.0)
+    }
+
+    // The inverse of position
+    pub fn value_at(&self, position: f64) -> f64 {
+        let (min, max) = self.scaled_range();
+        let scaled = min + position.clamp(0.0, 1.0) * (max - min);
+        if self.log { scaled.exp() } else { scaled }
+    }
+
+    fn scale(&self, value: f64) -> f64 {
+        if self.log { value.max(f64::MIN_POSITIVE).ln() } else { value }
+    }
+
+    fn scaled_range(&self) -> (f64, f64) {
+        (self.scale(self.range.0), self.scale(self.range.1))
+    }
+}
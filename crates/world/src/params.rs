@@ -0,0 +1,103 @@
+use crate::{World, SEA_LEVELS};
+
+// A number the generator is tuned by, which can be changed between generations, unlike those
+// only set up by the builder
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Param {
+    LowFrequency,
+    LowIntensity,
+    HighFrequency,
+    HighIntensity,
+    RelaxStrength,
+    EquatorTemperature,
+    PoleTemperature,
+    AxialTilt,
+    RainfallScale,
+    SeaLevel,
+}
+
+// How a parameter is shown and what it changes
+#[derive(Clone, Copy, Debug)]
+pub struct Info {
+    // Short, for the command lines of recordings
+    pub key: &'static str,
+    pub name: &'static str,
+    pub min: f64,
+    pub max: f64,
+    // Whether the slider moves by factors rather than steps
+    pub logarithmic: bool,
+    // The first stage of the generation the parameter changes; the stages before it stay as
+    // they are
+    pub stage: &'static str,
+}
+
+impl Param {
+    pub const ALL: [Param; 10] = [
+        Param::LowFrequency, Param::LowIntensity, Param::HighFrequency, Param::HighIntensity,
+        Param::RelaxStrength, Param::EquatorTemperature, Param::PoleTemperature, Param::AxialTilt,
+        Param::RainfallScale, Param::SeaLevel,
+    ];
+
+    pub fn info(&self) -> Info {
+        let info = |key, name, min, max, logarithmic, stage| Info { key, name, min, max, logarithmic, stage };
+        match self {
+            Param::LowFrequency => info("low-frequency", "Low noise frequency", 0.0001, 0.01, true, "heightmap"),
+            Param::LowIntensity => info("low-intensity", "Low noise intensity", 0.0, 2.0, false, "heightmap"),
+            Param::HighFrequency => info("high-frequency", "High noise frequency", 0.001, 0.1, true, "heightmap"),
+            Param::HighIntensity => info("high-intensity", "High noise intensity", 0.0, 1.0, false, "heightmap"),
+            Param::RelaxStrength => info("relax-strength", "Relaxation strength", 0.0, 1.0, false, "heightmap"),
+            Param::EquatorTemperature => info("equator-temperature", "Equator temperature (°C)", -10.0, 50.0, false, "temperature"),
+            Param::PoleTemperature => info("pole-temperature", "Pole temperature (°C)", -60.0, 20.0, false, "temperature"),
+            Param::AxialTilt => info("axial-tilt", "Axial tilt (°)", 0.0, 90.0, false, "biomes"),
+            Param::RainfallScale => info("rainfall-scale", "Rainfall scale (mm)", 1.0, 1000.0, true, "rainfall"),
+            // Floods or drains the world without touching the heights
+            Param::SeaLevel => info("sea-level", "Sea level", SEA_LEVELS.0, SEA_LEVELS.1, false, "terrain"),
+        }
+    }
+}
+
+impl std::str::FromStr for Param {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Param::ALL.into_iter()
+            .find(|param| param.info().key == s)
+            .ok_or_else(|| format!("Unknown parameter '{}'", s))
+    }
+}
+
+impl World<'_> {
+    pub fn param(&self, param: Param) -> f64 {
+        match param {
+            Param::LowFrequency => self.low_noise.frequency,
+            Param::LowIntensity => self.low_noise.intensity,
+            Param::HighFrequency => self.high_noise.frequency,
+            Param::HighIntensity => self.high_noise.intensity,
+            Param::RelaxStrength => self.relaxation.strength,
+            Param::EquatorTemperature => self.temperature_scale.equator,
+            Param::PoleTemperature => self.temperature_scale.poles,
+            Param::AxialTilt => self.orbit.axial_tilt,
+            Param::RainfallScale => self.rainfall_scale.mm_per_unit,
+            Param::SeaLevel => self.sea_level,
+        }
+    }
+
+    // Takes effect with the next generation, which only needs to run from the stage of the
+    // parameter on
+    pub fn set_param(&mut self, param: Param, value: f64) {
+        let info = param.info();
+        let value = value.clamp(info.min, info.max);
+        match param {
+            Param::LowFrequency => self.low_noise.frequency = value,
+            Param::LowIntensity => self.low_noise.intensity = value,
+            Param::HighFrequency => self.high_noise.frequency = value,
+            Param::HighIntensity => self.high_noise.intensity = value,
+            Param::RelaxStrength => self.relaxation.strength = value,
+            Param::EquatorTemperature => self.temperature_scale.equator = value,
+            Param::PoleTemperature => self.temperature_scale.poles = value,
+            Param::AxialTilt => self.orbit.axial_tilt = value,
+            Param::RainfallScale => self.rainfall_scale.mm_per_unit = value,
+            Param::SeaLevel => self.sea_level = value,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use polymap::*;
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+// Both sides of a pass must rise at least this much above it
+const MIN_RELIEF: f64 = 0.02;
+
+// A saddle between two higher grounds, where a road can cross a range
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Pass {
+    pub cell: CellId,
+    pub elevation: f64,
+    // How much the lower of the two sides rises above the pass
+    pub relief: f64,
+}
+
+// Walking around a saddle, the neighbors go up, down, up and down again. Saddles next to a
+// lower one are dropped, so that every pass is reported once.
+pub fn find_passes(poly: &PolyMap, heightmap: &Field<f64>, sea_level: f64) -> Vec<Pass> {
+    let mut candidates: Vec<Pass> = poly.cells()
+        .filter(|&(id, cell)| heightmap[id] >= sea_level && !cell.is_border())
+        .filter_map(|(id, _)| saddle_relief(poly, heightmap, id).map(|relief| Pass {
+            cell: id,
+            elevation: heightmap[id],
+            relief,
+        }))
+        .collect();
+    candidates.sort_by(|a, b| a.elevation.total_cmp(&b.elevation).then(a.cell.cmp(&b.cell)));
+
+    let mut taken = Field::uniform(poly, false);
+    let mut passes = vec![];
+    for pass in candidates {
+        if poly[pass.cell].neighbors().iter().any(|&neighbor| taken[neighbor]) {
+            continue;
+        }
+        taken[pass.cell] = true;
+        passes.push(pass);
+    }
+    passes
+}
+
+// The relief of the lower of the two highest rising sides, if the cell is a saddle
+fn saddle_relief(poly: &PolyMap, heightmap: &Field<f64>, id: CellId) -> Option<f64> {
+    let here = heightmap[id];
+    let mut ring: Vec<_> = poly[id].neighbors().to_vec();
+    ring.sort_by(|&a, &b| poly.angle_between_cells(id, a).total_cmp(&poly.angle_between_cells(id, b)));
+
+    // Rotate the ring so that it starts on a lower neighbor, then collect the runs of higher ones
+    let start = ring.iter().position(|&neighbor| heightmap[neighbor] < here)?;
+    ring.rotate_left(start);
+    let mut rises = vec![];
+    let mut current: Option<f64> = None;
+    for &neighbor in ring.iter() {
+        let rise = heightmap[neighbor] - here;
+        if rise > 0.0 {
+            current = Some(current.map_or(rise, |highest| highest.max(rise)));
+        } else if let Some(highest) = current.take() {
+            rises.push(highest);
+        }
+    }
+    rises.extend(current);
+
+    if rises.len() < 2 {
+        return None;
+    }
+    rises.sort_by(|a, b| b.total_cmp(a));
+    let relief = rises[1];
+    if relief >= MIN_RELIEF { Some(relief) } else { None }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use crate::generation::STAGES;
+
+// How much work the generation puts into the passes that can be cut short, trading detail for
+// speed, e.g. draft for previews and thumbnails
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Quality {
+    Draft,
+    #[default]
+    Normal,
+    High,
+}
+
+impl std::str::FromStr for Quality {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(Quality::Draft),
+            "normal" => Ok(Quality::Normal),
+            "high" => Ok(Quality::High),
+            _ => Err(format!("Unknown quality '{}', expected one of draft, normal, high", s)),
+        }
+    }
+}
+
+impl Quality {
+    pub const ALL: [Quality; 3] = [Quality::Draft, Quality::Normal, Quality::High];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quality::Draft => "draft",
+            Quality::Normal => "normal",
+            Quality::High => "high",
+        }
+    }
+
+    // The quality a stage falls back to when the one before it ran over its budget
+    pub fn lower(&self) -> Option<Quality> {
+        match self {
+            Quality::Draft => None,
+            Quality::Normal => Some(Quality::Draft),
+            Quality::High => Some(Quality::Normal),
+        }
+    }
+
+    // Passes of relaxation and smoothing. Draft halves them but keeps at least one, so that
+    // the look of the world stays the same.
+    pub fn passes(&self, passes: usize) -> usize {
+        match self {
+            Quality::Draft => if passes == 0 { 0 } else { (passes / 2).max(1) },
+            Quality::Normal => passes,
+            Quality::High => passes * 2,
+        }
+    }
+
+    // The least drop between a filled cell and the neighbor it drains to. Coarser drops are
+    // filled in fewer sweeps.
+    pub fn fill_epsilon(&self) -> f64 {
+        match self {
+            Quality::Draft => 0.004,
+            Quality::Normal => 0.001,
+            Quality::High => 0.0005,
+        }
+    }
+}
+
+// The quality asked for, and how long each stage may take before the following stages fall
+// back to a lower quality
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct QualitySettings {
+    pub quality: Quality,
+    pub stage_budget: Option<Duration>,
+}
+
+// The quality each of the STAGES ran at. With a budget they depend on how fast the machine
+// is, so they are kept with the seed to generate the same world elsewhere.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct StageQualities(pub [Quality; STAGES.len()]);
+
+impl StageQualities {
+    // Two bits per stage, the first stage lowest
+    pub fn to_bits(&self) -> u16 {
+        self.0.iter().enumerate().fold(0, |bits, (idx, quality)| bits | (*quality as u16) << (2 * idx))
+    }
+
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        let mut qualities = [Quality::default(); STAGES.len()];
+        for (idx, quality) in qualities.iter_mut().enumerate() {
+            *quality = *Quality::ALL.get((bits >> (2 * idx) & 3) as usize)?;
+        }
+        if bits >> (2 * STAGES.len()) != 0 {
+            return None;
+        }
+        Some(Self(qualities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stage_qualities_round_trip_through_bits() {
+        let qualities = StageQualities([Quality::High, Quality::High, Quality::Normal, Quality::Normal, Quality::Draft, Quality::Draft, Quality::Draft]);
+        assert_eq!(StageQualities::from_bits(qualities.to_bits()), Some(qualities));
+        assert_eq!(StageQualities::from_bits(3), None);
+        assert_eq!(StageQualities::from_bits(u16::MAX), None);
+    }
+}
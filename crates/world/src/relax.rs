@@ -0,0 +1,59 @@
+use polymap::*;
+use polymap::field::Field;
+
+// Which cells relaxation may move, by their height before relaxing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RelaxScope {
+    #[default]
+    All,
+    // Smooths the land and leaves the sea floor as rough as it was
+    Land,
+    Sea,
+}
+
+impl std::str::FromStr for RelaxScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(RelaxScope::All),
+            "land" => Ok(RelaxScope::Land),
+            "sea" => Ok(RelaxScope::Sea),
+            _ => Err(format!("Unknown relax scope '{}', expected one of all, land, sea", s)),
+        }
+    }
+}
+
+// Pulls every height towards the mean of its neighborhood, taking off the sharpest noise
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Relaxation {
+    pub iterations: usize,
+    // How far each iteration moves a height towards the mean of its neighborhood, in [0, 1]
+    pub strength: f64,
+    pub scope: RelaxScope,
+}
+
+impl Default for Relaxation {
+    // No relaxation at all
+    fn default() -> Self {
+        Self { iterations: 0, strength: 0.2, scope: RelaxScope::All }
+    }
+}
+
+impl Relaxation {
+    pub(crate) fn apply(&self, poly: &PolyMap, heightmap: &mut Field<f64>, sea_level: f64) {
+        let strength = self.strength.clamp(0.0, 1.0);
+        if self.iterations == 0 || strength == 0.0 {
+            return;
+        }
+        let weight = Field::with_fn(poly, |id, _| {
+            let movable = match self.scope {
+                RelaxScope::All => true,
+                RelaxScope::Land => heightmap[id] >= sea_level,
+                RelaxScope::Sea => heightmap[id] < sea_level,
+            };
+            if movable { strength } else { 0.0 }
+        });
+        heightmap.gpu_smooth_weighted(poly, self.iterations, &weight);
+    }
+}
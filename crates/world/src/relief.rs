@@ -0,0 +1,180 @@
+use polymap::*;
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+// Least squares fit of a plane through the cell and its neighbors. Returns the gradient of the
+// height in map units, with x to the right and y downwards like the cell centers.
+pub fn gradient(poly: &PolyMap, heightmap: &Field<f64>, id: CellId) -> (f64, f64) {
+    let (cx, cy) = poly[id].center();
+    let here = heightmap[id];
+
+    let (mut xx, mut xy, mut yy, mut xh, mut yh) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for &neighbor in poly[id].neighbors() {
+        let (nx, ny) = poly[neighbor].center();
+        let (dx, dy) = (nx - cx, ny - cy);
+        let dh = heightmap[neighbor] - here;
+        xx += dx * dx;
+        xy += dx * dy;
+        yy += dy * dy;
+        xh += dx * dh;
+        yh += dy * dh;
+    }
+
+    let determinant = xx * yy - xy * xy;
+    if determinant.abs() < f64::EPSILON {
+        return (0.0, 0.0);
+    }
+    ((yy * xh - xy * yh) / determinant, (xx * yh - xy * xh) / determinant)
+}
+
+// Steepness of every cell, as height units per 1000 map units
+pub fn slope(poly: &PolyMap, heightmap: &Field<f64>) -> Field<f64> {
+    Field::with_fn(poly, |id, _| {
+        let (gx, gy) = gradient(poly, heightmap, id);
+        (gx * gx + gy * gy).sqrt() * 1000.0
+    })
+}
+
+// The direction each cell faces, that is the direction of steepest descent, as an angle in
+// radians measured like PolyMap::angle_between_cells. Flat cells face east.
+pub fn aspect(poly: &PolyMap, heightmap: &Field<f64>) -> Field<f64> {
+    Field::with_fn(poly, |id, _| {
+        let (gx, gy) = gradient(poly, heightmap, id);
+        if gx == 0.0 && gy == 0.0 {
+            0.0
+        } else {
+            // Downhill is -gradient, and the y axis of the angles points up
+            f64::atan2(gy, -gx)
+        }
+    })
+}
+
+// Flatter cells than this, in the units of slope(), are flats whatever their curvature
+const FLAT_SLOPE: f64 = 0.1;
+// Cells curving more than this many standard deviations of the plan curvature are ridges or valleys
+const CURVATURE_DEVIATIONS: f64 = 0.5;
+
+// Second derivatives of the height, in height units per 1000 map units squared. Profile
+// curvature is along the slope and plan curvature across it; both are positive where the
+// terrain is concave (e.g. at the bottom of a valley) and negative where it is convex.
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Curvature {
+    pub profile: f64,
+    pub plan: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Landform {
+    #[default]
+    Flat,
+    Slope,
+    Ridge,
+    Valley,
+}
+
+impl Landform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Landform::Flat => "Flat",
+            Landform::Slope => "Slope",
+            Landform::Ridge => "Ridge",
+            Landform::Valley => "Valley",
+        }
+    }
+}
+
+pub fn curvature(poly: &PolyMap, heightmap: &Field<f64>) -> Field<Curvature> {
+    Field::with_fn(poly, |id, _| {
+        let [r, s, t, p, q] = match fit_quadratic(poly, heightmap, id) {
+            Some(coefficients) => coefficients,
+            None => return Curvature::default(),
+        };
+        let scale = 1000.0 * 1000.0;
+        let gradient = p * p + q * q;
+        if gradient < f64::EPSILON {
+            let mean = (r + t) / 2.0 * scale;
+            return Curvature { profile: mean, plan: mean };
+        }
+        Curvature {
+            profile: (r * p * p + 2.0 * s * p * q + t * q * q) / gradient * scale,
+            plan: (r * q * q - 2.0 * s * p * q + t * p * p) / gradient * scale,
+        }
+    })
+}
+
+// Sea cells are left flat
+pub fn landforms(poly: &PolyMap, heightmap: &Field<f64>, sea_level: f64, slope: &Field<f64>, curvature: &Field<Curvature>) -> Field<Landform> {
+    let land: Vec<_> = poly.cells().map(|(id, _)| id).filter(|&id| heightmap[id] >= sea_level).collect();
+    let deviation = if land.is_empty() {
+        0.0
+    } else {
+        let mean = land.iter().map(|&id| curvature[id].plan).sum::<f64>() / land.len() as f64;
+        let variance = land.iter().map(|&id| (curvature[id].plan - mean).powi(2)).sum::<f64>() / land.len() as f64;
+        variance.sqrt()
+    };
+    let threshold = deviation * CURVATURE_DEVIATIONS;
+
+    Field::with_fn(poly, |id, _| {
+        let plan = curvature[id].plan;
+        if heightmap[id] < sea_level {
+            Landform::Flat
+        } else if plan < -threshold {
+            Landform::Ridge
+        } else if plan > threshold {
+            Landform::Valley
+        } else if slope[id] < FLAT_SLOPE {
+            Landform::Flat
+        } else {
+            Landform::Slope
+        }
+    })
+}
+
+// Least squares fit of h = r/2 dx² + s dx dy + t/2 dy² + p dx + q dy through the neighbors,
+// returning [r, s, t, p, q]. Needs at least five neighbors.
+fn fit_quadratic(poly: &PolyMap, heightmap: &Field<f64>, id: CellId) -> Option<[f64; 5]> {
+    let neighbors = poly[id].neighbors();
+    if neighbors.len() < 5 {
+        return None;
+    }
+    let (cx, cy) = poly[id].center();
+    let here = heightmap[id];
+
+    // Normal equations, as an augmented 5x6 matrix
+    let mut matrix = [[0.0; 6]; 5];
+    for &neighbor in neighbors {
+        let (nx, ny) = poly[neighbor].center();
+        let (dx, dy) = (nx - cx, ny - cy);
+        let row = [dx * dx / 2.0, dx * dy, dy * dy / 2.0, dx, dy];
+        let dh = heightmap[neighbor] - here;
+        for i in 0..5 {
+            for j in 0..5 {
+                matrix[i][j] += row[i] * row[j];
+            }
+            matrix[i][5] += row[i] * dh;
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    for col in 0..5 {
+        let pivot = (col..5).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs()))?;
+        if matrix[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(col, pivot);
+        let pivot_row = matrix[col];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, pivot_value) in values.iter_mut().zip(pivot_row.iter()).skip(col) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let mut solution = [0.0; 5];
+    for (i, value) in solution.iter_mut().enumerate() {
+        *value = matrix[i][5] / matrix[i][i];
+    }
+    Some(solution)
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct StageReport {
+    pub name: &'static str,
+    pub duration: Duration,
+    pub counters: Vec<(&'static str, usize)>,
+}
+
+// Timings and counters of the last generation, one entry per stage
+#[derive(Clone, Default)]
+pub struct GenerationReport {
+    stages: Vec<StageReport>,
+}
+
+impl GenerationReport {
+    pub fn stages(&self) -> &[StageReport] {
+        self.stages.as_slice()
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|stage| stage.duration).sum()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.stages.clear();
+    }
+
+    pub(crate) fn begin(&mut self, name: &'static str) {
+        self.stages.push(StageReport {
+            name,
+            duration: Duration::ZERO,
+            counters: vec![],
+        });
+    }
+
+    // Adds a counter to the stage currently running
+    pub(crate) fn count(&mut self, counter: &'static str, value: usize) {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.counters.push((counter, value));
+        }
+    }
+
+    pub(crate) fn finish(&mut self, duration: Duration) {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.duration = duration;
+            tracing::info!(
+                stage = stage.name,
+                elapsed_ms = duration.as_secs_f64() * 1000.0,
+                counters = ?stage.counters,
+                "stage finished"
+            );
+        }
+    }
+}
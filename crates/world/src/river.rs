@@ -0,0 +1,122 @@
+use polymap::*;
+
+use polymap::field::Field;
+
+use crate::features::{Feature, FeatureId, FeatureKind};
+use crate::{CellVector, Path, World};
+
+// A river of the world, with its geometry and the water along it. The cells run downstream,
+// from the source to the mouth.
+#[derive(Clone, Copy)]
+pub struct River<'w> {
+    world: &'w World<'w>,
+    path: &'w Path,
+    idx: usize,
+}
+
+impl<'w> River<'w> {
+    pub(crate) fn new(world: &'w World<'w>, idx: usize) -> Self {
+        Self { world, path: &world.rivers()[idx], idx }
+    }
+
+    // Index of the river in World::rivers, like in crossings. It changes whenever the rivers
+    // are traced again; the id does not.
+    pub fn idx(&self) -> usize { self.idx }
+
+    // The id of the river in the features, which it keeps across regenerations as long as the
+    // end of its course stays put, see Geometry::key_cell
+    pub fn id(&self) -> Option<FeatureId> { self.feature().map(|feature| feature.id) }
+
+    pub fn name(&self) -> Option<&'w str> { self.feature().and_then(|feature| feature.name.as_deref()) }
+
+    fn feature(&self) -> Option<&'w Feature> {
+        self.world.features().of_kind(FeatureKind::River)
+            .find(|feature| feature.geometry.cells() == self.cells())
+    }
+
+    pub fn cells(&self) -> &'w [CellId] { self.path.cells() }
+
+    pub fn source(&self) -> CellId { self.cells()[0] }
+
+    pub fn mouth(&self) -> CellId { self.cells()[self.cells().len() - 1] }
+
+    // Where the water goes past the mouth: the sea, a bigger river or a lake, if anywhere
+    pub fn outlet(&self) -> Option<CellId> {
+        match self.world.downhill()[self.mouth()] {
+            CellVector::Towards(cell, _) => Some(cell),
+            CellVector::Stationary => None,
+        }
+    }
+
+    // In map units, between the centers of the cells
+    pub fn length(&self) -> f64 {
+        self.polyline().windows(2)
+            .map(|pair| ((pair[1].0 - pair[0].0).powi(2) + (pair[1].1 - pair[0].1).powi(2)).sqrt())
+            .sum()
+    }
+
+    // The water flowing through each cell, which grows downstream
+    pub fn flux(&self) -> Vec<f64> {
+        self.cells().iter().map(|&cell| self.world.drainage()[cell]).collect()
+    }
+
+    // All the water the river carries, tributaries included. A tributary ends on the river it
+    // joins, whose water at the confluence is not its own, so its water is that of the cell
+    // before.
+    pub fn mouth_flux(&self) -> f64 {
+        let cells = self.cells();
+        let last = match self.world.river_at(self.mouth()) {
+            Some(river) if river.idx != self.idx && cells.len() > 1 => cells[cells.len() - 2],
+            _ => self.mouth(),
+        };
+        self.world.drainage()[last]
+    }
+
+    // In m above the sea
+    pub fn source_elevation(&self) -> f64 { self.world.elevation(self.source()) }
+
+    // The river this one flows into, if it ends in another rather than in the sea or a lake
+    pub fn joins(&self) -> Option<River<'w>> {
+        let confluence = match self.world.river_at(self.mouth()) {
+            Some(river) if river.idx != self.idx => return Some(river),
+            _ => self.outlet()?,
+        };
+        self.world.river_at(confluence).filter(|river| river.idx != self.idx)
+    }
+
+    // The rivers flowing into this one, from the source down
+    pub fn tributaries(&self) -> Vec<River<'w>> {
+        let world = self.world;
+        let mut tributaries: Vec<_> = world.river_list()
+            .filter(|river| river.joins().map(|joined| joined.idx) == Some(self.idx))
+            .collect();
+        let position = |river: &River| self.cells().iter().position(|&cell| Some(cell) == river.outlet() || cell == river.mouth());
+        tributaries.sort_by_key(position);
+        tributaries
+    }
+
+    // The centers of the cells, in map coordinates
+    pub fn polyline(&self) -> Vec<(f64, f64)> {
+        let poly = self.world.poly();
+        self.cells().iter().map(|&cell| poly[cell].center()).collect()
+    }
+}
+
+// The river carrying the water on from each cell. A tributary ends on the river it joins, so
+// the cells a river does not end on are given to it first.
+pub(crate) fn index(poly: &PolyMap, rivers: &[Path]) -> Field<Option<usize>> {
+    let mut river_of = Field::uniform(poly, None);
+    for (idx, river) in rivers.iter().enumerate() {
+        if let Some((_, upstream)) = river.cells().split_last() {
+            for &cell in upstream {
+                river_of[cell].get_or_insert(idx);
+            }
+        }
+    }
+    for (idx, river) in rivers.iter().enumerate() {
+        if let Some(&mouth) = river.cells().last() {
+            river_of[mouth].get_or_insert(idx);
+        }
+    }
+    river_of
+}
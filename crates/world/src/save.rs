@@ -0,0 +1,238 @@
+use polymap::*;
+use polymap::field::Field;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::biome::{Ground, Vegetation};
+use crate::civilization::Civilization;
+use crate::harbor;
+use crate::hazard;
+use crate::hypsometry::TerrainLevels;
+use crate::marine::MarineBiome;
+use crate::mask::MaskClass;
+use crate::pass::Pass;
+use crate::river;
+use crate::traversal;
+use crate::wildlife;
+use crate::relief::{Curvature, Landform};
+use crate::{Path, TerrainCategory, Vec2, World};
+
+// Everything a generation made of a world, and the edits on top of it, so that the world can
+// be put back without generating it again. The generation settings are not saved: they are
+// only used by the next generation, and come from the world the save is restored into. The
+// downhill directions are recomputed from the heights, the lakes from the water depth, and
+// the difficulty, infinite at sea which JSON can't hold, from the terrain.
+#[derive(Serialize, Deserialize)]
+pub struct SavedWorld {
+    // Fingerprint of the map the world was generated on
+    map: u64,
+    heightmap: Field<f64>,
+    terrain_category: Field<TerrainCategory>,
+    levels: TerrainLevels,
+    slope: Field<f64>,
+    aspect: Field<f64>,
+    curvature: Field<Curvature>,
+    landform: Field<Landform>,
+    passes: Vec<Pass>,
+    temperature: Field<f64>,
+    celsius: Field<f64>,
+    wind: Field<Vec2>,
+    rainfall: Field<f64>,
+    evapotranspiration: Field<f64>,
+    humidity: Field<f64>,
+    drainage: Field<f64>,
+    water_depth: Field<f64>,
+    river_cutoff: f64,
+    sea_level: f64,
+    rivers: Vec<Path>,
+    is_river: Field<bool>,
+    ground: Field<Ground>,
+    vegetation: Field<Vegetation>,
+    marine: Field<MarineBiome>,
+    marine_productivity: Field<f64>,
+    fog: Field<f64>,
+    mask: Field<MaskClass>,
+    pins: Field<Option<f64>>,
+    // The features, tags, cultures and realms, in the format of World::export_civilization
+    civilization: String,
+}
+
+impl World<'_> {
+    pub fn save(&self) -> SavedWorld {
+        SavedWorld {
+            map: self.poly.fingerprint(),
+            heightmap: self.heightmap.clone(),
+            terrain_category: self.terrain_category.clone(),
+            levels: self.levels,
+            slope: self.slope.clone(),
+            aspect: self.aspect.clone(),
+            curvature: self.curvature.clone(),
+            landform: self.landform.clone(),
+            passes: self.passes.clone(),
+            temperature: self.temperature.clone(),
+            celsius: self.celsius.clone(),
+            wind: self.wind.clone(),
+            rainfall: self.rainfall.clone(),
+            evapotranspiration: self.evapotranspiration.clone(),
+            humidity: self.humidity.clone(),
+            drainage: self.drainage.clone(),
+            water_depth: self.water_depth.clone(),
+            river_cutoff: self.river_cutoff,
+            sea_level: self.sea_level,
+            rivers: self.rivers.clone(),
+            is_river: self.is_river.clone(),
+            ground: self.ground.clone(),
+            vegetation: self.vegetation.clone(),
+            marine: self.marine.clone(),
+            marine_productivity: self.marine_productivity.clone(),
+            fog: self.fog.clone(),
+            mask: self.mask.clone(),
+            pins: self.pins.clone(),
+            civilization: self.export_civilization(),
+        }
+    }
+
+    // Fails, leaving the world as it was, when the save is of another map or is inconsistent
+    pub fn restore(&mut self, saved: SavedWorld) -> Result<(), String> {
+        if saved.map != self.poly.fingerprint() {
+            return Err("The saved world was generated on a different map".to_owned());
+        }
+        saved.check(self.poly.cells().count())?;
+
+        // The civilization is checked against the map before anything changes. Its features
+        // come back as they were saved, without matching them against the current ones.
+        let civilization = Civilization::from_text(self.poly, &saved.civilization)?;
+
+        self.heightmap = saved.heightmap;
+        self.terrain_category = saved.terrain_category;
+        self.levels = saved.levels;
+        self.slope = saved.slope;
+        self.aspect = saved.aspect;
+        self.curvature = saved.curvature;
+        self.landform = saved.landform;
+        self.passes = saved.passes;
+        self.temperature = saved.temperature;
+        self.celsius = saved.celsius;
+        self.wind = saved.wind;
+        self.rainfall = saved.rainfall;
+        self.evapotranspiration = saved.evapotranspiration;
+        self.humidity = saved.humidity;
+        self.drainage = saved.drainage;
+        self.water_depth = saved.water_depth;
+        self.river_cutoff = saved.river_cutoff;
+        self.sea_level = saved.sea_level;
+        self.rivers = saved.rivers;
+        self.river_of = river::index(self.poly, &self.rivers);
+        self.is_river = saved.is_river;
+        self.ground = saved.ground;
+        self.vegetation = saved.vegetation;
+        self.marine = saved.marine;
+        self.marine_productivity = saved.marine_productivity;
+        self.fog = saved.fog;
+        self.mask = saved.mask;
+        self.pins = saved.pins;
+        self.features = civilization.features();
+        self.tags = civilization.tags();
+        self.cultures = civilization.cultures();
+        self.culture = civilization.culture(self.poly);
+        self.territory = civilization.territory(self.poly);
+        self.compute_downhill();
+        self.detect_lakes();
+        self.find_fresh_water();
+        self.classify_land_biomes();
+        self.difficulty = traversal::difficulty(self);
+        self.harbor = harbor::suitability(self);
+        self.hazards = hazard::hazards(self);
+        self.fauna = wildlife::fauna(self);
+        self.start_run();
+        Ok(())
+    }
+}
+
+impl SavedWorld {
+    // Every field has a value per cell, and every cell is on the map
+    fn check(&self, cells: usize) -> Result<(), String> {
+        let lengths = [
+            ("heightmap", self.heightmap.len()),
+            ("terrain", self.terrain_category.len()),
+            ("slope", self.slope.len()),
+            ("aspect", self.aspect.len()),
+            ("curvature", self.curvature.len()),
+            ("landform", self.landform.len()),
+            ("temperature", self.temperature.len()),
+            ("celsius", self.celsius.len()),
+            ("wind", self.wind.len()),
+            ("rainfall", self.rainfall.len()),
+            ("evapotranspiration", self.evapotranspiration.len()),
+            ("humidity", self.humidity.len()),
+            ("drainage", self.drainage.len()),
+            ("water depth", self.water_depth.len()),
+            ("rivers", self.is_river.len()),
+            ("ground", self.ground.len()),
+            ("vegetation", self.vegetation.len()),
+            ("marine", self.marine.len()),
+            ("marine productivity", self.marine_productivity.len()),
+            ("fog", self.fog.len()),
+            ("mask", self.mask.len()),
+            ("pins", self.pins.len()),
+        ];
+        if let Some((name, len)) = lengths.iter().find(|&&(_, len)| len != cells) {
+            return Err(format!("The saved {} has {} cells, the map {}", name, len, cells));
+        }
+
+        let on_map = |cell: &CellId| cell.idx() < cells;
+        let rivers_on_map = self.rivers.iter().all(|river| river.cells().iter().all(on_map));
+        if !rivers_on_map || !self.passes.iter().all(|pass| on_map(&pass.cell)) {
+            return Err("The saved rivers or passes are not on the map".to_owned());
+        }
+        // A river has at least its source
+        if self.rivers.iter().any(|river| river.cells().is_empty()) {
+            return Err("The saved rivers include an empty one".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::SeedTree;
+
+    fn features(world: &World) -> Vec<(usize, &'static str, Option<String>, Vec<CellId>)> {
+        world.features().iter()
+            .map(|feature| (feature.id.idx(), feature.kind.name(), feature.name.clone(), feature.geometry.cells().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(42));
+        assert!(world.features().iter().count() > 0);
+
+        let json = serde_json::to_string(&world.save()).unwrap();
+        let saved: SavedWorld = serde_json::from_str(&json).unwrap();
+        let mut restored = World::new(&poly);
+        restored.restore(saved).unwrap();
+
+        // serde_json reads floats back to within a unit in the last place, not always exactly
+        assert!(poly.cells().all(|(id, _)| (restored.heightmap()[id] - world.heightmap()[id]).abs() < 1e-12));
+        assert_eq!(features(&restored), features(&world));
+        let rivers = |world: &World| world.rivers().iter().map(|river| river.cells().to_vec()).collect::<Vec<_>>();
+        assert_eq!(rivers(&restored), rivers(&world));
+        assert!(poly.cells().all(|(id, _)| {
+            let (a, b) = (restored.difficulty()[id], world.difficulty()[id]);
+            a == b || (a - b).abs() < 1e-9
+        }));
+    }
+
+    #[test]
+    fn empty_rivers_are_refused() {
+        let poly = PolyMap::with_seed(100, 60, 6.0, 1);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(42));
+        let mut saved = world.save();
+        saved.rivers.push(Path(vec![]));
+        assert!(World::new(&poly).restore(saved).is_err());
+    }
+}
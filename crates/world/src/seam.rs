@@ -0,0 +1,198 @@
+use polymap::*;
+use polymap::field::Field;
+
+use crate::World;
+
+// How far into the map, in map units, the heights of a seam reach, fading out
+const SEAM_BAND: f64 = 40.0;
+
+// A side of the map, y downwards
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Side {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::North, Side::South, Side::East, Side::West];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Side::North => "north",
+            Side::South => "south",
+            Side::East => "east",
+            Side::West => "west",
+        }
+    }
+
+    // The side of the next map that meets this one
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::North => Side::South,
+            Side::South => Side::North,
+            Side::East => Side::West,
+            Side::West => Side::East,
+        }
+    }
+
+    // How long the side is, in map units
+    fn length(&self, poly: &PolyMap) -> f64 {
+        match self {
+            Side::North | Side::South => poly.width() as f64,
+            Side::East | Side::West => poly.height() as f64,
+        }
+    }
+
+    // How far along the side a point is, and how far from it
+    fn locate(&self, poly: &PolyMap, (x, y): (f64, f64)) -> (f64, f64) {
+        match self {
+            Side::North => (x, y),
+            Side::South => (x, poly.height() as f64 - y),
+            Side::West => (y, x),
+            Side::East => (y, poly.width() as f64 - x),
+        }
+    }
+}
+
+impl std::str::FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Side::ALL.into_iter()
+            .find(|side| side.name() == s)
+            .ok_or_else(|| format!("Unknown side '{}', expected one of north, south, east, west", s))
+    }
+}
+
+// What a map hands on to the one next to it along a side: the heights along the side, and
+// the rivers flowing out over it with their water. Positions run along the side, from the
+// top or the left, in map units.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Seam {
+    pub side: Side,
+    pub length: f64,
+    pub heights: Vec<(f64, f64)>,
+    pub rivers: Vec<(f64, f64)>,
+}
+
+impl Seam {
+    // The height where the seam meets the given position of the other map's side, from the
+    // closest samples on either side of it
+    fn height_at(&self, position: f64) -> Option<f64> {
+        let after = self.heights.iter().position(|&(at, _)| at >= position);
+        match after {
+            Some(0) => self.heights.first().map(|&(_, height)| height),
+            Some(idx) => {
+                let ((a, ha), (b, hb)) = (self.heights[idx - 1], self.heights[idx]);
+                let t = if b > a { (position - a) / (b - a) } else { 0.0 };
+                Some(ha + (hb - ha) * t)
+            }
+            None => self.heights.last().map(|&(_, height)| height),
+        }
+    }
+
+    // One line for the side and the length, then a line for each height and river
+    pub fn to_text(&self) -> String {
+        let mut text = format!("seam\t{}\t{}\n", self.side.name(), self.length);
+        for (position, height) in self.heights.iter() {
+            text.push_str(&format!("height\t{}\t{}\n", position, height));
+        }
+        for (position, flux) in self.rivers.iter() {
+            text.push_str(&format!("river\t{}\t{}\n", position, flux));
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut seam: Option<Seam> = None;
+        for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let error = |what: &str| format!("Line {} of the seam: {}", number + 1, what);
+            let fields: Vec<_> = line.split('\t').collect();
+            let number_at = |idx: usize| fields.get(idx)
+                .and_then(|field| field.parse::<f64>().ok())
+                .ok_or_else(|| error("expected a number"));
+            match (fields[0], seam.as_mut()) {
+                ("seam", None) => {
+                    let side = fields.get(1).ok_or_else(|| error("expected a side"))?.parse()?;
+                    seam = Some(Seam { side, length: number_at(2)?, heights: vec![], rivers: vec![] });
+                }
+                ("height", Some(seam)) => seam.heights.push((number_at(1)?, number_at(2)?)),
+                ("river", Some(seam)) => seam.rivers.push((number_at(1)?, number_at(2)?)),
+                (_, None) => return Err(error("expected the seam line first")),
+                (other, Some(_)) => return Err(error(&format!("unknown entry '{}'", other))),
+            }
+        }
+        let mut seam = seam.ok_or_else(|| "The seam is empty".to_owned())?;
+        seam.heights.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(seam)
+    }
+}
+
+// The border cells along a side, those closer to it than to any other
+fn border_cells(poly: &PolyMap, side: Side) -> impl Iterator<Item = (CellId, f64)> + '_ {
+    poly.borders().filter_map(move |(id, cell)| {
+        let (position, distance) = side.locate(poly, cell.center());
+        let closest = Side::ALL.into_iter()
+            .all(|other| other == side || other.locate(poly, cell.center()).1 >= distance);
+        closest.then_some((id, position))
+    })
+}
+
+impl World<'_> {
+    // The heights along the side and the rivers leaving over it, for the map next to it
+    pub fn export_seam(&self, side: Side) -> Seam {
+        let mut heights: Vec<_> = border_cells(self.poly, side)
+            .map(|(id, position)| (position, self.heightmap[id]))
+            .collect();
+        heights.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let rivers = border_cells(self.poly, side)
+            .filter(|&(id, _)| self.is_river[id])
+            .map(|(id, position)| (position, self.drainage[id]))
+            .collect();
+        Seam { side, length: side.length(self.poly), heights, rivers }
+    }
+
+    pub(crate) fn blend_seams(&mut self) {
+        let poly = self.poly;
+        for seam in self.seams.iter() {
+            let side = seam.side.opposite();
+            let scale = side.length(poly) / seam.length.max(f64::EPSILON);
+            // The border cells take the heights of the seam as they are, so that they match
+            let mut on_side = Field::uniform(poly, false);
+            for (id, _) in border_cells(poly, side) {
+                on_side[id] = true;
+            }
+            self.heightmap.update(|id, height| {
+                let (position, distance) = side.locate(poly, poly[id].center());
+                let weight = if on_side[id] { 1.0 } else { 1.0 - distance / SEAM_BAND };
+                if weight <= 0.0 {
+                    return;
+                }
+                if let Some(target) = seam.height_at(position / scale) {
+                    *height = *height * (1.0 - weight) + target * weight;
+                }
+            });
+        }
+    }
+
+    // The water of the rivers of the seams, on the border cells they flow into
+    pub(crate) fn seam_inflow(&self) -> Field<f64> {
+        let poly = self.poly;
+        let mut inflow = Field::uniform(poly, 0.0);
+        for seam in self.seams.iter() {
+            let side = seam.side.opposite();
+            let scale = side.length(poly) / seam.length.max(f64::EPSILON);
+            let cells: Vec<_> = border_cells(poly, side).collect();
+            for &(position, flux) in seam.rivers.iter() {
+                let closest = cells.iter()
+                    .min_by(|a, b| (a.1 - position * scale).abs().total_cmp(&(b.1 - position * scale).abs()));
+                if let Some(&(id, _)) = closest {
+                    inflow[id] += flux;
+                }
+            }
+        }
+        inflow
+    }
+}
@@ -0,0 +1,97 @@
+use polymap::*;
+
+use crate::World;
+
+// Named after the northern hemisphere; the south has the opposite season
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [Season::Spring, Season::Summer, Season::Autumn, Season::Winter];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Autumn => "autumn",
+            Season::Winter => "winter",
+        }
+    }
+
+    // How far north the sun stands, in parts of the axial tilt
+    fn declination(&self) -> f64 {
+        match self {
+            Season::Spring | Season::Autumn => 0.0,
+            Season::Summer => 1.0,
+            Season::Winter => -1.0,
+        }
+    }
+}
+
+impl std::str::FromStr for Season {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Season::ALL.into_iter()
+            .find(|season| season.name() == s)
+            .ok_or_else(|| format!("Unknown season '{}', expected one of spring, summer, autumn, winter", s))
+    }
+}
+
+// Where the map lies on its planet and how the planet is tilted. The top of the map is at the
+// northern latitude and the bottom at the southern one, in degrees, north positive.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Orbit {
+    pub axial_tilt: f64,
+    pub north: f64,
+    pub south: f64,
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        Self { axial_tilt: 23.44, north: 90.0, south: -90.0 }
+    }
+}
+
+impl Orbit {
+    // In degrees, of a point at the given distance from the top of the map
+    pub fn latitude(&self, poly: &PolyMap, y: f64) -> f64 {
+        let t = (y / poly.height() as f64).clamp(0.0, 1.0);
+        self.north + (self.south - self.north) * t
+    }
+
+    // How far a latitude is from the poles, 0 at the poles and 1 at the equator, the
+    // normalized temperature over the year
+    pub fn warmth(latitude: f64) -> f64 {
+        1.0 - latitude.abs().min(90.0) / 90.0
+    }
+
+    // How much warmer than over the year a latitude is in a season, in parts of the difference
+    // between the equator and the poles. Nothing at the equator, and the most at the poles,
+    // where the days grow longest and shortest.
+    pub fn seasonal_shift(&self, latitude: f64, season: Season) -> f64 {
+        let sun = season.declination() * self.axial_tilt;
+        let hemisphere = if latitude >= 0.0 { 1.0 } else { -1.0 };
+        hemisphere * sun / 90.0 * latitude.abs().min(90.0) / 90.0
+    }
+}
+
+impl World<'_> {
+    // In °C, of a cell in the season
+    pub fn cell_temperature_at(&self, cell: CellId, season: Season) -> f64 {
+        let latitude = self.orbit.latitude(self.poly, self.poly[cell].center().1);
+        let scale = self.temperature_scale;
+        self.celsius[cell] + self.orbit.seasonal_shift(latitude, season) * (scale.equator - scale.poles)
+    }
+
+    // The normalized temperature of a cell in the season, like World::temperature
+    pub fn seasonal_temperature(&self, cell: CellId, season: Season) -> f64 {
+        let latitude = self.orbit.latitude(self.poly, self.poly[cell].center().1);
+        (self.temperature[cell] + self.orbit.seasonal_shift(latitude, season)).clamp(0.0, 1.0)
+    }
+}
@@ -0,0 +1,56 @@
+use polymap::CellId;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+// A seed from which named child seeds are derived by hashing, e.g.
+// `seeds.child("hydrology").child("rivers")`. Every stage draws its randomness from its own
+// child, so adding a stage (or a plugin) does not shift the random numbers of the others.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct SeedTree(u64);
+
+impl SeedTree {
+    pub fn new(seed: u64) -> Self {
+        SeedTree(seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.0
+    }
+
+    pub fn child(&self, name: &str) -> SeedTree {
+        SeedTree(mix(self.0 ^ fnv1a(name.as_bytes())))
+    }
+
+    // Children for numbered things, like cells or iterations
+    pub fn indexed(&self, index: u64) -> SeedTree {
+        SeedTree(mix(self.0 ^ mix(index.wrapping_add(0x9e3779b97f4a7c15))))
+    }
+
+    // The same child for the same cell of a map, whatever else was drawn before
+    pub fn cell(&self, cell: CellId) -> SeedTree {
+        self.indexed(cell.idx() as u64)
+    }
+
+    pub fn rng(&self) -> SmallRng {
+        SmallRng::seed_from_u64(self.0)
+    }
+}
+
+// Seeds typed by people, usually memorable words. A number is taken as it is, so that numeric
+// seeds can be typed in too; any other text is hashed.
+pub fn seed_from_text(text: &str) -> u64 {
+    let text = text.trim();
+    text.parse().unwrap_or_else(|_| mix(fnv1a(text.as_bytes())))
+}
+
+// 64 bit FNV-1a, stable across platforms and releases unlike the std hashers
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// The splitmix64 finalizer, so that close inputs give unrelated seeds
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
@@ -1,14 +1,21 @@
-use noise::Perlin;
+use noise::{NoiseFn, OpenSimplex, Perlin, Seedable, SuperSimplex, Worley};
 use rand::Rng;
 use polymap::{*, field::Field};
 
+use crate::symmetry::Symmetry;
+use crate::terrain_noise::NoiseKind;
+
 pub trait SpatialFunction: Send + Sync + Sized {
     fn value(&self, x: f64, y: f64) -> f64;
 
     fn scale(self, intensity: f64) -> Scaled<Self> { Scaled(self, intensity) }
 
+    fn symmetric(self, symmetry: Symmetry, width: f64, height: f64) -> Symmetric<Self> {
+        Symmetric { inner: self, symmetry, width, height }
+    }
+
     fn add_to_field(&self, poly: &PolyMap, field: &mut Field<f64>) {
-        field.update(|id, field_value| {
+        field.par_update(|id, field_value| {
             let (cx, cy) = poly[id].center();
             *field_value += self.value(cx, cy)
         })
@@ -23,6 +30,19 @@ impl <T:SpatialFunction> SpatialFunction for Scaled<T> {
     }
 }
 
+pub struct Symmetric<T:SpatialFunction> {
+    inner: T,
+    symmetry: Symmetry,
+    width: f64,
+    height: f64,
+}
+
+impl <T:SpatialFunction> SpatialFunction for Symmetric<T> {
+    fn value(&self, x: f64, y: f64) -> f64 {
+        let (x, y) = self.symmetry.fold(self.width, self.height, x, y);
+        self.inner.value(x, y)
+    }
+}
 
 pub struct Slope {
     m: f64,
@@ -46,44 +66,33 @@ impl SpatialFunction for Slope {
     }
 }
 
-pub struct Band {
-    m: f64,
-    cx: f64,
-    cy: f64,
-    radius: f64,
+enum Backend {
+    Perlin(Perlin),
+    OpenSimplex(OpenSimplex),
+    SuperSimplex(SuperSimplex),
+    Worley(Worley),
 }
 
-impl Band {
-    pub fn new(cx: f64, cy: f64, m: f64, radius: f64) -> Self {
-        Self { cx, cy, m, radius }
-    }
-}
-
-impl SpatialFunction for Band {
-    fn value(&self, x: f64, y: f64) -> f64 {
-        let distance = ((x - self.cx) * self.m - (y - self.cy)).abs();
-        (1.0 - distance / self.radius).max(0.0)
-    }
-}
-
-pub struct PerlinField {
+pub struct NoiseField {
     pub frequency: f64,
     pub x_shift: f64,
     pub y_shift: f64,
-    noise: Perlin,
+    noise: Backend,
 }
 
-impl PerlinField {
-    pub fn with_rng(frequency: f64, rng: &mut impl Rng) -> Self {
+impl NoiseField {
+    // Every kind of noise is seeded as well as shifted. A shift alone only moves the same
+    // pattern around, so worlds of different seeds looked alike.
+    pub fn with_rng(kind: NoiseKind, frequency: f64, rng: &mut impl Rng) -> Self {
         let x_shift = rng.gen_range(0..100) as f64;
         let y_shift = rng.gen_range(0..100) as f64;
 
-        Self::new(x_shift, y_shift, frequency)
-    }
-
-    pub fn new(x_shift: f64, y_shift: f64, frequency: f64) -> Self {
-        let noise = Perlin::new();
-
+        let noise = match kind {
+            NoiseKind::Perlin => Backend::Perlin(Perlin::new().set_seed(rng.gen())),
+            NoiseKind::Simplex => Backend::OpenSimplex(OpenSimplex::new().set_seed(rng.gen())),
+            NoiseKind::OpenSimplex2 => Backend::SuperSimplex(SuperSimplex::new().set_seed(rng.gen())),
+            NoiseKind::Worley => Backend::Worley(Worley::new().set_seed(rng.gen())),
+        };
         Self {
             noise,
             frequency,
@@ -93,14 +102,15 @@ impl PerlinField {
     }
 }
 
-impl SpatialFunction for PerlinField {
+impl SpatialFunction for NoiseField {
     fn value(&self, x: f64, y: f64) -> f64 {
-        use noise::NoiseFn;
-
-        let px = self.x_shift + x * self.frequency;
-        let py = self.y_shift + y * self.frequency;
-        let noise = self.noise.get([px, py]);
-        noise
+        let point = [self.x_shift + x * self.frequency, self.y_shift + y * self.frequency];
+        match &self.noise {
+            Backend::Perlin(noise) => noise.get(point),
+            Backend::OpenSimplex(noise) => noise.get(point),
+            Backend::SuperSimplex(noise) => noise.get(point),
+            Backend::Worley(noise) => noise.get(point),
+        }
     }
 }
 
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::features::Geometry;
+use crate::layers::{Domain, LayerKind, LayerValue};
+use crate::World;
+
+// Loaded with `sqlite3 world.sqlite < world.sql`, or by write_sqlite. Coordinates are in map units, with y downwards.
+const SCHEMA: &str = "\
+-- world: key-value pairs describing the whole world (width, height, sea_level, map)
+-- cells: one row per cell, with its center and a column per layer of the world (see
+--   layers::LAYERS, vectors in two columns), temperatures in °C, elevations in m and rainfall
+--   and water balance (rainfall less potential evapotranspiration) in mm per year. Numbers
+--   without a finite value, such as the difficulty at sea, are NULL
+-- vertices: the corners of the cells, shared between the cells meeting there
+-- cell_vertices: the corners of each cell, in order around it
+-- edges: pairs of neighboring cells, with the two corners of the side they share
+-- cell_tags, feature_tags: key-value tags, with the values as text
+-- rivers, river_cells: rivers from source to mouth, with the water through each cell
+-- features, feature_cells: named features and their cells, in order for paths
+-- cell_bounds, feature_bounds: R-tree indices of the bounding boxes of cells and features
+CREATE TABLE world (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE vertices (id INTEGER PRIMARY KEY, x REAL NOT NULL, y REAL NOT NULL);
+CREATE TABLE cell_vertices (cell INTEGER NOT NULL, position INTEGER NOT NULL, vertex INTEGER NOT NULL, PRIMARY KEY (cell, position));
+CREATE TABLE edges (cell_a INTEGER NOT NULL, cell_b INTEGER NOT NULL, vertex_a INTEGER, vertex_b INTEGER, PRIMARY KEY (cell_a, cell_b));
+CREATE TABLE cell_tags (cell INTEGER NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL, PRIMARY KEY (cell, key));
+CREATE TABLE rivers (id INTEGER PRIMARY KEY, source INTEGER NOT NULL, mouth INTEGER NOT NULL, length REAL NOT NULL);
+CREATE TABLE river_cells (river INTEGER NOT NULL, position INTEGER NOT NULL, cell INTEGER NOT NULL, flux REAL NOT NULL, PRIMARY KEY (river, position));
+CREATE TABLE features (id INTEGER PRIMARY KEY, kind TEXT NOT NULL, name TEXT, geometry TEXT NOT NULL);
+CREATE TABLE feature_cells (feature INTEGER NOT NULL, position INTEGER NOT NULL, cell INTEGER NOT NULL, PRIMARY KEY (feature, position));
+CREATE TABLE feature_tags (feature INTEGER NOT NULL, key TEXT NOT NULL, value TEXT NOT NULL, PRIMARY KEY (feature, key));
+CREATE VIRTUAL TABLE cell_bounds USING rtree(id, min_x, max_x, min_y, max_y);
+CREATE VIRTUAL TABLE feature_bounds USING rtree(id, min_x, max_x, min_y, max_y);
+CREATE INDEX cell_vertices_by_vertex ON cell_vertices (vertex);
+CREATE INDEX river_cells_by_cell ON river_cells (cell);
+CREATE INDEX feature_cells_by_cell ON feature_cells (cell);
+";
+
+// The whole world as a SQLite script, which creates the tables of the schema above and fills
+// them in a single transaction
+pub fn to_sql(world: &World) -> String {
+    let poly = world.poly();
+    let mut sql = String::from("BEGIN TRANSACTION;\n");
+    sql.push_str(SCHEMA);
+    let layers: Vec<_> = world.layers().iter().filter(|layer| layer.domain == Domain::Cell).collect();
+    let mut columns = String::from("CREATE TABLE cells (\n    id INTEGER PRIMARY KEY,\n    x REAL NOT NULL,\n    y REAL NOT NULL,\n    is_border INTEGER NOT NULL");
+    for layer in layers.iter() {
+        let _ = match layer.kind {
+            LayerKind::Scalar => write!(columns, ",\n    {} REAL", layer.name),
+            LayerKind::Categorical => write!(columns, ",\n    {} TEXT NOT NULL", layer.name),
+            LayerKind::Flag => write!(columns, ",\n    {} INTEGER NOT NULL", layer.name),
+            LayerKind::Vector => write!(columns, ",\n    {0}_x REAL,\n    {0}_y REAL", layer.name),
+        };
+    }
+    let _ = writeln!(sql, "{}\n);", columns);
+
+    for (key, value) in [
+        ("width", poly.width().to_string()),
+        ("height", poly.height().to_string()),
+        ("sea_level", world.sea_level().to_string()),
+        ("map", poly.fingerprint().to_string()),
+    ] {
+        let _ = writeln!(sql, "INSERT INTO world VALUES ({}, {});", text(key), text(&value));
+    }
+
+    for (id, cell) in poly.cells() {
+        let (x, y) = cell.center();
+        let mut row = format!("{}, {}, {}, {}", id.idx(), x, y, cell.is_border() as u8);
+        for layer in layers.iter() {
+            let _ = match layer.value(world, id) {
+                LayerValue::Scalar(value) => write!(row, ", {}", real(value)),
+                LayerValue::Category(name) => write!(row, ", {}", text(&name)),
+                LayerValue::Flag(value) => write!(row, ", {}", value as u8),
+                LayerValue::Vector(x, y) => write!(row, ", {}, {}", real(x), real(y)),
+            };
+        }
+        let _ = writeln!(sql, "INSERT INTO cells VALUES ({});", row);
+        let (min_x, max_x, min_y, max_y) = bounds(cell.polygon().exterior().points_iter().map(|p| (p.x(), p.y())));
+        let _ = writeln!(sql, "INSERT INTO cell_bounds VALUES ({}, {}, {}, {}, {});", id.idx(), min_x, max_x, min_y, max_y);
+        for (key, value) in world.tags().of_cell(id) {
+            let _ = writeln!(sql, "INSERT INTO cell_tags VALUES ({}, {}, {});", id.idx(), text(key), text(&value.to_string()));
+        }
+    }
+
+    // Corners are the same points in every cell meeting there
+    let mut vertices: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut corners: Vec<Vec<usize>> = vec![];
+    for (id, cell) in poly.cells() {
+        let mut points: Vec<_> = cell.polygon().exterior().points_iter().map(|p| (p.x(), p.y())).collect();
+        // The ring is closed
+        points.pop();
+        let mut ids = vec![];
+        for (position, (x, y)) in points.into_iter().enumerate() {
+            let next = vertices.len();
+            let vertex = *vertices.entry((x.to_bits(), y.to_bits())).or_insert_with(|| {
+                let _ = writeln!(sql, "INSERT INTO vertices VALUES ({}, {}, {});", next, x, y);
+                next
+            });
+            let _ = writeln!(sql, "INSERT INTO cell_vertices VALUES ({}, {}, {});", id.idx(), position, vertex);
+            ids.push(vertex);
+        }
+        corners.push(ids);
+    }
+    for (id, cell) in poly.cells() {
+        for &neighbor in cell.neighbors().iter().filter(|&&neighbor| neighbor > id) {
+            let shared: Vec<_> = corners[id.idx()].iter().filter(|vertex| corners[neighbor.idx()].contains(vertex)).collect();
+            let vertex = |idx: usize| shared.get(idx).map(|vertex| vertex.to_string()).unwrap_or_else(|| "NULL".to_owned());
+            let _ = writeln!(sql, "INSERT INTO edges VALUES ({}, {}, {}, {});", id.idx(), neighbor.idx(), vertex(0), vertex(1));
+        }
+    }
+
+    for river in world.river_list() {
+        let _ = writeln!(sql, "INSERT INTO rivers VALUES ({}, {}, {}, {});",
+            river.idx(), river.source().idx(), river.mouth().idx(), river.length());
+        for (position, (&cell, flux)) in river.cells().iter().zip(river.flux()).enumerate() {
+            let _ = writeln!(sql, "INSERT INTO river_cells VALUES ({}, {}, {}, {});", river.idx(), position, cell.idx(), flux);
+        }
+    }
+
+    for feature in world.features().iter() {
+        let id = feature.id.idx();
+        let geometry = match feature.geometry {
+            Geometry::Point(_) => "point",
+            Geometry::Path(_) => "path",
+            Geometry::Area(_) => "area",
+        };
+        let name = feature.name.as_deref().map(text).unwrap_or_else(|| "NULL".to_owned());
+        let _ = writeln!(sql, "INSERT INTO features VALUES ({}, {}, {}, {});", id, text(feature.kind.name()), name, text(geometry));
+        let cells = feature.geometry.cells();
+        for (position, cell) in cells.iter().enumerate() {
+            let _ = writeln!(sql, "INSERT INTO feature_cells VALUES ({}, {}, {});", id, position, cell.idx());
+        }
+        if !cells.is_empty() {
+            let (min_x, max_x, min_y, max_y) = bounds(cells.iter()
+                .flat_map(|&cell| poly[cell].polygon().exterior().points_iter().map(|p| (p.x(), p.y()))));
+            let _ = writeln!(sql, "INSERT INTO feature_bounds VALUES ({}, {}, {}, {}, {});", id, min_x, max_x, min_y, max_y);
+        }
+        for (key, value) in feature.tags.iter() {
+            let _ = writeln!(sql, "INSERT INTO feature_tags VALUES ({}, {}, {});", id, text(key), text(&value.to_string()));
+        }
+    }
+
+    sql.push_str("COMMIT;\n");
+    sql
+}
+
+// The script run into a new SQLite database at the path, replacing any file there
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite(world: &World, path: &str) -> Result<(), String> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(format!("Failed to replace {}: {}", path, err)),
+        _ => {}
+    }
+    let connection = rusqlite::Connection::open(path).map_err(|err| format!("Failed to create {}: {}", path, err))?;
+    connection.execute_batch(&to_sql(world)).map_err(|err| format!("Failed to write {}: {}", path, err))
+}
+
+fn bounds(points: impl Iterator<Item = (f64, f64)>) -> (f64, f64, f64, f64) {
+    points.fold((f64::MAX, f64::MIN, f64::MAX, f64::MIN), |(min_x, max_x, min_y, max_y), (x, y)| {
+        (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y))
+    })
+}
+
+// A SQL string literal
+fn text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+// SQL has no literal for infinities or NaN
+fn real(value: f64) -> String {
+    if value.is_finite() { value.to_string() } else { "NULL".to_owned() }
+}
+
+#[cfg(test)]
+mod tests {
+    use polymap::PolyMap;
+
+    use super::*;
+    use crate::seed::SeedTree;
+
+    #[test]
+    fn writes_only_finite_numbers() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(42));
+        assert!(poly.cells().any(|(id, _)| world.difficulty()[id].is_infinite()));
+        let sql = to_sql(&world);
+        for line in sql.lines().filter(|line| line.starts_with("INSERT")) {
+            let values = line.split(['(', ',', ')']).map(str::trim);
+            for value in values {
+                assert!(!matches!(value, "inf" | "-inf" | "NaN"), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn loads_into_sqlite() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(42));
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute_batch(&to_sql(&world)).unwrap();
+        let count = |query: &str| db.query_row(query, [], |row| row.get::<_, i64>(0)).unwrap() as usize;
+
+        assert_eq!(count("SELECT COUNT(*) FROM cells"), poly.cells().count());
+        assert_eq!(count("SELECT COUNT(*) FROM rivers"), world.river_list().count());
+        assert_eq!(count("SELECT COUNT(*) FROM features"), world.features().iter().count());
+        let infinite = poly.cells().filter(|(id, _)| world.difficulty()[*id].is_infinite()).count();
+        assert_eq!(count("SELECT COUNT(*) FROM cells WHERE difficulty IS NULL"), infinite);
+        // The R-tree finds each cell around its center
+        for (id, cell) in poly.cells().step_by(37) {
+            let (x, y) = cell.center();
+            let query = format!("SELECT COUNT(*) FROM cell_bounds WHERE id = {} AND min_x <= {1} AND max_x >= {1} AND min_y <= {2} AND max_y >= {2}", id.idx(), x, y);
+            assert_eq!(count(&query), 1);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use polymap::*;
+
+use crate::mask::MaskClass;
+use crate::World;
+
+// A reusable terrain patch: a square grid of target heights, blend weights and biome
+// overrides, covering [-1, 1] x [-1, 1] in stamp space
+pub struct Stamp {
+    name: String,
+    size: usize,
+    heights: Vec<f64>,
+    alpha: Vec<f64>,
+    biome: Vec<MaskClass>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub x: f64,
+    pub y: f64,
+    // Radians, counter-clockwise
+    pub rotation: f64,
+    // Distance in map units from the stamp center to the middle of its edges
+    pub radius: f64,
+}
+
+const BUILTIN_SIZE: usize = 64;
+// Grid points along a side of a copied region
+const COPY_SIZE: usize = 64;
+
+impl Stamp {
+    pub fn builtins() -> Vec<Stamp> {
+        vec![Self::mountain_massif(), Self::volcanic_island(), Self::river_valley()]
+    }
+
+    pub fn mountain_massif() -> Stamp {
+        Self::with_fn("Mountain massif", BUILTIN_SIZE, |u, v| {
+            let r = (u * u + v * v).sqrt();
+            // A few radial ridges on top of a dome
+            let ridges = 0.1 * (5.0 * v.atan2(u)).cos() * r;
+            let height = 0.95 - 0.5 * r + ridges;
+            let biome = if r < 0.5 { MaskClass::Mountain } else { MaskClass::Free };
+            (height, edge_falloff(r), biome)
+        })
+    }
+
+    pub fn volcanic_island() -> Stamp {
+        Self::with_fn("Volcanic island", BUILTIN_SIZE, |u, v| {
+            let r = (u * u + v * v).sqrt();
+            let height = if r < 0.12 {
+                // The crater
+                0.7
+            } else if r < 0.7 {
+                0.9 - 0.85 * (r - 0.12)
+            } else {
+                0.15
+            };
+            let biome = if r < 0.7 { MaskClass::Free } else { MaskClass::Sea };
+            (height, edge_falloff(r), biome)
+        })
+    }
+
+    pub fn river_valley() -> Stamp {
+        Self::with_fn("River valley", BUILTIN_SIZE, |u, v| {
+            // A valley along the u axis, descending towards positive u
+            let height = 0.4 - 0.05 * u + 0.4 * v.abs();
+            let r = u.abs().max(v.abs());
+            (height, edge_falloff(r), MaskClass::Free)
+        })
+    }
+
+    pub fn with_fn(name: &str, size: usize, f: impl Fn(f64, f64) -> (f64, f64, MaskClass)) -> Stamp {
+        let mut heights = Vec::with_capacity(size * size);
+        let mut alpha = Vec::with_capacity(size * size);
+        let mut biome = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let u = 2.0 * (col as f64 + 0.5) / size as f64 - 1.0;
+                let v = 2.0 * (row as f64 + 0.5) / size as f64 - 1.0;
+                let (h, a, b) = f(u, v);
+                heights.push(h);
+                alpha.push(a);
+                biome.push(b);
+            }
+        }
+        Stamp { name: name.to_owned(), size, heights, alpha, biome }
+    }
+
+    // The heights and mask classes of the world under the placement, to paste elsewhere. The
+    // copy fades out towards its edges, and is clear where the region leaves the map.
+    pub fn copy_of(world: &World, name: &str, placement: &Placement) -> Stamp {
+        let (sin, cos) = placement.rotation.sin_cos();
+        Self::with_fn(name, COPY_SIZE, |u, v| {
+            let x = placement.x + placement.radius * (u * cos - v * sin);
+            let y = placement.y + placement.radius * (u * sin + v * cos);
+            match world.poly().cell_at(x, y) {
+                Some(cell) => (world.heightmap()[cell], edge_falloff(u.abs().max(v.abs())), world.mask[cell]),
+                None => (0.0, 0.0, MaskClass::Free),
+            }
+        })
+    }
+
+    // Loads a stamp from a square RGBA8 image: red is the height, alpha the blend weight
+    pub fn from_rgba(name: &str, size: usize, data: &[u8]) -> Stamp {
+        assert_eq!(data.len(), size * size * 4, "Stamp data does not match its size");
+        let heights = data.chunks_exact(4).map(|px| px[0] as f64 / 255.0).collect();
+        let alpha = data.chunks_exact(4).map(|px| px[3] as f64 / 255.0).collect();
+        Stamp {
+            name: name.to_owned(),
+            size,
+            heights,
+            alpha,
+            biome: vec![MaskClass::Free; size * size],
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+
+    // The height, weight and biome of the stamp at a map position, if the stamp covers it
+    pub(crate) fn sample(&self, placement: &Placement, x: f64, y: f64) -> Option<(f64, f64, MaskClass)> {
+        if self.size == 0 || placement.radius <= 0.0 {
+            return None;
+        }
+        let (dx, dy) = (x - placement.x, y - placement.y);
+        let (sin, cos) = (-placement.rotation).sin_cos();
+        let u = (dx * cos - dy * sin) / placement.radius;
+        let v = (dx * sin + dy * cos) / placement.radius;
+        if u.abs() >= 1.0 || v.abs() >= 1.0 {
+            return None;
+        }
+
+        let col = (((u + 1.0) / 2.0) * self.size as f64) as usize;
+        let row = (((v + 1.0) / 2.0) * self.size as f64) as usize;
+        let idx = row.min(self.size - 1) * self.size + col.min(self.size - 1);
+        Some((self.heights[idx], self.alpha[idx], self.biome[idx]))
+    }
+
+    pub(crate) fn covered_cells<'p>(&'p self, poly: &'p PolyMap, placement: &'p Placement)
+        -> impl Iterator<Item = (CellId, f64, f64, MaskClass)> + 'p {
+        // The square of the stamp, turned any way, stays within this reach of its center
+        let reach = placement.radius * std::f64::consts::SQRT_2;
+        let (x, y) = (placement.x, placement.y);
+        poly.cells_in_rect((x - reach, y - reach), (x + reach, y + reach)).into_iter().filter_map(move |id| {
+            let (cx, cy) = poly[id].center();
+            self.sample(placement, cx, cy).map(|(h, a, b)| (id, h, a, b))
+        })
+    }
+}
+
+fn edge_falloff(r: f64) -> f64 {
+    // Fully opaque in the middle, smoothly fading out towards the edge
+    let t = ((1.0 - r) / 0.4).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::SeedTree;
+
+    #[test]
+    fn copied_regions_paste_the_heights_under_them() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let mut world = World::new(&poly);
+        world.generate(&SeedTree::new(42));
+        let from = Placement { x: 60.0, y: 60.0, rotation: 0.5, radius: 30.0 };
+        let stamp = Stamp::copy_of(&world, "Copy", &from);
+        let to = Placement { x: 140.0, y: 50.0, ..from };
+
+        // Over the middle, where the copy is opaque, each point takes the height of the point
+        // as far from the copied center, or of a cell next to it on the grid of the copy
+        for id in poly.cells_in_rect((to.x - 10.0, to.y - 10.0), (to.x + 10.0, to.y + 10.0)) {
+            let (x, y) = poly[id].center();
+            let (height, alpha, _) = stamp.sample(&to, x, y).unwrap();
+            assert_eq!(alpha, 1.0);
+            let source = poly.cell_at(x - to.x + from.x, y - to.y + from.y).unwrap();
+            let near = std::iter::once(source).chain(poly[source].neighbors().iter().copied());
+            assert!(near.map(|cell| world.heightmap()[cell]).any(|near| near == height), "cell {}", id.idx());
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::features::FeatureKind;
+use crate::{gazetteer, island, LandBiome, TerrainCategory, World};
+
+// Climates covering less than this share of the land are left out of the summary
+const MIN_CLIMATE_SHARE: f64 = 0.05;
+// Mountain ranges named, the largest first
+const RANGES: usize = 3;
+
+// What a world is like, in a few facts, for logs, captions and introductions
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub continents: usize,
+    pub islands: usize,
+    pub archipelagos: usize,
+    pub land_fraction: f64,
+    // The one carrying the most water: its name if any, and its length in map units
+    pub largest_river: Option<(Option<String>, f64)>,
+    // The land biomes and their share of the land, most common first
+    pub climates: Vec<(&'static str, f64)>,
+    // By cells covered, largest first
+    pub ranges: Vec<String>,
+    // The settlements in each named region, most first
+    pub settlements: Vec<(String, usize)>,
+}
+
+impl Summary {
+    pub fn of(world: &World) -> Self {
+        let poly = world.poly();
+        let landmasses = island::landmasses(world);
+        let continents = landmasses.iter().filter(|landmass| !island::is_island(world, landmass)).count();
+        let land: Vec<_> = poly.cells()
+            .map(|(id, _)| id)
+            .filter(|&id| world.terrain_category()[id] != TerrainCategory::Sea && !world.is_lake(id))
+            .collect();
+        let cells = poly.cells().count();
+
+        let largest_river = world.river_list()
+            .max_by(|a, b| a.mouth_flux().total_cmp(&b.mouth_flux()))
+            .map(|river| (river.name().map(str::to_owned), river.length()));
+
+        let mut counts: Vec<(LandBiome, usize)> = vec![];
+        for &id in land.iter() {
+            let biome = world.land_biome()[id];
+            match counts.iter_mut().find(|(other, _)| *other == biome) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((biome, 1)),
+            }
+        }
+        let mut climates: Vec<_> = counts.into_iter()
+            .filter(|&(biome, _)| biome != LandBiome::None)
+            .map(|(biome, count)| (biome.name(), count as f64 / land.len().max(1) as f64))
+            .filter(|&(_, share)| share >= MIN_CLIMATE_SHARE)
+            .collect();
+        climates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let features = world.features();
+        let mut ranges: Vec<_> = features.of_kind(FeatureKind::Range)
+            .filter_map(|range| Some((range.geometry.cells().len(), range.name.clone()?)))
+            .collect();
+        ranges.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut settlements: Vec<_> = features.of_kind(FeatureKind::Region)
+            .filter_map(|region| {
+                let count = features.of_kind(FeatureKind::Settlement)
+                    .filter_map(|settlement| gazetteer::anchor(poly, settlement))
+                    .filter(|&cell| region.geometry.contains(cell))
+                    .count();
+                Some((region.name.clone()?, count))
+            })
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        settlements.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        Self {
+            continents,
+            islands: landmasses.len() - continents,
+            archipelagos: features.of_kind(FeatureKind::Archipelago).count(),
+            land_fraction: if cells == 0 { 0.0 } else { land.len() as f64 / cells as f64 },
+            largest_river,
+            climates,
+            ranges: ranges.into_iter().take(RANGES).map(|(_, name)| name).collect(),
+            settlements,
+        }
+    }
+
+    // A sentence or two for each fact the world has
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "A world of {} and {}, {} of them in archipelagos. Land covers {:.0}% of it.\n",
+            plural(self.continents, "continent"), plural(self.islands, "island"), self.archipelagos, self.land_fraction * 100.0,
+        );
+        match &self.largest_river {
+            Some((Some(name), length)) => text.push_str(&format!("The largest river is the {}, {:.0} long.\n", name, length)),
+            Some((None, length)) => text.push_str(&format!("The largest river runs {:.0} long.\n", length)),
+            None => text.push_str("No river runs through it.\n"),
+        }
+        if !self.climates.is_empty() {
+            let climates: Vec<_> = self.climates.iter()
+                .map(|(name, share)| format!("{} ({:.0}%)", name.to_lowercase(), share * 100.0))
+                .collect();
+            text.push_str(&format!("Its lands are {}.\n", climates.join(", ")));
+        }
+        if !self.ranges.is_empty() {
+            text.push_str(&format!("Notable mountains: {}.\n", self.ranges.join(", ")));
+        }
+        if !self.settlements.is_empty() {
+            let settlements: Vec<_> = self.settlements.iter()
+                .map(|(region, count)| format!("{} in {}", plural(*count, "settlement"), region))
+                .collect();
+            text.push_str(&format!("People live in {}.\n", settlements.join(", ")));
+        }
+        text
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { format!("1 {}", noun) } else { format!("{} {}s", count, noun) }
+}
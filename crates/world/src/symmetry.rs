@@ -0,0 +1,101 @@
+use polymap::*;
+use polymap::field::{Field, Smoothable};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Symmetry {
+    #[default]
+    None,
+    MirrorX,
+    MirrorY,
+    Rotate2,
+}
+
+impl std::str::FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Symmetry::None),
+            "mirror-x" => Ok(Symmetry::MirrorX),
+            "mirror-y" => Ok(Symmetry::MirrorY),
+            "rotate-2" => Ok(Symmetry::Rotate2),
+            _ => Err(format!("Unknown symmetry '{}', expected one of none, mirror-x, mirror-y, rotate-2", s)),
+        }
+    }
+}
+
+impl Symmetry {
+    // The image of a point under the symmetry
+    pub fn mirror(&self, width: f64, height: f64, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Symmetry::None => (x, y),
+            Symmetry::MirrorX => (width - x, y),
+            Symmetry::MirrorY => (x, height - y),
+            Symmetry::Rotate2 => (width - x, height - y),
+        }
+    }
+
+    // Whether a point is the image of one in the fundamental half of the map
+    pub fn is_mirrored(&self, width: f64, height: f64, x: f64, y: f64) -> bool {
+        match self {
+            Symmetry::None => false,
+            Symmetry::MirrorX | Symmetry::Rotate2 => x > width / 2.0,
+            Symmetry::MirrorY => y > height / 2.0,
+        }
+    }
+
+    // Maps a point to its representative in the fundamental half of the map
+    pub fn fold(&self, width: f64, height: f64, x: f64, y: f64) -> (f64, f64) {
+        if self.is_mirrored(width, height, x, y) {
+            self.mirror(width, height, x, y)
+        } else {
+            (x, y)
+        }
+    }
+
+    pub fn mirror_cells(&self, poly: &PolyMap) -> Field<CellId> {
+        let width = poly.width() as f64;
+        let height = poly.height() as f64;
+        Field::with_fn(poly, |id, cell| {
+            if *self == Symmetry::None {
+                return id;
+            }
+            let (cx, cy) = cell.center();
+            let (mx, my) = self.mirror(width, height, cx, cy);
+            poly.closest_cell(id, mx, my)
+        })
+    }
+}
+
+// Averages each cell with its mirror image, removing the asymmetry introduced by the
+// irregular cell layout and by random walks like the wind.
+pub fn symmetrize<T: Smoothable>(field: &mut Field<T>, poly: &PolyMap, mirror: &Field<CellId>) {
+    let data = Field::with_fn(poly, |id, _| {
+        let mut val = field[id].clone();
+        if mirror[id] != id {
+            val.add(&field[mirror[id]]);
+            val.divide(2);
+        }
+        val
+    });
+    *field = data;
+}
+
+// Gives the cells of the mirrored half the values of their images in the fundamental half, so
+// that both halves are the same up to the irregular cell layout. Averaging can't do that, as
+// the images of the cells are not always the other way round.
+pub fn copy_fundamental<T: Clone>(field: &mut Field<T>, poly: &PolyMap, symmetry: Symmetry, mirror: &Field<CellId>) {
+    let (width, height) = (poly.width() as f64, poly.height() as f64);
+    let is_mirrored = |id: CellId| {
+        let (x, y) = poly[id].center();
+        symmetry.is_mirrored(width, height, x, y)
+    };
+    let data = Field::with_fn(poly, |id, _| {
+        if is_mirrored(id) && !is_mirrored(mirror[id]) {
+            field[mirror[id]].clone()
+        } else {
+            field[id].clone()
+        }
+    });
+    *field = data;
+}
@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+
+use polymap::CellId;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TagValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl std::fmt::Display for TagValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagValue::Bool(x) => write!(f, "{}", x),
+            TagValue::Int(x) => write!(f, "{}", x),
+            TagValue::Float(x) => write!(f, "{}", x),
+            TagValue::Text(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl From<bool> for TagValue {
+    fn from(x: bool) -> Self { TagValue::Bool(x) }
+}
+
+impl From<i64> for TagValue {
+    fn from(x: i64) -> Self { TagValue::Int(x) }
+}
+
+impl From<f64> for TagValue {
+    fn from(x: f64) -> Self { TagValue::Float(x) }
+}
+
+impl From<String> for TagValue {
+    fn from(x: String) -> Self { TagValue::Text(x) }
+}
+
+impl From<&str> for TagValue {
+    fn from(x: &str) -> Self { TagValue::Text(x.to_owned()) }
+}
+
+// Types which can be stored behind a typed key
+pub trait TagType: Into<TagValue> + Sized {
+    fn from_tag(value: &TagValue) -> Option<Self>;
+}
+
+impl TagType for bool {
+    fn from_tag(value: &TagValue) -> Option<Self> {
+        match value { TagValue::Bool(x) => Some(*x), _ => None }
+    }
+}
+
+impl TagType for i64 {
+    fn from_tag(value: &TagValue) -> Option<Self> {
+        match value { TagValue::Int(x) => Some(*x), _ => None }
+    }
+}
+
+impl TagType for f64 {
+    fn from_tag(value: &TagValue) -> Option<Self> {
+        match value { TagValue::Float(x) => Some(*x), _ => None }
+    }
+}
+
+impl TagType for String {
+    fn from_tag(value: &TagValue) -> Option<Self> {
+        match value { TagValue::Text(x) => Some(x.clone()), _ => None }
+    }
+}
+
+// A key whose values always have the same type, e.g.
+// `const POPULATION: TagKey<i64> = TagKey::new("population");`
+pub struct TagKey<T> {
+    name: &'static str,
+    _type: PhantomData<T>,
+}
+
+impl <T> TagKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self { name, _type: PhantomData }
+    }
+
+    pub fn name(&self) -> &'static str { self.name }
+}
+
+// Free text left on the map by the user
+pub const NOTE: TagKey<String> = TagKey::new("note");
+
+// Free-form annotations attached to cells by downstream applications. Tags are not touched
+// by generation, so they survive regenerating the world.
+#[derive(Clone, Default)]
+pub struct Tags {
+    cells: HashMap<CellId, BTreeMap<String, TagValue>>,
+}
+
+impl Tags {
+    pub fn set(&mut self, cell: CellId, key: &str, value: impl Into<TagValue>) {
+        self.cells.entry(cell).or_default().insert(key.to_owned(), value.into());
+    }
+
+    pub fn get(&self, cell: CellId, key: &str) -> Option<&TagValue> {
+        self.cells.get(&cell).and_then(|tags| tags.get(key))
+    }
+
+    pub fn remove(&mut self, cell: CellId, key: &str) -> Option<TagValue> {
+        let tags = self.cells.get_mut(&cell)?;
+        let removed = tags.remove(key);
+        if tags.is_empty() {
+            self.cells.remove(&cell);
+        }
+        removed
+    }
+
+    pub fn set_typed<T: TagType>(&mut self, cell: CellId, key: &TagKey<T>, value: T) {
+        self.set(cell, key.name, value)
+    }
+
+    pub fn get_typed<T: TagType>(&self, cell: CellId, key: &TagKey<T>) -> Option<T> {
+        self.get(cell, key.name).and_then(T::from_tag)
+    }
+
+    // All the tags of a cell, sorted by key
+    pub fn of_cell(&self, cell: CellId) -> impl Iterator<Item = (&str, &TagValue)> {
+        self.cells.get(&cell).into_iter()
+            .flat_map(|tags| tags.iter().map(|(key, value)| (key.as_str(), value)))
+    }
+
+    // All the cells having a given key, sorted by cell
+    pub fn cells_with(&self, key: &str) -> Vec<(CellId, &TagValue)> {
+        let mut cells: Vec<_> = self.cells.iter()
+            .filter_map(|(&cell, tags)| tags.get(key).map(|value| (cell, value)))
+            .collect();
+        cells.sort_by_key(|&(cell, _)| cell);
+        cells
+    }
+
+    pub fn tagged_cells(&self) -> impl Iterator<Item = CellId> + '_ {
+        self.cells.keys().copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+}
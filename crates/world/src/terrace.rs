@@ -0,0 +1,54 @@
+use polymap::field::Field;
+
+use crate::mask::MaskClass;
+
+// Cuts the land into flat steps, for mesas and badlands. The sea floor is left alone.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Terracing {
+    // Number of steps from the coast to the highest peak, none at all with 0
+    pub steps: usize,
+    // How much of each step is a slope up to the next one instead of flat, in [0, 1]
+    pub smoothness: f64,
+    // Only the cells painted with this class in the mask are terraced
+    pub region: Option<MaskClass>,
+}
+
+impl Default for Terracing {
+    fn default() -> Self {
+        Self { steps: 0, smoothness: 0.25, region: None }
+    }
+}
+
+impl Terracing {
+    pub fn is_enabled(&self) -> bool {
+        self.steps > 0
+    }
+
+    pub fn covers(&self, height: f64, class: MaskClass, sea_level: f64) -> bool {
+        self.is_enabled() && height >= sea_level && (self.region.is_none() || self.region == Some(class))
+    }
+
+    pub(crate) fn apply(&self, heightmap: &mut Field<f64>, mask: &Field<MaskClass>, sea_level: f64) {
+        if !self.is_enabled() {
+            return;
+        }
+        let steps = self.steps as f64;
+        let smoothness = self.smoothness.clamp(0.0, 1.0);
+        heightmap.update(|id, height| {
+            if !self.covers(*height, mask[id], sea_level) {
+                return;
+            }
+            let level = ((*height - sea_level) / (1.0 - sea_level)).clamp(0.0, 1.0) * steps;
+            let step = level.floor().min(steps - 1.0);
+            let within = level - step;
+            // Flat for most of the step, then an eased rise to the next
+            let rise = if smoothness > 0.0 {
+                let t = ((within - (1.0 - smoothness)) / smoothness).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            } else {
+                0.0
+            };
+            *height = sea_level + (step + rise) / steps * (1.0 - sea_level);
+        });
+    }
+}
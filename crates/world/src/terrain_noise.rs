@@ -0,0 +1,49 @@
+// The kinds of noise the terrain can be rolled from. Perlin has a recognizable look of soft
+// ridges; the simplex kinds are smoother and less aligned to the axes. Worley noise is made
+// of flat cells with sharp edges, for plateaus, mesas and badlands.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NoiseKind {
+    #[default]
+    Perlin,
+    // OpenSimplex, the noise library has no patented simplex
+    Simplex,
+    // SuperSimplex, also known as OpenSimplex2S
+    OpenSimplex2,
+    Worley,
+}
+
+impl NoiseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseKind::Perlin => "perlin",
+            NoiseKind::Simplex => "simplex",
+            NoiseKind::OpenSimplex2 => "opensimplex2",
+            NoiseKind::Worley => "worley",
+        }
+    }
+}
+
+impl std::str::FromStr for NoiseKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::OpenSimplex2, NoiseKind::Worley].into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("Unknown noise '{}', expected one of perlin, simplex, opensimplex2, worley", s))
+    }
+}
+
+// One of the noise contributions summed into the raw heightmap. Frequencies are per map unit.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NoiseLayer {
+    pub kind: NoiseKind,
+    pub frequency: f64,
+    pub intensity: f64,
+}
+
+impl NoiseLayer {
+    // The continents
+    pub const LOW: NoiseLayer = NoiseLayer { kind: NoiseKind::Perlin, frequency: 0.001, intensity: 1.0 };
+    // The hills over them
+    pub const HIGH: NoiseLayer = NoiseLayer { kind: NoiseKind::Perlin, frequency: 0.01, intensity: 0.2 };
+}
@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use polymap::*;
+use polymap::field::Field;
+use rand::Rng;
+
+use crate::seed::SeedTree;
+use crate::{TerrainCategory, World};
+
+// Expanding along a river or a coast costs this much of the usual
+const WATERWAY_DISCOUNT: f64 = 0.6;
+// Taking land held by another realm in the previous epoch costs this much more
+const CONTESTED_PENALTY: f64 = 2.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Realm {
+    pub capital: CellId,
+    // Relative reach of the realm, 1 for an average one
+    pub strength: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TerritoryOptions {
+    pub epochs: usize,
+    // How far an average realm reaches in each epoch, in map units of open flat ground
+    pub reach_per_epoch: f64,
+}
+
+impl Default for TerritoryOptions {
+    fn default() -> Self {
+        Self { epochs: 4, reach_per_epoch: 120.0 }
+    }
+}
+
+// The cost of extending a realm into a cell: open plains are cheap, mountains are expensive,
+// and rivers and coasts carry people along
+fn expansion_cost(world: &World, id: CellId) -> f64 {
+    let mut cost = world.difficulty()[id];
+    if !cost.is_finite() {
+        return cost;
+    }
+    if world.is_river(id) {
+        // The river is a highway here, not an obstacle
+        cost -= 1.0;
+    }
+    cost += 2.0 * ((world.heightmap()[id] - 0.7) / 0.3).max(0.0);
+    let coastal = world.terrain_category()[id] == TerrainCategory::Coast;
+    if world.is_river(id) || coastal {
+        cost *= WATERWAY_DISCOUNT;
+    }
+    cost
+}
+
+#[derive(PartialEq)]
+struct Claim {
+    cost: f64,
+    cell: CellId,
+    realm: usize,
+}
+
+impl Eq for Claim {}
+
+impl Ord for Claim {
+    // Reversed, so that the heap pops the cheapest claim first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+            .then_with(|| other.realm.cmp(&self.realm))
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+impl PartialOrd for Claim {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Grows the realms from their capitals over a number of epochs. In every epoch all realms
+// expand at once, each as far as its strength allows, and the first to reach a cell takes
+// it. Borders held in the previous epoch are harder to push back, and between epochs the
+// strength of the realms drifts, so that borders shift where realms meet.
+pub fn expand(world: &World, realms: &[Realm], options: &TerritoryOptions, seeds: &SeedTree) -> Field<Option<usize>> {
+    let poly = world.poly();
+    let cost = Field::with_fn(poly, |id, _| expansion_cost(world, id));
+    let mut owner: Field<Option<usize>> = Field::uniform(poly, None);
+    let mut strengths: Vec<f64> = realms.iter().map(|realm| realm.strength.max(0.01)).collect();
+
+    for epoch in 0..options.epochs {
+        let budget = options.reach_per_epoch * (epoch + 1) as f64;
+        let previous = owner;
+        owner = Field::uniform(poly, None);
+
+        let mut heap = BinaryHeap::new();
+        for (idx, realm) in realms.iter().enumerate() {
+            heap.push(Claim { cost: 0.0, cell: realm.capital, realm: idx });
+        }
+        while let Some(Claim { cost: so_far, cell, realm }) = heap.pop() {
+            if owner[cell].is_some() {
+                continue;
+            }
+            owner[cell] = Some(realm);
+
+            for &neighbor in poly[cell].neighbors() {
+                if owner[neighbor].is_some() || !cost[neighbor].is_finite() {
+                    continue;
+                }
+                let (fx, fy) = poly[cell].center();
+                let (tx, ty) = poly[neighbor].center();
+                let distance = ((tx - fx).powi(2) + (ty - fy).powi(2)).sqrt();
+                let mut step = distance * (cost[cell] + cost[neighbor]) / 2.0;
+                if matches!(previous[neighbor], Some(holder) if holder != realm) {
+                    step *= CONTESTED_PENALTY;
+                }
+                let next = so_far + step / strengths[realm];
+                if next <= budget {
+                    heap.push(Claim { cost: next, cell: neighbor, realm });
+                }
+            }
+        }
+
+        // Realms holding more land than average grow stronger, with some luck on top
+        let mut rng = seeds.child("epoch").indexed(epoch as u64).rng();
+        let mut sizes = vec![0usize; realms.len()];
+        for (id, _) in poly.cells() {
+            if let Some(realm) = owner[id] {
+                sizes[realm] += 1;
+            }
+        }
+        let mean = sizes.iter().sum::<usize>() as f64 / realms.len().max(1) as f64;
+        for (strength, &size) in strengths.iter_mut().zip(sizes.iter()) {
+            let growth = if mean > 0.0 { 0.1 * (size as f64 / mean - 1.0) } else { 0.0 };
+            *strength = (*strength * (1.0 + growth.clamp(-0.2, 0.2) + rng.gen_range(-0.1..0.1))).max(0.01);
+        }
+    }
+    owner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(poly: &PolyMap) -> World<'_> {
+        let mut world = World::new(poly);
+        world.generate(&SeedTree::new(42));
+        world
+    }
+
+    // Inland cells spread over the order of the cells, so that each realm has room to grow
+    fn capitals(world: &World, count: usize) -> Vec<Realm> {
+        let land: Vec<_> = world.poly().cells()
+            .filter(|&(id, _)| world.terrain_category()[id] == TerrainCategory::Land && world.difficulty()[id].is_finite())
+            .map(|(id, _)| id)
+            .collect();
+        (0..count).map(|idx| Realm { capital: land[idx * land.len() / count], strength: 1.0 }).collect()
+    }
+
+    #[test]
+    fn realms_hold_connected_land_around_their_capitals() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let world = world(&poly);
+        let realms = capitals(&world, 3);
+        let owner = expand(&world, &realms, &TerritoryOptions::default(), &SeedTree::new(7));
+
+        for (idx, realm) in realms.iter().enumerate() {
+            assert_eq!(owner[realm.capital], Some(idx));
+            // Every cell of the realm is reached from the capital through the realm
+            let mut reached = vec![realm.capital];
+            let mut frontier = vec![realm.capital];
+            while let Some(cell) = frontier.pop() {
+                for &neighbor in poly[cell].neighbors() {
+                    if owner[neighbor] == Some(idx) && !reached.contains(&neighbor) {
+                        reached.push(neighbor);
+                        frontier.push(neighbor);
+                    }
+                }
+            }
+            assert_eq!(reached.len(), poly.cells().filter(|&(id, _)| owner[id] == Some(idx)).count());
+        }
+        for (id, _) in poly.cells().filter(|&(id, _)| !world.difficulty()[id].is_finite()) {
+            assert_eq!(owner[id], None, "cell {} can't be crossed", id.idx());
+        }
+    }
+
+    #[test]
+    fn a_longer_reach_only_adds_land() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let world = world(&poly);
+        let realms = capitals(&world, 1);
+        let options = |reach_per_epoch| TerritoryOptions { epochs: 1, reach_per_epoch };
+        let near = expand(&world, &realms, &options(20.0), &SeedTree::new(7));
+        let far = expand(&world, &realms, &options(80.0), &SeedTree::new(7));
+        let held = |owner: &Field<Option<usize>>| poly.cells().filter(|&(id, _)| owner[id].is_some()).count();
+        assert!(held(&far) > held(&near));
+        assert!(poly.cells().all(|(id, _)| near[id].is_none() || far[id] == near[id]));
+    }
+
+    #[test]
+    fn the_same_seeds_draw_the_same_borders() {
+        let poly = PolyMap::with_seed(200, 120, 6.0, 1);
+        let world = world(&poly);
+        let realms = capitals(&world, 3);
+        let first = expand(&world, &realms, &TerritoryOptions::default(), &SeedTree::new(7));
+        let second = expand(&world, &realms, &TerritoryOptions::default(), &SeedTree::new(7));
+        assert!(poly.cells().all(|(id, _)| first[id] == second[id]));
+    }
+}
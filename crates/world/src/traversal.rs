@@ -0,0 +1,135 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::Write;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::{measure, TerrainCategory, World};
+
+// Passes are the natural way across a range, so routes are drawn to them
+const PASS_DISCOUNT: f64 = 0.5;
+
+// How hard each cell is to cross on foot, as a multiple of the effort of crossing open flat
+// ground: 1 on plains, up to about 8 on steep forested marshland in a harsh climate. The sea
+// cannot be crossed at all.
+pub fn difficulty(world: &World) -> Field<f64> {
+    Field::with_fn(world.poly(), |id, _| {
+        if world.terrain_category()[id] == TerrainCategory::Sea {
+            return f64::INFINITY;
+        }
+
+        let steepness = (world.slope()[id] / 4.0).min(1.0);
+        let vegetation = world.vegetation()[id];
+        let forest = (vegetation.deciduous + vegetation.boreal).min(1.0);
+        let marsh = measure::DRAIN.normalize(world.drainage()[id]).min(1.0) * (1.0 - steepness);
+        let river = if world.is_river(id) { 1.0 } else { 0.0 };
+        let temperature = world.temperature()[id];
+        let harshness = ((0.2 - temperature).max(0.0) * 5.0 + (temperature - 0.85).max(0.0) * 5.0).min(1.0);
+
+        1.0 + 3.0 * steepness + 1.5 * forest + 1.5 * marsh + river + harshness
+    })
+}
+
+// The cost of walking between two adjacent cells
+fn step_cost(poly: &PolyMap, difficulty: &Field<f64>, is_pass: &Field<bool>, from: CellId, to: CellId) -> f64 {
+    let (fx, fy) = poly[from].center();
+    let (tx, ty) = poly[to].center();
+    let distance = ((tx - fx).powi(2) + (ty - fy).powi(2)).sqrt();
+    let cost = distance * (difficulty[from] + difficulty[to]) / 2.0;
+    if is_pass[to] { cost * PASS_DISCOUNT } else { cost }
+}
+
+#[derive(PartialEq)]
+struct Visit {
+    cost: f64,
+    cell: CellId,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    // Reversed, so that the heap pops the cheapest visit first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Cheapest overland route between two cells, with Dijkstra over the cell graph
+pub fn route(world: &World, from: CellId, to: CellId) -> Option<Vec<CellId>> {
+    route_within(world, from, to, f64::INFINITY)
+}
+
+// Like route, but only over cells no harder to cross than the given difficulty
+pub fn route_within(world: &World, from: CellId, to: CellId, max_difficulty: f64) -> Option<Vec<CellId>> {
+    let poly = world.poly();
+    let mut is_pass = Field::uniform(poly, false);
+    for pass in world.passes() {
+        is_pass[pass.cell] = true;
+    }
+
+    let mut cost = Field::uniform(poly, f64::INFINITY);
+    let mut previous: Field<Option<CellId>> = Field::uniform(poly, None);
+    let mut heap = BinaryHeap::new();
+
+    cost[from] = 0.0;
+    heap.push(Visit { cost: 0.0, cell: from });
+    while let Some(Visit { cost: so_far, cell }) = heap.pop() {
+        if cell == to {
+            break;
+        }
+        if so_far > cost[cell] {
+            continue;
+        }
+        for &neighbor in poly[cell].neighbors() {
+            if world.difficulty()[neighbor] > max_difficulty {
+                continue;
+            }
+            let next = so_far + step_cost(poly, world.difficulty(), &is_pass, cell, neighbor);
+            if next < cost[neighbor] {
+                cost[neighbor] = next;
+                previous[neighbor] = Some(cell);
+                heap.push(Visit { cost: next, cell: neighbor });
+            }
+        }
+    }
+
+    if !cost[to].is_finite() {
+        return None;
+    }
+    let mut path = vec![to];
+    while let Some(cell) = previous[*path.last().unwrap()] {
+        path.push(cell);
+    }
+    path.reverse();
+    Some(path)
+}
+
+// Tabletop travel table: for each band of difficulty, the travel speed relative to open
+// ground, the chance of a random encounter per watch, and the share of the land in the band
+pub fn travel_table_csv(world: &World) -> String {
+    const BANDS: [(&str, f64, f64, f64); 4] = [
+        ("Easy", 1.5, 1.0, 1.0 / 12.0),
+        ("Moderate", 3.0, 0.75, 1.0 / 8.0),
+        ("Hard", 5.0, 0.5, 1.0 / 6.0),
+        ("Extreme", f64::INFINITY, 0.25, 1.0 / 4.0),
+    ];
+
+    let difficulty = world.difficulty();
+    let land: Vec<_> = world.poly().cells().map(|(id, _)| difficulty[id]).filter(|x| x.is_finite()).collect();
+    let mut csv = String::from("band,max_difficulty,speed,encounter_chance,land_share\n");
+    let mut lower = 0.0;
+    for (name, upper, speed, encounter) in BANDS {
+        let count = land.iter().filter(|&&x| x >= lower && x < upper).count();
+        let share = if land.is_empty() { 0.0 } else { count as f64 / land.len() as f64 };
+        let _ = writeln!(csv, "{},{},{},{:.3},{:.3}", name, upper, speed, encounter, share);
+        lower = upper;
+    }
+    csv
+}
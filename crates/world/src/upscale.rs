@@ -0,0 +1,68 @@
+use polymap::field::Field;
+use polymap::resample::Resampling;
+
+use crate::seed::SeedTree;
+use crate::spatial_function::{NoiseField, SpatialFunction};
+use crate::{symmetry, World};
+
+impl World<'_> {
+    // Makes this world from one generated on another map of the same size, usually a coarser
+    // one, e.g. to bake a draft into a detailed world with the same continents. The heights,
+    // the rain and the water in the lakes are interpolated between the source cells, the wind,
+    // mask and pins taken from the closest one, and the rest derived again. Detail noise, finer
+    // than the hills as the cells are smaller, roughens what the interpolation left smooth. The
+    // features, tags, cultures and realms are on cells of the old map, and are not carried over.
+    pub fn bake(&mut self, source: &World, detail: f64, seeds: &SeedTree) {
+        let _span = tracing::info_span!("bake", detail).entered();
+        self.start_run();
+
+        let source_cells = source.poly.cells().count();
+        let cells = self.poly.cells().count();
+        let density = cells as f64 / source_cells.max(1) as f64;
+
+        self.stage("resample", |world| {
+            let resampling = Resampling::new(source.poly, world.poly);
+            world.heightmap = resampling.interpolate(&source.heightmap);
+            world.rainfall = resampling.interpolate(&source.rainfall);
+            world.water_depth = resampling.interpolate(&source.water_depth);
+            world.wind = resampling.nearest(&source.wind);
+            world.mask = resampling.nearest(&source.mask);
+            world.pins = resampling.nearest(&source.pins);
+            // The drainage sums the rain of all the cells upstream, so it grows with the cells
+            world.river_cutoff = source.river_cutoff * density;
+            world.sea_level = source.sea_level;
+            world.report.count("source cells", source_cells);
+            world.report.count("cells", cells);
+        });
+
+        self.stage("heightmap", |world| {
+            if detail > 0.0 {
+                let width = world.poly.width() as f64;
+                let height = world.poly.height() as f64;
+                let high = world.high_noise;
+                let mut noise = Field::uniform(world.poly, 0.0);
+                NoiseField::with_rng(high.kind, high.frequency * density.sqrt(), &mut seeds.child("detail").rng())
+                    .scale(detail)
+                    .symmetric(world.symmetry, width, height)
+                    .add_to_field(world.poly, &mut noise);
+                symmetry::symmetrize(&mut noise, world.poly, &world.mirror);
+                world.heightmap.update(|id, height| *height += noise[id]);
+            }
+            world.apply_mask_to_heightmap();
+            world.apply_pins();
+            world.fill_around_pins();
+            world.compute_downhill();
+        });
+
+        self.stage("terrain", |world| {
+            world.assign_terrain_types();
+            world.analyze_relief();
+        });
+
+        self.stage("temperature", |world| world.generate_temperature());
+
+        self.stage("rivers", |world| world.generate_rivers());
+
+        self.stage("biomes", |world| world.generate_biomes());
+    }
+}
@@ -0,0 +1,91 @@
+use polymap::*;
+use polymap::field::Field;
+use polymap::selection::ElementSet;
+
+// Map units per unit of height, i.e. how tall the terrain stands over the map. Water is seen
+// at its surface, so only the heights above sea level matter.
+const VERTICAL_SCALE: f64 = 20.0;
+
+fn elevation(heightmap: &Field<f64>, sea_level: f64, id: CellId) -> f64 {
+    (heightmap[id] - sea_level).max(0.0) * VERTICAL_SCALE
+}
+
+fn distance(poly: &PolyMap, a: CellId, b: CellId) -> f64 {
+    let (ax, ay) = poly[a].center();
+    let (bx, by) = poly[b].center();
+    ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt()
+}
+
+// All the cells visible from an observer standing the given height above the cell. Cells are
+// swept outwards by distance, and each one inherits the horizon of the neighbor that lies
+// closest to its line of sight back to the observer, so this is one pass over the map
+// rather than a ray per cell.
+pub fn viewshed(poly: &PolyMap, heightmap: &Field<f64>, sea_level: f64, observer: CellId, observer_height: f64) -> ElementSet {
+    let eye = elevation(heightmap, sea_level, observer) + observer_height * VERTICAL_SCALE;
+    let (ox, oy) = poly[observer].center();
+
+    let distances = Field::with_fn(poly, |id, _| distance(poly, observer, id));
+    let bearing = |id: CellId| {
+        let (x, y) = poly[id].center();
+        (y - oy).atan2(x - ox)
+    };
+
+    // The steepest slope of the terrain seen so far on the way to each cell
+    let mut horizon = Field::uniform(poly, f64::NEG_INFINITY);
+    let mut visible = ElementSet::empty(poly);
+    visible.insert(observer);
+
+    let order = distances.ascending_order();
+    for &id in order.iter().skip(1) {
+        let here = bearing(id);
+        let parent = poly[id].neighbors().iter().copied()
+            .filter(|&neighbor| distances[neighbor] < distances[id])
+            .min_by(|&a, &b| {
+                let off_a = angle_difference(bearing(a), here);
+                let off_b = angle_difference(bearing(b), here);
+                off_a.total_cmp(&off_b)
+            });
+        // Neighbors of the observer look straight at it
+        let inherited = match parent {
+            Some(parent) if parent != observer => horizon[parent],
+            _ => f64::NEG_INFINITY,
+        };
+
+        let slope = (elevation(heightmap, sea_level, id) - eye) / distances[id];
+        if slope >= inherited {
+            visible.insert(id);
+        }
+        horizon[id] = inherited.max(slope);
+    }
+    visible
+}
+
+// Whether the straight line between eye level above the two cells clears the terrain
+pub fn line_of_sight(poly: &PolyMap, heightmap: &Field<f64>, sea_level: f64, a: CellId, b: CellId, observer_height: f64) -> bool {
+    let (ax, ay) = poly[a].center();
+    let (bx, by) = poly[b].center();
+    let from = elevation(heightmap, sea_level, a) + observer_height * VERTICAL_SCALE;
+    let to = elevation(heightmap, sea_level, b) + observer_height * VERTICAL_SCALE;
+    let length = distance(poly, a, b);
+
+    // Sample at about a quarter of the typical cell size
+    let spacing = ((poly.width() * poly.height()) as f64 / poly.cells().count() as f64).sqrt() / 4.0;
+    let steps = (length / spacing).ceil().max(1.0) as usize;
+    let mut cell = a;
+    for step in 1..steps {
+        let t = step as f64 / steps as f64;
+        cell = poly.closest_cell(cell, ax + (bx - ax) * t, ay + (by - ay) * t);
+        if cell == b {
+            break;
+        }
+        if cell != a && elevation(heightmap, sea_level, cell) > from + (to - from) * t {
+            return false;
+        }
+    }
+    true
+}
+
+fn angle_difference(a: f64, b: f64) -> f64 {
+    let difference = (a - b).abs() % std::f64::consts::TAU;
+    difference.min(std::f64::consts::TAU - difference)
+}
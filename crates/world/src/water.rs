@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::{TerrainCategory, World};
+
+// Fresh water this far away, in map units, is half as available as at the doorstep
+const REACH: f64 = 30.0;
+// A water table this deep, in meters, is out of reach of a dug well
+const WELL_DEPTH: f64 = 200.0;
+// Fresh water this far away, in map units, soaks half as much into the soil as right by it
+const SOAK_REACH: f64 = 10.0;
+// How much of the water the soil gets from the rivers, the lakes and the water table it keeps,
+// at most, before evaporation
+const RIVER_SOAK: f64 = 0.5;
+const LAKE_SOAK: f64 = 0.7;
+const TABLE_SOAK: f64 = 0.3;
+// Potential evapotranspiration, in mm per year, that dries out half of the water soaking in
+const DRYING: f64 = 1500.0;
+
+// Where fresh water can be drawn from, by the index in World::rivers or World::lakes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FreshWater {
+    River(usize),
+    Lake(usize),
+}
+
+// The closest fresh water to a cell: what it is, the cell of it that is closest, and how far
+// that is, in map units between the centers of the cells
+#[derive(Clone, Copy, Debug)]
+pub struct WaterSource {
+    pub water: FreshWater,
+    pub cell: CellId,
+    pub distance: f64,
+}
+
+#[derive(PartialEq)]
+struct Visit {
+    distance: f64,
+    cell: CellId,
+}
+
+impl Eq for Visit {}
+
+impl Ord for Visit {
+    // Reversed, so that the heap pops the closest visit first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance).then_with(|| self.cell.cmp(&other.cell))
+    }
+}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// The closest river or lake to every land cell, overland, with Dijkstra from all of them at
+// once. The sea is salt, and is neither a source nor a way to one.
+pub fn nearest_sources(world: &World) -> Field<Option<WaterSource>> {
+    let poly = world.poly();
+    let is_sea = |id: CellId| world.terrain_category()[id] == TerrainCategory::Sea;
+    let mut nearest: Field<Option<WaterSource>> = Field::uniform(poly, None);
+    let mut heap = BinaryHeap::new();
+
+    for (idx, lake) in world.lakes().iter().enumerate() {
+        for &cell in lake.cells.iter() {
+            nearest[cell] = Some(WaterSource { water: FreshWater::Lake(idx), cell, distance: 0.0 });
+            heap.push(Visit { distance: 0.0, cell });
+        }
+    }
+    for (idx, river) in world.rivers().iter().enumerate() {
+        for &cell in river.cells().iter() {
+            if !is_sea(cell) && nearest[cell].is_none() {
+                nearest[cell] = Some(WaterSource { water: FreshWater::River(idx), cell, distance: 0.0 });
+                heap.push(Visit { distance: 0.0, cell });
+            }
+        }
+    }
+
+    while let Some(Visit { distance, cell }) = heap.pop() {
+        let source = match nearest[cell] {
+            Some(source) if source.distance == distance => source,
+            _ => continue,
+        };
+        let (cx, cy) = poly[cell].center();
+        for &neighbor in poly[cell].neighbors().iter().filter(|&&neighbor| !is_sea(neighbor)) {
+            let (nx, ny) = poly[neighbor].center();
+            let next = distance + ((nx - cx).powi(2) + (ny - cy).powi(2)).sqrt();
+            if !matches!(nearest[neighbor], Some(known) if known.distance <= next) {
+                nearest[neighbor] = Some(WaterSource { distance: next, ..source });
+                heap.push(Visit { distance: next, cell: neighbor });
+            }
+        }
+    }
+    nearest
+}
+
+// How deep the ground water lies under each land cell, in meters: about as deep as the cell
+// stands over the surface of its closest fresh water, and as deep as it stands over the sea
+// where none can be reached. Rain soaking in raises it, by up to half in the most humid
+// climates. 0 at sea.
+pub fn water_table(world: &World, sources: &Field<Option<WaterSource>>) -> Field<f64> {
+    Field::with_fn(world.poly(), |id, _| {
+        if world.terrain_category()[id] == TerrainCategory::Sea {
+            return 0.0;
+        }
+        let base = sources[id].map_or(0.0, |source| world.elevation(source.cell).max(0.0));
+        (world.elevation(id) - base).max(0.0) * (1.0 - 0.5 * world.humidity()[id])
+    })
+}
+
+// How easily people living on each cell get fresh water, between 0 and 1: from a river or a
+// lake nearby, from a well where the water table is shallow, or from the rain. 0 at sea.
+pub fn availability(world: &World, sources: &Field<Option<WaterSource>>, water_table: &Field<f64>) -> Field<f64> {
+    Field::with_fn(world.poly(), |id, _| {
+        if world.terrain_category()[id] == TerrainCategory::Sea {
+            return 0.0;
+        }
+        let proximity = sources[id].map_or(0.0, |source| 0.5f64.powf(source.distance / REACH));
+        let well = (1.0 - water_table[id] / WELL_DEPTH).max(0.0);
+        (0.5 * proximity + 0.3 * well + 0.2 * world.humidity()[id]).clamp(0.0, 1.0)
+    })
+}
+
+// How wet the soil of each land cell stays, between 0 and 1: as humid as its climate, with the
+// rain against the evaporation, and wetter by the rivers and lakes and over a shallow water
+// table. The warmer the cell, the more of that water evaporates. 0 at sea.
+pub fn soil_moisture(world: &World, sources: &Field<Option<WaterSource>>, water_table: &Field<f64>) -> Field<f64> {
+    Field::with_fn(world.poly(), |id, _| {
+        if world.terrain_category()[id] == TerrainCategory::Sea {
+            return 0.0;
+        }
+        let soak = match sources[id] {
+            Some(source) => {
+                let nearby = 0.5f64.powf(source.distance / SOAK_REACH);
+                match source.water {
+                    FreshWater::River(_) => RIVER_SOAK * nearby,
+                    FreshWater::Lake(_) => LAKE_SOAK * nearby,
+                }
+            }
+            None => 0.0,
+        };
+        let table = TABLE_SOAK * (1.0 - water_table[id] / WELL_DEPTH).max(0.0);
+        let kept = 1.0 - 0.5 * (world.evapotranspiration()[id] / DRYING).min(2.0);
+        let humidity = world.humidity()[id];
+        (humidity + (1.0 - humidity) * ((soak + table).min(1.0) * kept)).clamp(0.0, 1.0)
+    })
+}
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+use polymap::*;
+use polymap::field::Field;
+
+use crate::season::Season;
+use crate::{traversal, LandBiome, World};
+
+// Habitats of fewer cells are too small for the features
+const MIN_HABITAT: usize = 12;
+// Herds only migrate between ranges at least this far apart, in map units
+const MIN_MIGRATION: f64 = 60.0;
+// Herds keep off cells harder to cross than this, see traversal::difficulty
+const MAX_DIFFICULTY: f64 = 4.0;
+
+// The kind of animals which live off the land of a biome, for encounters and hunting grounds
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Fauna {
+    // The sea and the lakes
+    #[default]
+    None,
+    ArcticHerd,
+    ForestBrowser,
+    PlainsGrazer,
+    DesertForager,
+    CanopyDweller,
+}
+
+impl Fauna {
+    pub fn of_biome(biome: LandBiome) -> Self {
+        match biome {
+            LandBiome::None => Fauna::None,
+            LandBiome::Ice | LandBiome::Tundra => Fauna::ArcticHerd,
+            LandBiome::Taiga | LandBiome::TemperateForest | LandBiome::TemperateRainforest
+                | LandBiome::SeasonalForest => Fauna::ForestBrowser,
+            LandBiome::Steppe | LandBiome::Savanna => Fauna::PlainsGrazer,
+            LandBiome::ColdDesert | LandBiome::Desert => Fauna::DesertForager,
+            LandBiome::TropicalRainforest => Fauna::CanopyDweller,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fauna::None => "None",
+            Fauna::ArcticHerd => "Arctic herds",
+            Fauna::ForestBrowser => "Forest browsers",
+            Fauna::PlainsGrazer => "Plains grazers",
+            Fauna::DesertForager => "Desert foragers",
+            Fauna::CanopyDweller => "Canopy dwellers",
+        }
+    }
+
+    // Herds moving between a summer and a winter range; the others stay put all year
+    pub fn is_migratory(&self) -> bool {
+        matches!(self, Fauna::ArcticHerd | Fauna::PlainsGrazer)
+    }
+
+    // How much the herds want to be at the cell in the season, higher is better. Arctic herds
+    // calve in the cold of the far north in summer; grazers follow the green grass. Both
+    // winter where it is warmest.
+    fn seasonal_draw(&self, world: &World, cell: CellId, season: Season) -> f64 {
+        match (self, season) {
+            (Fauna::ArcticHerd, Season::Summer) => -world.cell_temperature_at(cell, season),
+            (Fauna::PlainsGrazer, Season::Summer) => world.cell_moisture(cell),
+            _ => world.cell_temperature_at(cell, season),
+        }
+    }
+}
+
+pub(crate) fn fauna(world: &World) -> Field<Fauna> {
+    Field::with_fn(world.poly(), |id, _| {
+        if world.is_lake(id) { Fauna::None } else { Fauna::of_biome(world.land_biome()[id]) }
+    })
+}
+
+// A connected stretch of land with the same fauna, its cells sorted
+pub(crate) struct Habitat {
+    pub fauna: Fauna,
+    pub cells: Vec<CellId>,
+}
+
+// The habitats large enough to be features
+pub(crate) fn habitats(world: &World, fauna: &Field<Fauna>) -> Vec<Habitat> {
+    let poly = world.poly();
+    let mut seen = Field::uniform(poly, false);
+    let mut habitats = vec![];
+    for (start, _) in poly.cells() {
+        let kind = fauna[start];
+        if seen[start] || kind == Fauna::None {
+            continue;
+        }
+        seen[start] = true;
+        let mut cells = vec![];
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            cells.push(id);
+            for &neighbor in poly[id].neighbors() {
+                if !seen[neighbor] && fauna[neighbor] == kind {
+                    seen[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        if cells.len() >= MIN_HABITAT {
+            cells.sort();
+            habitats.push(Habitat { fauna: kind, cells });
+        }
+    }
+    habitats
+}
+
+// The corridor of the herds of a habitat from their winter range to their summer range, over
+// cells they can cross, if they migrate and the ranges are far enough apart
+pub(crate) fn migration(world: &World, habitat: &Habitat) -> Option<Vec<CellId>> {
+    if !habitat.fauna.is_migratory() {
+        return None;
+    }
+    let poly = world.poly();
+    let range = |season: Season| habitat.cells.iter()
+        .copied()
+        .filter(|&id| world.difficulty()[id] <= MAX_DIFFICULTY)
+        .max_by(|&a, &b| {
+            let (da, db) = (habitat.fauna.seasonal_draw(world, a, season), habitat.fauna.seasonal_draw(world, b, season));
+            da.total_cmp(&db).then(b.cmp(&a))
+        });
+    let (winter, summer) = (range(Season::Winter)?, range(Season::Summer)?);
+    let ((wx, wy), (sx, sy)) = (poly[winter].center(), poly[summer].center());
+    if ((sx - wx).powi(2) + (sy - wy).powi(2)).sqrt() < MIN_MIGRATION {
+        return None;
+    }
+    traversal::route_within(world, winter, summer, MAX_DIFFICULTY)
+}
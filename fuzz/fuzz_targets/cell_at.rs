@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use worldgen_fuzz::MapInput;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    map: MapInput,
+    // Fractions of the map size, in 65536ths
+    points: Vec<(u16, u16)>,
+}
+
+// Every point strictly inside the map is in a cell, and since the cells are the Voronoi
+// cells of their centers, that is the cell with the closest center
+fuzz_target!(|input: Input| {
+    let poly = input.map.build();
+    for &(fx, fy) in input.points.iter().filter(|&&(fx, fy)| fx > 0 && fy > 0) {
+        let x = fx as f64 / 65536.0 * poly.width() as f64;
+        let y = fy as f64 / 65536.0 * poly.height() as f64;
+        let cell = poly.cell_at(x, y).unwrap_or_else(|| panic!("no cell at ({}, {})", x, y));
+
+        let distance = |(cx, cy): (f64, f64)| (cx - x).powi(2) + (cy - y).powi(2);
+        let found = distance(poly[cell].center());
+        let closest = poly.cells().map(|(_, other)| distance(other.center())).fold(f64::INFINITY, f64::min);
+        assert!(found <= closest + 1e-6, "({}, {}) is in cell {} but closer to another center", x, y, cell.idx());
+    }
+});
@@ -0,0 +1,46 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use polymap::field::Field;
+use worldgen_fuzz::MapInput;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    map: MapInput,
+    // Repeated over the cells, in thousandths
+    values: Vec<i16>,
+    iterations: u8,
+    // Picks the weights of the sides of the cells
+    weights: u64,
+}
+
+// The smoothing passes give the same values on the GPU as on the CPU, up to the precision of
+// f32. Without a GPU there is nothing to compare.
+fuzz_target!(|input: Input| {
+    if !polymap::gpu::available() || input.values.is_empty() {
+        return;
+    }
+    let poly = input.map.build();
+    let values = Field::with_fn(&poly, |id, _| input.values[id.idx() % input.values.len()] as f64 / 1000.0);
+    let iterations = (input.iterations % 16) as usize;
+    let weight = |id: polymap::CellId, neighbor: polymap::CellId| {
+        let bits = input.weights.rotate_left((id.idx() * 7 + neighbor.idx()) as u32 % 64);
+        (bits % 5) as f64 / 4.0
+    };
+    let strength = Field::with_fn(&poly, |id, _| weight(id, id));
+
+    let (mut cpu, mut gpu) = (values.clone(), values.clone());
+    cpu.smooth_with(&poly, iterations, weight);
+    gpu.gpu_smooth_with(&poly, iterations, weight);
+    let (mut cpu_weighted, mut gpu_weighted) = (values.clone(), values);
+    cpu_weighted.smooth_weighted(&poly, iterations, &strength);
+    gpu_weighted.gpu_smooth_weighted(&poly, iterations, &strength);
+
+    for (id, _) in poly.cells() {
+        for (pass, cpu, gpu) in [("smooth_with", &cpu, &gpu), ("smooth_weighted", &cpu_weighted, &gpu_weighted)] {
+            let tolerance = 1e-4 * (1.0 + cpu[id].abs());
+            assert!((cpu[id] - gpu[id]).abs() <= tolerance, "{} differs at cell {}: {} on the CPU, {} on the GPU", pass, id.idx(), cpu[id], gpu[id]);
+        }
+    }
+});
@@ -0,0 +1,44 @@
+#![no_main]
+
+use std::f64::consts::PI;
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use worldgen_fuzz::{angle_difference, MapInput};
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    map: MapInput,
+    queries: Vec<(u16, i16, u8)>,
+}
+
+// The neighbor found is the one closest to the direction, and within the tolerance; when
+// none is found, no neighbor is within the tolerance
+fuzz_target!(|input: Input| {
+    let poly = input.map.build();
+    let cells = poly.cells().count();
+    if cells == 0 {
+        return;
+    }
+    for &(cell, angle, tolerance) in input.queries.iter() {
+        let cell = poly.cell_id(cell as usize % cells).unwrap();
+        let direction = angle as f64 / 32768.0 * PI;
+        let tolerance = tolerance as f64 / 255.0 * PI;
+        let off = |neighbor| angle_difference(direction, poly.angle_between_cells(cell, neighbor)).abs();
+
+        match poly.neighbor_in_direction(cell, direction, tolerance) {
+            Some(found) => {
+                assert!(poly[cell].neighbors().contains(&found), "{} is not a neighbor of {}", found.idx(), cell.idx());
+                assert!(off(found) < tolerance);
+                for &other in poly[cell].neighbors() {
+                    assert!(off(found) <= off(other) + 1e-12, "{} is closer to the direction than {}", other.idx(), found.idx());
+                }
+            }
+            None => {
+                for &other in poly[cell].neighbors() {
+                    assert!(off(other) >= tolerance, "{} is within the tolerance but was not found", other.idx());
+                }
+            }
+        }
+    }
+});
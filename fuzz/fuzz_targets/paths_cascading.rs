@@ -0,0 +1,58 @@
+#![no_main]
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use polymap::{CellId, PolyMap};
+use world::Path;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    // For each cell: whether it has the property, and where it flows, cycles included
+    cells: Vec<(bool, Option<u16>)>,
+    order: Vec<u16>,
+}
+
+// Only the flow matters here, not the geometry, so one map does for all the inputs
+fn poly() -> &'static PolyMap {
+    static POLY: OnceLock<PolyMap> = OnceLock::new();
+    POLY.get_or_init(|| PolyMap::with_seed(64, 64, 8.0, 0))
+}
+
+// Ends for any flow, and gives paths along the flow which stay on cells with the property,
+// never visit a cell twice, cover every cell of the order with the property, and only share
+// their last cell, where they join, with the paths before them
+fuzz_target!(|input: Input| {
+    let poly = poly();
+    let count = poly.cells().count();
+    if input.cells.is_empty() || count == 0 {
+        return;
+    }
+    let entry = |cell: CellId| input.cells[cell.idx() % input.cells.len()];
+    let property = |cell: CellId| entry(cell).0;
+    let next = |cell: CellId| entry(cell).1.and_then(|idx| poly.cell_id(idx as usize % count));
+    let order: Vec<_> = input.order.iter().filter_map(|&idx| poly.cell_id(idx as usize % count)).collect();
+
+    let paths = Path::paths_cascading(&property, &next, order.iter().copied());
+
+    let mut covered = HashSet::new();
+    for path in paths.iter() {
+        let cells = path.cells();
+        assert!(!cells.is_empty());
+        let unique: HashSet<_> = cells.iter().collect();
+        assert_eq!(unique.len(), cells.len(), "a path visits a cell twice");
+        for pair in cells.windows(2) {
+            assert_eq!(next(pair[0]), Some(pair[1]), "a path leaves the flow");
+        }
+        assert!(cells.iter().all(|&cell| property(cell)), "a path leaves the property");
+        if let Some((_, upstream)) = cells.split_last() {
+            assert!(upstream.iter().all(|cell| !covered.contains(cell)), "a path runs on along an earlier one");
+        }
+        covered.extend(cells.iter().copied());
+    }
+    for &cell in order.iter().filter(|&&cell| property(cell)) {
+        assert!(covered.contains(&cell), "cell {} is not on any path", cell.idx());
+    }
+});
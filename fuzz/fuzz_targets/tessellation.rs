@@ -0,0 +1,27 @@
+#![no_main]
+
+use frontend::tessellation::{GridTessellation, TessellationOptions};
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use worldgen_fuzz::MapInput;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    map: MapInput,
+    fill_tolerance: u8,
+}
+
+// Every generated cell tessellates without panicking, into finite triangles
+fuzz_target!(|input: Input| {
+    let poly = input.map.build();
+    let options = TessellationOptions {
+        fill_tolerance: 0.01 + input.fill_tolerance as f32 / 255.0,
+        ..TessellationOptions::default()
+    };
+    let tessellation = GridTessellation::new(&poly, &options);
+    for (id, _) in poly.cells() {
+        for triangle in tessellation.polygon_of(id) {
+            assert!(triangle.iter().all(|point| point.x.is_finite() && point.y.is_finite()));
+        }
+    }
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+use polymap::PolyMap;
+use world::seed::SeedTree;
+use world::World;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    map_seed: u64,
+    seed: u64,
+    width: u8,
+    height: u8,
+    // Up to several times the map size, so that most maps have only a handful of cells
+    radius: u8,
+}
+
+// Maps down to nothing either fail to build with an error, or generate a whole world with
+// finite heights, like thumbnails and previews do
+fuzz_target!(|input: Input| {
+    let radius = 0.5 + input.radius as f64 / 2.0;
+    let poly = match PolyMap::try_with_seed(input.width as usize, input.height as usize, radius, input.map_seed) {
+        Ok(poly) => poly,
+        Err(_) => return,
+    };
+    let mut world = World::new(&poly);
+    world.generate(&SeedTree::new(input.seed));
+    for (id, _) in poly.cells() {
+        assert!(world.heightmap()[id].is_finite(), "cell {} has no height", id.idx());
+    }
+});
@@ -0,0 +1,25 @@
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use polymap::PolyMap;
+
+// The parameters of a small map. Maps stay small so that the fuzzer runs many of them.
+#[derive(Arbitrary, Debug)]
+pub struct MapInput {
+    seed: u64,
+    width: u8,
+    height: u8,
+    radius: u8,
+}
+
+impl MapInput {
+    pub fn build(&self) -> PolyMap {
+        let width = 32 + self.width as usize;
+        let height = 32 + self.height as usize;
+        let radius = 4.0 + (self.radius % 13) as f64;
+        PolyMap::with_seed(width, height, radius, self.seed)
+    }
+}
+
+// Signed difference between two angles, in (-PI, PI]
+pub fn angle_difference(a: f64, b: f64) -> f64 {
+    f64::atan2((b - a).sin(), (b - a).cos())
+}